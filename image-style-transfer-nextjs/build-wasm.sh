@@ -14,6 +14,21 @@ fi
 # Build the WebAssembly package
 wasm-pack build --target web --out-dir ../public/wasm --out-name style_transfer_wasm
 
+# The same module with wasm SIMD, for browsers that support it. It shares the
+# JavaScript bindings above, so only the binary is kept.
+RUSTFLAGS="-C target-feature=+simd128" wasm-pack build --target web --out-dir target/wasm-simd --out-name style_transfer_wasm -- --features simd128
+cp target/wasm-simd/style_transfer_wasm_bg.wasm ../public/wasm/style_transfer_wasm_simd_bg.wasm
+
+# Optionally a threaded build (initThreadPool, then use_thread_pool). Shared
+# memory changes the bindings, so it gets its own directory; it needs nightly
+# to rebuild std with atomics, and the page has to be served cross-origin
+# isolated.
+if [ "$WASM_THREADS" = "1" ]; then
+    RUSTFLAGS="-C target-feature=+atomics,+bulk-memory,+mutable-globals" \
+        rustup run nightly wasm-pack build --target web --out-dir ../public/wasm-threads --out-name style_transfer_wasm \
+        -- --features threads -Z build-std=panic_abort,std
+fi
+
 cd ..
 
 echo "WebAssembly build completed!"
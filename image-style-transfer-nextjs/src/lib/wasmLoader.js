@@ -159,7 +159,7 @@ async function performWasmLoad() {
     }
     
     // Step 4: Initialize WASM with binary file
-    const wasmPath = getWasmPath();
+    const wasmPath = await pickWasmPath();
     await initFunction(wasmPath);
     console.log('WASM binary initialized successfully');
     
@@ -185,22 +185,63 @@ async function performWasmLoad() {
   }
 }
 
+// A module using one v128 instruction, as probed by wasm-feature-detect
+const SIMD_PROBE = new Uint8Array([
+  0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3, 2, 1, 0, 10, 10, 1, 8, 0, 65, 0, 253,
+  15, 253, 98, 11,
+]);
+
+/**
+ * Whether this browser can instantiate wasm SIMD modules
+ * @returns {boolean}
+ */
+function simdSupported() {
+  try {
+    return typeof WebAssembly === 'object' && WebAssembly.validate(SIMD_PROBE);
+  } catch (error) {
+    return false;
+  }
+}
+
 /**
  * Get the WASM binary path dynamically based on the current environment
+ * @param {boolean} simd - Path of the SIMD build instead
  * @returns {string} Path to the WASM binary
  */
-function getWasmPath() {
+function getWasmPath(simd = false) {
+  const fileName = simd ? 'style_transfer_wasm_simd_bg.wasm' : 'style_transfer_wasm_bg.wasm';
+
   // Check if we're in a development environment
   const isDev = process.env.NODE_ENV === 'development';
   
   // Use relative path for development, absolute for production
   if (isDev) {
-    return '/wasm/style_transfer_wasm_bg.wasm';
+    return `/wasm/${fileName}`;
   }
   
   // For production, try to get the base path from the current location
   const basePath = typeof window !== 'undefined' ? window.location.pathname.replace(/\/$/, '') : '';
-  return `${basePath}/wasm/style_transfer_wasm_bg.wasm`;
+  return `${basePath}/wasm/${fileName}`;
+}
+
+/**
+ * The SIMD build when the browser supports it and it was built, the scalar one otherwise
+ * @returns {Promise<string>} Path to the WASM binary
+ */
+async function pickWasmPath() {
+  if (simdSupported()) {
+    const simdPath = getWasmPath(true);
+    try {
+      const response = await fetch(simdPath, { method: 'HEAD' });
+      if (response.ok) {
+        console.log('Using the wasm SIMD build');
+        return simdPath;
+      }
+    } catch (error) {
+      console.warn('SIMD build not reachable, using the scalar build:', error);
+    }
+  }
+  return getWasmPath();
 }
 
 /**
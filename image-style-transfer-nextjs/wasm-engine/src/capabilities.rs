@@ -0,0 +1,95 @@
+//! What this browser and build can do, as reported by `get_capabilities`.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::encode::EncoderSupport;
+use crate::options::OutputFormat;
+use crate::pipeline::tiling::max_output_side;
+
+/// A module using one v128 instruction, as probed by wasm-feature-detect.
+const SIMD_PROBE: [u8; 31] = [
+    0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3, 2, 1, 0, 10, 10, 1, 8, 0, 65, 0, 253,
+    15, 253, 98, 11,
+];
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    /// A WebGPU device is ready for the engine to use.
+    pub webgpu: bool,
+    /// The adapter's description, or vendor and architecture, when exposed.
+    pub webgpu_adapter: Option<String>,
+    /// `navigator.ml` exists. The engine doesn't run models on WebNN yet.
+    pub webnn: bool,
+    /// This build was compiled with wasm SIMD.
+    pub simd: bool,
+    /// The browser validates wasm SIMD modules, so a SIMD build would run.
+    pub simd_supported: bool,
+    /// SharedArrayBuffer is usable, which wasm threads need.
+    pub threads: bool,
+    pub cross_origin_isolated: bool,
+    pub offscreen_canvas: bool,
+    /// Output formats the browser's canvas can encode.
+    pub encoders: Vec<OutputFormat>,
+    /// `navigator.deviceMemory`, in GB, when the browser exposes it.
+    pub device_memory_gb: Option<f64>,
+    /// Longest source side worth processing with that much memory.
+    pub max_input_dimension: u32,
+}
+
+impl Capabilities {
+    /// Probes the global scope; `encoders` comes from `EncoderSupport::detect`.
+    pub fn detect(encoders: EncoderSupport, webgpu_adapter: Option<&JsValue>) -> Capabilities {
+        let global = js_sys::global();
+        let navigator = global_property(&global, "navigator");
+        let cross_origin_isolated = global_property(&global, "crossOriginIsolated").is_truthy();
+        let device_memory_gb = device_memory_gb();
+        Capabilities {
+            webgpu: webgpu_adapter.is_some(),
+            webgpu_adapter: webgpu_adapter.and_then(adapter_name),
+            webnn: !global_property(&navigator, "ml").is_undefined(),
+            simd: crate::pipeline::simd::ENABLED,
+            simd_supported: js_sys::WebAssembly::validate(&js_sys::Uint8Array::from(
+                &SIMD_PROBE[..],
+            ))
+            .unwrap_or(false),
+            // Browsers only expose it under cross-origin isolation
+            threads: cross_origin_isolated
+                && !global_property(&global, "SharedArrayBuffer").is_undefined(),
+            cross_origin_isolated,
+            offscreen_canvas: !global_property(&global, "OffscreenCanvas").is_undefined(),
+            encoders: encoders.formats(),
+            device_memory_gb,
+            max_input_dimension: max_output_side(device_memory_gb),
+        }
+    }
+}
+
+/// `navigator.deviceMemory`, which only Chromium exposes.
+pub fn device_memory_gb() -> Option<f64> {
+    let navigator = global_property(&js_sys::global(), "navigator");
+    global_property(&navigator, "deviceMemory").as_f64()
+}
+
+fn global_property(target: &JsValue, name: &str) -> JsValue {
+    if target.is_undefined() {
+        return JsValue::UNDEFINED;
+    }
+    js_sys::Reflect::get(target, &name.into()).unwrap_or(JsValue::UNDEFINED)
+}
+
+/// The adapter's description, or vendor and architecture. `adapter.info`
+/// replaced the async `requestAdapterInfo()`; older browsers get no name.
+pub fn adapter_name(adapter: &JsValue) -> Option<String> {
+    let info = global_property(adapter, "info");
+    let field = |name: &str| global_property(&info, name).as_string().unwrap_or_default();
+    let description = field("description");
+    let name = if description.is_empty() {
+        format!("{} {}", field("vendor"), field("architecture"))
+            .trim()
+            .to_string()
+    } else {
+        description
+    };
+    (!name.is_empty()).then_some(name)
+}
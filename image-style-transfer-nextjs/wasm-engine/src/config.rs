@@ -0,0 +1,166 @@
+//! User-facing engine settings that apps persist between sessions.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PreferredBackend {
+    /// WebGPU when the browser offers it, CPU otherwise.
+    Auto,
+    /// Never touch WebGPU.
+    Cpu,
+    Webgpu,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EngineConfig {
+    pub default_strength: f32,
+    pub preferred_style: Option<String>,
+    /// Fail instead of falling back to the simulated filter when an ONNX model can't run,
+    /// including at load time with `OnnxParseError` when no runtime can load it.
+    pub strict_mode: bool,
+    pub log_level: LogLevel,
+    /// Largest side, in pixels, that source-resolution paths will work at; 0 means no limit
+    /// beyond the model-relative cap of `pipeline::resize::working_size`.
+    pub max_input_dimension: u32,
+    pub preferred_backend: PreferredBackend,
+    /// Keep the downloaded ONNX bytes after tract has built a plan from them.
+    pub retain_model_bytes: bool,
+    /// Attempts per model download, including the first; at least 1.
+    pub download_attempts: u32,
+    /// Backoff before the first retry, doubled (with jitter) for each later one.
+    pub download_retry_delay_ms: u32,
+    /// Per-attempt timeout covering the whole download; 0 means none.
+    pub download_timeout_ms: u32,
+    /// When a model's concrete input shape disagrees with its metadata,
+    /// correct the metadata instead of failing the load.
+    pub trust_model_shapes: bool,
+    /// Keep downloaded model files in IndexedDB, keyed by name and
+    /// `version`, so later page loads skip the download.
+    pub persistent_model_cache: bool,
+    /// Convert tract plans to f16 as they load, for less memory and faster
+    /// convolutions at slightly lower precision. Models that can't be
+    /// converted load in f32, announced as a `"precision_fallback"` event.
+    pub half_precision: bool,
+    /// Load tract plans without optimizing them, so a model is usable as
+    /// soon as it's parsed, and optimize them when the page is idle. The
+    /// optimized plan replaces the first one on the model's next run,
+    /// announced as a `"model_optimized"` event; runs before that are slower.
+    pub deferred_optimization: bool,
+    /// Most `prefetch_models` downloads running at once; at least 1.
+    pub prefetch_concurrency: u32,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            default_strength: 1.0,
+            preferred_style: None,
+            strict_mode: false,
+            log_level: LogLevel::Info,
+            max_input_dimension: 0,
+            preferred_backend: PreferredBackend::Auto,
+            retain_model_bytes: true,
+            download_attempts: 3,
+            download_retry_delay_ms: 1000,
+            download_timeout_ms: 20_000,
+            trust_model_shapes: false,
+            persistent_model_cache: true,
+            half_precision: false,
+            deferred_optimization: false,
+            prefetch_concurrency: 1,
+        }
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: &Value) -> Result<T, String> {
+    serde_json::from_value(value.clone()).map_err(|e| e.to_string())
+}
+
+impl EngineConfig {
+    /// Returns this config with the known fields of `stored` applied.
+    ///
+    /// Unknown keys (from newer versions) are ignored. If any known field is
+    /// invalid nothing is applied and every offending field is reported as
+    /// `"field: reason"`.
+    pub fn merged(&self, stored: &Map<String, Value>) -> Result<EngineConfig, Vec<String>> {
+        let mut config = self.clone();
+        let mut rejected = Vec::new();
+
+        for (key, value) in stored {
+            let result = match key.as_str() {
+                "default_strength" => parse::<f32>(value).and_then(|strength| {
+                    if (0.0..=1.0).contains(&strength) {
+                        config.default_strength = strength;
+                        Ok(())
+                    } else {
+                        Err(format!("{} is outside [0, 1]", strength))
+                    }
+                }),
+                "preferred_style" => parse(value).map(|style| config.preferred_style = style),
+                "strict_mode" => parse(value).map(|strict| config.strict_mode = strict),
+                "log_level" => parse(value).map(|level| config.log_level = level),
+                "max_input_dimension" => {
+                    parse(value).map(|dimension| config.max_input_dimension = dimension)
+                }
+                "preferred_backend" => {
+                    parse(value).map(|backend| config.preferred_backend = backend)
+                }
+                "retain_model_bytes" => {
+                    parse(value).map(|retain| config.retain_model_bytes = retain)
+                }
+                "download_attempts" => parse::<u32>(value).and_then(|attempts| {
+                    if attempts >= 1 {
+                        config.download_attempts = attempts;
+                        Ok(())
+                    } else {
+                        Err("must be at least 1".to_string())
+                    }
+                }),
+                "download_retry_delay_ms" => {
+                    parse(value).map(|delay| config.download_retry_delay_ms = delay)
+                }
+                "download_timeout_ms" => {
+                    parse(value).map(|timeout| config.download_timeout_ms = timeout)
+                }
+                "trust_model_shapes" => parse(value).map(|trust| config.trust_model_shapes = trust),
+                "persistent_model_cache" => {
+                    parse(value).map(|persist| config.persistent_model_cache = persist)
+                }
+                "half_precision" => parse(value).map(|half| config.half_precision = half),
+                "deferred_optimization" => {
+                    parse(value).map(|deferred| config.deferred_optimization = deferred)
+                }
+                "prefetch_concurrency" => parse::<u32>(value).and_then(|limit| {
+                    if limit >= 1 {
+                        config.prefetch_concurrency = limit;
+                        Ok(())
+                    } else {
+                        Err("must be at least 1".to_string())
+                    }
+                }),
+                _ => Ok(()),
+            };
+            if let Err(reason) = result {
+                rejected.push(format!("{}: {}", key, reason));
+            }
+        }
+
+        if rejected.is_empty() {
+            Ok(config)
+        } else {
+            Err(rejected)
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Fetching model files with retries, a per-attempt timeout, progress
+//! reports and caller cancellation.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AbortController, AbortSignal, Headers, ReadableStreamDefaultReader, RequestInit, Response,
+};
+
+use crate::config::EngineConfig;
+use crate::error::EngineError;
+use crate::pipeline::integrity;
+use crate::pipeline::resume::{PartialDownload, ResponseInfo};
+use crate::pipeline::retry;
+use crate::pipeline::StreamingDecompressor;
+use crate::scope::Scope;
+
+/// What the caller of `load_model` handed in to follow or stop a download.
+#[derive(Default)]
+pub struct DownloadWatch {
+    /// Called as `on_progress(loaded, total)` whenever bytes arrive; `total`
+    /// is null when the server doesn't say. Exceptions are ignored.
+    pub on_progress: Option<js_sys::Function>,
+    /// Aborting it stops the download with `Cancelled`.
+    pub signal: Option<AbortSignal>,
+}
+
+impl DownloadWatch {
+    pub fn report(&self, loaded: usize, total: Option<usize>) {
+        if let Some(callback) = &self.on_progress {
+            let total = total.map_or(JsValue::NULL, |total| (total as f64).into());
+            let _ = callback.call2(&JsValue::NULL, &(loaded as f64).into(), &total);
+        }
+    }
+
+    fn aborted(&self) -> bool {
+        self.signal.as_ref().is_some_and(|signal| signal.aborted())
+    }
+}
+
+struct AttemptFailure {
+    message: String,
+    status: Option<u16>,
+    retryable: bool,
+    cancelled: bool,
+}
+
+impl AttemptFailure {
+    fn retryable(message: String, status: Option<u16>) -> AttemptFailure {
+        AttemptFailure {
+            message,
+            status,
+            retryable: true,
+            cancelled: false,
+        }
+    }
+
+    fn cancelled() -> AttemptFailure {
+        AttemptFailure {
+            message: "cancelled".to_string(),
+            status: None,
+            retryable: false,
+            cancelled: true,
+        }
+    }
+}
+
+/// Downloads `url`, retrying network errors, timeouts and retryable statuses
+/// as configured. `on_retry` is told about each failed attempt that will be
+/// retried, with the delay in milliseconds.
+///
+/// Bytes accumulate in `partial` as they arrive, and `decoder` decompresses
+/// them as they do. When the server supports ranges, later attempts (and
+/// later calls given the same `partial`) only request the remainder.
+/// `watch` hears about every chunk and can cancel between or during attempts.
+pub async fn fetch_model(
+    url: &str,
+    config: &EngineConfig,
+    partial: &mut PartialDownload,
+    decoder: &mut StreamingDecompressor,
+    watch: &DownloadWatch,
+    mut on_retry: impl FnMut(u32, &str, f64),
+) -> Result<Vec<u8>, EngineError> {
+    let scope = Scope::current().ok_or_else(|| {
+        EngineError::InvalidInput("Model downloads need a window or worker".to_string())
+    })?;
+    let attempts = config.download_attempts.max(1);
+
+    let mut attempt = 1;
+    loop {
+        let result = if watch.aborted() {
+            Err(AttemptFailure::cancelled())
+        } else {
+            fetch_once(
+                &scope,
+                url,
+                config.download_timeout_ms,
+                partial,
+                decoder,
+                watch,
+            )
+            .await
+        }
+        .and_then(|()| {
+            partial
+                .complete()
+                .map_err(|reason| AttemptFailure::retryable(reason, None))
+        });
+        let failure = match result {
+            Ok(bytes) => return Ok(bytes),
+            Err(failure) => failure,
+        };
+        if failure.cancelled {
+            return Err(EngineError::Cancelled(format!(
+                "Fetching {} was cancelled",
+                url
+            )));
+        }
+        if !failure.retryable || attempt >= attempts {
+            return Err(EngineError::DownloadFailed {
+                message: format!(
+                    "Fetching {} failed after {} attempt{}: {}",
+                    url,
+                    attempt,
+                    if attempt == 1 { "" } else { "s" },
+                    failure.message
+                ),
+                status: failure.status,
+                attempts: attempt,
+            });
+        }
+
+        let delay = retry::backoff_delay_ms(
+            config.download_retry_delay_ms,
+            attempt,
+            js_sys::Math::random(),
+        );
+        on_retry(attempt, &failure.message, delay);
+        sleep(&scope, delay).await;
+        attempt += 1;
+    }
+}
+
+/// One attempt, aborted through an `AbortController` once `timeout_ms` passes
+/// (0 means never) or the watch's signal fires. The timeout covers reading
+/// the body too.
+async fn fetch_once(
+    scope: &Scope,
+    url: &str,
+    timeout_ms: u32,
+    partial: &mut PartialDownload,
+    decoder: &mut StreamingDecompressor,
+    watch: &DownloadWatch,
+) -> Result<(), AttemptFailure> {
+    let controller =
+        AbortController::new().map_err(|e| AttemptFailure::retryable(describe(&e), None))?;
+    let timed_out = Rc::new(Cell::new(false));
+
+    let on_abort = watch.signal.as_ref().map(|signal| {
+        let controller = controller.clone();
+        let on_abort = Closure::<dyn FnMut()>::new(move || controller.abort());
+        let _ = signal.add_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref());
+        (signal, on_abort)
+    });
+
+    let timer = if timeout_ms > 0 {
+        let (controller, timed_out) = (controller.clone(), timed_out.clone());
+        let on_timeout = Closure::once(move || {
+            timed_out.set(true);
+            controller.abort();
+        });
+        scope
+            .set_timeout(on_timeout.as_ref().unchecked_ref(), timeout_ms as i32)
+            .ok()
+            .map(|handle| (handle, on_timeout))
+    } else {
+        None
+    };
+
+    let result = fetch_body(scope, url, &controller, partial, decoder, watch).await;
+    if let Some((handle, _on_timeout)) = timer {
+        scope.clear_timeout(handle);
+    }
+    if let Some((signal, on_abort)) = on_abort {
+        let _ =
+            signal.remove_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref());
+    }
+
+    match result {
+        Err(_) if watch.aborted() => Err(AttemptFailure::cancelled()),
+        Err(_) if timed_out.get() => Err(AttemptFailure::retryable(
+            format!("timed out after {} ms", timeout_ms),
+            None,
+        )),
+        result => result,
+    }
+}
+
+async fn fetch_body(
+    scope: &Scope,
+    url: &str,
+    controller: &AbortController,
+    partial: &mut PartialDownload,
+    decoder: &mut StreamingDecompressor,
+    watch: &DownloadWatch,
+) -> Result<(), AttemptFailure> {
+    let network_error = |e: JsValue| AttemptFailure::retryable(describe(&e), None);
+
+    let init = RequestInit::new();
+    init.set_signal(Some(&controller.signal()));
+    if let Some(range) = partial.range_header() {
+        let headers = Headers::new().map_err(network_error)?;
+        headers.set("Range", &range).map_err(network_error)?;
+        // Servers answer 200 with the whole file if it no longer matches
+        if let Some(etag) = &partial.etag {
+            headers.set("If-Range", etag).map_err(network_error)?;
+        }
+        init.set_headers(&headers);
+    }
+    let response: Response = JsFuture::from(scope.fetch_with_str_and_init(url, &init))
+        .await
+        .and_then(|response| response.dyn_into())
+        .map_err(network_error)?;
+
+    let status = response.status();
+    if status == 416 {
+        // The partial buffer is unusable; start over on the next attempt
+        *partial = PartialDownload::default();
+        return Err(AttemptFailure::retryable(
+            "range not satisfiable".to_string(),
+            Some(status),
+        ));
+    }
+    if !response.ok() {
+        return Err(AttemptFailure {
+            message: format!("HTTP {} {}", status, response.status_text()),
+            status: Some(status),
+            retryable: retry::is_retryable_status(status),
+            cancelled: false,
+        });
+    }
+
+    let header = |name: &str| response.headers().get(name).ok().flatten();
+    let (content_range, etag, accept_ranges, content_encoding) = (
+        header("Content-Range"),
+        header("ETag"),
+        header("Accept-Ranges"),
+        header("Content-Encoding"),
+    );
+    partial
+        .begin(ResponseInfo {
+            status,
+            content_length: header("Content-Length").and_then(|length| length.parse().ok()),
+            content_range: content_range.as_deref(),
+            etag: etag.as_deref(),
+            accept_ranges: accept_ranges.as_deref(),
+            content_encoding: content_encoding.as_deref(),
+        })
+        .map_err(|reason| AttemptFailure::retryable(reason, Some(status)))?;
+    // Notices a download that starts over
+    decoder.catch_up(&partial.bytes);
+
+    // Stream the body so whatever arrives before a failure is kept
+    let Some(body) = response.body() else {
+        let buffer = JsFuture::from(response.array_buffer().map_err(network_error)?)
+            .await
+            .map_err(network_error)?;
+        partial.bytes.extend(Uint8Array::new(&buffer).to_vec());
+        decoder.catch_up(&partial.bytes);
+        watch.report(partial.bytes.len(), partial.total_len);
+        return Ok(());
+    };
+    let reader: ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+    loop {
+        let chunk = JsFuture::from(reader.read()).await.map_err(network_error)?;
+        let done = js_sys::Reflect::get(&chunk, &"done".into()).map_err(network_error)?;
+        if done.as_bool() == Some(true) {
+            return Ok(());
+        }
+        let value = js_sys::Reflect::get(&chunk, &"value".into()).map_err(network_error)?;
+        partial
+            .bytes
+            .extend(value.unchecked_into::<Uint8Array>().to_vec());
+        decoder.catch_up(&partial.bytes);
+        watch.report(partial.bytes.len(), partial.total_len);
+    }
+}
+
+/// Checks `bytes` against the hex SHA-256 `expected`, computed with
+/// SubtleCrypto. Pages that aren't a secure context have none, so their
+/// checks fail rather than being skipped.
+pub async fn verify_sha256(
+    file_name: &str,
+    bytes: &[u8],
+    expected: &str,
+) -> Result<(), EngineError> {
+    let digest = async {
+        let scope = Scope::current().ok_or_else(|| JsValue::from_str("no global scope"))?;
+        let subtle = scope.crypto()?.subtle();
+        if subtle.is_undefined() {
+            return Err(JsValue::from_str("SubtleCrypto needs a secure context"));
+        }
+        let buffer = JsFuture::from(subtle.digest_with_str_and_u8_array("SHA-256", bytes)?).await?;
+        Ok(Uint8Array::new(&buffer).to_vec())
+    };
+    let digest: Vec<u8> = digest.await.map_err(|e: JsValue| {
+        EngineError::IntegrityError(format!("Cannot verify '{}': {}", file_name, describe(&e)))
+    })?;
+    integrity::verify_digest(&digest, expected).map_err(|reason| {
+        EngineError::IntegrityError(format!(
+            "'{}' failed its integrity check: {}",
+            file_name, reason
+        ))
+    })
+}
+
+async fn sleep(scope: &Scope, ms: f64) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = scope.set_timeout(&resolve, ms as i32);
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+fn describe(error: &JsValue) -> String {
+    error
+        .dyn_ref::<js_sys::Error>()
+        .map(|e| String::from(e.message()))
+        .or_else(|| error.as_string())
+        .unwrap_or_else(|| "network error".to_string())
+}
@@ -0,0 +1,141 @@
+//! Encoding the result canvas (or, in workers, raw pixels) into a data URL.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlCanvasElement;
+
+use crate::error::EngineError;
+use crate::options::{OutputFormat, ProcessOptions};
+use crate::pipeline::codec;
+use crate::scope;
+
+/// Which optional formats this browser's canvas can encode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EncoderSupport {
+    pub webp: bool,
+    pub avif: bool,
+}
+
+impl EncoderSupport {
+    /// Encodes a 1x1 canvas in each format. Browsers that can't encode a
+    /// type silently return PNG instead. Workers encode in Rust, which only
+    /// knows PNG and JPEG.
+    pub fn detect() -> Result<EncoderSupport, JsValue> {
+        if scope::document().is_none() {
+            return Ok(EncoderSupport::default());
+        }
+        let canvas = scope::create_canvas()?;
+        canvas.set_width(1);
+        canvas.set_height(1);
+
+        let encodes = |format: OutputFormat| {
+            canvas
+                .to_data_url_with_type(format.mime_type())
+                .is_ok_and(|url| url.starts_with(&format!("data:{}", format.mime_type())))
+        };
+        Ok(EncoderSupport {
+            webp: encodes(OutputFormat::Webp),
+            avif: encodes(OutputFormat::Avif),
+        })
+    }
+
+    pub fn supports(&self, format: OutputFormat) -> bool {
+        match format {
+            OutputFormat::Png | OutputFormat::Jpeg => true,
+            OutputFormat::Webp => self.webp,
+            OutputFormat::Avif => self.avif,
+        }
+    }
+
+    /// Every format that encodes as itself, PNG and JPEG first.
+    pub fn formats(&self) -> Vec<OutputFormat> {
+        [
+            OutputFormat::Png,
+            OutputFormat::Jpeg,
+            OutputFormat::Webp,
+            OutputFormat::Avif,
+        ]
+        .into_iter()
+        .filter(|&format| self.supports(format))
+        .collect()
+    }
+}
+
+/// A data URL and the format it was actually encoded in.
+#[derive(Serialize, Clone, Debug)]
+pub struct EncodedImage {
+    pub data_url: String,
+    pub format: OutputFormat,
+    pub mime_type: &'static str,
+    /// The requested format wasn't supported and PNG was used instead.
+    pub format_fallback: bool,
+}
+
+/// Encodes `canvas` as requested by `options`, falling back to PNG.
+pub fn encode_canvas(
+    canvas: &HtmlCanvasElement,
+    options: &ProcessOptions,
+    support: EncoderSupport,
+) -> Result<EncodedImage, JsValue> {
+    let (format, format_fallback) = resolve_format(options, support)?;
+    let data_url = match (format, options.quality) {
+        (OutputFormat::Png, _) | (_, None) => canvas.to_data_url_with_type(format.mime_type())?,
+        (_, Some(quality)) => {
+            canvas.to_data_url_with_type_and_encoder_options(format.mime_type(), &quality.into())?
+        }
+    };
+    Ok(EncodedImage {
+        data_url,
+        format,
+        mime_type: format.mime_type(),
+        format_fallback,
+    })
+}
+
+/// Encodes RGBA pixels without a canvas, as workers must. WebP and AVIF fall
+/// back to PNG.
+pub fn encode_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    options: &ProcessOptions,
+) -> Result<EncodedImage, JsValue> {
+    let (format, format_fallback) = resolve_format(options, EncoderSupport::default())?;
+    let bytes = match format {
+        OutputFormat::Jpeg => codec::encode_jpeg(
+            pixels,
+            width,
+            height,
+            options.quality.unwrap_or(codec::DEFAULT_JPEG_QUALITY),
+        ),
+        _ => codec::encode_png(pixels, width, height),
+    }
+    .map_err(|reason| EngineError::InvalidInput(format!("Encoding failed: {}", reason)))?;
+    Ok(EncodedImage {
+        data_url: codec::data_url(format.mime_type(), &bytes),
+        format,
+        mime_type: format.mime_type(),
+        format_fallback,
+    })
+}
+
+/// Checks `quality` and picks the format to encode in, and whether that is
+/// a fallback.
+fn resolve_format(
+    options: &ProcessOptions,
+    support: EncoderSupport,
+) -> Result<(OutputFormat, bool), EngineError> {
+    if let Some(quality) = options.quality {
+        if !(0.0..=1.0).contains(&quality) {
+            return Err(EngineError::InvalidInput(format!(
+                "quality must be in [0, 1], got {}",
+                quality
+            )));
+        }
+    }
+    if support.supports(options.format) {
+        Ok((options.format, false))
+    } else {
+        Ok((OutputFormat::Png, true))
+    }
+}
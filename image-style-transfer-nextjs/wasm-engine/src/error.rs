@@ -0,0 +1,184 @@
+//! Errors surfaced to JavaScript.
+
+use std::fmt;
+
+use wasm_bindgen::JsValue;
+
+/// Where an image that failed to decode came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageSourceKind {
+    DataUrl,
+    BlobUrl,
+    HttpUrl,
+    Other,
+}
+
+impl ImageSourceKind {
+    pub fn of(url: &str) -> ImageSourceKind {
+        let scheme = url
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match scheme.as_str() {
+            "data" => ImageSourceKind::DataUrl,
+            "blob" => ImageSourceKind::BlobUrl,
+            "http" | "https" => ImageSourceKind::HttpUrl,
+            _ => ImageSourceKind::Other,
+        }
+    }
+
+    /// The `source_kind` property of a `DecodeFailed` error.
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageSourceKind::DataUrl => "data",
+            ImageSourceKind::BlobUrl => "blob",
+            ImageSourceKind::HttpUrl => "http",
+            ImageSourceKind::Other => "other",
+        }
+    }
+
+    pub(crate) fn description(self) -> &'static str {
+        match self {
+            ImageSourceKind::DataUrl => "a data URL",
+            ImageSourceKind::BlobUrl => "a blob URL",
+            ImageSourceKind::HttpUrl => "an http URL",
+            ImageSourceKind::Other => "a URL",
+        }
+    }
+}
+
+/// A failure the frontend can branch on via the `code` property of the thrown error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EngineError {
+    /// No registry entry has the requested name.
+    ModelNotFound(String),
+    /// The caller passed something the engine can't use.
+    InvalidInput(String),
+    /// Running a model or filter failed.
+    InferenceError(String),
+    /// A model can't fit in the configured memory budget.
+    MemoryBudgetExceeded(String),
+    /// The browser refused access to the source pixels, e.g. a cross-origin image.
+    SecurityError(String),
+    /// A compressed model file couldn't be decompressed.
+    DecompressionError(String),
+    /// A downloaded model doesn't match the `sha256` its metadata declares,
+    /// or couldn't be checked against it.
+    IntegrityError(String),
+    /// A model file isn't an ONNX graph any runtime could load.
+    OnnxParseError(String),
+    /// A model's graph doesn't take or produce the shape its metadata declares.
+    ModelShapeMismatch(String),
+    /// `dispose()` was called on the engine.
+    EngineDisposed(String),
+    /// The caller aborted the operation through its `AbortSignal`.
+    Cancelled(String),
+    /// The browser couldn't decode an image; `source_kind` says what kind of
+    /// URL it was loaded from.
+    DecodeFailed {
+        message: String,
+        source_kind: ImageSourceKind,
+    },
+    /// Fetching an image failed before it could be decoded; `status` is the
+    /// HTTP status, if there was a response.
+    FetchFailed {
+        message: String,
+        status: Option<u16>,
+    },
+    /// A model download failed; `status` is the last HTTP status, if any.
+    DownloadFailed {
+        message: String,
+        status: Option<u16>,
+        attempts: u32,
+    },
+}
+
+impl EngineError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            EngineError::ModelNotFound(_) => "ModelNotFound",
+            EngineError::InvalidInput(_) => "InvalidInput",
+            EngineError::InferenceError(_) => "InferenceError",
+            EngineError::MemoryBudgetExceeded(_) => "MemoryBudgetExceeded",
+            EngineError::SecurityError(_) => "SecurityError",
+            EngineError::DecompressionError(_) => "DecompressionError",
+            EngineError::IntegrityError(_) => "IntegrityError",
+            EngineError::OnnxParseError(_) => "OnnxParseError",
+            EngineError::ModelShapeMismatch(_) => "ModelShapeMismatch",
+            EngineError::EngineDisposed(_) => "EngineDisposed",
+            EngineError::Cancelled(_) => "Cancelled",
+            EngineError::DecodeFailed { .. } => "DecodeFailed",
+            EngineError::FetchFailed { .. } => "FetchFailed",
+            EngineError::DownloadFailed { .. } => "DownloadFailed",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            EngineError::ModelNotFound(message)
+            | EngineError::InvalidInput(message)
+            | EngineError::InferenceError(message)
+            | EngineError::MemoryBudgetExceeded(message)
+            | EngineError::SecurityError(message)
+            | EngineError::DecompressionError(message)
+            | EngineError::IntegrityError(message)
+            | EngineError::OnnxParseError(message)
+            | EngineError::ModelShapeMismatch(message)
+            | EngineError::EngineDisposed(message)
+            | EngineError::Cancelled(message)
+            | EngineError::DecodeFailed { message, .. }
+            | EngineError::FetchFailed { message, .. }
+            | EngineError::DownloadFailed { message, .. } => message,
+        }
+    }
+
+    /// `DecodeFailed` for an image loaded from `url`, which is not repeated
+    /// in the message since data URLs can be huge.
+    pub fn decode_failed(url: &str) -> EngineError {
+        let source_kind = ImageSourceKind::of(url);
+        EngineError::DecodeFailed {
+            message: format!(
+                "The browser could not decode the image from {}",
+                source_kind.description()
+            ),
+            source_kind,
+        }
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<EngineError> for JsValue {
+    fn from(error: EngineError) -> JsValue {
+        // A real Error keeps stack traces in devtools; `code` is what callers match on
+        let js_error = js_sys::Error::new(error.message());
+        js_error.set_name(error.code());
+        let _ = js_sys::Reflect::set(&js_error, &"code".into(), &error.code().into());
+        match error {
+            EngineError::DownloadFailed {
+                status, attempts, ..
+            } => {
+                let status = status.map_or(JsValue::NULL, JsValue::from);
+                let _ = js_sys::Reflect::set(&js_error, &"status".into(), &status);
+                let _ = js_sys::Reflect::set(&js_error, &"attempts".into(), &attempts.into());
+            }
+            EngineError::FetchFailed { status, .. } => {
+                let status = status.map_or(JsValue::NULL, JsValue::from);
+                let _ = js_sys::Reflect::set(&js_error, &"status".into(), &status);
+            }
+            EngineError::DecodeFailed { source_kind, .. } => {
+                let kind = JsValue::from_str(source_kind.name());
+                let _ = js_sys::Reflect::set(&js_error, &"source_kind".into(), &kind);
+            }
+            _ => {}
+        }
+        js_error.into()
+    }
+}
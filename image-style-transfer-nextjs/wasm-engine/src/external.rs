@@ -0,0 +1,102 @@
+//! Inference delegated to a JavaScript runtime such as onnxruntime-web.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::js_filter::describe_js_error;
+use crate::pipeline::{normalize, ModelMetadata};
+
+/// A callback that receives one request object per call and may return a
+/// promise:
+///
+/// - `{ kind: "load", model, bytes: Uint8Array }` when a model is loaded;
+/// - `{ kind: "run", model, input: Float32Array, shape: [1, 3, H, W] }` for
+///   inference (`[1, H, W, 3]` for NHWC models), resolving to a
+///   `Float32Array` of the same shape;
+/// - `{ kind: "unload", model }` when the engine drops a model; the result
+///   is ignored.
+///
+/// Tensors are in the model's `layout` and always copies of engine memory. Inputs are
+/// already normalized with the model's `input_range`, `mean` and `std`, and
+/// outputs are expected in its `output_range`.
+pub struct ExternalBackend {
+    callback: js_sys::Function,
+}
+
+impl ExternalBackend {
+    pub fn new(callback: js_sys::Function) -> ExternalBackend {
+        ExternalBackend { callback }
+    }
+
+    async fn call(&self, request: &js_sys::Object) -> Result<JsValue, String> {
+        let returned = self
+            .callback
+            .call1(&JsValue::NULL, request)
+            .map_err(|e| describe_js_error(&e))?;
+        JsFuture::from(js_sys::Promise::resolve(&returned))
+            .await
+            .map_err(|e| describe_js_error(&e))
+    }
+
+    fn request(kind: &str, model: &str) -> Result<js_sys::Object, String> {
+        let request = js_sys::Object::new();
+        for (key, value) in [("kind", kind), ("model", model)] {
+            js_sys::Reflect::set(&request, &key.into(), &value.into())
+                .map_err(|e| describe_js_error(&e))?;
+        }
+        Ok(request)
+    }
+
+    pub async fn load(&self, model: &str, bytes: &[u8]) -> Result<(), String> {
+        let request = Self::request("load", model)?;
+        js_sys::Reflect::set(&request, &"bytes".into(), &js_sys::Uint8Array::from(bytes))
+            .map_err(|e| describe_js_error(&e))?;
+        self.call(&request).await.map(|_| ())
+    }
+
+    pub fn unload(&self, model: &str) {
+        if let Ok(request) = Self::request("unload", model) {
+            let _ = self.callback.call1(&JsValue::NULL, &request);
+        }
+    }
+
+    /// Runs `metadata.name` on an interleaved tensor, returning an interleaved one.
+    pub async fn run(
+        &self,
+        metadata: &ModelMetadata,
+        input_tensor: &[f32],
+    ) -> Result<Vec<f32>, String> {
+        let request = Self::request("run", &metadata.name)?;
+        let layout = metadata.layout;
+        let shape: js_sys::Array = layout
+            .image_shape(
+                3,
+                metadata.input_height as usize,
+                metadata.input_width as usize,
+            )
+            .iter()
+            .map(|&dim| JsValue::from(dim as u32))
+            .collect();
+        let input = js_sys::Float32Array::from(&layout.from_interleaved(input_tensor.to_vec())[..]);
+        js_sys::Reflect::set(&request, &"input".into(), &input)
+            .and_then(|_| js_sys::Reflect::set(&request, &"shape".into(), &shape))
+            .map_err(|e| describe_js_error(&e))?;
+
+        let output = self
+            .call(&request)
+            .await?
+            .dyn_into::<js_sys::Float32Array>()
+            .map_err(|_| "external backend did not return a Float32Array".to_string())?;
+        if output.length() as usize != input_tensor.len() {
+            return Err(format!(
+                "external backend returned {} values, expected {}",
+                output.length(),
+                input_tensor.len()
+            ));
+        }
+        let mut output = layout.interleaved(&output.to_vec());
+        normalize::model_output(&mut output, metadata);
+        Ok(output)
+    }
+}
@@ -0,0 +1,711 @@
+//! Runs a lowered [`Graph`] with WebGPU compute shaders.
+//!
+//! Each op becomes one dispatch of a WGSL kernel that computes exactly what
+//! [`Graph::run_reference`] does. Weights are uploaded once when the model is
+//! created; a run allocates one storage buffer per value, records every op in
+//! a single command buffer and reads the output back.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::js_filter::describe_js_error;
+use crate::pipeline::graph::{BinaryKind, Graph, Op, Operand, PadMode, Shape, UnaryKind};
+
+// GPUBufferUsage and GPUMapMode flags
+const MAP_READ: u32 = 0x1;
+const COPY_SRC: u32 = 0x4;
+const COPY_DST: u32 = 0x8;
+const UNIFORM: u32 = 0x40;
+const STORAGE: u32 = 0x80;
+
+const WORKGROUP_SIZE: u32 = 64;
+const MAX_WORKGROUPS_PER_DIMENSION: u32 = 65535;
+
+#[wasm_bindgen]
+extern "C" {
+    pub type Device;
+    #[wasm_bindgen(method, getter)]
+    fn queue(this: &Device) -> Queue;
+    #[wasm_bindgen(method, js_name = createBuffer)]
+    fn create_buffer(this: &Device, descriptor: &JsValue) -> Buffer;
+    #[wasm_bindgen(method, js_name = createShaderModule)]
+    fn create_shader_module(this: &Device, descriptor: &JsValue) -> JsValue;
+    #[wasm_bindgen(method, js_name = createComputePipeline)]
+    fn create_compute_pipeline(this: &Device, descriptor: &JsValue) -> Pipeline;
+    #[wasm_bindgen(method, js_name = createBindGroup)]
+    fn create_bind_group(this: &Device, descriptor: &JsValue) -> JsValue;
+    #[wasm_bindgen(method, js_name = createCommandEncoder)]
+    fn create_command_encoder(this: &Device) -> Encoder;
+    #[wasm_bindgen(method, js_name = pushErrorScope)]
+    fn push_error_scope(this: &Device, filter: &str);
+    #[wasm_bindgen(method, js_name = popErrorScope)]
+    fn pop_error_scope(this: &Device) -> js_sys::Promise;
+
+    type Queue;
+    #[wasm_bindgen(method, catch, js_name = writeBuffer)]
+    fn write_buffer(
+        this: &Queue,
+        buffer: &Buffer,
+        offset: u32,
+        data: &js_sys::Uint8Array,
+    ) -> Result<(), JsValue>;
+    #[wasm_bindgen(method)]
+    fn submit(this: &Queue, command_buffers: &js_sys::Array);
+
+    type Buffer;
+    #[wasm_bindgen(method, js_name = mapAsync)]
+    fn map_async(this: &Buffer, mode: u32) -> js_sys::Promise;
+    #[wasm_bindgen(method, catch, js_name = getMappedRange)]
+    fn get_mapped_range(this: &Buffer) -> Result<js_sys::ArrayBuffer, JsValue>;
+    #[wasm_bindgen(method)]
+    fn unmap(this: &Buffer);
+    #[wasm_bindgen(method)]
+    fn destroy(this: &Buffer);
+
+    type Pipeline;
+    #[wasm_bindgen(method, js_name = getBindGroupLayout)]
+    fn get_bind_group_layout(this: &Pipeline, index: u32) -> JsValue;
+
+    type Encoder;
+    #[wasm_bindgen(method, js_name = beginComputePass)]
+    fn begin_compute_pass(this: &Encoder) -> Pass;
+    #[wasm_bindgen(method, js_name = copyBufferToBuffer)]
+    fn copy_buffer_to_buffer(
+        this: &Encoder,
+        source: &Buffer,
+        source_offset: u32,
+        destination: &Buffer,
+        destination_offset: u32,
+        size: u32,
+    );
+    #[wasm_bindgen(method)]
+    fn finish(this: &Encoder) -> JsValue;
+
+    type Pass;
+    #[wasm_bindgen(method, js_name = setPipeline)]
+    fn set_pipeline(this: &Pass, pipeline: &Pipeline);
+    #[wasm_bindgen(method, js_name = setBindGroup)]
+    fn set_bind_group(this: &Pass, index: u32, bind_group: &JsValue);
+    #[wasm_bindgen(method, js_name = dispatchWorkgroups)]
+    fn dispatch_workgroups(this: &Pass, x: u32, y: u32);
+    #[wasm_bindgen(method)]
+    fn end(this: &Pass);
+}
+
+/// The flat index of an invocation, for dispatches that spill into a
+/// second dimension.
+const INDEX: &str = "
+fn flat_index(id: vec3<u32>, groups: vec3<u32>) -> u32 {
+    return id.y * groups.x * 64u + id.x;
+}
+";
+
+const CONV: &str = "
+struct Params {
+    in_channels: u32, in_height: u32, in_width: u32, out_channels: u32,
+    out_height: u32, out_width: u32, kernel_height: u32, kernel_width: u32,
+    stride_y: u32, stride_x: u32, pad_top: u32, pad_left: u32,
+}
+@group(0) @binding(0) var<storage, read> input: array<f32>;
+@group(0) @binding(1) var<storage, read> weights: array<f32>;
+@group(0) @binding(2) var<storage, read> bias: array<f32>;
+@group(0) @binding(3) var<storage, read_write> output: array<f32>;
+@group(0) @binding(4) var<uniform> p: Params;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = flat_index(id, groups);
+    let plane = p.out_height * p.out_width;
+    if (index >= p.out_channels * plane) {
+        return;
+    }
+    let o = index / plane;
+    let oy = (index % plane) / p.out_width;
+    let ox = index % p.out_width;
+    var sum = bias[o];
+    for (var i = 0u; i < p.in_channels; i++) {
+        for (var ky = 0u; ky < p.kernel_height; ky++) {
+            let y = i32(oy * p.stride_y + ky) - i32(p.pad_top);
+            if (y < 0 || y >= i32(p.in_height)) {
+                continue;
+            }
+            for (var kx = 0u; kx < p.kernel_width; kx++) {
+                let x = i32(ox * p.stride_x + kx) - i32(p.pad_left);
+                if (x < 0 || x >= i32(p.in_width)) {
+                    continue;
+                }
+                let w = ((o * p.in_channels + i) * p.kernel_height + ky) * p.kernel_width + kx;
+                sum += weights[w] * input[(i * p.in_height + u32(y)) * p.in_width + u32(x)];
+            }
+        }
+    }
+    output[index] = sum;
+}
+";
+
+/// One workgroup per channel: a shared-memory reduction for the mean, then
+/// another for the variance around it.
+const INSTANCE_NORM: &str = "
+struct Params { plane: u32, epsilon: f32 }
+@group(0) @binding(0) var<storage, read> input: array<f32>;
+@group(0) @binding(1) var<storage, read> scale: array<f32>;
+@group(0) @binding(2) var<storage, read> bias: array<f32>;
+@group(0) @binding(3) var<storage, read_write> output: array<f32>;
+@group(0) @binding(4) var<uniform> p: Params;
+
+var<workgroup> sums: array<f32, 64>;
+
+fn workgroup_sum(local: u32) -> f32 {
+    workgroupBarrier();
+    for (var stride = 32u; stride > 0u; stride /= 2u) {
+        if (local < stride) {
+            sums[local] += sums[local + stride];
+        }
+        workgroupBarrier();
+    }
+    let total = sums[0];
+    workgroupBarrier();
+    return total;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(workgroup_id) group: vec3<u32>, @builtin(local_invocation_index) local: u32) {
+    let c = group.x;
+    let start = c * p.plane;
+    var sum = 0.0;
+    for (var i = local; i < p.plane; i += 64u) {
+        sum += input[start + i];
+    }
+    sums[local] = sum;
+    let mean = workgroup_sum(local) / f32(p.plane);
+    var squares = 0.0;
+    for (var i = local; i < p.plane; i += 64u) {
+        let d = input[start + i] - mean;
+        squares += d * d;
+    }
+    sums[local] = squares;
+    let inv_std = inverseSqrt(workgroup_sum(local) / f32(p.plane) + p.epsilon);
+    for (var i = local; i < p.plane; i += 64u) {
+        output[start + i] = (input[start + i] - mean) * inv_std * scale[c] + bias[c];
+    }
+}
+";
+
+const UNARY: &str = "
+struct Params { len: u32, kind: u32, a: f32, b: f32 }
+@group(0) @binding(0) var<storage, read> input: array<f32>;
+@group(0) @binding(1) var<storage, read_write> output: array<f32>;
+@group(0) @binding(2) var<uniform> p: Params;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = flat_index(id, groups);
+    if (index >= p.len) {
+        return;
+    }
+    let x = input[index];
+    var y = x;
+    switch p.kind {
+        case 0u: { y = max(x, 0.0); }
+        case 1u: { y = select(x, p.a * x, x < 0.0); }
+        case 2u: { y = 1.0 / (1.0 + exp(-x)); }
+        case 3u: { y = tanh(x); }
+        default: { y = min(max(x, p.a), p.b); }
+    }
+    output[index] = y;
+}
+";
+
+/// Operands are read per `mode`: 0 a value, 1 one constant per channel,
+/// 2 the scalar in the params.
+const BINARY: &str = "
+struct Params {
+    len: u32, plane: u32, kind: u32, lhs_mode: u32,
+    rhs_mode: u32, lhs_scalar: f32, rhs_scalar: f32,
+}
+@group(0) @binding(0) var<storage, read> lhs: array<f32>;
+@group(0) @binding(1) var<storage, read> rhs: array<f32>;
+@group(0) @binding(2) var<storage, read_write> output: array<f32>;
+@group(0) @binding(3) var<uniform> p: Params;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = flat_index(id, groups);
+    if (index >= p.len) {
+        return;
+    }
+    let channel = index / p.plane;
+    let lhs_index = select(select(index, channel, p.lhs_mode == 1u), 0u, p.lhs_mode == 2u);
+    let rhs_index = select(select(index, channel, p.rhs_mode == 1u), 0u, p.rhs_mode == 2u);
+    let a = select(lhs[lhs_index], p.lhs_scalar, p.lhs_mode == 2u);
+    let b = select(rhs[rhs_index], p.rhs_scalar, p.rhs_mode == 2u);
+    var y = 0.0;
+    switch p.kind {
+        case 0u: { y = a + b; }
+        case 1u: { y = a - b; }
+        case 2u: { y = a * b; }
+        default: { y = a / b; }
+    }
+    output[index] = y;
+}
+";
+
+const UPSAMPLE: &str = "
+struct Params { channels: u32, in_height: u32, in_width: u32, scale_y: u32, scale_x: u32 }
+@group(0) @binding(0) var<storage, read> input: array<f32>;
+@group(0) @binding(1) var<storage, read_write> output: array<f32>;
+@group(0) @binding(2) var<uniform> p: Params;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = flat_index(id, groups);
+    let out_width = p.in_width * p.scale_x;
+    let plane = p.in_height * p.scale_y * out_width;
+    if (index >= p.channels * plane) {
+        return;
+    }
+    let c = index / plane;
+    let y = (index % plane) / out_width / p.scale_y;
+    let x = index % out_width / p.scale_x;
+    output[index] = input[(c * p.in_height + y) * p.in_width + x];
+}
+";
+
+/// `mode` 0 is constant, 1 reflect and 2 edge padding.
+const PAD: &str = "
+struct Params {
+    channels: u32, in_height: u32, in_width: u32, out_height: u32,
+    out_width: u32, top: u32, left: u32, mode: u32, value: f32,
+}
+@group(0) @binding(0) var<storage, read> input: array<f32>;
+@group(0) @binding(1) var<storage, read_write> output: array<f32>;
+@group(0) @binding(2) var<uniform> p: Params;
+
+fn source_index(position: i32, length: i32) -> i32 {
+    if (p.mode == 1u) {
+        let reflected = abs(position);
+        return select(reflected, 2 * (length - 1) - reflected, reflected >= length);
+    }
+    return clamp(position, 0, length - 1);
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = flat_index(id, groups);
+    let plane = p.out_height * p.out_width;
+    if (index >= p.channels * plane) {
+        return;
+    }
+    let c = index / plane;
+    let y = i32((index % plane) / p.out_width) - i32(p.top);
+    let x = i32(index % p.out_width) - i32(p.left);
+    let h = i32(p.in_height);
+    let w = i32(p.in_width);
+    if (p.mode == 0u && (y < 0 || y >= h || x < 0 || x >= w)) {
+        output[index] = p.value;
+        return;
+    }
+    output[index] = input[(c * p.in_height + u32(source_index(y, h))) * p.in_width + u32(source_index(x, w))];
+}
+";
+
+/// A parameter word as the shaders see it.
+#[derive(Clone, Copy)]
+enum Word {
+    U(usize),
+    F(f32),
+}
+
+impl Word {
+    fn bytes(self) -> [u8; 4] {
+        match self {
+            Word::U(value) => (value as u32).to_le_bytes(),
+            Word::F(value) => value.to_le_bytes(),
+        }
+    }
+}
+
+struct Kernels {
+    conv: Pipeline,
+    instance_norm: Pipeline,
+    unary: Pipeline,
+    binary: Pipeline,
+    upsample: Pipeline,
+    pad: Pipeline,
+}
+
+/// Constants an op reads besides its input values.
+enum OpBuffers {
+    None,
+    /// Weights and bias, or scale and bias.
+    Pair(Buffer, Buffer),
+    /// Per-channel constants of either side of a binary op.
+    Binary(Option<Buffer>, Option<Buffer>),
+}
+
+/// A graph whose weights live on a WebGPU device.
+pub struct GpuModel {
+    device: Device,
+    graph: Graph,
+    kernels: Kernels,
+    constants: Vec<OpBuffers>,
+    /// Bound in place of operands the kernel doesn't read.
+    placeholder: Buffer,
+}
+
+fn object(entries: &[(&str, &JsValue)]) -> JsValue {
+    let object = js_sys::Object::new();
+    for (key, value) in entries {
+        let _ = js_sys::Reflect::set(&object, &(*key).into(), value);
+    }
+    object.into()
+}
+
+fn workgroups(invocations: usize) -> (u32, u32) {
+    let groups = (invocations as u32).div_ceil(WORKGROUP_SIZE).max(1);
+    if groups <= MAX_WORKGROUPS_PER_DIMENSION {
+        (groups, 1)
+    } else {
+        (
+            MAX_WORKGROUPS_PER_DIMENSION,
+            groups.div_ceil(MAX_WORKGROUPS_PER_DIMENSION),
+        )
+    }
+}
+
+/// Resolves to the message of the first validation error since the
+/// matching `push_error_scope`.
+async fn pop_errors(device: &Device) -> Result<(), String> {
+    let error = JsFuture::from(device.pop_error_scope())
+        .await
+        .map_err(|e| describe_js_error(&e))?;
+    if error.is_null() || error.is_undefined() {
+        return Ok(());
+    }
+    Err(js_sys::Reflect::get(&error, &"message".into())
+        .ok()
+        .and_then(|message| message.as_string())
+        .unwrap_or_else(|| "WebGPU validation error".to_string()))
+}
+
+impl GpuModel {
+    /// Compiles the kernels and uploads `graph`'s constants to `device`.
+    pub async fn new(device: &JsValue, graph: Graph) -> Result<GpuModel, String> {
+        let device: Device = device.clone().unchecked_into();
+        device.push_error_scope("validation");
+        let pipeline = |source: &str| {
+            let code = JsValue::from_str(&format!("{}{}", INDEX, source));
+            let module = device.create_shader_module(&object(&[("code", &code)]));
+            let stage = object(&[("module", &module), ("entryPoint", &"main".into())]);
+            device.create_compute_pipeline(&object(&[
+                ("layout", &"auto".into()),
+                ("compute", &stage),
+            ]))
+        };
+        let kernels = Kernels {
+            conv: pipeline(CONV),
+            instance_norm: pipeline(INSTANCE_NORM),
+            unary: pipeline(UNARY),
+            binary: pipeline(BINARY),
+            upsample: pipeline(UPSAMPLE),
+            pad: pipeline(PAD),
+        };
+        let mut model = GpuModel {
+            placeholder: Self::buffer(&device, 4, STORAGE),
+            device,
+            graph,
+            kernels,
+            constants: Vec::new(),
+        };
+        let mut constants = Vec::with_capacity(model.graph.ops.len());
+        for op in &model.graph.ops {
+            let channel = |operand: &Operand| match operand {
+                Operand::Channel(values) => model.upload(values).map(Some),
+                _ => Ok(None),
+            };
+            constants.push(match op {
+                Op::Conv { weights, bias, .. } => {
+                    OpBuffers::Pair(model.upload(weights)?, model.upload(bias)?)
+                }
+                Op::InstanceNorm { scale, bias, .. } => {
+                    OpBuffers::Pair(model.upload(scale)?, model.upload(bias)?)
+                }
+                Op::Binary { lhs, rhs, .. } => OpBuffers::Binary(channel(lhs)?, channel(rhs)?),
+                _ => OpBuffers::None,
+            });
+        }
+        model.constants = constants;
+        pop_errors(&model.device).await?;
+        Ok(model)
+    }
+
+    fn buffer(device: &Device, bytes: usize, usage: u32) -> Buffer {
+        // Zero-sized bindings aren't allowed
+        let size = JsValue::from((bytes.max(4) as u32).next_multiple_of(4));
+        device.create_buffer(&object(&[
+            ("size", &size),
+            ("usage", &JsValue::from(usage)),
+        ]))
+    }
+
+    fn write(&self, buffer: &Buffer, bytes: &[u8]) -> Result<(), String> {
+        let data = js_sys::Uint8Array::from(bytes);
+        self.device
+            .queue()
+            .write_buffer(buffer, 0, &data)
+            .map_err(|e| describe_js_error(&e))
+    }
+
+    fn upload(&self, values: &[f32]) -> Result<Buffer, String> {
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let buffer = Self::buffer(&self.device, bytes.len(), STORAGE | COPY_DST);
+        self.write(&buffer, &bytes)?;
+        Ok(buffer)
+    }
+
+    fn params(&self, words: &[Word]) -> Result<Buffer, String> {
+        // Uniform structs are padded to 16 bytes
+        let mut bytes: Vec<u8> = words.iter().flat_map(|word| word.bytes()).collect();
+        bytes.resize(bytes.len().next_multiple_of(16), 0);
+        let buffer = Self::buffer(&self.device, bytes.len(), UNIFORM | COPY_DST);
+        self.write(&buffer, &bytes)?;
+        Ok(buffer)
+    }
+
+    fn bind(&self, pipeline: &Pipeline, buffers: &[&Buffer]) -> JsValue {
+        let entries = js_sys::Array::new();
+        for (binding, buffer) in buffers.iter().enumerate() {
+            let resource = object(&[("buffer", buffer.as_ref())]);
+            entries.push(&object(&[
+                ("binding", &JsValue::from(binding as u32)),
+                ("resource", &resource),
+            ]));
+        }
+        self.device.create_bind_group(&object(&[
+            ("layout", &pipeline.get_bind_group_layout(0)),
+            ("entries", &entries),
+        ]))
+    }
+
+    /// Runs the graph on a planar input of `shape`, returning the planar
+    /// output and its shape.
+    pub async fn run(&self, input: &[f32], shape: Shape) -> Result<(Vec<f32>, Shape), String> {
+        let shapes = self.graph.shapes(shape)?;
+        if input.len() != shape.iter().product::<usize>() {
+            return Err(format!("{} values for a {:?} input", input.len(), shape));
+        }
+        self.device.push_error_scope("validation");
+        let mut scratch = Vec::new();
+        let result = self.record_and_read(input, &shapes, &mut scratch).await;
+        let validation = pop_errors(&self.device).await;
+        for buffer in scratch {
+            buffer.destroy();
+        }
+        let output = result?;
+        validation?;
+        Ok((output, shapes[self.graph.output]))
+    }
+
+    async fn record_and_read(
+        &self,
+        input: &[f32],
+        shapes: &[Shape],
+        scratch: &mut Vec<Buffer>,
+    ) -> Result<Vec<f32>, String> {
+        let len = |id: usize| shapes[id].iter().product::<usize>();
+        let input_buffer = self.upload(input)?;
+        scratch.push(input_buffer);
+        for id in 1..shapes.len() {
+            scratch.push(Self::buffer(&self.device, len(id) * 4, STORAGE | COPY_SRC));
+        }
+        let encoder = self.device.create_command_encoder();
+        let pass = encoder.begin_compute_pass();
+        let mut params = Vec::with_capacity(self.graph.ops.len());
+        let ops = self.graph.ops.iter().zip(&self.constants);
+        for (index, (op, constants)) in ops.enumerate() {
+            let encoded = self.encode(op, constants, shapes, scratch, index + 1);
+            let (pipeline, bind_group, invocations, op_params) = match encoded {
+                Ok(encoded) => encoded,
+                Err(e) => {
+                    pass.end();
+                    scratch.extend(params);
+                    return Err(e);
+                }
+            };
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group);
+            let (x, y) = workgroups(invocations);
+            pass.dispatch_workgroups(x, y);
+            params.push(op_params);
+        }
+        pass.end();
+        scratch.extend(params);
+
+        let bytes = (len(self.graph.output) * 4) as u32;
+        let readback = Self::buffer(&self.device, bytes as usize, MAP_READ | COPY_DST);
+        encoder.copy_buffer_to_buffer(&scratch[self.graph.output], 0, &readback, 0, bytes);
+        self.device
+            .queue()
+            .submit(&js_sys::Array::of1(&encoder.finish()));
+        let mapped = JsFuture::from(readback.map_async(MAP_READ)).await;
+        let output = mapped
+            .and_then(|_| readback.get_mapped_range())
+            .map(|range| js_sys::Float32Array::new(&range).to_vec())
+            .map_err(|e| describe_js_error(&e));
+        readback.unmap();
+        readback.destroy();
+        output
+    }
+
+    /// The kernel, bind group, invocation count and params buffer of `op`.
+    fn encode<'a>(
+        &'a self,
+        op: &Op,
+        constants: &'a OpBuffers,
+        shapes: &[Shape],
+        values: &'a [Buffer],
+        id: usize,
+    ) -> Result<(&'a Pipeline, JsValue, usize, Buffer), String> {
+        use Word::{F, U};
+        let [oc, oh, ow] = shapes[id];
+        let output = &values[id];
+        let total = oc * oh * ow;
+        Ok(match (op, constants) {
+            (
+                Op::Conv {
+                    input,
+                    kernel: (kh, kw),
+                    stride: (sy, sx),
+                    pads,
+                    ..
+                },
+                OpBuffers::Pair(weights, bias),
+            ) => {
+                let [ic, ih, iw] = shapes[*input];
+                let params = self.params(&[
+                    U(ic),
+                    U(ih),
+                    U(iw),
+                    U(oc),
+                    U(oh),
+                    U(ow),
+                    U(*kh),
+                    U(*kw),
+                    U(*sy),
+                    U(*sx),
+                    U(pads[0]),
+                    U(pads[1]),
+                ])?;
+                let pipeline = &self.kernels.conv;
+                let bind_group =
+                    self.bind(pipeline, &[&values[*input], weights, bias, output, &params]);
+                (pipeline, bind_group, total, params)
+            }
+            (Op::InstanceNorm { input, epsilon, .. }, OpBuffers::Pair(scale, bias)) => {
+                let params = self.params(&[U(oh * ow), F(*epsilon)])?;
+                let pipeline = &self.kernels.instance_norm;
+                let bind_group =
+                    self.bind(pipeline, &[&values[*input], scale, bias, output, &params]);
+                // One workgroup per channel
+                (pipeline, bind_group, oc * WORKGROUP_SIZE as usize, params)
+            }
+            (Op::Unary { input, kind }, _) => {
+                let (code, a, b) = match *kind {
+                    UnaryKind::Relu => (0, 0.0, 0.0),
+                    UnaryKind::LeakyRelu(alpha) => (1, alpha, 0.0),
+                    UnaryKind::Sigmoid => (2, 0.0, 0.0),
+                    UnaryKind::Tanh => (3, 0.0, 0.0),
+                    UnaryKind::Clip(min, max) => (4, min, max),
+                };
+                let params = self.params(&[U(total), U(code), F(a), F(b)])?;
+                let pipeline = &self.kernels.unary;
+                let bind_group = self.bind(pipeline, &[&values[*input], output, &params]);
+                (pipeline, bind_group, total, params)
+            }
+            (Op::Binary { lhs, rhs, kind }, OpBuffers::Binary(lhs_channel, rhs_channel)) => {
+                let side = |operand: &Operand, channel: &'a Option<Buffer>| match operand {
+                    Operand::Value(id) => (&values[*id], 0, 0.0),
+                    Operand::Channel(_) => (channel.as_ref().unwrap_or(&self.placeholder), 1, 0.0),
+                    Operand::Scalar(value) => (&self.placeholder, 2, *value),
+                };
+                let (lhs_buffer, lhs_mode, lhs_scalar) = side(lhs, lhs_channel);
+                let (rhs_buffer, rhs_mode, rhs_scalar) = side(rhs, rhs_channel);
+                let code = match kind {
+                    BinaryKind::Add => 0,
+                    BinaryKind::Sub => 1,
+                    BinaryKind::Mul => 2,
+                    BinaryKind::Div => 3,
+                };
+                let params = self.params(&[
+                    U(total),
+                    U(oh * ow),
+                    U(code),
+                    U(lhs_mode),
+                    U(rhs_mode),
+                    F(lhs_scalar),
+                    F(rhs_scalar),
+                ])?;
+                let pipeline = &self.kernels.binary;
+                let bind_group = self.bind(pipeline, &[lhs_buffer, rhs_buffer, output, &params]);
+                (pipeline, bind_group, total, params)
+            }
+            (
+                Op::Upsample {
+                    input,
+                    scale: (sy, sx),
+                },
+                _,
+            ) => {
+                let [c, ih, iw] = shapes[*input];
+                let params = self.params(&[U(c), U(ih), U(iw), U(*sy), U(*sx)])?;
+                let pipeline = &self.kernels.upsample;
+                let bind_group = self.bind(pipeline, &[&values[*input], output, &params]);
+                (pipeline, bind_group, total, params)
+            }
+            (Op::Pad { input, pads, mode }, _) => {
+                let [c, ih, iw] = shapes[*input];
+                let (code, value) = match *mode {
+                    PadMode::Constant(value) => (0, value),
+                    PadMode::Reflect => (1, 0.0),
+                    PadMode::Edge => (2, 0.0),
+                };
+                let params = self.params(&[
+                    U(c),
+                    U(ih),
+                    U(iw),
+                    U(oh),
+                    U(ow),
+                    U(pads[0]),
+                    U(pads[1]),
+                    U(code),
+                    F(value),
+                ])?;
+                let pipeline = &self.kernels.pad;
+                let bind_group = self.bind(pipeline, &[&values[*input], output, &params]);
+                (pipeline, bind_group, total, params)
+            }
+            _ => return Err("op constants weren't uploaded".to_string()),
+        })
+    }
+}
+
+impl Drop for GpuModel {
+    fn drop(&mut self) {
+        for constants in &self.constants {
+            match constants {
+                OpBuffers::Pair(a, b) => {
+                    a.destroy();
+                    b.destroy();
+                }
+                OpBuffers::Binary(a, b) => {
+                    for buffer in [a, b].into_iter().flatten() {
+                        buffer.destroy();
+                    }
+                }
+                OpBuffers::None => {}
+            }
+        }
+        self.placeholder.destroy();
+    }
+}
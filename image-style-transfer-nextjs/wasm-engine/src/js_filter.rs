@@ -0,0 +1,51 @@
+//! Simulated styles implemented by JavaScript callbacks.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::error::EngineError;
+
+/// Calls `callback(tensor, width, height)` and returns the filtered tensor.
+///
+/// The callback receives a fresh `Float32Array` copy, so nothing it does can
+/// write back into engine memory.
+pub fn run_js_filter(
+    name: &str,
+    callback: &js_sys::Function,
+    input_tensor: &[f32],
+    width: u32,
+    height: u32,
+) -> Result<Vec<f32>, EngineError> {
+    let input = js_sys::Float32Array::from(input_tensor);
+    let result = callback
+        .call3(&JsValue::NULL, &input, &width.into(), &height.into())
+        .map_err(|e| {
+            EngineError::InferenceError(format!(
+                "JS filter '{}' threw: {}",
+                name,
+                describe_js_error(&e)
+            ))
+        })?;
+
+    let output = result.dyn_into::<js_sys::Float32Array>().map_err(|_| {
+        EngineError::InferenceError(format!("JS filter '{}' did not return a Float32Array", name))
+    })?;
+
+    if output.length() as usize != input_tensor.len() {
+        return Err(EngineError::InferenceError(format!(
+            "JS filter '{}' returned {} values, expected {}",
+            name,
+            output.length(),
+            input_tensor.len()
+        )));
+    }
+
+    Ok(output.to_vec())
+}
+
+pub(crate) fn describe_js_error(error: &JsValue) -> String {
+    if let Some(error) = error.dyn_ref::<js_sys::Error>() {
+        return String::from(error.message());
+    }
+    error.as_string().unwrap_or_else(|| format!("{:?}", error))
+}
@@ -1,45 +1,203 @@
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{HtmlCanvasElement, CanvasRenderingContext2d, ImageData};
-use js_sys::{Uint8Array};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU8, Ordering};
 
-// ONNX inference imports
-use tract_onnx::prelude::*;
+mod capabilities;
+pub mod config;
+mod download;
+mod encode;
+mod error;
+mod gpu;
+#[cfg(feature = "backend-ort-web")]
+mod external;
+mod js_filter;
+pub mod memory;
+mod model_store;
+pub mod options;
+pub mod pipeline;
+mod prefetch;
+mod report;
+mod result;
+mod scope;
+mod source;
+#[cfg(feature = "threads")]
+mod threads;
+mod usage;
+mod video;
 
+pub use capabilities::Capabilities;
+pub use config::{EngineConfig, LogLevel};
+pub use error::{EngineError, ImageSourceKind};
+pub use options::{OutputFormat, ProcessOptions};
+pub use pipeline::{ModelKind, ModelMetadata};
+pub use result::{Backend, BackendBenchmark, BenchmarkReport, ModelRuntime, ModelState, ProcessResult, SequenceFrame, StreamSummary, StrengthVariant, Timings, WebGpuState};
+pub use usage::ModelUsage;
+#[cfg(feature = "threads")]
+pub use threads::{init_thread_pool, use_thread_pool};
+use pipeline::budget::{self, ResidentModel, UsageClock};
+use pipeline::cache::{CacheKey, CachedResult, ResultCache};
+use pipeline::prefetch::PrefetchPriority;
+use pipeline::progress::Stage;
+use pipeline::resume::PartialDownload;
+use pipeline::{registry, ColorSpace, InferencePath, TractPlan};
+use encode::EncoderSupport;
+use source::ElementSource;
+
+// std's allocator (dlmalloc on wasm) unless size-optimized builds opt into
+// wee_alloc. Either is counted for get_stats.
+#[cfg(all(feature = "wee-alloc", feature = "threads"))]
+compile_error!("wee_alloc is single-threaded; the wee-alloc and threads features can't be combined");
+#[cfg(feature = "wee-alloc")]
+#[global_allocator]
+static ALLOC: memory::Counting<wee_alloc::WeeAlloc> = memory::Counting::new(wee_alloc::WeeAlloc::INIT);
+#[cfg(not(feature = "wee-alloc"))]
 #[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+static ALLOC: memory::Counting<std::alloc::System> = memory::Counting::new(std::alloc::System);
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
+// Native builds (cargo test, benchmarks) have no console to import
+#[cfg(not(target_arch = "wasm32"))]
+fn log(s: &str) {
+    eprintln!("{}", s);
+}
+
+// Mirrors the engine's configured log level so the macro can check it cheaply
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+fn log_enabled(level: LogLevel) -> bool {
+    level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
 macro_rules! console_log {
-    ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
+    ($($t:tt)*) => (if log_enabled(LogLevel::Info) { log(&format_args!($($t)*).to_string()) })
+}
+
+macro_rules! console_warn {
+    ($($t:tt)*) => (if log_enabled(LogLevel::Warn) { log(&format_args!($($t)*).to_string()) })
+}
+
+/// A downloaded model. `bytes` is dropped once tract has a plan, unless the
+/// config retains them; `byte_len` stands for what the runtime holds either way.
+struct LoadedModel {
+    bytes: Option<Vec<u8>>,
+    byte_len: usize,
+    runtime: ModelRuntime,
+}
+
+impl LoadedModel {
+    /// Retained bytes the model runs without. The simulated fallback's bytes
+    /// are all there is of it, and already counted in `byte_len`.
+    fn droppable_bytes(&self) -> usize {
+        match (&self.bytes, self.runtime) {
+            (Some(bytes), runtime) if runtime != ModelRuntime::Simulated => bytes.len(),
+            _ => 0,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct ModelMetadata {
-    pub name: String,
-    pub size_mb: f32,
-    pub input_width: u32,
-    pub input_height: u32,
-    pub input_channels: u32,
-    pub model_url: String,
-    pub description: String,
+/// xxHash64 of the golden-harness output for `style_name` over the synthetic
+/// `input` (`"gradient"`, `"checkerboard"` or `"noise"`), as hex. Lets a
+/// deployed build be checked against the hashes the tests record.
+#[doc(hidden)]
+#[wasm_bindgen]
+pub fn debug_process_rgba_hash(style_name: &str, input: &str) -> Result<String, JsValue> {
+    let input = pipeline::golden::GoldenInput::parse(input)
+        .ok_or_else(|| EngineError::InvalidInput(format!("Unknown golden input '{}'", input)))?;
+    let hash = pipeline::golden::golden_hash(style_name, input).map_err(EngineError::InvalidInput)?;
+    Ok(format!("{:016x}", hash))
 }
 
+/// Runs on the main thread or in a Web Worker. Without a document, images
+/// are decoded and encoded in Rust (PNG and JPEG only), `process_element`
+/// takes only `ImageBitmap`s and strength grids are unlabeled.
 #[wasm_bindgen]
 pub struct StyleTransferEngine {
-    loaded_models: HashMap<String, Vec<u8>>,
+    loaded_models: HashMap<String, LoadedModel>,
     model_registry: Vec<ModelMetadata>,
     webgpu_available: bool,
     webgpu_adapter: Option<js_sys::Object>,
     webgpu_device: Option<js_sys::Object>,
-    tract_models: HashMap<String, SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>,
+    webgpu_loss: Rc<RefCell<DeviceLoss>>,
+    tract_models: HashMap<String, TractPlan>,
+    // Decoders of loaded AdaIN models; their encoders are in tract_models
+    adain_decoders: HashMap<String, TractPlan>,
+    // Optimized plans of deferred_optimization, filled in by idle-time tasks
+    deferred_plans: HashMap<String, Rc<RefCell<Option<TractPlan>>>>,
+    // Models lowered onto the WebGPU device; their tract plans stay as the CPU fallback
+    gpu_models: HashMap<String, gpu::GpuModel>,
+    // Style statistics of the process_with_style_image call in progress
+    adain_style: Option<AdainStyle>,
+    // The `backend` option of the processing call in progress
+    call_backend: Option<config::PreferredBackend>,
+    // Strength from the people process_with_segmentation found, on top of options.mask
+    call_mask: Option<pipeline::StrengthMap>,
+    // The second style of the process_image_dual call in progress
+    call_mix: Option<StyleMix>,
+    // Inference steps of the processing call in progress, for `on_progress`
+    progress: pipeline::progress::Progress,
+    simulation_seed: u64,
+    js_filters: HashMap<String, js_sys::Function>,
+    // LUTs registered with register_lut, shared with the results using them
+    luts: HashMap<String, Rc<pipeline::lut::Lut3d>>,
+    config: EngineConfig,
+    result_cache: ResultCache,
+    // 0 means no budget
+    memory_budget_bytes: usize,
+    // 0 means no cap
+    max_resident_models: usize,
+    eviction_stats: budget::EvictionStats,
+    usage_clock: UsageClock,
+    // Never evicted, even when it is the least recently used
+    in_flight_model: Option<String>,
+    disposed: bool,
+    // Shared with the device loss handler, which fires outside any call
+    event_listener: Rc<RefCell<Option<js_sys::Function>>>,
+    reporter: report::Reporter,
+    // Detected by initialize(), or on first encode
+    encoder_support: Option<EncoderSupport>,
+    // Detected by initialize() and refresh_capabilities()
+    capabilities: Option<Capabilities>,
+    // Interrupted downloads the server lets us resume
+    partial_downloads: HashMap<String, PartialDownload>,
+    // Opened on first use of the persistent model cache
+    model_store: Option<model_store::ModelStore>,
+    // Models downloading, parsing or whose last load failed; the others are
+    // ready when loaded and not loaded otherwise
+    load_states: HashMap<String, ModelState>,
+    // Progress callback and abort signal of the load_model call in progress
+    download_watch: download::DownloadWatch,
+    // Shared with the prefetch tasks, which run outside any call
+    prefetcher: prefetch::SharedPrefetcher,
+    // Reused for caller-provided tensors so each call doesn't allocate
+    input_pool: [Vec<f32>; 2],
+    // Blend and pixel buffers by resolution, shared by results and their strength variants
+    buffer_pool: pipeline::pool::BufferPool,
+    // Ordered so get_stats output is stable
+    model_usage: BTreeMap<String, ModelUsage>,
+    // Warmup inference time per loaded model, for tile planning
+    tile_costs: HashMap<String, f64>,
+    // The sequence begun with begin_sequence, if any
+    sequence: Option<Sequence>,
+    // Of the last result finish() produced, for get_last_timings
+    last_timings: Option<Timings>,
+    #[cfg(feature = "backend-ort-web")]
+    external_backend: Option<external::ExternalBackend>,
+}
+
+impl Default for StyleTransferEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -47,72 +205,77 @@ impl StyleTransferEngine {
     #[wasm_bindgen(constructor)]
     pub fn new() -> StyleTransferEngine {
         console_log!("Initializing real Style Transfer Engine with ONNX support");
+        // Native test runs keep the default hook
+        #[cfg(target_arch = "wasm32")]
+        report::install_panic_hook();
         
-        let model_registry = vec![
-            ModelMetadata {
-                name: "van_gogh_starry_night".to_string(),
-                size_mb: 2.4,
-                input_width: 256,
-                input_height: 256,
-                input_channels: 3,
-                model_url: "/models/van_gogh_starry_night.onnx".to_string(),
-                description: "Neural style transfer trained on Van Gogh's masterpiece".to_string(),
-            },
-            ModelMetadata {
-                name: "picasso_cubist".to_string(),
-                size_mb: 2.1,
-                input_width: 256,
-                input_height: 256,
-                input_channels: 3,
-                model_url: "/models/picasso_cubist.onnx".to_string(),
-                description: "Geometric abstraction in revolutionary cubist style".to_string(),
-            },
-            ModelMetadata {
-                name: "cyberpunk_neon".to_string(),
-                size_mb: 2.8,
-                input_width: 256,
-                input_height: 256,
-                input_channels: 3,
-                model_url: "/models/cyberpunk_neon.onnx".to_string(),
-                description: "Futuristic digital enhancement with neon aesthetics".to_string(),
-            },
-            ModelMetadata {
-                name: "monet_water_lilies".to_string(),
-                size_mb: 2.3,
-                input_width: 256,
-                input_height: 256,
-                input_channels: 3,
-                model_url: "/models/monet_water_lilies.onnx".to_string(),
-                description: "Impressionist technique capturing light and atmosphere".to_string(),
-            },
-            ModelMetadata {
-                name: "anime_studio_ghibli".to_string(),
-                size_mb: 2.6,
-                input_width: 256,
-                input_height: 256,
-                input_channels: 3,
-                model_url: "/models/anime_studio_ghibli.onnx".to_string(),
-                description: "Studio Ghibli inspired animation transformation".to_string(),
-            },
-        ];
-        
+        let model_registry = pipeline::default_registry();
+
         StyleTransferEngine {
             loaded_models: HashMap::new(),
             model_registry,
             webgpu_available: false,
             webgpu_adapter: None,
             webgpu_device: None,
+            webgpu_loss: Rc::default(),
             tract_models: HashMap::new(),
+            adain_decoders: HashMap::new(),
+            deferred_plans: HashMap::new(),
+            gpu_models: HashMap::new(),
+            adain_style: None,
+            call_backend: None,
+            call_mask: None,
+            call_mix: None,
+            progress: Default::default(),
+            simulation_seed: pipeline::DEFAULT_SIMULATION_SEED,
+            js_filters: HashMap::new(),
+            luts: HashMap::new(),
+            config: EngineConfig::default(),
+            result_cache: ResultCache::default(),
+            memory_budget_bytes: 0,
+            max_resident_models: 0,
+            eviction_stats: Default::default(),
+            usage_clock: UsageClock::default(),
+            in_flight_model: None,
+            disposed: false,
+            event_listener: Rc::default(),
+            reporter: report::Reporter::default(),
+            encoder_support: None,
+            capabilities: None,
+            partial_downloads: HashMap::new(),
+            model_store: None,
+            load_states: HashMap::new(),
+            download_watch: Default::default(),
+            prefetcher: Rc::default(),
+            input_pool: Default::default(),
+            buffer_pool: Default::default(),
+            model_usage: BTreeMap::new(),
+            tile_costs: HashMap::new(),
+            sequence: None,
+            last_timings: None,
+            #[cfg(feature = "backend-ort-web")]
+            external_backend: None,
         }
     }
 
     #[wasm_bindgen]
     pub async fn initialize(&mut self) -> Result<(), JsValue> {
+        self.check_live()?;
         console_log!("Initializing WebGPU and checking browser support");
-        
-        // Check WebGPU availability
-        let window = web_sys::window().ok_or("No window object")?;
-        let navigator = window.navigator();
+
+        if let Ok(encoder_support) = EncoderSupport::detect() {
+            console_log!("Optional encoders - webp: {}, avif: {}", encoder_support.webp, encoder_support.avif);
+            self.encoder_support = Some(encoder_support);
+        }
+
+        if self.config.preferred_backend == config::PreferredBackend::Cpu {
+            console_log!("CPU backend preferred - skipping WebGPU");
+            self.detect_capabilities();
+            return Ok(());
+        }
+
+        // Check WebGPU availability; workers have a navigator too
+        let navigator = js_sys::Reflect::get(&js_sys::global(), &"navigator".into())?;
         
         if let Ok(gpu) = js_sys::Reflect::get(&navigator, &"gpu".into()) {
             if !gpu.is_undefined() {
@@ -129,13 +292,57 @@ impl StyleTransferEngine {
             }
         }
         
+        self.detect_capabilities();
         Ok(())
     }
 
+    /// What this browser and build support, as detected by `initialize` or
+    /// the last `refresh_capabilities`: WebGPU (with the adapter name when
+    /// the browser exposes it), WebNN, SIMD, threads, OffscreenCanvas, the
+    /// output encoders and the largest input worth processing given
+    /// `navigator.deviceMemory`. The WebGPU fields always reflect the current
+    /// device. Before `initialize` the report is detected on the spot.
+    #[wasm_bindgen]
+    pub fn get_capabilities(&self) -> Result<JsValue, JsValue> {
+        let capabilities = match &self.capabilities {
+            Some(cached) => Capabilities {
+                webgpu: self.is_webgpu_ready(),
+                webgpu_adapter: self.ready_adapter().and_then(capabilities::adapter_name),
+                ..cached.clone()
+            },
+            None => {
+                let support = match self.encoder_support {
+                    Some(support) => support,
+                    None => EncoderSupport::detect()?,
+                };
+                Capabilities::detect(support, None)
+            }
+        };
+        to_js(&capabilities)
+    }
+
+    /// Probes the encoders again and rebuilds the capability report.
+    #[wasm_bindgen]
+    pub fn refresh_capabilities(&mut self) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        self.encoder_support = Some(EncoderSupport::detect()?);
+        self.detect_capabilities();
+        to_js(&self.capabilities)
+    }
+
+    fn detect_capabilities(&mut self) {
+        let support = self.encoder_support.unwrap_or_default();
+        self.capabilities = Some(Capabilities::detect(support, self.ready_adapter()));
+    }
+
+    fn ready_adapter(&self) -> Option<&JsValue> {
+        self.webgpu_adapter.as_deref().filter(|_| self.is_webgpu_ready())
+    }
+
     async fn initialize_webgpu(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         // Check if WebGPU is available
-        let window = web_sys::window().ok_or("No window object")?;
-        let navigator = window.navigator();
+        let navigator = js_sys::Reflect::get(&js_sys::global(), &"navigator".into())
+            .map_err(|_| "No navigator")?;
         
         // Use proper web-sys API for WebGPU
         let gpu = js_sys::Reflect::get(&navigator, &"gpu".into())
@@ -146,16 +353,15 @@ impl StyleTransferEngine {
         }
         
         // Request adapter using proper Promise handling
-        let adapter_promise = js_sys::Reflect::get(&gpu, &"requestAdapter".into())
-            .map_err(|_| "Failed to get requestAdapter")?;
-        
-        if !adapter_promise.is_object() {
-            return Err("requestAdapter is not a function".into());
-        }
+        let request_adapter = js_sys::Reflect::get(&gpu, &"requestAdapter".into())
+            .map_err(|_| "Failed to get requestAdapter")?
+            .dyn_into::<js_sys::Function>()
+            .map_err(|_| "requestAdapter is not a function")?;
         
         // Convert to a Rust Future
-        let adapter_promise_js = adapter_promise.dyn_into::<js_sys::Promise>()
-            .map_err(|_| "Failed to convert to Promise")?;
+        let adapter_promise_js = request_adapter.call0(&gpu)
+            .and_then(|promise| promise.dyn_into::<js_sys::Promise>())
+            .map_err(|_| "requestAdapter did not return a Promise")?;
         let adapter_future = wasm_bindgen_futures::JsFuture::from(adapter_promise_js);
         let adapter_result = adapter_future.await
             .map_err(|_| "Failed to get adapter")?;
@@ -170,15 +376,14 @@ impl StyleTransferEngine {
         self.webgpu_adapter = Some(adapter_result.clone().into());
         
         // Request device
-        let device_promise = js_sys::Reflect::get(&adapter_result, &"requestDevice".into())
-            .map_err(|_| "Failed to get requestDevice")?;
+        let request_device = js_sys::Reflect::get(&adapter_result, &"requestDevice".into())
+            .map_err(|_| "Failed to get requestDevice")?
+            .dyn_into::<js_sys::Function>()
+            .map_err(|_| "requestDevice is not a function")?;
         
-        if !device_promise.is_object() {
-            return Err("requestDevice is not a function".into());
-        }
-        
-        let device_promise_js = device_promise.dyn_into::<js_sys::Promise>()
-            .map_err(|_| "Failed to convert to Promise")?;
+        let device_promise_js = request_device.call0(&adapter_result)
+            .and_then(|promise| promise.dyn_into::<js_sys::Promise>())
+            .map_err(|_| "requestDevice did not return a Promise")?;
         let device_future = wasm_bindgen_futures::JsFuture::from(device_promise_js);
         let device_result = device_future.await
             .map_err(|_| "Failed to get device")?;
@@ -188,6 +393,7 @@ impl StyleTransferEngine {
         }
         
         // Store the device
+        self.watch_device_loss(&device_result);
         self.webgpu_device = Some(device_result.into());
         
         console_log!("WebGPU device obtained and stored successfully");
@@ -199,14 +405,277 @@ impl StyleTransferEngine {
         serde_wasm_bindgen::to_value(&self.model_registry).unwrap()
     }
 
+    #[wasm_bindgen]
+    pub fn model_count(&self) -> usize {
+        self.model_registry.len()
+    }
+
+    #[wasm_bindgen]
+    pub fn style_names(&self) -> Vec<String> {
+        self.model_registry.iter().map(|m| m.name.clone()).collect()
+    }
+
+    /// Seeds any stochastic element of the simulated fallback styles.
+    #[wasm_bindgen]
+    pub fn set_simulation_seed(&mut self, seed: u64) {
+        self.simulation_seed = seed;
+    }
+
+    /// Registers a style implemented in JavaScript.
+    ///
+    /// `callback(tensor, width, height)` receives a copy of the interleaved RGB
+    /// tensor and must return a `Float32Array` of the same length. Re-registering
+    /// an existing JS filter replaces it; ONNX entries can't be shadowed.
+    #[wasm_bindgen]
+    pub fn register_js_filter(&mut self, name: &str, metadata: JsValue, callback: js_sys::Function) -> Result<(), JsValue> {
+        self.check_live()?;
+        if name.is_empty() {
+            return Err(EngineError::InvalidInput("Filter name must not be empty".to_string()).into());
+        }
+
+        let mut metadata: ModelMetadata = if metadata.is_undefined() || metadata.is_null() {
+            ModelMetadata::default()
+        } else {
+            serde_wasm_bindgen::from_value(metadata)
+                .map_err(|e| EngineError::InvalidInput(format!("Invalid filter metadata: {}", e)))?
+        };
+        metadata.name = name.to_string();
+        metadata.kind = ModelKind::JsFilter;
+        metadata.model_url.clear();
+
+        if metadata.input_width == 0 || metadata.input_height == 0 {
+            return Err(EngineError::InvalidInput(format!("Filter '{}' needs a non-zero input size", name)).into());
+        }
+
+        match self.model_registry.iter_mut().find(|m| m.name == name) {
+            Some(existing) if existing.kind != ModelKind::JsFilter => {
+                return Err(EngineError::InvalidInput(format!("'{}' is already registered as a model", name)).into());
+            }
+            Some(existing) => *existing = metadata,
+            None => self.model_registry.push(metadata),
+        }
+
+        console_log!("Registered JS filter: {}", name);
+        self.js_filters.insert(name.to_string(), callback);
+        Ok(())
+    }
+
+    /// Replaces the simulated filter for `name`, or defines a new simulated-only
+    /// style when no entry of that name exists. Missing fields are neutral.
+    #[wasm_bindgen]
+    pub fn set_simulated_style_config(&mut self, name: &str, config: JsValue) -> Result<(), JsValue> {
+        self.check_live()?;
+        if name.is_empty() {
+            return Err(EngineError::InvalidInput("Style name must not be empty".to_string()).into());
+        }
+        let config: pipeline::SimulatedStyleConfig = serde_wasm_bindgen::from_value(config)
+            .map_err(|e| EngineError::InvalidInput(format!("Invalid simulated style config: {}", e)))?;
+
+        self.result_cache.invalidate_style(name);
+        match self.model_registry.iter_mut().find(|m| m.name == name) {
+            Some(existing) => existing.simulated_style = Some(config),
+            None => self.model_registry.push(ModelMetadata {
+                name: name.to_string(),
+                kind: ModelKind::Simulated,
+                simulated_style: Some(config),
+                ..ModelMetadata::default()
+            }),
+        }
+        Ok(())
+    }
+
+    /// Sets the listener called as `callback(kind, detail)` for engine events
+    /// such as `"model_evicted"`. Pass `undefined` to remove it.
+    #[wasm_bindgen]
+    pub fn set_event_listener(&mut self, callback: Option<js_sys::Function>) {
+        if self.disposed {
+            return;
+        }
+        *self.event_listener.borrow_mut() = callback;
+    }
+
+    /// Sets `callback(error, context)`, called after panics and failed
+    /// `load_model`/`process_*` calls on this engine. `context` holds
+    /// `operation`, `model`, `input_width`, `input_height`, `backend` and an
+    /// increasing `operation_id`. Panics aren't tied to an engine, so the most
+    /// recently set reporter receives them.
+    ///
+    /// The callback runs in a microtask once the failing call has returned;
+    /// anything it throws is ignored.
+    #[wasm_bindgen]
+    pub fn set_error_reporter(&mut self, callback: Option<js_sys::Function>) {
+        if self.disposed {
+            return;
+        }
+        self.reporter.set_callback(callback);
+    }
+
+    /// Registers the JavaScript runtime (e.g. an onnxruntime-web shim) that
+    /// loads models tract can't. It is called with one request object:
+    /// `{ kind: "load", model, bytes }`, `{ kind: "run", model, input, shape }`
+    /// (planar `Float32Array`, `[1, 3, H, W]`; return a `Float32Array` of the
+    /// same shape, or a promise of one) or `{ kind: "unload", model }`.
+    #[cfg(feature = "backend-ort-web")]
+    #[wasm_bindgen]
+    pub fn set_external_backend(&mut self, callback: js_sys::Function) {
+        if self.disposed {
+            return;
+        }
+        self.external_backend = Some(external::ExternalBackend::new(callback));
+    }
+
+    /// Caps the bytes held by loaded models, counting each model's file size
+    /// for what it runs on plus the file itself while it is retained; 0
+    /// removes the cap. Going over it first drops the retained files of the
+    /// least recently used models, then unloads the least recently used.
+    #[wasm_bindgen]
+    pub fn set_memory_budget_mb(&mut self, budget: f32) -> Result<(), JsValue> {
+        self.check_live()?;
+        if !budget.is_finite() || budget < 0.0 {
+            return Err(EngineError::InvalidInput(format!("Invalid memory budget: {} MB", budget)).into());
+        }
+        self.memory_budget_bytes = (budget as f64 * 1024.0 * 1024.0) as usize;
+        self.evict_for(None).map_err(|reason| {
+            EngineError::MemoryBudgetExceeded(format!("Loaded models do not fit in {} MB: {}", budget, reason)).into()
+        })
+    }
+
+    /// Caps how many models may be loaded at once; 0 removes the cap.
+    /// Loading one more first unloads the least recently used.
+    #[wasm_bindgen]
+    pub fn set_max_resident_models(&mut self, count: u32) -> Result<(), JsValue> {
+        self.check_live()?;
+        self.max_resident_models = count as usize;
+        self.evict_for(None).map_err(|reason| {
+            EngineError::MemoryBudgetExceeded(format!("Loaded models do not fit in {} slots: {}", count, reason)).into()
+        })
+    }
+
+    /// The current settings as a plain object, for apps to persist.
+    #[wasm_bindgen]
+    pub fn export_config(&self) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        serde_wasm_bindgen::to_value(&self.config).map_err(|e| e.into())
+    }
+
+    /// Applies a previously exported config. Unknown fields are ignored; if any
+    /// known field is invalid nothing is applied and the error names them all.
+    #[wasm_bindgen]
+    pub fn import_config(&mut self, config: JsValue) -> Result<(), JsValue> {
+        self.check_live()?;
+        let stored: serde_json::Map<String, serde_json::Value> = serde_wasm_bindgen::from_value(config)
+            .map_err(|e| EngineError::InvalidInput(format!("Config must be an object: {}", e)))?;
+        self.apply_stored_config(&stored)
+    }
+
+    /// Saves the current settings to `window.localStorage` under `key`.
+    #[wasm_bindgen]
+    pub fn save_config_to_storage(&self, key: &str) -> Result<(), JsValue> {
+        self.check_live()?;
+        let storage = local_storage()?;
+        let json = serde_json::to_string(&self.config)
+            .map_err(|e| EngineError::InvalidInput(e.to_string()))?;
+        storage.set_item(key, &json)
+    }
+
+    /// Restores settings saved with `save_config_to_storage`. Returns false
+    /// when nothing is stored under `key`.
+    #[wasm_bindgen]
+    pub fn load_config_from_storage(&mut self, key: &str) -> Result<bool, JsValue> {
+        self.check_live()?;
+        let Some(json) = local_storage()?.get_item(key)? else {
+            return Ok(false);
+        };
+        let stored: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json)
+            .map_err(|e| EngineError::InvalidInput(format!("Stored config under '{}' is not an object: {}", key, e)))?;
+        self.apply_stored_config(&stored)?;
+        Ok(true)
+    }
+
+    /// False once the device has been lost, until `reinitialize_webgpu`
+    /// acquires a new one.
     #[wasm_bindgen]
     pub fn is_webgpu_ready(&self) -> bool {
-        self.webgpu_available && self.webgpu_adapter.is_some() && self.webgpu_device.is_some()
+        self.webgpu_available && self.webgpu_adapter.is_some() && self.webgpu_device.is_some() && self.webgpu_loss.borrow().reason.is_none()
     }
 
     #[wasm_bindgen]
     pub fn get_webgpu_device(&self) -> Option<js_sys::Object> {
-        self.webgpu_device.clone()
+        self.webgpu_device.clone().filter(|_| self.is_webgpu_ready())
+    }
+
+    /// Drops the current WebGPU device, if any, and requests a new one, e.g.
+    /// after a `"webgpu_lost"` event. Returns whether WebGPU is ready again;
+    /// the engine keeps running on the CPU either way.
+    #[wasm_bindgen]
+    pub async fn reinitialize_webgpu(&mut self) -> Result<bool, JsValue> {
+        self.check_live()?;
+        self.release_webgpu();
+        match self.initialize_webgpu().await {
+            Ok(()) => {
+                self.webgpu_available = true;
+                self.webgpu_loss.borrow_mut().reason = None;
+                console_log!("WebGPU device reacquired");
+            }
+            Err(e) => {
+                console_warn!("WebGPU reinitialization failed: {}, staying on CPU", e);
+                self.webgpu_available = false;
+            }
+        }
+        Ok(self.is_webgpu_ready())
+    }
+
+    /// `Never` until a device was acquired, `Lost` after losing it.
+    fn webgpu_state(&self) -> WebGpuState {
+        if self.webgpu_loss.borrow().reason.is_some() {
+            WebGpuState::Lost
+        } else if self.is_webgpu_ready() {
+            WebGpuState::Available
+        } else {
+            WebGpuState::Never
+        }
+    }
+
+    /// Destroys the device without reporting it as lost.
+    fn release_webgpu(&mut self) {
+        // Handlers of older devices see the generation change and stay quiet
+        self.webgpu_loss.borrow_mut().generation += 1;
+        // Their buffers belong to the device; runs fall back to tract without them
+        self.gpu_models.clear();
+        if let Some(device) = self.webgpu_device.take() {
+            if let Ok(destroy) = js_sys::Reflect::get(&device, &"destroy".into()).and_then(|f| f.dyn_into::<js_sys::Function>()) {
+                let _ = destroy.call0(&device);
+            }
+        }
+        self.webgpu_adapter = None;
+    }
+
+    /// Watches `device.lost`: a loss marks WebGPU unusable and is announced
+    /// as a `"webgpu_lost"` event with the browser's `reason` and `message`.
+    fn watch_device_loss(&self, device: &JsValue) {
+        let Ok(lost) = js_sys::Reflect::get(device, &"lost".into()).and_then(|lost| lost.dyn_into::<js_sys::Promise>()) else {
+            return;
+        };
+        let loss = self.webgpu_loss.clone();
+        let listener = self.event_listener.clone();
+        let generation = loss.borrow().generation;
+        let on_lost = Closure::once(move |info: JsValue| {
+            if loss.borrow().generation != generation {
+                return;
+            }
+            let field = |name: &str| js_sys::Reflect::get(&info, &name.into()).ok().and_then(|v| v.as_string()).unwrap_or_default();
+            let (reason, message) = (field("reason"), field("message"));
+            console_warn!("WebGPU device lost ({}): {}; falling back to CPU", reason, message);
+            loss.borrow_mut().reason = Some(reason.clone());
+            // Cloned out so the listener may replace itself
+            let listener = listener.borrow().clone();
+            if let Some(listener) = listener {
+                let detail = to_js(&serde_json::json!({ "reason": reason, "message": message })).unwrap_or(JsValue::NULL);
+                let _ = listener.call2(&JsValue::NULL, &JsValue::from_str("webgpu_lost"), &detail);
+            }
+        });
+        let _ = lost.then(&on_lost);
+        on_lost.forget();
     }
 
     #[wasm_bindgen]
@@ -216,12 +685,22 @@ impl StyleTransferEngine {
 
     #[wasm_bindgen]
     pub fn unload_model(&mut self, model_name: &str) -> Result<(), JsValue> {
+        self.check_live()?;
+        self.partial_downloads.remove(model_name);
         if self.loaded_models.contains_key(model_name) {
             console_log!("Unloading model: {}", model_name);
             
             // Remove from both tracking maps
-            self.loaded_models.remove(model_name);
+            if let Some(model) = self.loaded_models.remove(model_name) {
+                self.release_runtime(model_name, model.runtime);
+            }
             self.tract_models.remove(model_name);
+            self.deferred_plans.remove(model_name);
+            self.adain_decoders.remove(model_name);
+            self.gpu_models.remove(model_name);
+            self.tile_costs.remove(model_name);
+            self.usage_clock.forget(model_name);
+            self.result_cache.invalidate_style(model_name);
             
             // Force garbage collection hint
             if let Ok(js_global) = js_sys::global().dyn_into::<js_sys::Object>() {
@@ -236,16 +715,31 @@ impl StyleTransferEngine {
         } else {
             console_log!("Model not loaded: {}", model_name);
         }
+        self.set_model_state(model_name, ModelState::NotLoaded);
         Ok(())
     }
 
     #[wasm_bindgen]
     pub fn unload_all_models(&mut self) -> Result<(), JsValue> {
+        self.check_live()?;
         console_log!("Unloading all models...");
+        let mut unloaded: Vec<String> = self.loaded_models.keys().chain(self.load_states.keys()).cloned().collect();
+        unloaded.sort();
+        unloaded.dedup();
         
         // Clear both tracking maps
-        self.loaded_models.clear();
+        for (name, model) in std::mem::take(&mut self.loaded_models) {
+            self.release_runtime(&name, model.runtime);
+        }
+        self.load_states.clear();
         self.tract_models.clear();
+        self.deferred_plans.clear();
+        self.adain_decoders.clear();
+        self.gpu_models.clear();
+        self.tile_costs.clear();
+        self.usage_clock.clear();
+        self.partial_downloads.clear();
+        self.result_cache.clear();
         
         // Force garbage collection hint
         if let Ok(js_global) = js_sys::global().dyn_into::<js_sys::Object>() {
@@ -257,355 +751,3164 @@ impl StyleTransferEngine {
         }
         
         console_log!("All models unloaded");
+        for name in unloaded {
+            self.emit_model_state(&name);
+        }
         Ok(())
     }
 
+    /// Releases everything the engine holds: loaded models (including their
+    /// copies in an external runtime), caches, pooled buffers, listeners and
+    /// the WebGPU device. Afterwards fallible methods fail with
+    /// `EngineDisposed`; setters do nothing and getters report an empty
+    /// engine. Disposing again is a no-op.
     #[wasm_bindgen]
-    pub fn get_loaded_models(&self) -> Vec<String> {
-        self.loaded_models.keys().cloned().collect()
+    pub fn dispose(&mut self) {
+        if self.disposed {
+            return;
+        }
+        console_log!("Disposing engine");
+        for (name, model) in std::mem::take(&mut self.loaded_models) {
+            self.release_runtime(&name, model.runtime);
+        }
+        self.load_states.clear();
+        self.tract_models.clear();
+        self.deferred_plans.clear();
+        self.adain_decoders.clear();
+        self.gpu_models.clear();
+        self.tile_costs.clear();
+        self.usage_clock.clear();
+        self.partial_downloads.clear();
+        prefetch::clear(&self.prefetcher);
+        self.result_cache.clear();
+        self.input_pool = Default::default();
+        self.buffer_pool.clear();
+        self.sequence = None;
+        self.model_usage.clear();
+        self.js_filters.clear();
+        *self.event_listener.borrow_mut() = None;
+        self.reporter.clear_callback();
+        if let Some(store) = self.model_store.take() {
+            store.close();
+        }
+        #[cfg(feature = "backend-ort-web")]
+        {
+            self.external_backend = None;
+        }
+
+        self.release_webgpu();
+        self.webgpu_available = false;
+        self.disposed = true;
     }
 
     #[wasm_bindgen]
-    pub async fn load_model(&mut self, model_name: &str) -> Result<(), JsValue> {
-        if self.loaded_models.contains_key(model_name) {
-            console_log!("Model already loaded: {}", model_name);
-            return Ok(());
+    pub fn is_disposed(&self) -> bool {
+        self.disposed
+    }
+
+    /// Adds a new entry to the registry.
+    #[wasm_bindgen]
+    pub fn register_model(&mut self, metadata: JsValue) -> Result<(), JsValue> {
+        self.check_live()?;
+        let metadata: ModelMetadata = serde_wasm_bindgen::from_value(metadata)
+            .map_err(|e| EngineError::InvalidInput(format!("Invalid model metadata: {}", e)))?;
+        registry::validate_metadata(&metadata).map_err(EngineError::InvalidInput)?;
+        if metadata.kind == ModelKind::JsFilter {
+            return Err(EngineError::InvalidInput("Use register_js_filter for JS filters".to_string()).into());
+        }
+        if self.model_registry.iter().any(|m| m.name == metadata.name) {
+            return Err(EngineError::InvalidInput(format!("'{}' is already registered", metadata.name)).into());
         }
 
-        let metadata = self.model_registry
-            .iter()
-            .find(|m| m.name == model_name)
-            .ok_or_else(|| JsValue::from_str("Model not found"))?;
+        console_log!("Registered model: {}", metadata.name);
+        self.model_registry.push(metadata);
+        Ok(())
+    }
 
-        console_log!("Loading ONNX model: {} ({} MB)", model_name, metadata.size_mb);
+    /// Applies a partial update (description, model_url, decoder_url, version, size_mb, input size,
+    /// simulated_style, style_affinity) to an entry. The input size of a
+    /// loaded model is locked until it is unloaded; a new version unloads it.
+    #[wasm_bindgen]
+    pub fn update_model_metadata(&mut self, name: &str, patch: JsValue) -> Result<(), JsValue> {
+        self.check_live()?;
+        let patch: registry::MetadataPatch = serde_wasm_bindgen::from_value(patch)
+            .map_err(|e| EngineError::InvalidInput(format!("Invalid metadata patch: {}", e)))?;
+        let index = self.model_registry
+            .iter()
+            .position(|m| m.name == name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", name)))?;
 
-        // Fetch model file
-        let window = web_sys::window().unwrap();
-        let response = wasm_bindgen_futures::JsFuture::from(
-            window.fetch_with_str(&metadata.model_url)
-        ).await?;
-        
-        let response: web_sys::Response = response.dyn_into()?;
-        if !response.ok() {
-            return Err(JsValue::from_str("Failed to fetch model"));
+        if self.loaded_models.contains_key(name) && patch.changes_shape(&self.model_registry[index]) {
+            return Err(EngineError::InvalidInput(format!(
+                "'{}' is loaded; unload it before changing its input shape", name
+            )).into());
         }
 
-        let array_buffer = wasm_bindgen_futures::JsFuture::from(
-            response.array_buffer()?
-        ).await?;
-        
-        let model_bytes = Uint8Array::new(&array_buffer).to_vec();
-        console_log!("Loaded {} bytes for model: {}", model_bytes.len(), model_name);
-        
-        // Parse and load ONNX model with tract
-        match self.load_tract_model(&model_bytes, model_name) {
-            Ok(_) => {
-                console_log!("ONNX model loaded successfully with tract: {}", model_name);
-                self.loaded_models.insert(model_name.to_string(), model_bytes);
-                Ok(())
-            }
-            Err(e) => {
-                console_log!("Failed to load ONNX model with tract: {}, falling back to simulation", e);
-                self.loaded_models.insert(model_name.to_string(), model_bytes);
-                Ok(())
-            }
-        }
+        let before = self.model_registry[index].clone();
+        self.model_registry[index] = patch
+            .apply(&before)
+            .map_err(EngineError::InvalidInput)?;
+        self.result_cache.invalidate_style(name);
+        self.retire_stale_versions(std::slice::from_ref(&before));
+        Ok(())
     }
 
-    fn load_tract_model(&mut self, model_bytes: &[u8], model_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        console_log!("Loading ONNX model with tract: {}", model_name);
-        
-        // Create a tract model from the ONNX bytes
-        let model = tract_onnx::onnx()
-            .model_for_read(&mut std::io::Cursor::new(model_bytes))?;
-        
-        // Optimize the model for inference
-        let model = model
-            .into_optimized()?
-            .into_runnable()?;
-        
-        // Store the model in our HashMap
-        self.tract_models.insert(model_name.to_string(), model);
-        
-        console_log!("ONNX model loaded successfully: {}", model_name);
+    /// Unloads a model (if loaded) and removes it from the registry.
+    #[wasm_bindgen]
+    pub fn remove_model(&mut self, name: &str) -> Result<(), JsValue> {
+        self.check_live()?;
+        let index = self.model_registry
+            .iter()
+            .position(|m| m.name == name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", name)))?;
+
+        self.unload_model(name)?;
+        self.js_filters.remove(name);
+        self.result_cache.invalidate_style(name);
+        prefetch::forget(&self.prefetcher, name);
+        self.load_states.remove(name);
+        self.model_registry.remove(index);
+        console_log!("Removed model from registry: {}", name);
         Ok(())
     }
 
-    #[wasm_bindgen] 
-    pub async fn process_image(&mut self, image_data_url: &str, style_name: &str, strength: f32) -> Result<String, JsValue> {
-        console_log!("Processing image with style: {}", style_name);
+    /// Registers a 3D LUT from the text of a `.cube` file for the `lut`
+    /// option. Registered LUTs take precedence over presets of the same name
+    /// and re-registering replaces them.
+    #[wasm_bindgen]
+    pub fn register_lut(&mut self, name: &str, cube: &str) -> Result<(), JsValue> {
+        self.check_live()?;
+        if name.is_empty() {
+            return Err(EngineError::InvalidInput("LUT name must not be empty".to_string()).into());
+        }
+        let lut = pipeline::lut::Lut3d::parse_cube(cube)
+            .map_err(|reason| EngineError::InvalidInput(format!("LUT '{}': {}", name, reason)))?;
+        console_log!("Registered {}³ LUT: {}", lut.size, name);
+        self.luts.insert(name.to_string(), Rc::new(lut));
+        Ok(())
+    }
 
-        // Load model if not already loaded
-        if !self.loaded_models.contains_key(style_name) {
-            self.load_model(style_name).await?;
+    /// Removes a LUT added with `register_lut`; returns whether there was one.
+    #[wasm_bindgen]
+    pub fn remove_lut(&mut self, name: &str) -> Result<bool, JsValue> {
+        self.check_live()?;
+        Ok(self.luts.remove(name).is_some())
+    }
+
+    /// The LUT `options.lut` names: a registered one, else a preset.
+    fn resolve_lut(&self, options: &ProcessOptions) -> Result<Option<Rc<pipeline::lut::Lut3d>>, EngineError> {
+        let Some(name) = &options.lut else {
+            return Ok(None);
+        };
+        if let Some(lut) = self.luts.get(name) {
+            return Ok(Some(lut.clone()));
         }
+        pipeline::lut::Lut3d::preset(name).map(|lut| Some(Rc::new(lut))).ok_or_else(|| {
+            EngineError::InvalidInput(format!("lut '{}' isn't registered or one of the presets {:?}", name, pipeline::lut::LUT_PRESETS))
+        })
+    }
 
-        // Get canvas from image data URL
-        let document = web_sys::window().unwrap().document().unwrap();
-        let canvas: HtmlCanvasElement = document
-            .create_element("canvas")?
-            .dyn_into::<HtmlCanvasElement>()?;
-        let ctx: CanvasRenderingContext2d = canvas
-            .get_context("2d")?
-            .unwrap()
-            .dyn_into::<CanvasRenderingContext2d>()?;
+    /// The full registry as plain objects, suitable for persisting.
+    #[wasm_bindgen]
+    pub fn export_registry(&self) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        serde_wasm_bindgen::to_value(&self.model_registry).map_err(|e| e.into())
+    }
 
-        // Load image into canvas
-        let img = web_sys::HtmlImageElement::new()?;
-        img.set_cross_origin(Some("anonymous"));
-        
-        let img_promise = js_sys::Promise::new(&mut |resolve, reject| {
-            let img_clone = img.clone();
-            let resolve_clone = resolve.clone();
-            let reject_clone = reject.clone();
-            
-            let onload = Closure::wrap(Box::new(move || {
-                resolve_clone.call0(&JsValue::NULL).unwrap();
-            }) as Box<dyn FnMut()>);
-            
-            let onerror = Closure::wrap(Box::new(move || {
-                reject_clone.call1(&JsValue::NULL, &"Image load failed".into()).unwrap();
-            }) as Box<dyn FnMut()>);
-            
-            img_clone.set_onload(Some(onload.as_ref().unchecked_ref()));
-            img_clone.set_onerror(Some(onerror.as_ref().unchecked_ref()));
-            
-            onload.forget();
-            onerror.forget();
-        });
+    /// Imports entries previously produced by `export_registry`.
+    ///
+    /// Returns `{ imported: [name], rejected: [{ index, name, reason }] }`.
+    /// With `replace`, entries missing from the import are dropped and their
+    /// models unloaded; otherwise existing names count as duplicates.
+    #[wasm_bindgen]
+    pub fn import_registry(&mut self, entries: JsValue, replace: bool) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let entries: Vec<serde_json::Value> = serde_wasm_bindgen::from_value(entries)
+            .map_err(|e| EngineError::InvalidInput(format!("Registry import must be an array: {}", e)))?;
 
-        img.set_src(image_data_url);
-        wasm_bindgen_futures::JsFuture::from(img_promise).await?;
+        let (new_registry, report) = registry::import_entries(
+            &self.model_registry,
+            entries,
+            replace,
+            |name| self.loaded_models.contains_key(name),
+            |name| self.js_filters.contains_key(name),
+        );
 
-        // Get model metadata for proper resolution
-        let model_metadata = self.model_registry
+        let dropped: Vec<String> = self.model_registry
             .iter()
-            .find(|m| m.name == style_name)
-            .ok_or_else(|| JsValue::from_str("Model not found"))?;
-        
-        let input_width = model_metadata.input_width;
-        let input_height = model_metadata.input_height;
-        
-        // Set canvas size and draw image
-        canvas.set_width(input_width);
-        canvas.set_height(input_height);
-        ctx.draw_image_with_html_image_element_and_dw_and_dh(&img, 0.0, 0.0, input_width as f64, input_height as f64)?;
-
-        let image_data = ctx.get_image_data(0.0, 0.0, input_width as f64, input_height as f64)?;
-        let clamped = image_data.data(); // Clamped<Vec<u8>>
-        let pixels: Vec<u8> = clamped.0; // take ownership of inner Vec<u8>
-
-        // Convert to normalized tensor (RGB, ignore alpha)
-        let mut input_tensor = Vec::with_capacity((input_width * input_height * 3) as usize);
-        for i in (0..pixels.len()).step_by(4) {
-            let r = pixels[i] as f32 / 255.0;
-            let g = pixels[i + 1] as f32 / 255.0;
-            let b = pixels[i + 2] as f32 / 255.0;
-            input_tensor.push(r);
-            input_tensor.push(g);
-            input_tensor.push(b);
-        }
-
-        // Run neural style transfer inference
-        let output_tensor = self.run_neural_inference(&input_tensor, style_name)?;
-
-        // Apply strength blending
-        let blended_tensor = if strength < 1.0 {
-            self.blend_tensors(&input_tensor, &output_tensor, strength)
-        } else {
-            output_tensor
-        };
+            .filter(|m| !new_registry.iter().any(|n| n.name == m.name))
+            .map(|m| m.name.clone())
+            .collect();
+        for name in &dropped {
+            self.unload_model(name)?;
+            self.js_filters.remove(name);
+        }
+        for name in &report.imported {
+            self.result_cache.invalidate_style(name);
+        }
+        let before = std::mem::replace(&mut self.model_registry, new_registry);
+        self.retire_stale_versions(&before);
 
-        // Build RGBA buffer in a plain Vec<u8>
-        let pixel_count = (input_width * input_height) as usize;
-        let mut output_pixels: Vec<u8> = vec![0; pixel_count * 4];
-        for i in 0..pixel_count {
-            let r = (blended_tensor[i * 3] * 255.0).clamp(0.0, 255.0) as u8;
-            let g = (blended_tensor[i * 3 + 1] * 255.0).clamp(0.0, 255.0) as u8;
-            let b = (blended_tensor[i * 3 + 2] * 255.0).clamp(0.0, 255.0) as u8;
-            let a = 255u8;
-
-            let base = i * 4;
-            output_pixels[base] = r;
-            output_pixels[base + 1] = g;
-            output_pixels[base + 2] = b;
-            output_pixels[base + 3] = a;
-        }
-
-        // ImageData expects a Clamped<&[u8]> slice
-        let output_image_data = ImageData::new_with_u8_clamped_array_and_sh(
-            wasm_bindgen::Clamped(&output_pixels[..]),
-            input_width,
-            input_height,
-        )?;
-        
-        ctx.put_image_data(&output_image_data, 0.0, 0.0)?;
-        
-        Ok(canvas.to_data_url()?)
+        console_log!(
+            "Imported {} registry entries, rejected {}",
+            report.imported.len(),
+            report.rejected.len()
+        );
+        serde_wasm_bindgen::to_value(&report).map_err(|e| e.into())
     }
 
-    fn run_neural_inference(&self, input_tensor: &[f32], style_name: &str) -> Result<Vec<f32>, JsValue> {
-        console_log!("Running neural network inference for: {}", style_name);
-        
-        // Try to use real ONNX model first
-        if let Some(plan) = self.tract_models.get(style_name) {
-            match self.run_onnx_inference(plan, input_tensor, style_name) {
-                Ok(result) => {
-                    console_log!("ONNX inference successful for: {}", style_name);
-                    return Ok(result);
-                }
-                Err(e) => {
-                    console_log!("ONNX inference failed: {}, falling back to simulation", e);
+    /// Fetches a JSON manifest of registry entries from `url` and merges it
+    /// into the registry, so styles can be added without a new build. The
+    /// manifest is an array of entries as `export_registry` produces them, or
+    /// an object with one under `models`; `.gz` and `.br` manifests are
+    /// decompressed. Fetching is retried as model downloads are.
+    ///
+    /// New names are added and existing ones replaced; styles the manifest
+    /// doesn't mention are kept. An entry whose `version` changes is
+    /// unloaded and its copies in the persistent model cache are deleted,
+    /// so its next use downloads the new file. Returns `{ added, updated,
+    /// unchanged: [name], rejected: [{ index, name, reason }] }`.
+    #[wasm_bindgen]
+    pub async fn load_registry(&mut self, url: &str) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let mut decoder = pipeline::StreamingDecompressor::new(pipeline::compression_from_url(url));
+        let fetched = download::fetch_model(url, &self.config, &mut PartialDownload::default(), &mut decoder, &download::DownloadWatch::default(), |attempt, reason, delay_ms| {
+            console_warn!("Fetching the registry manifest failed (attempt {}): {}; retrying in {:.0} ms", attempt, reason, delay_ms);
+        }).await?;
+        let manifest = decoder.finish(&fetched).map_err(|reason| {
+            EngineError::DecompressionError(format!("Cannot decompress the manifest at {}: {}", url, reason))
+        })?;
+        let entries = serde_json::from_slice(&manifest)
+            .map_err(|e| e.to_string())
+            .and_then(registry::manifest_entries)
+            .map_err(|reason| EngineError::InvalidInput(format!("Invalid registry manifest at {}: {}", url, reason)))?;
+
+        let (new_registry, report) = registry::merge_entries(
+            &self.model_registry,
+            entries,
+            |name| self.loaded_models.contains_key(name),
+            |name| self.js_filters.contains_key(name),
+        );
+        for name in &report.updated {
+            self.result_cache.invalidate_style(name);
+        }
+        let before = std::mem::replace(&mut self.model_registry, new_registry);
+        let stale = self.retire_stale_versions(&before);
+        if !stale.is_empty() && self.config.persistent_model_cache {
+            match self.model_store().await {
+                Ok(store) => {
+                    for name in &stale {
+                        for file_name in [name.clone(), format!("{}:decoder", name)] {
+                            if let Err(e) = store.remove(&file_name).await {
+                                console_warn!("Evicting the old version of {} from the persistent cache failed: {}", file_name, js_filter::describe_js_error(&e));
+                            }
+                        }
+                    }
                 }
+                Err(e) => console_warn!("Persistent model cache unavailable: {}", js_filter::describe_js_error(&e)),
             }
         }
-        
-        // Fallback to simulated processing if ONNX fails
-        console_log!("Using simulated neural network processing for: {}", style_name);
-        self.run_simulated_inference(input_tensor, style_name)
-    }
 
-    fn run_onnx_inference(&self, plan: &SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>, input_tensor: &[f32], style_name: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
-        // Get model metadata for proper resolution
-        let model_metadata = self.model_registry
-            .iter()
-            .find(|m| m.name == style_name)
-            .ok_or_else(|| "Model not found")?;
-        
-        let input_width = model_metadata.input_width;
-        let input_height = model_metadata.input_height;
-        
-        // Prepare input tensor for ONNX model
-        let input_shape = vec![1, 3, input_height as usize, input_width as usize]; // Batch, Channels, Height, Width
-        
-        // Create input tensor
-        let input_tensor = Tensor::from_shape(&input_shape, input_tensor)?;
-        
-        // Run inference
-        let outputs = plan.run(tvec!(input_tensor.into()))?;
-        
-        // Extract output tensor
-        let output = outputs[0].as_slice::<f32>()?;
-        
-        Ok(output.to_vec())
+        console_log!(
+            "Merged registry manifest {}: {} added, {} updated, {} rejected",
+            url,
+            report.added.len(),
+            report.updated.len(),
+            report.rejected.len()
+        );
+        serde_wasm_bindgen::to_value(&report).map_err(|e| e.into())
     }
 
-    fn run_simulated_inference(&self, input_tensor: &[f32], style_name: &str) -> Result<Vec<f32>, JsValue> {
-        let mut output_tensor = Vec::with_capacity(input_tensor.len());
-        
-        // Get model metadata for proper resolution
-        let model_metadata = self.model_registry
-            .iter()
-            .find(|m| m.name == style_name)
-            .ok_or_else(|| JsValue::from_str("Model not found"))?;
-        
-        let width = model_metadata.input_width;
-        let _height = model_metadata.input_height;
-        
-        for (i, &pixel) in input_tensor.iter().enumerate() {
-            let channel = i % 3;
-            let position = i / 3;
-            let x = position % width as usize;
-            let y = position / width as usize;
-            
-            let processed_pixel = match style_name {
-                "van_gogh_starry_night" => {
-                    // Simulate Van Gogh's swirling brushstrokes and color enhancement
-                    let swirl_x = (x as f32 * 0.02).sin() * 0.1;
-                    let swirl_y = (y as f32 * 0.02).cos() * 0.1;
-                    let color_boost = match channel {
-                        0 => 1.4, // Red enhancement
-                        1 => 1.2, // Green enhancement  
-                        2 => 1.1, // Blue slight boost
-                        _ => 1.0,
-                    };
-                    (pixel * color_boost + swirl_x + swirl_y + 0.1).clamp(0.0, 1.0)
-                },
-                "picasso_cubist" => {
-                    // Simulate geometric fragmentation and high contrast
-                    let block_size = 16;
-                    let block_x = (x / block_size) * block_size;
-                    let block_y = (y / block_size) * block_size;
-                    let is_edge = (block_x + block_y) % 32 == 0;
-                    
-                    if is_edge {
-                        (pixel * 2.0).clamp(0.0, 1.0)
-                    } else {
-                        (pixel * 0.6 + 0.2).clamp(0.0, 1.0)
-                    }
-                },
-                "cyberpunk_neon" => {
-                    // Simulate neon glow and cyberpunk color grading
-                    let glow = ((x as f32 + y as f32) * 0.01).sin().abs() * 0.2;
-                    let color_shift = match channel {
-                        0 => pixel * 1.3 + glow, // Red/magenta boost
-                        1 => pixel * 0.8,        // Green reduction
-                        2 => pixel * 1.5 + glow, // Blue/cyan boost
-                        _ => pixel,
-                    };
-                    color_shift.clamp(0.0, 1.0)
-                },
-                "monet_water_lilies" => {
-                    // Simulate impressionist soft brushwork
-                    let _blur_radius = 2.0;
-                    let soft_light = 0.05 * (1.0 + (position as f32 * 0.001).sin());
-                    (pixel * 1.1 + soft_light).clamp(0.0, 1.0)
-                },
-                "anime_studio_ghibli" => {
-                    // Simulate anime color saturation and cel-shading
-                    let quantized = (pixel * 6.0).round() / 6.0; // Quantize colors
-                    let saturated = if quantized > 0.5 {
-                        (quantized * 1.3).clamp(0.0, 1.0)
-                        } else {
-                        quantized * 0.9
-                    };
-                    saturated
-                },
-                _ => pixel,
-            };
-            
-            output_tensor.push(processed_pixel);
+    /// Unloads the models whose entry names another version of their file
+    /// than in `before`, and drops their partial downloads, announcing each
+    /// as a `"model_version_changed"` event. Returns their names.
+    fn retire_stale_versions(&mut self, before: &[ModelMetadata]) -> Vec<String> {
+        let changes = registry::version_changes(before, &self.model_registry);
+        for change in &changes {
+            let was_loaded = self.loaded_models.contains_key(&change.name);
+            if was_loaded {
+                console_log!("{} is now at version '{}'; unloading '{}'", change.name, change.to, change.from);
+                let _ = self.unload_model(&change.name);
+            }
+            self.partial_downloads.remove(&change.name);
+            self.partial_downloads.remove(&format!("{}:decoder", change.name));
+            prefetch::forget(&self.prefetcher, &change.name);
+            self.emit_event("model_version_changed", serde_json::json!({ "name": change.name, "from": change.from, "to": change.to, "was_loaded": was_loaded }));
         }
-        
-        Ok(output_tensor)
+        changes.into_iter().map(|change| change.name).collect()
     }
 
-    fn blend_tensors(&self, original: &[f32], stylized: &[f32], strength: f32) -> Vec<f32> {
-        // Ensure both tensors have the same length
-        let min_len = original.len().min(stylized.len());
-        let mut blended = Vec::with_capacity(min_len);
-        
-        for i in 0..min_len {
-            let orig = original[i];
-            let style = stylized[i];
-            
-            // Apply proper blending with gamma correction for better visual results
-            let gamma = 2.2;
-            let orig_gamma = orig.powf(gamma);
-            let style_gamma = style.powf(gamma);
-            let blended_gamma = orig_gamma * (1.0 - strength) + style_gamma * strength;
-            let blended_value = blended_gamma.powf(1.0 / gamma);
-            
-            // Clamp to valid range
-            blended.push(blended_value.clamp(0.0, 1.0));
+    #[wasm_bindgen]
+    pub fn get_loaded_models(&self) -> Vec<String> {
+        self.loaded_models.keys().cloned().collect()
+    }
+
+    /// Whether `model_name` is among `get_loaded_models`. JS filters and
+    /// simulated styles are never loaded, having nothing to load.
+    #[wasm_bindgen]
+    pub fn is_model_loaded(&self, model_name: &str) -> bool {
+        self.loaded_models.contains_key(model_name)
+    }
+
+    /// `{ state, error }` of a registry entry: `"not_loaded"`,
+    /// `"downloading"`, `"parsing"`, `"ready"` or `"failed"`, with `error`
+    /// only when failed. A load in progress holds the engine, so to follow one
+    /// listen for `"model_state_changed"` events, which carry the same fields
+    /// and the `name`.
+    #[wasm_bindgen]
+    pub fn get_model_state(&self, model_name: &str) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        if !self.model_registry.iter().any(|m| m.name == model_name) {
+            return Err(EngineError::ModelNotFound(format!("Model not found: {}", model_name)).into());
+        }
+        to_js(&self.model_state(model_name))
+    }
+
+    /// `get_model_state` of every registry entry, keyed by name.
+    #[wasm_bindgen]
+    pub fn get_model_states(&self) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let states: BTreeMap<&str, ModelState> = self.model_registry
+            .iter()
+            .map(|m| (m.name.as_str(), self.model_state(&m.name)))
+            .collect();
+        to_js(&states)
+    }
+
+    /// Downloads (or reads from the persistent cache) and loads a model.
+    /// `on_progress(loaded, total)` is called as bytes arrive, with a null
+    /// `total` when the server doesn't send one; aborting `signal` stops the
+    /// download with a `Cancelled` error.
+    #[wasm_bindgen]
+    pub async fn load_model(&mut self, model_name: &str, on_progress: Option<js_sys::Function>, signal: Option<web_sys::AbortSignal>) -> Result<(), JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("load_model", Some(model_name));
+        self.download_watch = download::DownloadWatch { on_progress, signal };
+        let result = self.fetch_and_load_model(model_name).await;
+        self.download_watch = Default::default();
+        if result.is_err() {
+            self.record_processed(model_name, None);
+        }
+        self.reporter.finish(started, result)
+    }
+
+    /// Downloads and parses the ONNX styles `names` in idle time, before they
+    /// are picked, so that loading them later skips both. `priority` is
+    /// `"low"`, `"normal"` (the default) or `"high"`: more urgent prefetches
+    /// start first, at most `prefetch_concurrency` run at once, and none
+    /// start while a model a caller asked for is downloading. Returns at once
+    /// with the names queued, leaving out loaded models and entries that
+    /// aren't ONNX styles with a `model_url`.
+    ///
+    /// Loading a model whose prefetch is running waits for it; one that
+    /// hasn't started is cancelled and the model downloaded as usual.
+    #[wasm_bindgen]
+    pub fn prefetch_models(&mut self, names: Vec<String>, priority: Option<String>) -> Result<Vec<String>, JsValue> {
+        self.check_live()?;
+        let priority: PrefetchPriority = match priority {
+            Some(priority) => serde_json::from_value(serde_json::Value::String(priority.clone()))
+                .map_err(|_| EngineError::InvalidInput(format!("Unknown prefetch priority '{}'", priority)))?,
+            None => PrefetchPriority::default(),
+        };
+        let mut requests = Vec::new();
+        for name in names {
+            let metadata = self.model_registry
+                .iter()
+                .find(|m| m.name == name)
+                .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", name)))?;
+            if metadata.kind != ModelKind::Onnx || metadata.model_url.is_empty() || self.loaded_models.contains_key(&name) {
+                continue;
+            }
+            let request = prefetch::PrefetchRequest {
+                url: metadata.model_url.clone(),
+                version: metadata.version.clone(),
+                compression: metadata.compression,
+                sha256: metadata.sha256.clone(),
+            };
+            requests.push((name, request));
+        }
+        let mut queued = Vec::new();
+        for (name, request) in requests {
+            prefetch::enqueue(&self.prefetcher, &name, request, priority, &self.config);
+            queued.push(name);
+        }
+        console_log!("Prefetching {:?} at {:?} priority", queued, priority);
+        Ok(queued)
+    }
+
+    /// Cancels the prefetches that haven't started and drops the finished
+    /// ones. Running prefetches complete, but their results are kept.
+    #[wasm_bindgen]
+    pub fn cancel_prefetches(&mut self) -> Result<(), JsValue> {
+        self.check_live()?;
+        prefetch::clear(&self.prefetcher);
+        Ok(())
+    }
+
+    /// `{ waiting, running, ready }`: the prefetches not started yet in the
+    /// order they will start, those downloading or parsing, and those whose
+    /// results are waiting to be loaded.
+    #[wasm_bindgen]
+    pub fn get_prefetch_status(&self) -> Result<JsValue, JsValue> {
+        to_js(&prefetch::status(&self.prefetcher))
+    }
+
+    /// Loads an ONNX model from bytes the app already has, e.g. bundled with
+    /// it or picked with a file input, instead of fetching `model_url`.
+    /// `metadata` is registered as the entry for `model_name` (its `name` may
+    /// be left out) and needs no `model_url`; it replaces any entry of that
+    /// name, unloading it first. Without `metadata` the existing entry is
+    /// reloaded from the bytes. The bytes may be compressed as
+    /// `metadata.compression` says, and must match its `sha256` if it has one.
+    ///
+    /// An entry without a `model_url` can't be fetched again, so once
+    /// unloaded or evicted it has to be loaded from bytes again.
+    #[wasm_bindgen]
+    pub async fn load_model_from_bytes(&mut self, model_name: &str, bytes: &js_sys::Uint8Array, metadata: JsValue) -> Result<(), JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("load_model_from_bytes", Some(model_name));
+        let result = self.load_local_model(model_name, bytes.to_vec(), metadata).await;
+        self.finish_load_state(model_name, &result);
+        if result.is_err() {
+            self.record_processed(model_name, None);
+        }
+        self.reporter.finish(started, result)
+    }
+
+    async fn load_local_model(&mut self, model_name: &str, bytes: Vec<u8>, metadata: JsValue) -> Result<(), JsValue> {
+        let load_started = now_ms();
+        let existing = self.model_registry.iter().position(|m| m.name == model_name);
+        if existing.is_some_and(|index| self.model_registry[index].kind == ModelKind::JsFilter) {
+            return Err(EngineError::InvalidInput(format!("'{}' is a JS filter; remove it first", model_name)).into());
+        }
+        let metadata = if metadata.is_undefined() || metadata.is_null() {
+            let index = existing.ok_or_else(|| {
+                EngineError::ModelNotFound(format!("'{}' is not registered; pass its metadata", model_name))
+            })?;
+            self.model_registry[index].clone()
+        } else {
+            let mut metadata: ModelMetadata = serde_wasm_bindgen::from_value(metadata)
+                .map_err(|e| EngineError::InvalidInput(format!("Invalid model metadata: {}", e)))?;
+            if metadata.name.is_empty() {
+                metadata.name = model_name.to_string();
+            }
+            if metadata.name != model_name {
+                return Err(EngineError::InvalidInput(format!("Metadata is for '{}', not '{}'", metadata.name, model_name)).into());
+            }
+            if metadata.size_mb == 0.0 {
+                metadata.size_mb = bytes.len() as f32 / (1024.0 * 1024.0);
+            }
+            metadata
+        };
+        if metadata.kind != ModelKind::Onnx {
+            return Err(EngineError::InvalidInput(format!("'{}' must be an ONNX model to load from bytes", model_name)).into());
+        }
+        registry::validate_local_metadata(&metadata).map_err(EngineError::InvalidInput)?;
+        if let Some(expected) = &metadata.sha256 {
+            download::verify_sha256(model_name, &bytes, expected).await?;
+        }
+        let model_bytes = pipeline::decompress_model(bytes, metadata.compression).map_err(|reason| {
+            EngineError::DecompressionError(format!("Cannot decompress '{}': {}", model_name, reason))
+        })?;
+
+        self.unload_model(model_name)?;
+        match existing {
+            Some(index) => self.model_registry[index] = metadata,
+            None => self.model_registry.push(metadata),
+        }
+        self.result_cache.invalidate_style(model_name);
+        console_log!("Loading {} bytes supplied for model: {}", model_bytes.len(), model_name);
+        self.load_onnx_bytes(model_name, model_bytes, None, load_started).await
+    }
+
+    /// Summarizes an ONNX model for debugging: `{ opset, inputs, outputs, ops,
+    /// parameter_count, optimize_error }`, where `ops` counts nodes per
+    /// operator type and `optimize_error` is `{ message, node }` when tract
+    /// can't optimize the graph. The model is downloaded if its bytes aren't
+    /// retained, but nothing is loaded or cached.
+    #[wasm_bindgen]
+    pub async fn inspect_model(&mut self, model_name: &str) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == model_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+        if metadata.kind != ModelKind::Onnx {
+            return Err(EngineError::InvalidInput(format!("'{}' is not an ONNX model", model_name)).into());
+        }
+        let retained = self.loaded_models.get(model_name).and_then(|model| model.bytes.clone());
+        let model_bytes = match retained {
+            Some(bytes) => bytes,
+            None => self.download_model_bytes(model_name).await?,
+        };
+        let inspection = pipeline::inspect_model(&model_bytes)
+            .map_err(|reason| EngineError::OnnxParseError(format!("Cannot parse '{}': {}", model_name, reason)))?;
+        to_js(&inspection)
+    }
+
+    /// What the runtime found in a loaded model: `{ name, runtime, inputs,
+    /// outputs, decoder, metadata_mismatch }`. `inputs` and `outputs` are
+    /// `[{ name, shape, dtype }]` from tract's plan (null on runtimes that
+    /// don't keep one); `decoder` is the same for the decoder of an AdaIN
+    /// entry; `metadata_mismatch` says how the registry entry disagrees with
+    /// the plan, when it does.
+    #[wasm_bindgen]
+    pub fn get_model_info(&self, model_name: &str) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == model_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+        let model = self.loaded_models.get(model_name).ok_or_else(|| {
+            EngineError::InvalidInput(format!("'{}' is not loaded; load it first", model_name))
+        })?;
+        let plan = self.tract_models.get(model_name);
+        let facts = plan.map(pipeline::plan_facts);
+        let mismatch = plan
+            .filter(|_| metadata.kind == ModelKind::Onnx)
+            .and_then(|plan| pipeline::plan_shapes(plan).ok())
+            .and_then(|(input, output)| pipeline::check_model_shapes(&input, &output, metadata).err())
+            .map(|mismatch| mismatch.to_string());
+        to_js(&serde_json::json!({
+            "name": model_name,
+            "runtime": model.runtime,
+            "inputs": facts.as_ref().map(|f| &f.inputs),
+            "outputs": facts.as_ref().map(|f| &f.outputs),
+            "decoder": self.adain_decoders.get(model_name).map(pipeline::plan_facts),
+            "metadata_mismatch": mismatch,
+        }))
+    }
+
+    /// Model files kept in IndexedDB by the persistent model cache:
+    /// `[{ name, version, bytes, stored_at }]`, with sizes as stored (i.e.
+    /// compressed when the download was).
+    #[wasm_bindgen]
+    pub async fn get_cached_models(&mut self) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let store = self.model_store().await?;
+        to_js(&store.list().await?)
+    }
+
+    /// Deletes every cached version of `model_name`, returning how many
+    /// files were removed. A loaded model stays loaded.
+    #[wasm_bindgen]
+    pub async fn invalidate_cached_model(&mut self, model_name: &str) -> Result<u32, JsValue> {
+        self.check_live()?;
+        let store = self.model_store().await?;
+        // AdaIN entries have their decoder stored beside them
+        Ok(store.remove(model_name).await? + store.remove(&format!("{}:decoder", model_name)).await?)
+    }
+
+    /// Deletes every file in the persistent model cache.
+    #[wasm_bindgen]
+    pub async fn clear_model_cache(&mut self) -> Result<(), JsValue> {
+        self.check_live()?;
+        let store = self.model_store().await?;
+        store.clear().await
+    }
+
+    async fn model_store(&mut self) -> Result<model_store::ModelStore, JsValue> {
+        if let Some(store) = &self.model_store {
+            return Ok(store.clone());
+        }
+        let store = model_store::ModelStore::open().await?;
+        Ok(self.model_store.insert(store).clone())
+    }
+
+    async fn fetch_and_load_model(&mut self, model_name: &str) -> Result<(), JsValue> {
+        if self.loaded_models.contains_key(model_name) {
+            console_log!("Model already loaded: {}", model_name);
+            return Ok(());
+        }
+
+        let kind = self.model_registry
+            .iter()
+            .find(|m| m.name == model_name)
+            .map(|m| m.kind)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+
+        self.set_model_state(model_name, ModelState::Downloading);
+        let result = match kind {
+            ModelKind::Onnx => self.load_onnx_model(model_name).await,
+            ModelKind::Adain => self.load_adain_model(model_name).await,
+            ModelKind::Segmentation | ModelKind::SuperResolution => self.load_helper_model(model_name, kind).await,
+            // JS filters and simulated styles have nothing to download
+            ModelKind::JsFilter | ModelKind::Simulated => Ok(()),
+        };
+        self.finish_load_state(model_name, &result);
+        result
+    }
+
+    async fn load_onnx_model(&mut self, model_name: &str) -> Result<(), JsValue> {
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == model_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+        console_log!("Loading ONNX model: {} ({} MB)", model_name, metadata.size_mb);
+        let load_started = now_ms();
+        let version = metadata.version.clone();
+
+        let (model_bytes, plan) = match prefetch::take(&self.prefetcher, model_name, &version).await {
+            Some(Ok(prefetched)) => {
+                console_log!("Using the prefetched copy of {}", model_name);
+                (prefetched.bytes, prefetched.plan)
+            }
+            Some(Err(reason)) => {
+                console_warn!("Prefetching {} failed: {}; downloading it now", model_name, reason);
+                (self.download_model_bytes(model_name).await?, None)
+            }
+            None => (self.download_model_bytes(model_name).await?, None),
+        };
+        self.load_onnx_bytes(model_name, model_bytes, plan, load_started).await
+    }
+
+    /// Loads an ONNX entry's bytes on the first runtime that takes them.
+    /// `plan` is tract's, when a prefetch already parsed them.
+    async fn load_onnx_bytes(&mut self, model_name: &str, model_bytes: Vec<u8>, plan: Option<TractPlan>, load_started: f64) -> Result<(), JsValue> {
+        self.set_model_state(model_name, ModelState::Parsing);
+        self.evict_for(Some(model_bytes.len())).map_err(|reason| {
+            EngineError::MemoryBudgetExceeded(format!("Cannot load '{}': {}", model_name, reason))
+        })?;
+
+        // Try each compiled-in runtime in turn, ending with the simulated filter
+        let byte_len = model_bytes.len();
+        let mut runtime = ModelRuntime::Simulated;
+        let mut failures: Vec<(ModelRuntime, String)> = Vec::new();
+        if cfg!(feature = "backend-tract") {
+            match self.load_tract_model(&model_bytes, plan, model_name) {
+                Ok(_) => runtime = ModelRuntime::Tract,
+                // Wrong metadata isn't something another runtime would fix
+                Err(e) => match e.downcast::<EngineError>() {
+                    Ok(e) => return Err((*e).into()),
+                    Err(e) => failures.push((ModelRuntime::Tract, e.to_string())),
+                },
+            }
+        }
+        #[cfg(feature = "backend-ort-web")]
+        if runtime == ModelRuntime::Simulated {
+            match &self.external_backend {
+                Some(backend) => match backend.load(model_name, &model_bytes).await {
+                    Ok(()) => {
+                        runtime = ModelRuntime::External;
+                        self.result_cache.invalidate_style(model_name);
+                    }
+                    Err(e) => failures.push((ModelRuntime::External, e)),
+                },
+                None => failures.push((ModelRuntime::External, "no external backend registered".to_string())),
+            }
+        }
+
+        // WebGPU runs the lowered graph and keeps any tract plan as its fallback
+        if cfg!(feature = "webgpu") && self.config.preferred_backend != config::PreferredBackend::Cpu && self.is_webgpu_ready() {
+            match self.load_gpu_model(&model_bytes, model_name).await {
+                Ok(()) => runtime = ModelRuntime::WebGpu,
+                Err(e) => failures.insert(0, (ModelRuntime::WebGpu, e)),
+            }
+        }
+
+        // Strict mode would refuse every inference on the simulated fallback
+        if runtime == ModelRuntime::Simulated && self.config.strict_mode && !failures.is_empty() {
+            let reasons: Vec<String> = failures.iter().map(|(from, reason)| format!("{:?}: {}", from, reason)).collect();
+            return Err(EngineError::OnnxParseError(format!("Cannot load '{}': {}", model_name, reasons.join("; "))).into());
+        }
+
+        // Every fallback is announced, not just logged
+        for (index, (from, reason)) in failures.iter().enumerate() {
+            let to = failures.get(index + 1).map_or(runtime, |(next, _)| *next);
+            console_warn!("{:?} runtime could not load {}: {}; falling back to {:?}", from, model_name, reason, to);
+            self.emit_event("backend_failover", serde_json::json!({ "name": model_name, "from": from, "to": to, "reason": reason }));
+        }
+        console_log!("Model {} runs on {:?}", model_name, runtime);
+
+        // The simulated fallback keeps its bytes so a later runtime could still use them
+        let bytes = if runtime == ModelRuntime::Simulated || self.config.retain_model_bytes { Some(model_bytes) } else { None };
+        self.insert_loaded_model(model_name, LoadedModel { bytes, byte_len, runtime });
+        let load_ms = now_ms() - load_started;
+        self.usage(model_name).record_load(load_ms, js_sys::Date::now());
+        Ok(())
+    }
+
+    /// Downloads both halves of an AdaIN entry. When either can't be parsed
+    /// the entry stays loaded on the pixel statistics fallback, announced as
+    /// a `"backend_failover"` event.
+    async fn load_adain_model(&mut self, model_name: &str) -> Result<(), JsValue> {
+        console_log!("Loading AdaIN model: {}", model_name);
+        let load_started = now_ms();
+        let (decoder_url, version, compression) = self.model_registry
+            .iter()
+            .find(|m| m.name == model_name)
+            .map(|m| (m.decoder_url.clone(), m.version.clone(), m.compression))
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+
+        let encoder_bytes = self.download_model_bytes(model_name).await?;
+        let decoder_bytes = self.download_file(&format!("{}:decoder", model_name), &decoder_url, &version, compression, None).await?;
+        self.set_model_state(model_name, ModelState::Parsing);
+        let byte_len = encoder_bytes.len() + decoder_bytes.len();
+        self.evict_for(Some(byte_len)).map_err(|reason| {
+            EngineError::MemoryBudgetExceeded(format!("Cannot load '{}': {}", model_name, reason))
+        })?;
+
+        let runtime = match (pipeline::load_plan(&encoder_bytes), pipeline::load_plan(&decoder_bytes)) {
+            (Ok(encoder), Ok(decoder)) => {
+                self.tract_models.insert(model_name.to_string(), encoder);
+                self.adain_decoders.insert(model_name.to_string(), decoder);
+                ModelRuntime::Tract
+            }
+            (encoder, decoder) => {
+                let reason = encoder.err().or(decoder.err()).map(|e| e.to_string()).unwrap_or_default();
+                console_warn!("Tract runtime could not load {}: {}; falling back to {:?}", model_name, reason, ModelRuntime::Simulated);
+                self.emit_event("backend_failover", serde_json::json!({ "name": model_name, "from": ModelRuntime::Tract, "to": ModelRuntime::Simulated, "reason": reason }));
+                ModelRuntime::Simulated
+            }
+        };
+        console_log!("Model {} runs on {:?}", model_name, runtime);
+        self.result_cache.invalidate_style(model_name);
+        self.insert_loaded_model(model_name, LoadedModel { bytes: None, byte_len, runtime });
+        let load_ms = now_ms() - load_started;
+        self.usage(model_name).record_load(load_ms, js_sys::Date::now());
+        Ok(())
+    }
+
+    /// Loads a segmentation or super-resolution model onto tract. These
+    /// models are optional, so when one can't be downloaded or parsed it is
+    /// marked as simulated and the saliency heuristic or a bicubic resize
+    /// stands in for it; only cancellation is an error.
+    async fn load_helper_model(&mut self, model_name: &str, kind: ModelKind) -> Result<(), JsValue> {
+        let (role, fallback) = match kind {
+            ModelKind::SuperResolution => ("super-resolution", "a bicubic resize"),
+            _ => ("segmentation", "the saliency heuristic"),
+        };
+        console_log!("Loading {} model: {}", role, model_name);
+        let load_started = now_ms();
+        let loaded = match self.download_model_bytes(model_name).await {
+            Ok(bytes) => {
+                self.set_model_state(model_name, ModelState::Parsing);
+                self.evict_for(Some(bytes.len())).map_err(|reason| {
+                    EngineError::MemoryBudgetExceeded(format!("Cannot load '{}': {}", model_name, reason))
+                })?;
+                let byte_len = bytes.len();
+                pipeline::load_plan(&bytes).map(|plan| (plan, byte_len)).map_err(|e| e.to_string())
+            }
+            Err(_) if self.download_watch.signal.as_ref().is_some_and(|signal| signal.aborted()) => {
+                return Err(EngineError::Cancelled(format!("Loading '{}' was cancelled", model_name)).into());
+            }
+            Err(error) => Err(js_filter::describe_js_error(&error)),
+        };
+        let (runtime, byte_len) = match loaded {
+            Ok((plan, byte_len)) => {
+                self.tract_models.insert(model_name.to_string(), plan);
+                (ModelRuntime::Tract, byte_len)
+            }
+            Err(reason) => {
+                console_warn!("The {} model {} is unavailable: {}; using {}", role, model_name, reason, fallback);
+                self.emit_event("backend_failover", serde_json::json!({ "name": model_name, "from": ModelRuntime::Tract, "to": ModelRuntime::Simulated, "reason": reason }));
+                (ModelRuntime::Simulated, 0)
+            }
+        };
+        self.insert_loaded_model(model_name, LoadedModel { bytes: None, byte_len, runtime });
+        let load_ms = now_ms() - load_started;
+        self.usage(model_name).record_load(load_ms, js_sys::Date::now());
+        Ok(())
+    }
+
+    /// Downloads and decompresses the file behind an ONNX registry entry.
+    async fn download_model_bytes(&mut self, model_name: &str) -> Result<Vec<u8>, JsValue> {
+        let (model_url, version, compression, sha256) = self.model_registry
+            .iter()
+            .find(|m| m.name == model_name)
+            .map(|m| (m.model_url.clone(), m.version.clone(), m.compression, m.sha256.clone()))
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+        if model_url.is_empty() {
+            return Err(EngineError::ModelNotFound(format!("'{}' has no model_url; load it with load_model_from_bytes", model_name)).into());
+        }
+        self.download_file(model_name, &model_url, &version, compression, sha256.as_deref()).await
+    }
+
+    /// Downloads and decompresses `file_name` from `url`, retrying transient
+    /// failures and resuming earlier partial downloads. With
+    /// `persistent_model_cache` the file comes from IndexedDB when it holds
+    /// this version, and is stored there after downloading.
+    ///
+    /// Without a declared `compression`, `.gz` and `.br` URLs are taken to be
+    /// gzip and brotli; otherwise gzip is sniffed. Chunks are decompressed
+    /// as they arrive.
+    ///
+    /// With a `sha256`, the file as served is checked against it before it is
+    /// decompressed or cached. A cached copy that fails the check is
+    /// downloaded again; a download that fails it is an `IntegrityError`.
+    async fn download_file(&mut self, file_name: &str, url: &str, version: &str, compression: Option<pipeline::ModelCompression>, sha256: Option<&str>) -> Result<Vec<u8>, JsValue> {
+        let _foreground = prefetch::Foreground::begin(&self.prefetcher);
+        let compression = compression.or_else(|| pipeline::compression_from_url(url));
+        // Storage failures only cost the download they would have saved
+        let store = if self.config.persistent_model_cache {
+            self.model_store().await.map_err(|e| {
+                console_warn!("Persistent model cache unavailable: {}", js_filter::describe_js_error(&e));
+            }).ok()
+        } else {
+            None
+        };
+        if let Some(store) = &store {
+            let cached = match (store.get(file_name, version).await, sha256) {
+                (Ok(Some(cached)), Some(expected)) => match download::verify_sha256(file_name, &cached, expected).await {
+                    Ok(()) => Ok(Some(cached)),
+                    Err(e) => {
+                        console_warn!("Cached copy of {} is unusable ({}); downloading it again", file_name, e.message());
+                        let _ = store.remove(file_name).await;
+                        Ok(None)
+                    }
+                },
+                (cached, _) => cached,
+            };
+            match cached {
+                Ok(Some(cached)) => match (cached.len(), pipeline::decompress_model(cached, compression)) {
+                    (cached_len, Ok(model_bytes)) => {
+                        console_log!("Loaded {} bytes for model {} from the persistent cache", model_bytes.len(), file_name);
+                        self.download_watch.report(cached_len, Some(cached_len));
+                        return Ok(model_bytes);
+                    }
+                    (_, Err(reason)) => {
+                        console_warn!("Cached copy of {} is unusable ({}); downloading it again", file_name, reason);
+                        let _ = store.remove(file_name).await;
+                    }
+                },
+                Ok(None) => {}
+                Err(e) => console_warn!("Reading {} from the persistent cache failed: {}", file_name, js_filter::describe_js_error(&e)),
+            }
+        }
+
+        let mut partial = self.partial_downloads.remove(file_name).unwrap_or_default();
+        if !partial.bytes.is_empty() {
+            console_log!("Resuming download of {} from byte {}", file_name, partial.bytes.len());
+        }
+        let mut decoder = pipeline::StreamingDecompressor::new(compression);
+        let fetched = download::fetch_model(url, &self.config, &mut partial, &mut decoder, &self.download_watch, |attempt, reason, delay_ms| {
+            console_warn!("Download of {} failed (attempt {}): {}; retrying in {:.0} ms", file_name, attempt, reason, delay_ms);
+        }).await;
+        let fetched = match fetched {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                if partial.range_header().is_some() {
+                    self.partial_downloads.insert(file_name.to_string(), partial);
+                }
+                return Err(e.into());
+            }
+        };
+        if let Some(expected) = sha256 {
+            download::verify_sha256(file_name, &fetched, expected).await?;
+        }
+        if let Some(store) = &store {
+            if let Err(e) = store.put(file_name, version, &fetched).await {
+                console_warn!("Storing {} in the persistent cache failed: {}", file_name, js_filter::describe_js_error(&e));
+            }
+        }
+        let fetched_len = fetched.len();
+        let model_bytes = decoder.finish(&fetched).map_err(|reason| {
+            EngineError::DecompressionError(format!("Cannot decompress '{}': {}", file_name, reason))
+        })?;
+        if model_bytes.len() != fetched_len {
+            console_log!("Decompressed model {}: {} -> {} bytes", file_name, fetched_len, model_bytes.len());
+        }
+        console_log!("Loaded {} bytes for model: {}", model_bytes.len(), file_name);
+        Ok(model_bytes)
+    }
+
+    fn load_tract_model(&mut self, model_bytes: &[u8], prefetched: Option<TractPlan>, model_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        console_log!("Loading ONNX model with tract: {}", model_name);
+        
+        // Parse, optimize (now or when idle) and make the model runnable;
+        // prefetched plans were optimized in idle time already
+        let deferred = self.config.deferred_optimization && prefetched.is_none();
+        let load = |half: bool| match (deferred, half) {
+            (true, half) => pipeline::inference::load_unoptimized_plan(model_bytes, half),
+            (false, true) => pipeline::inference::load_half_plan(model_bytes),
+            (false, false) => pipeline::load_plan(model_bytes),
+        };
+        let half_plan = match prefetched {
+            Some(plan) => Some(Ok(plan)),
+            None => self.config.half_precision.then(|| load(true)),
+        };
+        let model = match half_plan {
+            Some(Ok(plan)) => plan,
+            Some(Err(e)) => {
+                let reason = e.to_string();
+                console_warn!("Cannot run {} in f16: {}; loading it in f32", model_name, reason);
+                self.emit_event("precision_fallback", serde_json::json!({ "name": model_name, "reason": reason }));
+                load(false)?
+            }
+            None => load(false)?,
+        };
+        self.check_plan_shapes(&model, model_name)?;
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == model_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+        pipeline::inference::check_quantization(&model, metadata).map_err(EngineError::ModelShapeMismatch)?;
+
+        // Store the model in our HashMap; earlier simulated results are stale now
+        if deferred {
+            self.defer_optimization(model_name, &model);
+        }
+        self.tract_models.insert(model_name.to_string(), model);
+        self.result_cache.invalidate_style(model_name);
+        
+        console_log!("ONNX model loaded successfully: {}", model_name);
+        Ok(())
+    }
+
+    /// Optimizes a copy of `plan` when the page is next idle, for
+    /// `swap_optimized_plan` to pick up. Unloading the model meanwhile
+    /// discards the result.
+    fn defer_optimization(&mut self, model_name: &str, plan: &TractPlan) {
+        let slot = Rc::new(RefCell::new(None));
+        self.deferred_plans.insert(model_name.to_string(), slot.clone());
+        let unoptimized = pipeline::inference::Unoptimized::of(plan);
+        let name = model_name.to_string();
+        let optimize = move || {
+            // Only the task holds the slot once the model is unloaded
+            if Rc::strong_count(&slot) == 1 {
+                return;
+            }
+            match unoptimized.optimize() {
+                Ok(plan) => *slot.borrow_mut() = Some(plan),
+                Err(e) => console_warn!("Optimizing {} failed: {}; keeping the unoptimized plan", name, e),
+            }
+        };
+        // Outside a browser there's no event loop to wait on
+        if scope::Scope::current().is_none() {
+            optimize();
+            return;
+        }
+        wasm_bindgen_futures::spawn_local(async move {
+            scope::idle().await;
+            optimize();
+        });
+    }
+
+    /// Replaces `model_name`'s plan with its optimized one if idle-time
+    /// optimization has finished it.
+    fn swap_optimized_plan(&mut self, model_name: &str) {
+        let ready = self.deferred_plans.get(model_name).and_then(|slot| slot.borrow_mut().take());
+        let Some(plan) = ready else {
+            return;
+        };
+        self.deferred_plans.remove(model_name);
+        self.tract_models.insert(model_name.to_string(), plan);
+        self.tile_costs.remove(model_name);
+        console_log!("Swapped in the optimized plan of {}", model_name);
+        self.emit_event("model_optimized", serde_json::json!({ "name": model_name }));
+    }
+
+    /// Lowers the model to compute shaders on the WebGPU device. Fails on ops
+    /// the shaders don't cover, leaving the model to the CPU runtimes.
+    async fn load_gpu_model(&mut self, model_bytes: &[u8], model_name: &str) -> Result<(), String> {
+        console_log!("Loading ONNX model on WebGPU: {}", model_name);
+        let graph = pipeline::graph::lower_onnx(model_bytes)?;
+        let metadata = self.model_registry.iter().find(|m| m.name == model_name).ok_or("Model not found")?;
+        if metadata.quantization.is_some() {
+            return Err("the shaders don't take 8-bit inputs".to_string());
+        }
+        let shape = [3, metadata.input_height as usize, metadata.input_width as usize];
+        let output_shape = graph.shapes(shape)?[graph.output];
+        if output_shape != shape {
+            return Err(format!("model output shape {:?} doesn't match input shape {:?}", output_shape, shape));
+        }
+        let device = self.webgpu_device.clone().ok_or("no WebGPU device")?;
+        let model = gpu::GpuModel::new(&device, graph).await?;
+        self.gpu_models.insert(model_name.to_string(), model);
+        self.result_cache.invalidate_style(model_name);
+        Ok(())
+    }
+
+    /// Whether `style_name` is an ONNX style whose tract plan leaves its input
+    /// height and width symbolic, so it isn't bound to its registered size.
+    fn has_dynamic_size(&self, style_name: &str) -> bool {
+        let Some(metadata) = self.model_registry.iter().find(|m| m.name == style_name && m.kind == ModelKind::Onnx) else {
+            return false;
+        };
+        self.tract_models
+            .get(style_name)
+            .and_then(|plan| pipeline::plan_shapes(plan).ok())
+            .is_some_and(|(input, _)| pipeline::shapes::has_dynamic_size(&input, metadata.layout))
+    }
+
+    /// Fails with `ModelShapeMismatch` when the plan's declared shapes don't
+    /// match the registry entry, unless `trust_model_shapes` lets a concrete
+    /// graph input correct it (announced as a `"metadata_corrected"` event).
+    fn check_plan_shapes(&mut self, plan: &TractPlan, model_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (input, output) = pipeline::plan_shapes(plan)?;
+        let index = self.model_registry
+            .iter()
+            .position(|m| m.name == model_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+        let metadata = &self.model_registry[index];
+        if pipeline::shapes::has_dynamic_size(&input, metadata.layout) {
+            console_log!("{} takes any input size; images run at their own size", model_name);
+        }
+        let Err(mismatch) = pipeline::check_model_shapes(&input, &output, metadata) else {
+            return Ok(());
+        };
+
+        let corrected = pipeline::corrected_metadata(&input, metadata)
+            .filter(|corrected| self.config.trust_model_shapes && pipeline::check_model_shapes(&input, &output, corrected).is_ok());
+        let Some(corrected) = corrected else {
+            return Err(Box::new(EngineError::ModelShapeMismatch(format!("Cannot load '{}': {}", model_name, mismatch))));
+        };
+        console_warn!("Correcting metadata of {}: {}", model_name, mismatch);
+        let shape = |m: &ModelMetadata| serde_json::json!({ "width": m.input_width, "height": m.input_height, "channels": m.input_channels });
+        self.emit_event("metadata_corrected", serde_json::json!({
+            "name": model_name,
+            "from": shape(metadata),
+            "to": shape(&corrected),
+            "reason": mismatch.to_string(),
+        }));
+        self.model_registry[index] = corrected;
+        self.result_cache.invalidate_style(model_name);
+        Ok(())
+    }
+
+    #[wasm_bindgen] 
+    pub async fn process_image(&mut self, image_data_url: &str, style_name: &str, strength: f32) -> Result<String, JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("process_image", Some(style_name));
+        let result = self.process_image_result(image_data_url, style_name, strength, &ProcessOptions::default()).await;
+        Ok(self.reporter.finish(started, result)?.data_url)
+    }
+
+    /// Runs `style_name` on an already normalized `[1, 3, height, width]`
+    /// tensor in `layout` (`"nchw"` or `"nhwc"`), returning the raw stylized
+    /// tensor in the same layout. No strength blending or encoding happens,
+    /// and the returned array is always a fresh copy.
+    #[wasm_bindgen]
+    pub async fn run_inference_raw(&mut self, style_name: &str, input: &js_sys::Float32Array, width: u32, height: u32, layout: &str) -> Result<js_sys::Float32Array, JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("run_inference_raw", Some(style_name));
+        let result = self.run_raw(style_name, input, width, height, layout).await;
+        self.record_processed(style_name, result.as_ref().ok().map(|(_, inferred)| (inferred.backend, inferred.from_cache, inferred.inference_ms)));
+        self.reporter.finish(started, result.map(|(output, _)| output))
+    }
+
+    async fn run_raw(&mut self, style_name: &str, input: &js_sys::Float32Array, width: u32, height: u32, layout: &str) -> Result<(js_sys::Float32Array, RawInference), JsValue> {
+        let layout = pipeline::TensorLayout::parse(layout).map_err(EngineError::InvalidInput)?;
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", style_name)))?;
+        if (width, height) != (metadata.input_width, metadata.input_height) {
+            return Err(EngineError::InvalidInput(format!(
+                "'{}' takes {}x{} tensors, got {}x{}",
+                style_name, metadata.input_width, metadata.input_height, width, height
+            )).into());
+        }
+        let expected_len = width as usize * height as usize * 3;
+        if input.length() as usize != expected_len {
+            return Err(EngineError::InvalidInput(format!(
+                "Tensor has {} values; {}x{}x3 needs {}", input.length(), width, height, expected_len
+            )).into());
+        }
+
+        if !self.loaded_models.contains_key(style_name) {
+            self.fetch_and_load_model(style_name).await?;
+        }
+        self.touch_model(style_name);
+
+        let [mut tensor, mut scratch] = std::mem::take(&mut self.input_pool);
+        tensor.resize(expected_len, 0.0);
+        input.copy_to(&mut tensor);
+        layout.to_interleaved(&mut tensor, &mut scratch);
+        self.in_flight_model = Some(style_name.to_string());
+        let started = now_ms();
+        let inferred = self.run_neural_inference(&tensor, style_name).await;
+        let inference_ms = now_ms() - started;
+        self.in_flight_model = None;
+        self.input_pool = [tensor, scratch];
+        let inferred = inferred?;
+
+        // Float32Array::from copies, so callers never see engine memory
+        let output = js_sys::Float32Array::from(&layout.from_interleaved(inferred.tensor)[..]);
+        Ok((output, RawInference { backend: inferred.backend, from_cache: inferred.from_cache, inference_ms }))
+    }
+
+    /// Stylizes raw RGBA bytes, such as `ImageData.data`, without a data URL
+    /// or any DOM decode. Returns RGBA bytes at the same `width` x `height`,
+    /// resampled from the model's resolution, with the alpha of `pixels`.
+    #[wasm_bindgen]
+    pub async fn process_pixels(&mut self, pixels: &[u8], width: u32, height: u32, style_name: &str, strength: f32) -> Result<Vec<u8>, JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("process_pixels", Some(style_name));
+        let result = self.stylize_pixels(pixels, (width, height), style_name, strength).await;
+        self.record_processed(style_name, result.as_ref().ok().map(|(_, inferred)| (inferred.backend, inferred.from_cache, inferred.inference_ms)));
+        self.reporter.finish(started, result.map(|(output, _)| output))
+    }
+
+    async fn stylize_pixels(&mut self, pixels: &[u8], (width, height): (u32, u32), style_name: &str, strength: f32) -> Result<(Vec<u8>, RawInference), JsValue> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize * 4 {
+            return Err(EngineError::InvalidInput(format!(
+                "{} bytes are not {}x{} RGBA pixels", pixels.len(), width, height
+            )).into());
+        }
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", style_name)))?;
+        let (model_width, model_height) = (metadata.input_width, metadata.input_height);
+        self.reporter.update(|context| {
+            context.input_width = Some(width);
+            context.input_height = Some(height);
+        });
+
+        if !self.loaded_models.contains_key(style_name) {
+            self.fetch_and_load_model(style_name).await?;
+        }
+        self.touch_model(style_name);
+
+        let filter = pipeline::ResizeFilter::default();
+        let input_tensor = pipeline::rgba_to_tensor(&pipeline::resize_rgba(pixels, width, height, model_width, model_height, filter));
+        self.in_flight_model = Some(style_name.to_string());
+        let started = now_ms();
+        let inferred = self.run_neural_inference(&input_tensor, style_name).await;
+        let inference_ms = now_ms() - started;
+        self.in_flight_model = None;
+        let inferred = inferred?;
+
+        let blended = pipeline::apply_strength(&input_tensor, inferred.tensor, strength.clamp(0.0, 1.0), None, ColorSpace::Srgb);
+        let stylized = pipeline::tensor_to_rgba(&blended, (model_width * model_height) as usize);
+        let mut output = pipeline::resize_rgba(&stylized, model_width, model_height, width, height, filter);
+        if let Some(alpha) = pipeline::alpha_channel(pixels) {
+            pipeline::tensor::restore_alpha(&mut output, &alpha);
+        }
+        Ok((output, RawInference { backend: inferred.backend, from_cache: inferred.from_cache, inference_ms }))
+    }
+
+    /// Only the decode, resize and normalize stage of `process_image` for
+    /// `style_name`: returns `{ tensor, width, height, layout }` where
+    /// `tensor` is a `Float32Array` in `layout` (`"nchw"` unless `"nhwc"` is
+    /// given), ready for `run_inference_raw`. No model is loaded.
+    #[wasm_bindgen]
+    pub async fn preprocess_image(&mut self, image_data_url: &str, style_name: &str, layout: Option<String>) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let layout = parse_layout(layout)?;
+        let source = source::load_source(image_data_url).await?;
+        let preprocessed = self.preprocess_source(&source, style_name, &ProcessOptions::default(), None)?;
+        let (width, height) = preprocessed.input_size;
+        let result = to_js(&serde_json::json!({ "width": width, "height": height, "layout": layout }))?;
+        let tensor = js_sys::Float32Array::from(&layout.from_interleaved(preprocessed.input_tensor)[..]);
+        js_sys::Reflect::set(&result, &"tensor".into(), &tensor)?;
+        Ok(result)
+    }
+
+    /// The second half of `process_image`: blends a stylized tensor (e.g.
+    /// from `run_inference_raw`) with `original` at `strength` and encodes it
+    /// as a PNG data URL. Both tensors are in `layout` at the model
+    /// resolution of `style_name`; without `original`, `strength` must be 1.
+    #[wasm_bindgen]
+    pub fn postprocess_tensor(&mut self, tensor: &js_sys::Float32Array, style_name: &str, strength: f32, original: Option<js_sys::Float32Array>, layout: Option<String>) -> Result<String, JsValue> {
+        self.check_live()?;
+        let layout = parse_layout(layout)?;
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", style_name)))?;
+        let (width, height) = (metadata.input_width, metadata.input_height);
+        let expected_len = width as usize * height as usize * 3;
+        let read = |array: &js_sys::Float32Array, what: &str| -> Result<Vec<f32>, EngineError> {
+            if array.length() as usize != expected_len {
+                return Err(EngineError::InvalidInput(format!(
+                    "{} has {} values; {}x{}x3 needs {}", what, array.length(), width, height, expected_len
+                )));
+            }
+            let mut values = array.to_vec();
+            layout.to_interleaved(&mut values, &mut Vec::new());
+            Ok(values)
+        };
+        let stylized = read(tensor, "Tensor")?;
+        let blended = match original {
+            Some(original) => pipeline::apply_strength(&read(&original, "Original")?, stylized, strength.clamp(0.0, 1.0), None, ColorSpace::Srgb),
+            None if strength >= 1.0 => stylized,
+            None => return Err(EngineError::InvalidInput("Blending below strength 1 needs the original tensor".to_string()).into()),
+        };
+
+        let pixels = pipeline::tensor_to_rgba(&blended, (width * height) as usize);
+        Ok(self.encode_rgba(&pixels, width, height, &ProcessOptions::default())?.data_url)
+    }
+
+    /// Ranks registered styles for an image from cheap statistics (brightness,
+    /// saturation, Sobel edge density, warm/cool hues) against each entry's
+    /// `style_affinity`. Returns up to `top_n` `{ name, score, reasons }`,
+    /// best first. No model is loaded.
+    #[wasm_bindgen]
+    pub async fn suggest_styles(&mut self, image_data_url: &str, top_n: u32) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        const STATS_SIZE: u32 = 64;
+
+        let pixels = source::load_source(image_data_url).await?.sample_rgba(STATS_SIZE, STATS_SIZE)?;
+
+        let stats = pipeline::ImageStats::from_rgba(&pixels, STATS_SIZE, STATS_SIZE);
+        let suggestions = pipeline::rank_styles(&stats, &self.model_registry, top_n as usize);
+        to_js(&suggestions)
+    }
+
+    /// `process_image` with per-call options, returning a `ProcessResult`
+    /// object: `{ data_url, format, mime_type, format_fallback, width, height,
+    /// backend, simulated, from_cache, downscale_factor, timings }`.
+    #[wasm_bindgen]
+    pub async fn process_image_v2(&mut self, image_data_url: &str, style_name: &str, strength: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("process_image_v2", Some(style_name));
+        let result = async {
+            let options = parse_options(options)?;
+            let result = self.process_image_result(image_data_url, style_name, strength, &options).await?;
+            to_js(&result)
+        }.await;
+        self.reporter.finish(started, result)
+    }
+
+    /// `process_image_v2` returning the stylized pixels as `ImageData`, ready
+    /// for `putImageData` or for transferring `data.buffer` to another
+    /// thread, without encoding a data URL. Options that only affect
+    /// encoding (`format`, `quality`, `strength_variants`, `debug_saliency`)
+    /// are ignored.
+    #[wasm_bindgen]
+    pub async fn process_image_data(&mut self, image_data_url: &str, style_name: &str, strength: f32, options: JsValue) -> Result<ImageData, JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("process_image_data", Some(style_name));
+        let result = async {
+            console_log!("Processing image to ImageData with style: {}", style_name);
+            let options = ProcessOptions { strength_variants: Vec::new(), debug_saliency: false, ..parse_options(options)? };
+            options.report_progress(Stage::Decode, 0.0);
+            let source = source::load_source(image_data_url).await?;
+            let result = self.process_source(&source, style_name, strength, &options, None).await;
+            self.record_processed(style_name, result.as_ref().ok().map(|r| (r.backend, r.from_cache, r.timings.inference_ms)));
+            let rendered = result?;
+            let image_data = rendered.image_data();
+            self.recycle(rendered);
+            image_data
+        }.await;
+        self.reporter.finish(started, result)
+    }
+
+    /// Arbitrary style transfer with an `adain` registry entry: like
+    /// `process_image_v2`, but the style comes from `style_image_data_url`.
+    /// The style image is drawn at the model's input size and its feature
+    /// statistics are computed once per call. Without a usable encoder and
+    /// decoder the content takes on the style image's color statistics.
+    #[wasm_bindgen]
+    pub async fn process_with_style_image(&mut self, image_data_url: &str, style_image_data_url: &str, model_name: &str, strength: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("process_with_style_image", Some(model_name));
+        let result = async {
+            let options = parse_options(options)?;
+            let kind = self.model_registry
+                .iter()
+                .find(|m| m.name == model_name)
+                .map(|m| m.kind)
+                .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", model_name)))?;
+            if kind != ModelKind::Adain {
+                return Err(EngineError::InvalidInput(format!("'{}' is not an AdaIN model", model_name)).into());
+            }
+            let style_source = source::load_source(style_image_data_url).await?;
+            self.in_flight_model = Some(model_name.to_string());
+            let style = self.adain_style_of(&style_source, model_name, &options).await;
+            self.in_flight_model = None;
+            self.adain_style = Some(style?);
+            let result = self.process_image_result(image_data_url, model_name, strength, &options).await;
+            self.adain_style = None;
+            to_js(&result?)
+        }.await;
+        self.reporter.finish(started, result)
+    }
+
+    /// Loads `model_name` if needed and measures the style image as the
+    /// model will see it.
+    async fn adain_style_of(&mut self, source: &ElementSource, model_name: &str, options: &ProcessOptions) -> Result<AdainStyle, JsValue> {
+        if !self.loaded_models.contains_key(model_name) {
+            self.fetch_and_load_model(model_name).await?;
+        }
+        let Preprocessed { mut input_tensor, input_size, .. } = self.preprocess_source(source, model_name, options, None)?;
+        let model_space = self.model_registry
+            .iter()
+            .find(|m| m.name == model_name)
+            .map_or(ColorSpace::Srgb, |m| m.model_color_space);
+        pipeline::color::convert(&mut input_tensor, options.working_space, model_space);
+        let features = self.tract_models.get(model_name).and_then(|encoder| {
+            pipeline::adain::feature_stats(encoder, &input_tensor, input_size)
+                .map_err(|e| console_warn!("Cannot encode the style image for {}: {}", model_name, e))
+                .ok()
+        });
+        Ok(AdainStyle { pixels: pipeline::adain::ChannelStats::of_rgb(&input_tensor), features })
+    }
+
+    async fn process_image_result(&mut self, image_data_url: &str, style_name: &str, strength: f32, options: &ProcessOptions) -> Result<ProcessResult, JsValue> {
+        console_log!("Processing image with style: {}", style_name);
+        let started = now_ms();
+
+        let result = async {
+            options.report_progress(Stage::Decode, 0.0);
+            let source = source::load_source(image_data_url).await?;
+            let decode_ms = now_ms() - started;
+            let rendered = self.process_source(&source, style_name, strength, options, None).await?;
+            self.finish(rendered, options, decode_ms, started)
+        }.await;
+        self.record_processed(style_name, result.as_ref().ok().map(|r| (r.backend, r.from_cache, r.timings.inference_ms)));
+        result
+    }
+
+    /// Like `process_image`, but draws the result straight into `target`
+    /// without producing a data URL.
+    ///
+    /// The target is resized to the model output unless `options.keep_size`
+    /// is set, in which case the result is scaled to fit its current size.
+    #[wasm_bindgen]
+    pub async fn process_to_canvas(&mut self, image_data_url: &str, style_name: &str, strength: f32, target: &HtmlCanvasElement, options: JsValue) -> Result<(), JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("process_to_canvas", Some(style_name));
+        let result = self.draw_to_canvas(image_data_url, style_name, strength, target, options).await;
+        self.reporter.finish(started, result)
+    }
+
+    async fn draw_to_canvas(&mut self, image_data_url: &str, style_name: &str, strength: f32, target: &HtmlCanvasElement, options: JsValue) -> Result<(), JsValue> {
+        console_log!("Processing image to canvas with style: {}", style_name);
+        let options = parse_options(options)?;
+        let target_ctx = target
+            .get_context("2d")?
+            .ok_or_else(|| EngineError::InvalidInput("Target canvas has no 2d context".to_string()))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        let result = async {
+            options.report_progress(Stage::Decode, 0.0);
+            let source = source::load_source(image_data_url).await?;
+            let rendered = self.process_source(&source, style_name, strength, &options, None).await?;
+            let output = rendered.image_data()?;
+
+            if options.keep_size {
+                let Surface::Canvas(canvas, ctx) = &rendered.surface else {
+                    return Err(EngineError::InvalidInput("keep_size needs a DOM canvas".to_string()).into());
+                };
+                ctx.put_image_data(&output, 0.0, 0.0)?;
+                let (x, y, width, height) = fit_rect(output.width(), output.height(), target.width(), target.height());
+                target_ctx.clear_rect(0.0, 0.0, target.width() as f64, target.height() as f64);
+                target_ctx.draw_image_with_html_canvas_element_and_dw_and_dh(canvas, x, y, width, height)?;
+            } else {
+                target.set_width(output.width());
+                target.set_height(output.height());
+                target_ctx.put_image_data(&output, 0.0, 0.0)?;
+            }
+            let inference = (rendered.backend, rendered.from_cache, rendered.timings.inference_ms);
+            self.recycle(rendered);
+            Ok(inference)
+        }.await;
+        self.record_processed(style_name, result.as_ref().ok().copied());
+        result.map(|_| ())
+    }
+
+    /// Stylizes an already decoded `<img>`, `<video>` (current frame),
+    /// `<canvas>` or `ImageBitmap` without a data URL round trip, returning a
+    /// `ProcessResult`. Cross-origin sources that taint the canvas fail with a
+    /// `SecurityError`. Bitmaps are closed afterwards only when
+    /// `options.consume` is set.
+    #[wasm_bindgen]
+    pub async fn process_element(&mut self, source: &JsValue, style_name: &str, strength: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let reported = self.reporter.begin("process_element", Some(style_name));
+        let result = async {
+            console_log!("Processing element with style: {}", style_name);
+            let started = now_ms();
+            let options = parse_options(options)?;
+            let source = ElementSource::from_js(source)?;
+            let rendered = self.process_source(&source, style_name, strength, &options, None).await;
+            if options.consume {
+                source.close();
+            }
+            let result = rendered.and_then(|rendered| self.finish(rendered, &options, 0.0, started));
+            self.record_processed(style_name, result.as_ref().ok().map(|r| (r.backend, r.from_cache, r.timings.inference_ms)));
+            to_js(&result?)
+        }.await;
+        self.reporter.finish(reported, result)
+    }
+
+    /// Stylizes a WebCodecs `VideoFrame`, returning a new RGBA `VideoFrame`
+    /// with the same timestamp and duration at the output size, so the engine
+    /// can sit between a decoder (or `MediaStreamTrackProcessor`) and an
+    /// encoder. I420, I420A, NV12 and the RGB formats are read from the
+    /// frame's planes directly; other formats are converted by the browser
+    /// where it can. The input frame is closed only when `options.consume`
+    /// is set; closing the returned one is up to the caller. Frames join the
+    /// sequence begun with `begin_sequence` for the same style, which damps
+    /// flicker between them.
+    #[wasm_bindgen]
+    pub async fn process_video_frame(&mut self, frame: &JsValue, style_name: &str, strength: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let reported = self.reporter.begin("process_video_frame", Some(style_name));
+        let result = async {
+            let options = parse_options(options)?;
+            let frame = frame.dyn_ref::<video::VideoFrame>()
+                .ok_or_else(|| EngineError::InvalidInput("Source must be a VideoFrame".to_string()))?;
+            let output = self.stylize_video_frame(frame, style_name, strength, &options).await;
+            if options.consume {
+                frame.close();
+            }
+            Ok(output?.into())
+        }.await;
+        self.reporter.finish(reported, result)
+    }
+
+    /// Stylizes a live video track until it ends or `options.signal` aborts,
+    /// reading frames from a `MediaStreamTrackProcessor` and writing them to
+    /// a `MediaStreamTrackGenerator`, whose track can be shown in a `<video>`
+    /// or sent over WebRTC. Frames captured while the previous one was being
+    /// stylized are dropped, so a slow model lowers the frame rate instead
+    /// of adding latency. Call `begin_sequence` with the same style first
+    /// to smooth the output over time. Resolves with a `StreamSummary` and
+    /// ends the generator's track; the engine takes no other calls until
+    /// then.
+    #[wasm_bindgen]
+    pub async fn stream_video(&mut self, processor: &JsValue, generator: &JsValue, style_name: &str, strength: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let reported = self.reporter.begin("stream_video", Some(style_name));
+        let result = async {
+            let options = parse_options(options)?;
+            let processor = processor.dyn_ref::<video::MediaStreamTrackProcessor>()
+                .ok_or_else(|| EngineError::InvalidInput("Input must be a MediaStreamTrackProcessor".to_string()))?;
+            let generator = generator.dyn_ref::<video::MediaStreamTrackGenerator>()
+                .ok_or_else(|| EngineError::InvalidInput("Output must be a MediaStreamTrackGenerator".to_string()))?;
+            let reader: web_sys::ReadableStreamDefaultReader = processor.readable().get_reader().unchecked_into();
+            let writer = generator.writable().get_writer()?;
+            let streamed = self.pump_frames(&reader, &writer, style_name, strength, &options).await;
+            // Whichever way the stream ended, stop the camera side and end the output track
+            let _ = reader.cancel();
+            let _ = writer.close();
+            to_js(&streamed?)
+        }.await;
+        self.reporter.finish(reported, result)
+    }
+
+    async fn pump_frames(&mut self, reader: &web_sys::ReadableStreamDefaultReader, writer: &web_sys::WritableStreamDefaultWriter, style_name: &str, strength: f32, options: &ProcessOptions) -> Result<StreamSummary, JsValue> {
+        let mut pacer = pipeline::frame::FramePacer::default();
+        while !options.cancelled() {
+            let chunk = wasm_bindgen_futures::JsFuture::from(reader.read()).await?;
+            if js_sys::Reflect::get(&chunk, &"done".into())?.as_bool() == Some(true) {
+                break;
+            }
+            let frame: video::VideoFrame = js_sys::Reflect::get(&chunk, &"value".into())?.unchecked_into();
+            let timestamp = frame.timestamp();
+            if !pacer.admit(timestamp) {
+                frame.close();
+                continue;
+            }
+            let started = now_ms();
+            let stylized = self.stylize_video_frame(&frame, style_name, strength, options).await;
+            frame.close();
+            let stylized = match stylized {
+                Err(_) if options.cancelled() => break,
+                stylized => stylized?,
+            };
+            // The generator closes frames once they're written
+            wasm_bindgen_futures::JsFuture::from(writer.write_with_chunk(&stylized)).await?;
+            pacer.finished(timestamp, now_ms() - started);
+        }
+        Ok(StreamSummary { frames_processed: pacer.processed, frames_skipped: pacer.skipped })
+    }
+
+    /// Stylizes one frame into a new RGBA frame with the same timestamp and
+    /// duration, as the next frame of the sequence in progress when it uses
+    /// `style_name`; the caller closes `frame`.
+    async fn stylize_video_frame(&mut self, frame: &video::VideoFrame, style_name: &str, strength: f32, options: &ProcessOptions) -> Result<video::VideoFrame, JsValue> {
+        let (timestamp, duration) = (frame.timestamp(), frame.duration());
+        let source = ElementSource::Pixels(video::read_frame(frame).await?);
+        let mut sequence = self.sequence.take_if(|sequence| sequence.style_name == style_name);
+        let rendered = self.process_source(&source, style_name, strength, options, sequence.as_mut().map(|sequence| &mut sequence.temporal)).await;
+        // end_sequence or a new begin_sequence may have run meanwhile
+        if self.sequence.is_none() && !self.disposed {
+            self.sequence = sequence;
+        }
+        self.record_processed(style_name, rendered.as_ref().ok().map(|r| (r.backend, r.from_cache, r.timings.inference_ms)));
+        let rendered = rendered?;
+        let frame = video::rgba_frame(&rendered.pixels, rendered.size, timestamp, duration);
+        self.recycle(rendered);
+        frame
+    }
+
+    /// Like `process_image_v2`, but people are found first with a
+    /// segmentation model and kept out of the style while the background is
+    /// fully stylized; `options.invert_mask` styles only the people instead.
+    /// The first registry entry of kind `segmentation` is used, or else the
+    /// built-in `portrait_segmentation` entry, which is registered on first
+    /// use. When no model can run, the saliency heuristic behind
+    /// `protect_subject` guesses the subject instead. `options.mask` and
+    /// `strength_map` still apply on top.
+    #[wasm_bindgen]
+    pub async fn process_with_segmentation(&mut self, image_data_url: &str, style_name: &str, strength: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let reported = self.reporter.begin("process_with_segmentation", Some(style_name));
+        let result = async {
+            console_log!("Processing image with segmentation and style: {}", style_name);
+            let started = now_ms();
+            let options = parse_options(options)?;
+            options.report_progress(Stage::Decode, 0.0);
+            let source = source::load_source(image_data_url).await?;
+            let decode_ms = now_ms() - started;
+            let person = self.find_people(&source).await?;
+            self.call_mask = Some(person.map_values(|person| pipeline::segmentation::subject_strength(person, options.invert_mask)));
+            let rendered = self.process_source(&source, style_name, strength, &options, None).await;
+            self.call_mask = None;
+            let result = rendered.and_then(|rendered| self.finish(rendered, &options, decode_ms, started));
+            self.record_processed(style_name, result.as_ref().ok().map(|r| (r.backend, r.from_cache, r.timings.inference_ms)));
+            to_js(&result?)
+        }.await;
+        self.reporter.finish(reported, result)
+    }
+
+    /// Probability of each pixel of `source` showing a person, at the
+    /// segmentation model's input size.
+    async fn find_people(&mut self, source: &ElementSource) -> Result<pipeline::StrengthMap, JsValue> {
+        let metadata = match self.model_registry.iter().find(|m| m.kind == ModelKind::Segmentation) {
+            Some(metadata) => metadata.clone(),
+            None => {
+                let metadata = pipeline::portrait_segmentation();
+                self.model_registry.push(metadata.clone());
+                metadata
+            }
+        };
+        if !self.loaded_models.contains_key(&metadata.name) {
+            self.fetch_and_load_model(&metadata.name).await?;
+        }
+        self.touch_model(&metadata.name);
+
+        let (width, height) = (metadata.input_width, metadata.input_height);
+        let tensor = pipeline::rgba_to_tensor(&source.sample_rgba(width, height)?);
+        let (person, (width, height)) = match self.tract_models.get(&metadata.name) {
+            Some(plan) => {
+                let layout = metadata.layout;
+                let input = layout.from_interleaved(pipeline::normalize::model_input(&tensor, &metadata).into_owned());
+                let (output, shape) = pipeline::run_features(plan, &input, layout.image_shape(3, height as usize, width as usize))
+                    .map_err(|e| EngineError::InferenceError(format!("Segmentation with '{}' failed: {}", metadata.name, e)))?;
+                let (output, shape) = layout.to_nchw(output, &shape);
+                pipeline::segmentation::person_probability(&output, &shape)
+                    .map_err(|reason| EngineError::InferenceError(format!("Segmentation with '{}': {}", metadata.name, reason)))?
+            }
+            None => (pipeline::saliency_map(&tensor, width, height), (width, height)),
+        };
+        Ok(pipeline::StrengthMap::new(person, width, height).map_err(EngineError::InferenceError)?)
+    }
+
+    /// Enlarges RGBA `pixels` `factor` times with the registry's
+    /// super-resolution model for that factor, or only resizes them
+    /// bicubically when it isn't available.
+    async fn super_resolve(&mut self, pixels: &[u8], (width, height): (u32, u32), factor: u32) -> Result<Vec<u8>, JsValue> {
+        let metadata = match self.model_registry.iter().find(|m| m.kind == ModelKind::SuperResolution && m.upscale == factor) {
+            Some(metadata) => metadata.clone(),
+            None => {
+                let metadata = pipeline::super_resolution(factor);
+                self.model_registry.push(metadata.clone());
+                metadata
+            }
+        };
+        if !self.loaded_models.contains_key(&metadata.name) {
+            self.fetch_and_load_model(&metadata.name).await?;
+        }
+        self.touch_model(&metadata.name);
+
+        // The model only replaces the colors; alpha keeps the resize
+        let (upscaled_width, upscaled_height) = (width * factor, height * factor);
+        let mut upscaled = pipeline::resize_rgba(pixels, width, height, upscaled_width, upscaled_height, pipeline::ResizeFilter::Bicubic);
+        let Some(plan) = self.tract_models.get(&metadata.name) else {
+            return Ok(upscaled);
+        };
+        let tile = (metadata.input_width, metadata.input_height);
+        let layout = metadata.layout;
+        let enlarged = pipeline::upscale::upscale_tiled(&pipeline::rgba_to_tensor(pixels), (width, height), tile, factor, |tile_tensor| {
+            let input = layout.from_interleaved(pipeline::normalize::model_input(tile_tensor, &metadata).into_owned());
+            let (output, shape) = pipeline::run_features(plan, &input, layout.image_shape(3, tile.1 as usize, tile.0 as usize))
+                .map_err(|e| e.to_string())?;
+            let (output, shape) = layout.to_nchw(output, &shape);
+            pipeline::upscale::check_output(&shape, tile, factor)?;
+            let mut output = pipeline::planar_to_interleaved(&output);
+            pipeline::normalize::model_output(&mut output, &metadata);
+            Ok(output)
+        })
+        .map_err(|reason: String| EngineError::InferenceError(format!("Upscaling with '{}' failed: {}", metadata.name, reason)))?;
+        let colors = pipeline::tensor_to_rgba(&enlarged, (upscaled_width * upscaled_height) as usize);
+        for (pixel, color) in upscaled.chunks_exact_mut(4).zip(colors.chunks_exact(4)) {
+            pixel[..3].copy_from_slice(&color[..3]);
+        }
+        Ok(upscaled)
+    }
+
+    /// Stylizes with two styles and mixes them before the strength blend:
+    /// `mix` 0 is all `style_a`, 1 all `style_b`, so 0.3 gives a 70/30
+    /// hybrid. With `options.mix_mask` the mix also varies per pixel. Both
+    /// models run at `style_a`'s input size. Returns a `ProcessResult`
+    /// whose `backend` is `style_a`'s; the tiling and letterbox options
+    /// aren't supported.
+    #[wasm_bindgen]
+    pub async fn process_image_dual(&mut self, image_data_url: &str, style_a: &str, style_b: &str, mix: f32, strength: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let reported = self.reporter.begin("process_image_dual", Some(style_a));
+        let result = async {
+            console_log!("Processing image with styles {} and {}", style_a, style_b);
+            let options = parse_options(options)?;
+            if !(0.0..=1.0).contains(&mix) {
+                return Err(EngineError::InvalidInput(format!("mix must be in [0, 1], got {}", mix)).into());
+            }
+            if options.tiled || options.time_budget_ms.is_some() || options.letterbox {
+                return Err(EngineError::InvalidInput("process_image_dual doesn't tile or letterbox; unset tiled, time_budget_ms and letterbox".to_string()).into());
+            }
+            if !self.model_registry.iter().any(|m| m.name == style_b) {
+                return Err(EngineError::ModelNotFound(format!("Model not found: {}", style_b)).into());
+            }
+            let mask = match &options.mix_mask {
+                Some(url) => Some(source::load_source(url).await?),
+                None => None,
+            };
+            self.call_mix = Some(StyleMix { style_name: style_b.to_string(), mix, mask });
+            let result = self.process_image_result(image_data_url, style_a, strength, &options).await;
+            self.call_mix = None;
+            if let Ok(result) = &result {
+                self.record_processed(style_b, Some((result.backend, result.from_cache, result.timings.inference_ms)));
+            }
+            to_js(&result?)
+        }.await;
+        self.reporter.finish(reported, result)
+    }
+
+    /// Runs the second style of a `process_image_dual` call on the first
+    /// style's input and mixes the two outputs.
+    async fn mix_second_style(&mut self, style_mix: &StyleMix, input_tensor: &[f32], size: (u32, u32), first: Inferred, options: &ProcessOptions, timings: &mut Timings) -> Result<Inferred, JsValue> {
+        let style_name = &style_mix.style_name;
+        if !self.loaded_models.contains_key(style_name) {
+            self.fetch_and_load_model(style_name).await?;
+        }
+        self.touch_model(style_name);
+        let second = self.infer_passes(input_tensor, size, style_name, options, Some(size), timings).await?;
+        timings.inference_ms = timings.pass_ms.iter().sum();
+        let mask = match &style_mix.mask {
+            Some(mask) => {
+                let pixels = mask.sample_rgba(size.0, size.1)?;
+                let map = pipeline::StrengthMap::from_mask(&pixels, size.0, size.1, false)
+                    .map_err(|reason| EngineError::InvalidInput(format!("mix_mask: {}", reason)))?;
+                Some(map.resampled(size.0, size.1))
+            }
+            None => None,
+        };
+        let mut tensor = Vec::new();
+        pipeline::blend_tensors_into(&first.tensor, &second.tensor, style_mix.mix, mask.as_deref(), options.working_space, &mut tensor);
+        Ok(Inferred { tensor, backend: first.backend, from_cache: first.from_cache && second.from_cache })
+    }
+
+    /// Plans tiled full-resolution processing of an image so it finishes
+    /// within `time_budget_ms`: `{ tile_width, tile_height, overlap, columns,
+    /// rows, tile_count, output_width, output_height, estimated_ms }`. The
+    /// per-tile cost comes from one timed warmup inference per loaded model
+    /// and the output size is also capped by `navigator.deviceMemory`.
+    ///
+    /// Processing calls with `options.time_budget_ms` announce the same plan
+    /// as a `"tile_plan"` event and then run it, returning output at the
+    /// plan's resolution.
+    #[wasm_bindgen]
+    pub async fn plan_tiles(&mut self, image_data_url: &str, style_name: &str, time_budget_ms: f64) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        if !(time_budget_ms.is_finite() && time_budget_ms > 0.0) {
+            return Err(EngineError::InvalidInput(format!("time_budget_ms must be positive, got {}", time_budget_ms)).into());
+        }
+        let source = source::load_source(image_data_url).await?;
+        let plan = self.tile_plan(style_name, source.dimensions(), time_budget_ms).await?;
+        to_js(&plan)
+    }
+
+    async fn tile_plan(&mut self, style_name: &str, (source_width, source_height): (u32, u32), time_budget_ms: f64) -> Result<pipeline::TilePlan, JsValue> {
+        if !self.loaded_models.contains_key(style_name) {
+            self.fetch_and_load_model(style_name).await?;
+        }
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", style_name)))?;
+        let (model_width, model_height) = (metadata.input_width, metadata.input_height);
+        let dynamic = self.has_dynamic_size(style_name);
+
+        let tile_ms = match self.tile_costs.get(style_name) {
+            Some(&cost) => cost,
+            None => self.warmup_inference(style_name).await?,
+        };
+
+        let device_memory_gb = capabilities::device_memory_gb();
+        Ok(pipeline::plan_tiles(&pipeline::TileBudget {
+            source_width,
+            source_height,
+            model_width,
+            model_height,
+            dynamic,
+            tile_ms,
+            device_memory_gb,
+            time_budget_ms,
+        }))
+    }
+
+    /// Runs `style_name` once on a dummy frame of its input size, loading it
+    /// first if needed, so that the lazy allocations of a model's first
+    /// inference happen now (e.g. behind a loading screen) rather than on
+    /// the first real image. Resolves with the milliseconds the inference
+    /// took. AdaIN entries need a style image, so they can't be warmed up
+    /// this way.
+    #[wasm_bindgen]
+    pub async fn warm_up(&mut self, style_name: &str) -> Result<f64, JsValue> {
+        self.check_live()?;
+        let reported = self.reporter.begin("warm_up", Some(style_name));
+        let result = async {
+            if !self.loaded_models.contains_key(style_name) {
+                self.fetch_and_load_model(style_name).await?;
+            }
+            self.touch_model(style_name);
+            self.in_flight_model = Some(style_name.to_string());
+            let warmed = self.warmup_inference(style_name).await;
+            self.in_flight_model = None;
+            warmed
+        }.await;
+        self.reporter.finish(reported, result)
+    }
+
+    /// Times one inference of `style_name` on noise, past the result cache
+    /// so that it neither answers the run nor keeps the noise in place of
+    /// real results. The first one after loading is kept as the model's
+    /// per-tile cost.
+    async fn warmup_inference(&mut self, style_name: &str) -> Result<f64, JsValue> {
+        let (model_width, model_height) = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .map(|m| (m.input_width, m.input_height))
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", style_name)))?;
+        let mut rng = pipeline::XorShift64::new(js_sys::Date::now() as u64);
+        let warmup: Vec<f32> = (0..model_width * model_height * 3).map(|_| rng.next_f32()).collect();
+        let started = now_ms();
+        self.run_neural_inference_at(&warmup, style_name, None, false).await?;
+        let cost = now_ms() - started;
+        self.tile_costs.entry(style_name.to_string()).or_insert(cost);
+        Ok(cost)
+    }
+
+    /// Times preprocessing, inference and postprocessing of `style_name` on
+    /// each backend that can run it here (tract on the CPU, WebGPU, a JS
+    /// filter, and the simulated fallback) over a synthetic source twice
+    /// the model input per side, and resolves with a `BenchmarkReport`.
+    /// SIMD and threads are fixed by the build and `init_thread_pool`, so
+    /// the report says whether the CPU run had them. Each backend gets one
+    /// warm-up run and then `iterations` timed ones (default 5, at most 20);
+    /// the result cache is bypassed.
+    #[wasm_bindgen]
+    pub async fn run_benchmark(&mut self, style_name: &str, iterations: Option<u32>) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let reported = self.reporter.begin("run_benchmark", Some(style_name));
+        let result = async {
+            let iterations = iterations.unwrap_or(pipeline::benchmark::DEFAULT_BENCHMARK_ITERATIONS);
+            if !(1..=pipeline::benchmark::MAX_BENCHMARK_ITERATIONS).contains(&iterations) {
+                return Err(EngineError::InvalidInput(format!("iterations must be from 1 to {}, got {}", pipeline::benchmark::MAX_BENCHMARK_ITERATIONS, iterations)).into());
+            }
+            if !self.loaded_models.contains_key(style_name) {
+                self.fetch_and_load_model(style_name).await?;
+            }
+            self.touch_model(style_name);
+            self.in_flight_model = Some(style_name.to_string());
+            let report = self.benchmark_backends(style_name, iterations).await;
+            self.in_flight_model = None;
+            to_js(&report?)
+        }.await;
+        self.reporter.finish(reported, result)
+    }
+
+    async fn benchmark_backends(&mut self, style_name: &str, iterations: u32) -> Result<BenchmarkReport, JsValue> {
+        let metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .cloned()
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", style_name)))?;
+        if matches!(metadata.kind, ModelKind::Segmentation | ModelKind::SuperResolution | ModelKind::Adain) {
+            return Err(EngineError::InvalidInput(format!("'{}' can't be benchmarked on its own", style_name)).into());
+        }
+        let (width, height) = (metadata.input_width, metadata.input_height);
+        let (source_width, source_height) = (width * 2, height * 2);
+        let source = pipeline::benchmark::synthetic_image(source_width, source_height, self.simulation_seed);
+        let filter = pipeline::ResizeFilter::default();
+
+        let mut backends = Vec::new();
+        if self.tract_models.contains_key(style_name) {
+            backends.push(Backend::Onnx);
+        }
+        if self.gpu_models.contains_key(style_name) && self.is_webgpu_ready() {
+            backends.push(Backend::WebGpu);
+        }
+        if self.js_filters.contains_key(style_name) {
+            backends.push(Backend::JsFilter);
+        }
+        backends.push(Backend::Simulated);
+
+        let mut results = Vec::with_capacity(backends.len());
+        for backend in backends {
+            let mut samples = [Vec::new(), Vec::new(), Vec::new()];
+            let mut error = None;
+            // The first run warms caches and shader pipelines and isn't timed
+            for run in 0..=iterations {
+                let started = now_ms();
+                let tensor = pipeline::rgba_to_tensor(&pipeline::resize_rgba(&source, source_width, source_height, width, height, filter));
+                let preprocessed = now_ms();
+                let stylized = match backend {
+                    Backend::Onnx => pipeline::run_plan(&self.tract_models[style_name], &tensor, &metadata).map_err(|e| e.to_string()),
+                    Backend::WebGpu => self.run_gpu_only(&tensor, &metadata).await,
+                    Backend::JsFilter => js_filter::run_js_filter(style_name, &self.js_filters[style_name], &tensor, width, height)
+                        .map_err(|e| e.to_string()),
+                    Backend::Simulated => Ok(pipeline::stylize(&tensor, &metadata, None, self.simulation_seed).tensor),
+                };
+                let inferred = now_ms();
+                let stylized = match stylized {
+                    Ok(stylized) => stylized,
+                    Err(reason) => {
+                        error = Some(reason);
+                        break;
+                    }
+                };
+                let blended = pipeline::apply_strength(&tensor, stylized, 1.0, None, ColorSpace::Srgb);
+                let pixels = pipeline::tensor_to_rgba(&blended, (width * height) as usize);
+                pipeline::resize_rgba(&pixels, width, height, source_width, source_height, filter);
+                let finished = now_ms();
+                if run > 0 {
+                    samples[0].push(preprocessed - started);
+                    samples[1].push(inferred - preprocessed);
+                    samples[2].push(finished - inferred);
+                }
+            }
+            if error.is_some() {
+                samples.iter_mut().for_each(Vec::clear);
+            }
+            let [preprocess, inference, postprocess] = samples.map(|samples| pipeline::benchmark::StageTiming::from_samples(&samples));
+            results.push(BackendBenchmark {
+                backend,
+                total_ms: preprocess.median_ms + inference.median_ms + postprocess.median_ms,
+                preprocess,
+                inference,
+                postprocess,
+                error,
+            });
+        }
+
+        let fastest = results
+            .iter()
+            .filter(|result| result.error.is_none() && result.backend != Backend::Simulated)
+            .min_by(|a, b| a.total_ms.total_cmp(&b.total_ms))
+            .map(|result| result.backend);
+        #[cfg(feature = "threads")]
+        let threads = threads::pool_threads().max(1);
+        #[cfg(not(feature = "threads"))]
+        let threads = 1;
+        Ok(BenchmarkReport {
+            style: style_name.to_string(),
+            source_width,
+            source_height,
+            iterations,
+            simd: pipeline::simd::ENABLED,
+            threads,
+            backends: results,
+            fastest,
+        })
+    }
+
+    /// Renders one image at several strengths side by side, `columns` per row
+    /// (0 picks a near-square layout), and returns the grid as a PNG data URL.
+    /// The model runs once; each cell is that result blended at its strength,
+    /// at model resolution and labeled with it. Strengths are clamped to
+    /// [0, 1] and at most 9 are accepted.
+    #[wasm_bindgen]
+    pub async fn process_strength_grid(&mut self, image_data_url: &str, style_name: &str, strengths: Vec<f32>, columns: u32) -> Result<String, JsValue> {
+        self.check_live()?;
+        let started = self.reporter.begin("process_strength_grid", Some(style_name));
+        let result = async {
+            let strengths = pipeline::grid_strengths(&strengths).map_err(EngineError::InvalidInput)?;
+            let source = source::load_source(image_data_url).await?;
+            self.in_flight_model = Some(style_name.to_string());
+            let prepared = self.prepare_and_infer(&source, style_name, &ProcessOptions::default()).await;
+            self.in_flight_model = None;
+            let Prepared { surface, input_tensor, inferred, input_size: (width, height), timings, .. } = prepared?;
+
+            let pixel_count = (width * height) as usize;
+            let cells: Vec<Vec<u8>> = strengths
+                .iter()
+                .map(|&strength| {
+                    let blended = pipeline::apply_strength(&input_tensor, inferred.tensor.clone(), strength, None, ColorSpace::Srgb);
+                    pipeline::tensor_to_rgba(&blended, pixel_count)
+                })
+                .collect();
+            let (grid_columns, grid_rows) = pipeline::grid_dimensions(cells.len(), columns);
+            let pixels = pipeline::compose_grid(&cells, width, height, columns);
+            let inference = (inferred.backend, inferred.from_cache, timings.inference_ms);
+            let Surface::Canvas(canvas, ctx) = surface else {
+                // Workers can't draw text, so their grids are unlabeled
+                let encoded = encode::encode_pixels(&pixels, width * grid_columns, height * grid_rows, &ProcessOptions::default())?;
+                return Ok((encoded.data_url, inference));
+            };
+            canvas.set_width(width * grid_columns);
+            canvas.set_height(height * grid_rows);
+            let grid = ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&pixels[..]), width * grid_columns, height * grid_rows)?;
+            ctx.put_image_data(&grid, 0.0, 0.0)?;
+
+            ctx.set_font("12px sans-serif");
+            ctx.set_text_baseline("top");
+            for (index, strength) in strengths.iter().enumerate() {
+                let x = (index as u32 % grid_columns * width) as f64;
+                let y = (index as u32 / grid_columns * height) as f64;
+                ctx.set_fill_style_str("rgba(0, 0, 0, 0.6)");
+                ctx.fill_rect(x, y, 36.0, 18.0);
+                ctx.set_fill_style_str("#fff");
+                ctx.fill_text(&format!("{:.2}", strength), x + 4.0, y + 3.0)?;
+            }
+            Ok((canvas.to_data_url()?, inference))
+        }.await;
+        self.record_processed(style_name, result.as_ref().ok().map(|(_, inference)| *inference));
+        self.reporter.finish(started, result.map(|(data_url, _)| data_url))
+    }
+
+    /// Starts a sequence of frames (e.g. video) stylized with `style_name`.
+    /// Each frame's stylized tensor is blended with the previous frame's
+    /// result, weighted by `blend_previous` (clamped to [0, 0.95]) before the
+    /// strength blend, which damps flicker. The weight ramps in over the
+    /// first few frames and starts over whenever the resolution changes.
+    /// With a `motion_threshold` (mean per-channel input change, in [0, 1],
+    /// e.g. 0.1) pixels that moved since the previous frame take less of it,
+    /// down to nothing at the threshold, so motion doesn't smear.
+    /// Replaces any sequence already in progress; `process_video_frame` and
+    /// `stream_video` join it when they use the same style.
+    #[wasm_bindgen]
+    pub fn begin_sequence(&mut self, style_name: &str, blend_previous: f32, motion_threshold: Option<f32>) -> Result<(), JsValue> {
+        self.check_live()?;
+        if !self.model_registry.iter().any(|m| m.name == style_name) {
+            return Err(EngineError::ModelNotFound(format!("Model not found: {}", style_name)).into());
+        }
+        let mut temporal = pipeline::TemporalBlend::new(blend_previous)
+            .map_err(|reason| EngineError::InvalidInput(format!("blend_previous: {}", reason)))?;
+        if let Some(threshold) = motion_threshold {
+            temporal = temporal.with_motion_threshold(threshold)
+                .map_err(|reason| EngineError::InvalidInput(format!("motion_threshold: {}", reason)))?;
+        }
+        self.sequence = Some(Sequence { style_name: style_name.to_string(), temporal });
+        Ok(())
+    }
+
+    /// Stylizes the next frame of the current sequence from any source
+    /// `process_element` accepts, returning its `ProcessResult` plus
+    /// `frame_index` and the `effective_blend` applied to it.
+    #[wasm_bindgen]
+    pub async fn process_sequence_frame(&mut self, source: &JsValue, strength: f32, options: JsValue) -> Result<JsValue, JsValue> {
+        self.check_live()?;
+        let Some(mut sequence) = self.sequence.take() else {
+            return Err(EngineError::InvalidInput("No sequence in progress; call begin_sequence first".to_string()).into());
+        };
+        let style_name = sequence.style_name.clone();
+        let reported = self.reporter.begin("process_sequence_frame", Some(&style_name));
+        let result = async {
+            let started = now_ms();
+            let options = parse_options(options)?;
+            let source = ElementSource::from_js(source)?;
+            let rendered = self.process_source(&source, &style_name, strength, &options, Some(&mut sequence.temporal)).await;
+            if options.consume {
+                source.close();
+            }
+            let result = rendered.and_then(|rendered| self.finish(rendered, &options, 0.0, started));
+            self.record_processed(&style_name, result.as_ref().ok().map(|r| (r.backend, r.from_cache, r.timings.inference_ms)));
+            to_js(&SequenceFrame {
+                result: result?,
+                frame_index: sequence.temporal.frames() - 1,
+                effective_blend: sequence.temporal.last_effective_blend(),
+            })
+        }.await;
+        // end_sequence or a new begin_sequence may have run meanwhile
+        if self.sequence.is_none() && !self.disposed {
+            self.sequence = Some(sequence);
+        }
+        self.reporter.finish(reported, result)
+    }
+
+    /// Ends the current sequence and drops the frame it kept.
+    #[wasm_bindgen]
+    pub fn end_sequence(&mut self) {
+        self.sequence = None;
+    }
+
+    /// Runs the pipeline on `source`, returning the model-sized canvas holding
+    /// the result along with its pixels.
+    async fn process_source(&mut self, source: &ElementSource, style_name: &str, strength: f32, options: &ProcessOptions, temporal: Option<&mut pipeline::TemporalBlend>) -> Result<Rendered, JsValue> {
+        self.in_flight_model = Some(style_name.to_string());
+        // A model download the call starts is cancelled with it
+        let watch = std::mem::replace(&mut self.download_watch.signal, options.signal.clone());
+        self.call_backend = options.backend;
+        let passes = pipeline::jitter::jitter_offsets(options.passes, self.simulation_seed).len() as u32;
+        // process_image_dual runs every pass twice
+        let styles = if self.call_mix.is_some() { 2 } else { 1 };
+        self.progress = pipeline::progress::Progress::new(passes * styles);
+        let result = self.process_source_pinned(source, style_name, strength, options, temporal).await;
+        self.call_backend = None;
+        self.download_watch.signal = watch;
+        self.in_flight_model = None;
+        result
+    }
+
+    /// Loads the model if needed, draws `source` at model resolution and runs
+    /// inference on it.
+    async fn prepare_and_infer(&mut self, source: &ElementSource, style_name: &str, options: &ProcessOptions) -> Result<Prepared, JsValue> {
+        // Load model if not already loaded
+        if !self.loaded_models.contains_key(style_name) {
+            self.fetch_and_load_model(style_name).await?;
+        }
+        self.touch_model(style_name);
+
+        // Models with a symbolic height and width run at the image's own size
+        let size = self.has_dynamic_size(style_name).then(|| {
+            let (width, height) = source.dimensions();
+            let max_side = pipeline::tiling::max_tile_side(capabilities::device_memory_gb());
+            let max_side = match self.config.max_input_dimension {
+                0 => max_side,
+                max => max.min(max_side),
+            };
+            pipeline::shapes::dynamic_input_size(width, height, max_side)
+        });
+        // Otherwise letterboxing draws the source at its fitted size and pads it
+        let letterbox = match size {
+            None if options.letterbox => self.model_registry
+                .iter()
+                .find(|m| m.name == style_name)
+                .map(|m| pipeline::Letterbox::fit(source.dimensions(), (m.input_width, m.input_height)))
+                .filter(|letterbox| !letterbox.is_full()),
+            _ => None,
+        };
+        let size = size.or(letterbox.map(|letterbox| (letterbox.width, letterbox.height)));
+        let Preprocessed { surface, input_tensor, alpha, input_size: (input_width, input_height), source_size, downscale_factor, mut timings } =
+            self.preprocess_source(source, style_name, options, size)?;
+
+        let inferred = match letterbox {
+            Some(letterbox) => {
+                let padded = letterbox.pad(&input_tensor);
+                let model_size = (letterbox.model_width, letterbox.model_height);
+                let mut inferred = self.infer_passes(&padded, model_size, style_name, options, None, &mut timings).await?;
+                inferred.tensor = letterbox.crop(&inferred.tensor);
+                inferred
+            }
+            None => self.infer_passes(&input_tensor, (input_width, input_height), style_name, options, size, &mut timings).await?,
+        };
+        self.reporter.update(|context| context.backend = Some(inferred.backend));
+        timings.inference_ms = timings.pass_ms.iter().sum();
+
+        Ok(Prepared {
+            surface,
+            input_tensor,
+            inferred,
+            alpha,
+            input_size: (input_width, input_height),
+            source_size,
+            downscale_factor,
+            timings,
+        })
+    }
+
+    /// Runs the jittered passes of `options` on a `width` x `height` tensor in
+    /// the working space and averages them. `size` is passed through to
+    /// inference for tiles that aren't the model's input size.
+    async fn infer_passes(&mut self, input_tensor: &[f32], (width, height): (u32, u32), style_name: &str, options: &ProcessOptions, size: Option<(u32, u32)>, timings: &mut Timings) -> Result<Inferred, JsValue> {
+        // The model sees the space it was trained in, and its output is
+        // converted back so that passes are averaged in the working space
+        let model_space = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .map_or(ColorSpace::Srgb, |m| m.model_color_space);
+        let model_input = (model_space != options.working_space).then(|| {
+            let mut converted = input_tensor.to_vec();
+            pipeline::color::convert(&mut converted, options.working_space, model_space);
+            converted
+        });
+        let model_input = model_input.as_deref().unwrap_or(input_tensor);
+
+        // Run neural style transfer inference, averaging jittered passes
+        let offsets = pipeline::jitter::jitter_offsets(options.passes, self.simulation_seed);
+        let mut inferred: Option<Inferred> = None;
+        for &(dx, dy) in &offsets {
+            checkpoint(options, "inference").await?;
+            options.report_progress(Stage::Inference, self.progress.percent(Stage::Inference));
+            let stage_started = now_ms();
+            let mut pass = if (dx, dy) == (0, 0) {
+                self.run_neural_inference_at(model_input, style_name, size, true).await?
+            } else {
+                let shifted = pipeline::jitter::shift_tensor(model_input, width, height, (dx, dy));
+                let mut pass = self.run_neural_inference_at(&shifted, style_name, size, true).await?;
+                pass.tensor = pipeline::jitter::shift_tensor(&pass.tensor, width, height, (-dx, -dy));
+                pass
+            };
+            pipeline::color::convert(&mut pass.tensor, model_space, options.working_space);
+            timings.pass_ms.push(now_ms() - stage_started);
+            self.progress.done += 1;
+            match &mut inferred {
+                None => inferred = Some(pass),
+                Some(sum) => {
+                    pipeline::jitter::accumulate(&mut sum.tensor, &pass.tensor);
+                    sum.from_cache &= pass.from_cache;
+                }
+            }
+        }
+        let mut inferred = inferred.expect("jitter_offsets yields at least one pass");
+        if offsets.len() > 1 {
+            pipeline::jitter::finish_average(&mut inferred.tensor, offsets.len());
+        }
+        Ok(inferred)
+    }
+
+    /// `prepare_and_infer` at the plan's output size: the source is drawn
+    /// once at that size, each overlapping tile is stylized on its own and
+    /// the tiles are feather-blended across their overlaps.
+    async fn prepare_tiled(&mut self, source: &ElementSource, style_name: &str, options: &ProcessOptions, plan: &pipeline::TilePlan) -> Result<Prepared, JsValue> {
+        if !self.loaded_models.contains_key(style_name) {
+            self.fetch_and_load_model(style_name).await?;
+        }
+        self.touch_model(style_name);
+
+        let output_size = (plan.output_width, plan.output_height);
+        let Preprocessed { surface, input_tensor, alpha, source_size, mut timings, .. } =
+            self.preprocess_source(source, style_name, options, Some(output_size))?;
+
+        let tile = (plan.tile_width, plan.tile_height);
+        let columns = pipeline::tiling::tile_origins(plan.output_width, plan.tile_width, plan.overlap);
+        let rows = pipeline::tiling::tile_origins(plan.output_height, plan.tile_height, plan.overlap);
+        let mut blender = pipeline::tiling::TileBlender::new(plan.output_width, plan.output_height);
+        self.progress.steps *= (columns.len() * rows.len()) as u32;
+        let (mut backend, mut from_cache) = (Backend::Simulated, true);
+        for (row, &y0) in rows.iter().enumerate() {
+            let row_weights = pipeline::tiling::axis_weights(&rows, row, plan.tile_height);
+            for (column, &x0) in columns.iter().enumerate() {
+                let column_weights = pipeline::tiling::axis_weights(&columns, column, plan.tile_width);
+                let tile_tensor = pipeline::tiling::extract_tile(&input_tensor, output_size, (x0, y0), tile);
+                let inferred = self.infer_passes(&tile_tensor, tile, style_name, options, Some(tile), &mut timings).await?;
+                blender.add(&inferred.tensor, (x0, y0), &column_weights, &row_weights);
+                backend = inferred.backend;
+                from_cache &= inferred.from_cache;
+            }
+        }
+        self.reporter.update(|context| context.backend = Some(backend));
+        timings.inference_ms = timings.pass_ms.iter().sum();
+
+        Ok(Prepared {
+            surface,
+            input_tensor,
+            inferred: Inferred { tensor: blender.finish(), backend, from_cache },
+            alpha,
+            input_size: output_size,
+            source_size,
+            downscale_factor: plan.output_width as f32 / source_size.0 as f32,
+            timings,
+        })
+    }
+
+    /// Draws `source` and converts it to the tensor `style_name` takes,
+    /// exactly as processing does. `size` overrides the model's input size,
+    /// e.g. for tiling.
+    fn preprocess_source(&mut self, source: &ElementSource, style_name: &str, options: &ProcessOptions, size: Option<(u32, u32)>) -> Result<Preprocessed, JsValue> {
+        // Get model metadata for proper resolution
+        let model_metadata = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", style_name)))?;
+        
+        let (input_width, input_height) = size.unwrap_or((model_metadata.input_width, model_metadata.input_height));
+
+        // Draw the source at a capped working size; the final resize happens in Rust
+        let surface = Surface::new(options.tone_map != pipeline::ToneMap::Clamp)?;
+        
+        let mut timings = Timings::default();
+        let stage_started = now_ms();
+        let (source_width, source_height) = source.dimensions();
+        if source_width == 0 || source_height == 0 {
+            return Err(EngineError::InvalidInput(format!("Source is {}x{} pixels", source_width, source_height)).into());
+        }
+        self.reporter.update(|context| {
+            context.input_width = Some(source_width);
+            context.input_height = Some(source_height);
+        });
+        let downscale_factor = (input_width as f32 / source_width as f32).min(input_height as f32 / source_height as f32);
+        let (work_width, work_height) = pipeline::resize::working_size(source_width, source_height, (input_width, input_height), self.config.max_input_dimension);
+        // Resize to the model input, then convert to a normalized tensor (RGB); alpha is kept aside for the output
+        let resized = match &surface {
+            Surface::Canvas(canvas, ctx) => {
+                canvas.set_width(work_width);
+                canvas.set_height(work_height);
+                source.draw(ctx, work_width, work_height)?;
+                let resized = source::read_resized(
+                    ctx,
+                    (work_width, work_height),
+                    (input_width, input_height),
+                    options.tone_map,
+                    options.resize_filter,
+                    options.working_space,
+                )?;
+                canvas.set_width(input_width);
+                canvas.set_height(input_height);
+                resized
+            }
+            // Headless reads are 8-bit, so tone_map has nothing to do
+            Surface::Headless => {
+                let (sample_width, sample_height) = match source {
+                    ElementSource::Pixels(_) => (source_width, source_height),
+                    _ => (work_width, work_height),
+                };
+                source::resize_pixels(
+                    &source.sample_rgba(sample_width, sample_height)?,
+                    (sample_width, sample_height),
+                    (input_width, input_height),
+                    options.resize_filter,
+                    options.working_space,
+                )
+            }
+        };
+        timings.resize_ms = now_ms() - stage_started;
+        let (input_tensor, alpha) = resized.into_tensor();
+        timings.preprocess_ms = now_ms() - stage_started;
+        timings.tensor_ms = timings.preprocess_ms - timings.resize_ms;
+
+        Ok(Preprocessed {
+            surface,
+            input_tensor,
+            alpha,
+            input_size: (input_width, input_height),
+            source_size: (source_width, source_height),
+            downscale_factor,
+            timings,
+        })
+    }
+
+    async fn process_source_pinned(&mut self, source: &ElementSource, style_name: &str, strength: f32, options: &ProcessOptions, temporal: Option<&mut pipeline::TemporalBlend>) -> Result<Rendered, JsValue> {
+        // Reject bad options before doing any work
+        let background = options.background_rgb()?;
+        let protection = options.subject_protection()?;
+        let wants_variants = !options.variant_strengths()?.is_empty();
+        let channels = options.channel_strengths()?;
+        let time_budget_ms = options.time_budget()?;
+        let upscale = options.upscale_factor()?;
+        let postprocess = options.postprocess()?;
+        let lut = self.resolve_lut(options)?;
+        options.check_target_size()?;
+        let oriented = match options.source_orientation()? {
+            pipeline::orientation::Orientation::Normal => None,
+            orientation => Some(source.oriented(orientation)?),
+        };
+        let source = oriented.as_ref().unwrap_or(source);
+        let mask = match &options.mask {
+            Some(url) => Some(source::load_source(url).await?),
+            None => None,
+        };
+        checkpoint(options, "loading the model").await?;
+        options.report_progress(Stage::Preprocess, self.progress.percent(Stage::Preprocess));
+        let plan = match time_budget_ms {
+            Some(time_budget_ms) => Some(self.tile_plan(style_name, source.dimensions(), time_budget_ms).await?),
+            None if options.tiled => Some(self.tile_plan(style_name, source.dimensions(), f64::INFINITY).await?),
+            None => None,
+        };
+        // Tiles are already at full resolution
+        let full_resolution = options.full_resolution && plan.is_none();
+        let prepared = match plan {
+            Some(plan) => {
+                self.emit_event("tile_plan", serde_json::json!({ "name": style_name, "plan": plan }));
+                self.prepare_tiled(source, style_name, options, &plan).await?
+            }
+            None => self.prepare_and_infer(source, style_name, options).await?,
+        };
+        let Prepared { surface, input_tensor, mut inferred, alpha, input_size: (input_width, input_height), source_size: (source_width, source_height), downscale_factor, mut timings } = prepared;
+        if let Some(style_mix) = self.call_mix.take() {
+            let mixed = self.mix_second_style(&style_mix, &input_tensor, (input_width, input_height), inferred, options, &mut timings).await;
+            self.call_mix = Some(style_mix);
+            inferred = mixed?;
+        }
+        checkpoint(options, "blending").await?;
+        options.report_progress(Stage::Postprocess, self.progress.percent(Stage::Postprocess));
+
+        // Apply strength blending, after damping flicker against the previous frame
+        let stage_started = now_ms();
+        if let Some(temporal) = temporal {
+            inferred.tensor = temporal.apply_with_input(inferred.tensor, &input_tensor, (input_width, input_height));
+        }
+        if options.preserve_color {
+            pipeline::color::preserve_chroma(&input_tensor, &mut inferred.tensor, options.working_space);
+        }
+        postprocess.apply(&mut inferred.tensor, (input_width, input_height));
+        if options.match_histogram {
+            pipeline::postprocess::match_histogram(&mut inferred.tensor, &input_tensor);
+        }
+
+        let strength_map = match options.strength_map.clone() {
+            Some(values) => {
+                let map = match options.strength_map_width {
+                    Some(width) if width > 0 => {
+                        let height = values.len() as u32 / width;
+                        pipeline::StrengthMap::new(values, width, height)
+                    }
+                    _ => pipeline::StrengthMap::infer(values, &[(source_width, source_height), (input_width, input_height)]),
+                }
+                .map_err(|reason| EngineError::InvalidInput(format!("strength_map: {}", reason)))?;
+                Some(map.resampled(input_width, input_height))
+            }
+            None => None,
+        };
+        let mut masks = Vec::new();
+        if let Some(mask) = mask {
+            let pixels = mask.sample_rgba(input_width, input_height)?;
+            masks.push(pipeline::StrengthMap::from_mask(&pixels, input_width, input_height, options.invert_mask)
+                .map_err(|reason| EngineError::InvalidInput(format!("mask: {}", reason)))?);
         }
+        masks.extend(self.call_mask.clone());
+        let strength_map = masks.iter().fold(strength_map, |map, mask| {
+            let mask = mask.resampled(input_width, input_height);
+            Some(match map {
+                Some(map) => map.iter().zip(&mask).map(|(strength, mask)| strength * mask).collect(),
+                None => mask,
+            })
+        });
+        let saliency = (protection > 0.0 || options.debug_saliency)
+            .then(|| pipeline::saliency_map(&input_tensor, input_width, input_height));
+        let strength_map = match &saliency {
+            Some(saliency) if protection > 0.0 => {
+                let mut map = strength_map.unwrap_or_else(|| vec![1.0; saliency.len()]);
+                pipeline::saliency::attenuate_salient(&mut map, saliency, protection);
+                Some(map)
+            }
+            _ => strength_map,
+        };
+        let blend_started = now_ms();
+        let mut buffers = self.buffer_pool.take((input_width, input_height));
+        match channels {
+            Some(channels) => pipeline::blend_channels_into(&input_tensor, &inferred.tensor, strength, strength_map.as_deref(), channels, options.working_space, &mut buffers.blend),
+            None => pipeline::apply_strength_into(&input_tensor, &inferred.tensor, strength, strength_map.as_deref(), options.working_space, &mut buffers.blend),
+        }
+        let mut blended_tensor = std::mem::take(&mut buffers.blend);
+        timings.blend_ms = now_ms() - blend_started;
+        // finish() blends the variants from the same inference
+        let variant_stylized = wants_variants.then_some(inferred.tensor);
+        // The full-resolution source guides the result up to its own size
+        let (mut result_width, mut result_height) = (input_width, input_height);
+        let mut high_alpha = None;
+        if full_resolution {
+            let max_side = pipeline::tiling::max_output_side(capabilities::device_memory_gb());
+            let max_side = match self.config.max_input_dimension {
+                0 => max_side,
+                max => max.min(max_side),
+            };
+            let (high_width, high_height) = pipeline::resize::fit_within(source_width, source_height, max_side);
+            if high_width > input_width || high_height > input_height {
+                let high_size = (high_width, high_height);
+                let pixels = source.sample_rgba(high_width, high_height)?;
+                let (high_input, upsampled_alpha) = source::pixels_to_tensor(&pixels, high_size, high_size, options.resize_filter, options.working_space);
+                blended_tensor = pipeline::guided::guided_upsample(&input_tensor, &blended_tensor, (input_width, input_height), high_input, high_size);
+                high_alpha = Some(upsampled_alpha);
+                (result_width, result_height) = high_size;
+            }
+        }
+        pipeline::color::convert(&mut blended_tensor, options.working_space, ColorSpace::Srgb);
+        if let Some(lut) = &lut {
+            lut.apply(&mut blended_tensor);
+        }
+
+        // Build RGBA buffer in a plain Vec<u8>
+        let pixel_count = (result_width * result_height) as usize;
+        let mut output_pixels = buffers.pixels;
+        pipeline::tensor_to_rgba_into(&blended_tensor, pixel_count, &mut output_pixels);
+        if let Some(alpha) = high_alpha.as_ref().unwrap_or(&alpha) {
+            pipeline::tensor::restore_alpha(&mut output_pixels, alpha);
+        }
+        if options.needs_flattening() {
+            pipeline::flatten_alpha(&mut output_pixels, background);
+        }
+        if let Some(factor) = upscale {
+            let max_side = pipeline::tiling::max_output_side(capabilities::device_memory_gb());
+            if result_width.max(result_height) * factor > max_side {
+                return Err(EngineError::InvalidInput(format!("upscaling {}x{} by {} exceeds this device's {}px output limit", result_width, result_height, factor, max_side)).into());
+            }
+            checkpoint(options, "upscaling").await?;
+            output_pixels = self.super_resolve(&output_pixels, (result_width, result_height), factor).await?;
+            (result_width, result_height) = (result_width * factor, result_height * factor);
+        }
+        let (output_width, output_height) = options.output_size((result_width, result_height));
+        if (output_width, output_height) != (result_width, result_height) {
+            output_pixels = pipeline::resize_rgba(&output_pixels, result_width, result_height, output_width, output_height, options.resize_filter);
+        }
+        if (output_width, output_height) != (input_width, input_height) {
+            surface.resize(output_width, output_height);
+        }
+
+        // The pixels stay in wasm memory until a caller needs them drawn or copied out
+        self.buffer_pool.give((input_width, input_height), pipeline::pool::Buffers { blend: blended_tensor, pixels: Vec::new() });
+        timings.postprocess_ms = now_ms() - stage_started;
         
-        blended
+        Ok(Rendered {
+            surface,
+            pixels: output_pixels,
+            size: (output_width, output_height),
+            input_size: (input_width, input_height),
+            backend: inferred.backend,
+            from_cache: inferred.from_cache,
+            downscale_factor,
+            timings,
+            saliency: saliency.filter(|_| options.debug_saliency),
+            variants: variant_stylized.map(|stylized| VariantSource { original: input_tensor, stylized, strength_map, alpha, lut }),
+        })
+    }
+
+    /// Encodes a rendered result and fills in the remaining timings.
+    fn finish(&mut self, mut rendered: Rendered, options: &ProcessOptions, decode_ms: f64, started: f64) -> Result<ProcessResult, JsValue> {
+        options.report_progress(Stage::Encode, self.progress.percent(Stage::Encode));
+        let encode_started = now_ms();
+        let encoded = self.encode(&rendered.surface, &rendered.pixels, rendered.size, options)?;
+        let saliency_data_url = match &rendered.saliency {
+            // PNG data URL of the map drawn as grayscale
+            Some(saliency) => {
+                let pixels = pipeline::saliency::map_to_rgba(saliency);
+                let (width, height) = rendered.input_size;
+                Some(self.encode_rgba(&pixels, width, height, &ProcessOptions::default())?.data_url)
+            }
+            None => None,
+        };
+        let encode_ms = now_ms() - encode_started;
+        let (variants, variant_ms) = match &rendered.variants {
+            Some(source) => self.encode_variants(&rendered, source, options)?,
+            None => Default::default(),
+        };
+        let timings = Timings {
+            decode_ms,
+            encode_ms,
+            variant_ms,
+            total_ms: now_ms() - started,
+            ..std::mem::take(&mut rendered.timings)
+        };
+        self.last_timings = Some(timings.clone());
+        let result = ProcessResult {
+            data_url: encoded.data_url,
+            format: encoded.format,
+            mime_type: encoded.mime_type,
+            format_fallback: encoded.format_fallback,
+            width: rendered.size.0,
+            height: rendered.size.1,
+            backend: rendered.backend,
+            simulated: rendered.backend == Backend::Simulated,
+            from_cache: rendered.from_cache,
+            downscale_factor: rendered.downscale_factor,
+            timings,
+            saliency_data_url,
+            variants,
+        };
+        self.recycle(rendered);
+        Ok(result)
+    }
+
+    /// Blends and encodes each of `strength_variants` on the result canvas,
+    /// one after the other through the pooled buffers, and times each. A
+    /// variant that fails to draw or encode reports its error in place.
+    fn encode_variants(&mut self, rendered: &Rendered, source: &VariantSource, options: &ProcessOptions) -> Result<(Vec<StrengthVariant>, Vec<f64>), JsValue> {
+        let background = options.background_rgb()?;
+        let (input_width, input_height) = rendered.input_size;
+        let (width, height) = rendered.size;
+        let pipeline::pool::Buffers { blend: mut blended, mut pixels } = self.buffer_pool.take((input_width, input_height));
+        let mut variants = Vec::with_capacity(options.strength_variants.len());
+        let mut variant_ms = Vec::with_capacity(options.strength_variants.len());
+        for &strength in &options.strength_variants {
+            let started = now_ms();
+            match options.channel_strength {
+                Some(channels) => pipeline::blend_channels_into(&source.original, &source.stylized, strength, source.strength_map.as_deref(), channels, options.working_space, &mut blended),
+                None => pipeline::apply_strength_into(&source.original, &source.stylized, strength, source.strength_map.as_deref(), options.working_space, &mut blended),
+            }
+            pipeline::color::convert(&mut blended, options.working_space, ColorSpace::Srgb);
+            if let Some(lut) = &source.lut {
+                lut.apply(&mut blended);
+            }
+            pipeline::tensor_to_rgba_into(&blended, (input_width * input_height) as usize, &mut pixels);
+            if let Some(alpha) = &source.alpha {
+                pipeline::tensor::restore_alpha(&mut pixels, alpha);
+            }
+            if options.needs_flattening() {
+                pipeline::flatten_alpha(&mut pixels, background);
+            }
+            if (width, height) != (input_width, input_height) {
+                pixels = pipeline::resize_rgba(&pixels, input_width, input_height, width, height, options.resize_filter);
+            }
+            let encoded = self.encode(&rendered.surface, &pixels, (width, height), options);
+            variants.push(match encoded {
+                Ok(encoded) => StrengthVariant { strength, data_url: Some(encoded.data_url), error: None },
+                Err(error) => {
+                    let error = js_filter::describe_js_error(&error);
+                    console_warn!("Encoding the {} strength variant failed: {}", strength, error);
+                    StrengthVariant { strength, data_url: None, error: Some(error) }
+                }
+            });
+            variant_ms.push(now_ms() - started);
+        }
+        self.buffer_pool.give((input_width, input_height), pipeline::pool::Buffers { blend: blended, pixels });
+        Ok((variants, variant_ms))
+    }
+
+    async fn run_neural_inference(&mut self, input_tensor: &[f32], style_name: &str) -> Result<Inferred, JsValue> {
+        self.run_neural_inference_at(input_tensor, style_name, None, true).await
+    }
+
+    /// Inference on a `size` input instead of the model's input size, for
+    /// tiles of models with symbolic height and width. Unless `cached`, the
+    /// result cache is neither read nor filled.
+    async fn run_neural_inference_at(&mut self, input_tensor: &[f32], style_name: &str, size: Option<(u32, u32)>, cached: bool) -> Result<Inferred, JsValue> {
+        console_log!("Running neural network inference for: {}", style_name);
+        self.swap_optimized_plan(style_name);
+
+        let registered = self.model_registry
+            .iter()
+            .find(|m| m.name == style_name)
+            .ok_or_else(|| EngineError::ModelNotFound(format!("Model not found: {}", style_name)))?;
+        let resized;
+        let metadata = match size {
+            Some((input_width, input_height)) if (input_width, input_height) != (registered.input_width, registered.input_height) => {
+                resized = ModelMetadata { input_width, input_height, ..registered.clone() };
+                &resized
+            }
+            _ => registered,
+        };
+
+        let helper = match metadata.kind {
+            ModelKind::Segmentation => Some("segmentation"),
+            ModelKind::SuperResolution => Some("super-resolution"),
+            _ => None,
+        };
+        if let Some(helper) = helper {
+            return Err(EngineError::InvalidInput(format!("'{}' is a {} model, not a style", style_name, helper)).into());
+        }
+        // The style image isn't part of the cache key, so AdaIN is never cached
+        if metadata.kind == ModelKind::Adain {
+            return self.run_adain(input_tensor, metadata);
+        }
+
+        // JS filters may not be deterministic, so they are never cached
+        if let Some(callback) = self.js_filters.get(style_name) {
+            let output = js_filter::run_js_filter(
+                style_name,
+                callback,
+                input_tensor,
+                metadata.input_width,
+                metadata.input_height,
+            )?;
+            return Ok(Inferred { tensor: output, backend: Backend::JsFilter, from_cache: false });
+        }
+
+        let cache_key = cached.then(|| CacheKey::new(style_name, &metadata.version, input_tensor, self.simulation_seed));
+        let (result, onnx_error, from_cache) = match cache_key.as_ref().and_then(|key| self.result_cache.get(key)) {
+            Some(hit) => {
+                console_log!("Using cached inference result for: {}", style_name);
+                (hit, None, true)
+            }
+            None => {
+                // Try to use real ONNX model first, the pipeline falls back to simulation
+                let stylized = match self.loaded_models.get(style_name).map(|model| model.runtime) {
+                    #[cfg(feature = "backend-ort-web")]
+                    Some(ModelRuntime::External) => self.run_external(input_tensor, metadata).await,
+                    Some(ModelRuntime::WebGpu) if self.call_backend != Some(config::PreferredBackend::Cpu) => {
+                        self.run_gpu(input_tensor, metadata).await
+                    }
+                    _ => pipeline::stylize(
+                        input_tensor,
+                        metadata,
+                        self.tract_models.get(style_name),
+                        self.simulation_seed,
+                    ),
+                };
+                if let Some(e) = &stylized.onnx_error {
+                    console_log!("ONNX inference failed: {}, falling back to simulation", e);
+                }
+                match stylized.path {
+                    InferencePath::Onnx => console_log!("ONNX inference successful for: {}", style_name),
+                    InferencePath::WebGpu => console_log!("WebGPU inference successful for: {}", style_name),
+                    InferencePath::Simulated => {
+                        console_log!("Using simulated neural network processing for: {}", style_name)
+                    }
+                }
+                let result = CachedResult { tensor: stylized.tensor, path: stylized.path };
+                (result, stylized.onnx_error, false)
+            }
+        };
+
+        if self.config.strict_mode && metadata.kind == ModelKind::Onnx && result.path == InferencePath::Simulated {
+            return Err(EngineError::InferenceError(format!(
+                "'{}' could not run as ONNX and strict mode forbids the simulated fallback{}",
+                style_name,
+                onnx_error.map(|e| format!(": {}", e)).unwrap_or_default()
+            )).into());
+        }
+
+        // Only results that made it this far are worth keeping
+        if let Some(cache_key) = cache_key {
+            self.result_cache.insert(cache_key, result.clone());
+        }
+        Ok(Inferred { tensor: result.tensor, backend: result.path.into(), from_cache })
+    }
+
+    /// AdaIN between the encoder and decoder of `metadata` with the current
+    /// style image, or on pixels when they aren't loaded or fail.
+    fn run_adain(&self, input_tensor: &[f32], metadata: &ModelMetadata) -> Result<Inferred, JsValue> {
+        let style = self.adain_style.as_ref().ok_or_else(|| {
+            EngineError::InvalidInput(format!("'{}' needs a style image; use process_with_style_image", metadata.name))
+        })?;
+        let size = (metadata.input_width, metadata.input_height);
+        let networks = self.tract_models.get(&metadata.name).zip(self.adain_decoders.get(&metadata.name));
+        let onnx_error = match (networks, &style.features) {
+            (Some((encoder, decoder)), Some(features)) => match pipeline::adain::transfer(encoder, decoder, input_tensor, size, features) {
+                Ok(tensor) => return Ok(Inferred { tensor, backend: Backend::Onnx, from_cache: false }),
+                Err(e) => Some(e),
+            },
+            _ => None,
+        };
+        if let Some(e) = &onnx_error {
+            console_log!("AdaIN inference failed: {}, matching pixel statistics instead", e);
+        }
+        if self.config.strict_mode {
+            return Err(EngineError::InferenceError(format!(
+                "'{}' could not run its encoder and decoder and strict mode forbids the pixel statistics fallback{}",
+                metadata.name,
+                onnx_error.map(|e| format!(": {}", e)).unwrap_or_default()
+            )).into());
+        }
+        let tensor = pipeline::adain::match_pixel_stats(input_tensor, &style.pixels);
+        Ok(Inferred { tensor, backend: Backend::Simulated, from_cache: false })
+    }
+
+    /// Lets the external runtime free a model it loaded.
+    fn release_runtime(&self, _model_name: &str, runtime: ModelRuntime) {
+        #[cfg(feature = "backend-ort-web")]
+        if let (ModelRuntime::External, Some(backend)) = (runtime, &self.external_backend) {
+            backend.unload(_model_name);
+        }
+        #[cfg(not(feature = "backend-ort-web"))]
+        let _ = runtime;
+    }
+
+    /// Inference with compute shaders, falling back to tract when the device
+    /// is gone, the run fails or the model isn't NCHW.
+    async fn run_gpu(&self, input_tensor: &[f32], metadata: &ModelMetadata) -> pipeline::Stylized {
+        match self.run_gpu_only(input_tensor, metadata).await {
+            Ok(tensor) => pipeline::Stylized { tensor, path: InferencePath::WebGpu, onnx_error: None },
+            Err(e) => {
+                console_warn!("WebGPU inference failed for {}: {}; running on the CPU", metadata.name, e);
+                pipeline::stylize(input_tensor, metadata, self.tract_models.get(&metadata.name), self.simulation_seed)
+            }
+        }
+    }
+
+    /// `run_gpu` without the CPU fallback.
+    async fn run_gpu_only(&self, input_tensor: &[f32], metadata: &ModelMetadata) -> Result<Vec<f32>, String> {
+        let shape = [3, metadata.input_height as usize, metadata.input_width as usize];
+        match self.gpu_models.get(&metadata.name).filter(|_| self.is_webgpu_ready()) {
+            Some(_) if metadata.layout != pipeline::TensorLayout::Nchw => Err("the shaders only take NCHW inputs".to_string()),
+            Some(model) => model.run(&pipeline::interleaved_to_planar(&pipeline::normalize::model_input(input_tensor, metadata)), shape).await.and_then(|(output, output_shape)| {
+                if output_shape == shape {
+                    let mut output = pipeline::planar_to_interleaved(&output);
+                    pipeline::normalize::model_output(&mut output, metadata);
+                    Ok(output)
+                } else {
+                    Err(format!("model output shape {:?} doesn't match input shape {:?}", output_shape, shape))
+                }
+            }),
+            None => Err("no WebGPU device".to_string()),
+        }
+    }
+
+    /// Inference on the external runtime, falling back to simulation like
+    /// `pipeline::stylize` does for tract.
+    #[cfg(feature = "backend-ort-web")]
+    async fn run_external(&self, input_tensor: &[f32], metadata: &ModelMetadata) -> pipeline::Stylized {
+        let result = match &self.external_backend {
+            Some(backend) => backend.run(metadata, &pipeline::normalize::model_input(input_tensor, metadata)).await,
+            None => Err("no external backend registered".to_string()),
+        };
+        match result {
+            Ok(tensor) => pipeline::Stylized { tensor, path: InferencePath::Onnx, onnx_error: None },
+            Err(e) => pipeline::Stylized {
+                onnx_error: Some(e),
+                ..pipeline::stylize(input_tensor, metadata, None, self.simulation_seed)
+            },
+        }
+    }
+
+    fn check_live(&self) -> Result<(), EngineError> {
+        if self.disposed {
+            return Err(EngineError::EngineDisposed("The engine has been disposed".to_string()));
+        }
+        Ok(())
+    }
+
+    fn usage(&mut self, model_name: &str) -> &mut ModelUsage {
+        self.model_usage.entry(model_name.to_string()).or_default()
+    }
+
+    /// Counts a finished call: `(backend, from_cache, inference_ms)` once it
+    /// succeeded through encoding, `None` when it failed. Names outside the
+    /// registry aren't tracked.
+    fn record_processed(&mut self, model_name: &str, inference: Option<(Backend, bool, f64)>) {
+        if !self.model_registry.iter().any(|m| m.name == model_name) {
+            return;
+        }
+        let now = js_sys::Date::now();
+        match inference {
+            Some((backend, from_cache, inference_ms)) => self.usage(model_name).record_inference(backend, from_cache, inference_ms, now),
+            None => self.usage(model_name).record_error(now),
+        }
+    }
+
+    /// Zeroes the per-model usage counters and the result cache hit/miss counts.
+    #[wasm_bindgen]
+    pub fn reset_stats(&mut self) {
+        self.model_usage.clear();
+        self.result_cache.reset_counters();
+    }
+
+    /// Sets the result cache capacity in bytes; 0 disables caching.
+    #[wasm_bindgen]
+    pub fn set_result_cache_size(&mut self, bytes: usize) {
+        self.result_cache.set_capacity(bytes);
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_result_cache(&mut self) {
+        self.result_cache.clear();
     }
 
+    /// Engine state and memory use. `total_memory_mb` counts loaded models
+    /// as the memory budget does, plus partial downloads; `evictions` totals
+    /// what the budget and `set_max_resident_models` freed;
+    /// `plan_estimate_mb` estimates each optimized tract plan, `heap_mb` and
+    /// `peak_heap_mb` are what the allocator has handed out now and at most
+    /// (see `reset_peak_memory`), and `wasm_memory_pages` is the size of
+    /// linear memory, which never shrinks.
     #[wasm_bindgen]
     pub fn get_stats(&self) -> JsValue {
         let stats = serde_json::json!({
+            "disposed": self.disposed,
             "models_loaded": self.loaded_models.len(),
-            "webgpu_available": self.webgpu_available,
+            "webgpu_available": self.is_webgpu_ready(),
+            "webgpu_state": self.webgpu_state(),
+            "webgpu_lost_reason": self.webgpu_loss.borrow().reason,
             "total_memory_mb": self.get_memory_usage(),
+            "retained_model_bytes_mb": self.retained_model_bytes() as f32 / (1024.0 * 1024.0),
+            "partial_download_mb": self.partial_download_bytes() as f32 / (1024.0 * 1024.0),
+            "model_runtimes": self.loaded_models.iter()
+                .map(|(name, model)| (name.clone(), model.runtime))
+                .collect::<std::collections::BTreeMap<_, _>>(),
+            "memory_budget_mb": self.memory_budget_bytes as f64 / (1024.0 * 1024.0),
+            "max_resident_models": self.max_resident_models,
+            "evictions": self.eviction_stats,
+            "plan_estimate_mb": self.tract_models.iter()
+                .chain(&self.adain_decoders)
+                .fold(std::collections::BTreeMap::new(), |mut sizes, (name, plan)| {
+                    *sizes.entry(name.clone()).or_insert(0.0) += pipeline::plan_size_estimate(plan) as f32 / (1024.0 * 1024.0);
+                    sizes
+                }),
+            "wasm_memory_pages": memory::wasm_memory_pages(),
+            "wasm_memory_mb": memory::wasm_memory_pages().map(|pages| (pages * memory::WASM_PAGE_BYTES) as f32 / (1024.0 * 1024.0)),
+            "heap_mb": ALLOC.heap_bytes() as f32 / (1024.0 * 1024.0),
+            "peak_heap_mb": ALLOC.peak_heap_bytes() as f32 / (1024.0 * 1024.0),
+            "result_cache": self.result_cache.stats(),
+            "buffer_pool_mb": self.buffer_pool.byte_len() as f32 / (1024.0 * 1024.0),
+            "per_model": self.model_usage,
         });
         serde_wasm_bindgen::to_value(&stats).unwrap()
     }
 
+    /// Restarts the `peak_heap_mb` of `get_stats` from the current usage, to
+    /// measure the peak of a single call.
+    #[wasm_bindgen]
+    pub fn reset_peak_memory(&self) {
+        ALLOC.reset_peak();
+    }
+
+    /// The `timings` of the most recent result from any processing call, so
+    /// they can be logged after the fact; `null` before the first.
+    #[wasm_bindgen]
+    pub fn get_last_timings(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.last_timings)
+    }
+
     fn get_memory_usage(&self) -> f32 {
+        let model_bytes: usize = self.loaded_models.values().map(|model| model.byte_len + model.droppable_bytes()).sum();
+        (model_bytes + self.partial_download_bytes()) as f32 / (1024.0 * 1024.0)
+    }
+
+    fn partial_download_bytes(&self) -> usize {
+        self.partial_downloads.values().map(|partial| partial.bytes.len()).sum()
+    }
+
+    fn retained_model_bytes(&self) -> usize {
         self.loaded_models.values()
-            .map(|model| model.len() as f32 / (1024.0 * 1024.0))
+            .filter_map(|model| model.bytes.as_ref())
+            .map(Vec::len)
             .sum()
     }
-}
\ No newline at end of file
+}
+
+impl StyleTransferEngine {
+    fn apply_stored_config(&mut self, stored: &serde_json::Map<String, serde_json::Value>) -> Result<(), JsValue> {
+        let config = self.config.merged(stored).map_err(|rejected| {
+            EngineError::InvalidInput(format!("Rejected config fields: {}", rejected.join("; ")))
+        })?;
+        LOG_LEVEL.store(config.log_level as u8, Ordering::Relaxed);
+        self.config = config;
+
+        // Models that fell back to simulation keep their bytes regardless
+        if !self.config.retain_model_bytes {
+            for model in self.loaded_models.values_mut() {
+                if model.runtime != ModelRuntime::Simulated {
+                    model.bytes = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes `pixels`: drawn on the surface's canvas for the browser's
+    /// encoders, or straight from wasm memory when headless.
+    fn encode(&mut self, surface: &Surface, pixels: &[u8], (width, height): (u32, u32), options: &ProcessOptions) -> Result<encode::EncodedImage, JsValue> {
+        let support = match self.encoder_support {
+            Some(support) => support,
+            None => *self.encoder_support.insert(EncoderSupport::detect()?),
+        };
+        let encoded = match surface {
+            Surface::Canvas(canvas, ctx) => {
+                let image_data = ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(pixels), width, height)?;
+                ctx.put_image_data(&image_data, 0.0, 0.0)?;
+                encode::encode_canvas(canvas, options, support)?
+            }
+            Surface::Headless => encode::encode_pixels(pixels, width, height, options)?,
+        };
+        if encoded.format_fallback {
+            console_warn!("{} encoding is not supported by this browser, using PNG", options.format.mime_type());
+        }
+        Ok(encoded)
+    }
+
+    /// Encodes RGBA pixels on a fresh surface.
+    fn encode_rgba(&mut self, pixels: &[u8], width: u32, height: u32, options: &ProcessOptions) -> Result<encode::EncodedImage, JsValue> {
+        let surface = Surface::new(false)?;
+        surface.resize(width, height);
+        self.encode(&surface, pixels, (width, height), options)
+    }
+
+    /// Returns a result's pixels to the buffer pool once they're copied out.
+    fn recycle(&mut self, rendered: Rendered) {
+        self.buffer_pool.give(rendered.input_size, pipeline::pool::Buffers { blend: Vec::new(), pixels: rendered.pixels });
+    }
+
+    /// A freshly loaded model counts as the most recently used one.
+    fn insert_loaded_model(&mut self, model_name: &str, model: LoadedModel) {
+        self.loaded_models.insert(model_name.to_string(), model);
+        self.tile_costs.remove(model_name);
+        self.touch_model(model_name);
+        // Room was made for the model, not for the file it may retain
+        if let Err(reason) = self.evict_for(None) {
+            console_warn!("Loaded models exceed the memory budget: {}", reason);
+        }
+    }
+
+    fn touch_model(&mut self, model_name: &str) {
+        if self.loaded_models.contains_key(model_name) {
+            self.usage_clock.touch(model_name);
+        }
+    }
+
+    /// Frees what it takes for one more model of `incoming_bytes` (or none)
+    /// to fit in the memory budget and the resident model cap: retained
+    /// files of the least recently used models first, then those models.
+    /// The in-flight model is never chosen.
+    fn evict_for(&mut self, incoming_bytes: Option<usize>) -> Result<(), String> {
+        let limits = budget::ResidencyLimits { max_bytes: self.memory_budget_bytes, max_models: self.max_resident_models };
+        if limits == budget::ResidencyLimits::default() {
+            return Ok(());
+        }
+        let resident: Vec<ResidentModel> = self.loaded_models
+            .iter()
+            .map(|(name, model)| ResidentModel {
+                name: name.clone(),
+                bytes: model.byte_len,
+                retained_bytes: model.droppable_bytes(),
+                last_used: self.usage_clock.last_used(name),
+            })
+            .collect();
+        let pinned: Vec<&str> = self.in_flight_model.iter().map(String::as_str).collect();
+        let plan = budget::plan_residency(&resident, incoming_bytes, limits, &pinned)?;
+
+        for name in plan.drop_retained {
+            let Some(model) = self.loaded_models.get_mut(&name) else { continue };
+            let bytes = model.droppable_bytes();
+            model.bytes = None;
+            console_log!("Dropping the retained file of {} ({} bytes) to stay within the memory budget", name, bytes);
+            self.eviction_stats.retained_dropped += 1;
+            self.eviction_stats.bytes_freed += bytes as u64;
+            self.emit_event("model_bytes_dropped", serde_json::json!({ "name": name, "bytes": bytes }));
+        }
+        for name in plan.unload {
+            let bytes = self.loaded_models.get(&name).map_or(0, |model| model.byte_len + model.droppable_bytes());
+            console_log!("Evicting model {} ({} bytes) to stay within the memory budget", name, bytes);
+            let _ = self.unload_model(&name);
+            self.eviction_stats.models_unloaded += 1;
+            self.eviction_stats.bytes_freed += bytes as u64;
+            self.emit_event("model_evicted", serde_json::json!({ "name": name, "bytes": bytes }));
+        }
+        Ok(())
+    }
+
+    /// What `get_model_state` reports for a registry entry.
+    fn model_state(&self, model_name: &str) -> ModelState {
+        if self.loaded_models.contains_key(model_name) {
+            return ModelState::Ready;
+        }
+        let kind = self.model_registry.iter().find(|m| m.name == model_name).map(|m| m.kind);
+        if matches!(kind, Some(ModelKind::JsFilter | ModelKind::Simulated)) {
+            return ModelState::Ready;
+        }
+        self.load_states.get(model_name).cloned().unwrap_or(ModelState::NotLoaded)
+    }
+
+    /// Records a step of loading a registry entry, announcing it as a
+    /// `"model_state_changed"` event when the reported state changes. Ready
+    /// and not loaded follow from `loaded_models`, so they just clear the
+    /// recorded step.
+    fn set_model_state(&mut self, model_name: &str, state: ModelState) {
+        if !self.model_registry.iter().any(|m| m.name == model_name) {
+            return;
+        }
+        let before = self.model_state(model_name);
+        match state {
+            ModelState::NotLoaded | ModelState::Ready => self.load_states.remove(model_name),
+            state => self.load_states.insert(model_name.to_string(), state),
+        };
+        if self.model_state(model_name) != before {
+            self.emit_model_state(model_name);
+        }
+    }
+
+    /// Ends the state of a load with its `result`; a cancelled load counts as
+    /// never attempted.
+    fn finish_load_state(&mut self, model_name: &str, result: &Result<(), JsValue>) {
+        let cancelled = self.download_watch.signal.as_ref().is_some_and(|signal| signal.aborted());
+        let state = match result {
+            Ok(()) => ModelState::Ready,
+            Err(_) if cancelled => ModelState::NotLoaded,
+            Err(error) => ModelState::Failed { error: js_filter::describe_js_error(error) },
+        };
+        self.set_model_state(model_name, state);
+    }
+
+    fn emit_model_state(&self, model_name: &str) {
+        let mut detail = serde_json::to_value(self.model_state(model_name)).unwrap_or_default();
+        detail["name"] = model_name.into();
+        self.emit_event("model_state_changed", detail);
+    }
+
+    // Listener exceptions are swallowed; they must not break the engine
+    fn emit_event(&self, kind: &str, detail: serde_json::Value) {
+        let listener = self.event_listener.borrow().clone();
+        if let Some(listener) = listener {
+            let detail = to_js(&detail).unwrap_or(JsValue::NULL);
+            let _ = listener.call2(&JsValue::NULL, &JsValue::from_str(kind), &detail);
+        }
+    }
+
+    /// Blends an original and a stylized tensor at the given strength.
+    pub fn blend_tensors(&self, original: &[f32], stylized: &[f32], strength: f32) -> Vec<f32> {
+        pipeline::blend_tensors(original, stylized, strength)
+    }
+
+    /// `blend_tensors` with optional red, green and blue strength multipliers
+    /// and a per-pixel strength map (one value per 3 tensor values), both in
+    /// [0, 1] and multiplied with `strength`.
+    pub fn blend_tensors_with(&self, original: &[f32], stylized: &[f32], strength: f32, channel_strengths: Option<Vec<f32>>, strength_map: Option<Vec<f32>>) -> Result<Vec<f32>, JsValue> {
+        let channels = match channel_strengths.as_deref() {
+            None => [1.0; 3],
+            Some(&[red, green, blue]) => [red, green, blue],
+            Some(other) => return Err(EngineError::InvalidInput(format!("channel_strengths needs 3 values, got {}", other.len())).into()),
+        };
+        if let Some(map) = &strength_map {
+            if map.len() * 3 < original.len().min(stylized.len()) {
+                return Err(EngineError::InvalidInput(format!("strength_map has {} values for {} pixels", map.len(), original.len().min(stylized.len()) / 3)).into());
+            }
+        }
+        let mut blended = Vec::new();
+        pipeline::blend_channels_into(original, stylized, strength, strength_map.as_deref(), channels, ColorSpace::Srgb, &mut blended);
+        Ok(blended)
+    }
+}
+
+/// Output of the shared pipeline before encoding.
+struct Rendered {
+    surface: Surface,
+    /// RGBA at `size`, drawn on `surface` only when encoding.
+    pixels: Vec<u8>,
+    size: (u32, u32),
+    /// The size blending ran at, before scaling to the target size.
+    input_size: (u32, u32),
+    backend: Backend,
+    from_cache: bool,
+    downscale_factor: f32,
+    timings: Timings,
+    /// Only kept when `debug_saliency` asked for it.
+    saliency: Option<Vec<f32>>,
+    /// Only kept when `strength_variants` asked for them.
+    variants: Option<VariantSource>,
+}
+
+impl Rendered {
+    /// The pixels as `ImageData`, copied out of wasm memory once.
+    fn image_data(&self) -> Result<ImageData, JsValue> {
+        ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&self.pixels), self.size.0, self.size.1)
+    }
+}
+
+/// What `finish` needs to blend a rendered result at other strengths.
+struct VariantSource {
+    original: Vec<f32>,
+    stylized: Vec<f32>,
+    strength_map: Option<Vec<f32>>,
+    alpha: Option<Vec<u8>>,
+    lut: Option<Rc<pipeline::lut::Lut3d>>,
+}
+
+/// Where results are drawn before encoding: a DOM canvas where there is a
+/// document, nothing in workers, which encode the pixels in Rust.
+enum Surface {
+    Canvas(HtmlCanvasElement, CanvasRenderingContext2d),
+    Headless,
+}
+
+impl Surface {
+    fn new(high_dynamic_range: bool) -> Result<Surface, JsValue> {
+        if scope::document().is_none() {
+            return Ok(Surface::Headless);
+        }
+        let canvas = scope::create_canvas()?;
+        let ctx = source::context_2d(&canvas, high_dynamic_range)?;
+        Ok(Surface::Canvas(canvas, ctx))
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        if let Surface::Canvas(canvas, _) = self {
+            canvas.set_width(width);
+            canvas.set_height(height);
+        }
+    }
+}
+
+/// How the current WebGPU device was lost, shared with its `lost` handler.
+#[derive(Default)]
+struct DeviceLoss {
+    /// `GPUDeviceLostInfo.reason` once lost; cleared by a new device.
+    reason: Option<String>,
+    /// Bumped whenever the engine drops a device itself.
+    generation: u32,
+}
+
+/// The style `process_image_dual` mixes into the first one.
+struct StyleMix {
+    style_name: String,
+    /// Weight of this style, in [0, 1].
+    mix: f32,
+    mask: Option<ElementSource>,
+}
+
+/// A style image's statistics for AdaIN.
+struct AdainStyle {
+    pixels: pipeline::adain::ChannelStats,
+    /// `None` when the encoder isn't loaded or failed on the style image.
+    features: Option<pipeline::adain::ChannelStats>,
+}
+
+/// A run of frames stylized with temporal smoothing.
+struct Sequence {
+    style_name: String,
+    temporal: pipeline::TemporalBlend,
+}
+
+/// A source drawn at model resolution, as a tensor.
+struct Preprocessed {
+    surface: Surface,
+    input_tensor: Vec<f32>,
+    /// The source's alpha at the input size; `None` when it is opaque.
+    alpha: Option<Vec<u8>>,
+    input_size: (u32, u32),
+    source_size: (u32, u32),
+    downscale_factor: f32,
+    timings: Timings,
+}
+
+/// A source drawn at model resolution and what the model made of it.
+struct Prepared {
+    surface: Surface,
+    input_tensor: Vec<f32>,
+    inferred: Inferred,
+    alpha: Option<Vec<u8>>,
+    input_size: (u32, u32),
+    source_size: (u32, u32),
+    downscale_factor: f32,
+    timings: Timings,
+}
+
+struct RawInference {
+    backend: Backend,
+    from_cache: bool,
+    inference_ms: f64,
+}
+
+struct Inferred {
+    tensor: Vec<f32>,
+    backend: Backend,
+    from_cache: bool,
+}
+
+fn now_ms() -> f64 {
+    scope::Scope::current()
+        .and_then(|scope| scope.performance())
+        .map_or(0.0, |performance| performance.now())
+}
+
+// Plain objects rather than Maps, and u64 as numbers
+fn to_js<T: serde::Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| e.into())
+}
+
+/// A caller's tensor layout name; `None` means NCHW.
+fn parse_layout(layout: Option<String>) -> Result<pipeline::TensorLayout, EngineError> {
+    layout.map_or(Ok(pipeline::TensorLayout::default()), |name| {
+        pipeline::TensorLayout::parse(&name).map_err(EngineError::InvalidInput)
+    })
+}
+
+fn parse_options(options: JsValue) -> Result<ProcessOptions, EngineError> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(ProcessOptions::default());
+    }
+    serde_wasm_bindgen::from_value(options)
+        .map_err(|e| EngineError::InvalidInput(format!("Invalid process options: {}", e)))
+}
+
+/// Fails with `Cancelled` if `options.signal` has been aborted once queued
+/// events have run; `next` names the stage that would have started.
+async fn checkpoint(options: &ProcessOptions, next: &str) -> Result<(), EngineError> {
+    if options.signal.is_none() {
+        return Ok(());
+    }
+    scope::yield_to_event_loop().await;
+    if options.cancelled() {
+        return Err(EngineError::Cancelled(format!("Processing was cancelled before {}", next)));
+    }
+    Ok(())
+}
+
+/// Largest rect with the source's aspect ratio centered in the target.
+fn fit_rect(width: u32, height: u32, target_width: u32, target_height: u32) -> (f64, f64, f64, f64) {
+    let scale = (target_width as f64 / width as f64).min(target_height as f64 / height as f64);
+    let (fit_width, fit_height) = (width as f64 * scale, height as f64 * scale);
+    ((target_width as f64 - fit_width) / 2.0, (target_height as f64 - fit_height) / 2.0, fit_width, fit_height)
+}
+
+fn local_storage() -> Result<web_sys::Storage, JsValue> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| EngineError::InvalidInput("localStorage is not available".to_string()).into())
+}
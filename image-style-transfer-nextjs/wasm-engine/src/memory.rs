@@ -0,0 +1,91 @@
+//! Where the engine's memory goes: heap bytes counted by the global
+//! allocator, and the size of WebAssembly linear memory.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Size of a WebAssembly memory page.
+pub const WASM_PAGE_BYTES: usize = 64 * 1024;
+
+/// Wraps an allocator to count the bytes currently allocated through it
+/// and the most ever allocated at once.
+pub struct Counting<A> {
+    inner: A,
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl<A> Counting<A> {
+    pub const fn new(inner: A) -> Self {
+        Counting {
+            inner,
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Bytes allocated and not yet freed.
+    pub fn heap_bytes(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    /// Most bytes allocated at once since start-up or `reset_peak`.
+    pub fn peak_heap_bytes(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// Starts measuring the peak again from the current usage.
+    pub fn reset_peak(&self) {
+        self.peak.store(self.heap_bytes(), Ordering::Relaxed);
+    }
+
+    fn grew(&self, bytes: usize) {
+        let current = self.current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn shrank(&self, bytes: usize) {
+        self.current.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for Counting<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            self.grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        self.shrank(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            self.shrank(layout.size());
+            self.grew(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Pages of WebAssembly linear memory, which only ever grows; `None` in
+/// native builds.
+pub fn wasm_memory_pages() -> Option<usize> {
+    #[cfg(target_arch = "wasm32")]
+    return Some(core::arch::wasm32::memory_size(0));
+    #[cfg(not(target_arch = "wasm32"))]
+    None
+}
@@ -0,0 +1,171 @@
+//! Downloaded model files kept in IndexedDB across page loads.
+//!
+//! Records are keyed by `[name, version]` and hold the file as fetched, so
+//! compressed models stay compressed at rest.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+
+use crate::error::EngineError;
+use crate::scope::Scope;
+
+const DB_NAME: &str = "style-transfer-models";
+const DB_VERSION: u32 = 1;
+const STORE: &str = "models";
+
+/// A cached file, as `get_cached_models` lists it.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CachedModel {
+    pub name: String,
+    pub version: String,
+    pub bytes: usize,
+    /// `Date.now()` when it was stored.
+    pub stored_at: f64,
+}
+
+#[derive(Clone)]
+pub struct ModelStore {
+    db: IdbDatabase,
+}
+
+impl ModelStore {
+    /// Opens (creating on first use) the engine's database. Fails where
+    /// IndexedDB is unavailable, e.g. in some private browsing modes.
+    pub async fn open() -> Result<ModelStore, JsValue> {
+        let factory = Scope::current()
+            .and_then(|scope| scope.indexed_db())
+            .ok_or_else(|| EngineError::InvalidInput("IndexedDB is not available".to_string()))?;
+        let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+        let upgrading = request.clone();
+        let on_upgrade = Closure::once(move || {
+            if let Ok(db) = upgrading
+                .result()
+                .and_then(|db| db.dyn_into::<IdbDatabase>())
+            {
+                if !db.object_store_names().contains(STORE) {
+                    let _ = db.create_object_store(STORE);
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+        let db = settle(&request).await?;
+        request.set_onupgradeneeded(None);
+        Ok(ModelStore { db: db.dyn_into()? })
+    }
+
+    /// The file stored for `name` at `version`, if any.
+    pub async fn get(&self, name: &str, version: &str) -> Result<Option<Vec<u8>>, JsValue> {
+        let record = settle(
+            &self
+                .store(IdbTransactionMode::Readonly)?
+                .get(&key(name, version))?,
+        )
+        .await?;
+        if record.is_undefined() {
+            return Ok(None);
+        }
+        let data = js_sys::Reflect::get(&record, &"data".into())?;
+        Ok(Some(data.dyn_into::<js_sys::Uint8Array>()?.to_vec()))
+    }
+
+    /// Stores the file for `name` at `version`, replacing every other
+    /// version of it.
+    pub async fn put(&self, name: &str, version: &str, bytes: &[u8]) -> Result<(), JsValue> {
+        for stale in self.keys_of(name).await? {
+            if stale.get(1).as_string().as_deref() != Some(version) {
+                settle(&self.store(IdbTransactionMode::Readwrite)?.delete(&stale)?).await?;
+            }
+        }
+        let record = js_sys::Object::new();
+        js_sys::Reflect::set(&record, &"name".into(), &name.into())?;
+        js_sys::Reflect::set(&record, &"version".into(), &version.into())?;
+        js_sys::Reflect::set(&record, &"bytes".into(), &(bytes.len() as f64).into())?;
+        js_sys::Reflect::set(&record, &"stored_at".into(), &js_sys::Date::now().into())?;
+        js_sys::Reflect::set(&record, &"data".into(), &js_sys::Uint8Array::from(bytes))?;
+        let store = self.store(IdbTransactionMode::Readwrite)?;
+        settle(&store.put_with_key(&record, &key(name, version))?).await?;
+        Ok(())
+    }
+
+    /// Every stored file, without reading the files into wasm.
+    pub async fn list(&self) -> Result<Vec<CachedModel>, JsValue> {
+        let records: js_sys::Array = settle(&self.store(IdbTransactionMode::Readonly)?.get_all()?)
+            .await?
+            .dyn_into()?;
+        let field = |record: &JsValue, name: &str| js_sys::Reflect::get(record, &name.into());
+        records
+            .iter()
+            .map(|record| {
+                Ok(CachedModel {
+                    name: field(&record, "name")?.as_string().unwrap_or_default(),
+                    version: field(&record, "version")?.as_string().unwrap_or_default(),
+                    bytes: field(&record, "bytes")?.as_f64().unwrap_or(0.0) as usize,
+                    stored_at: field(&record, "stored_at")?.as_f64().unwrap_or(0.0),
+                })
+            })
+            .collect()
+    }
+
+    /// Deletes every version of `name`, returning how many there were.
+    pub async fn remove(&self, name: &str) -> Result<u32, JsValue> {
+        let keys = self.keys_of(name).await?;
+        for key in &keys {
+            settle(&self.store(IdbTransactionMode::Readwrite)?.delete(key)?).await?;
+        }
+        Ok(keys.len() as u32)
+    }
+
+    pub async fn clear(&self) -> Result<(), JsValue> {
+        settle(&self.store(IdbTransactionMode::Readwrite)?.clear()?).await?;
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        self.db.close();
+    }
+
+    async fn keys_of(&self, name: &str) -> Result<Vec<js_sys::Array>, JsValue> {
+        let keys: js_sys::Array =
+            settle(&self.store(IdbTransactionMode::Readonly)?.get_all_keys()?)
+                .await?
+                .dyn_into()?;
+        Ok(keys
+            .iter()
+            .filter_map(|key| key.dyn_into::<js_sys::Array>().ok())
+            .filter(|key| key.get(0).as_string().as_deref() == Some(name))
+            .collect())
+    }
+
+    // One transaction per request, so none is left waiting on wasm between requests
+    fn store(&self, mode: IdbTransactionMode) -> Result<IdbObjectStore, JsValue> {
+        self.db
+            .transaction_with_str_and_mode(STORE, mode)?
+            .object_store(STORE)
+    }
+}
+
+fn key(name: &str, version: &str) -> JsValue {
+    js_sys::Array::of2(&name.into(), &version.into()).into()
+}
+
+/// Waits for `request` and returns its result, or the `DOMException` it
+/// failed with.
+async fn settle(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    let settled = JsFuture::from(promise).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    if settled.is_err() {
+        return Err(match request.error() {
+            Ok(Some(exception)) => exception.into(),
+            _ => EngineError::InvalidInput("IndexedDB request failed".to_string()).into(),
+        });
+    }
+    request.result()
+}
@@ -0,0 +1,343 @@
+//! Per-call options for the processing entry points.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::AbortSignal;
+
+use crate::config::PreferredBackend;
+use crate::error::EngineError;
+use crate::pipeline::orientation::Orientation;
+use crate::pipeline::postprocess::Postprocess;
+use crate::pipeline::progress::Stage;
+use crate::pipeline::upscale::UPSCALE_FACTORS;
+use crate::pipeline::{self, ColorSpace, ResizeFilter, ToneMap};
+
+/// Most `strength_variants` one call may ask for.
+pub const MAX_STRENGTH_VARIANTS: usize = 8;
+
+/// Largest `target_width` or `target_height`, the canvas limit of most browsers.
+pub const MAX_TARGET_DIMENSION: u32 = 16384;
+
+/// Image format of data URLs produced by the engine.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+}
+
+impl OutputFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Avif => "image/avif",
+        }
+    }
+}
+
+/// Options accepted as a plain JS object; missing fields take their defaults.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ProcessOptions {
+    /// Keep the target canvas size and scale the result to fit inside it.
+    pub keep_size: bool,
+    /// Fit the source inside the model input without stretching it, padding
+    /// the rest, and crop the padding off the result, which keeps the
+    /// source's aspect ratio. Tiling and models that take any input size
+    /// keep it anyway, so it only affects fixed-size models.
+    pub letterbox: bool,
+    /// Return the result at the source's resolution, within the device's
+    /// output limit and `max_input_dimension`: inference stays at the model
+    /// resolution and a guided filter carries the result up along the
+    /// source's edges. Strength variants are only resized.
+    pub full_resolution: bool,
+    /// Enlarge the result 2 or 4 times with a super-resolution model after
+    /// blending, within the device's output limit. The first registered
+    /// `super_resolution` model of that factor is used, or the built-in
+    /// one is registered; a bicubic resize stands in when it can't load.
+    pub upscale: Option<u32>,
+    /// Close an `ImageBitmap` or `VideoFrame` source once it has been read.
+    pub consume: bool,
+    /// Applied when the canvas hands back float (wide-gamut/HDR) pixels.
+    /// Anything but `clamp` also asks for a float16 canvas.
+    pub tone_map: ToneMap,
+    /// Filter used to resize the source to the model resolution.
+    pub resize_filter: ResizeFilter,
+    /// EXIF orientation (1–8) of the source, which is rotated and flipped
+    /// upright before inference. Only for sources drawn as stored, e.g.
+    /// bitmaps made with `imageOrientation: "none"`: browsers already turn
+    /// `<img>` upright, and worker decoding reads the tag itself.
+    pub orientation: Option<u16>,
+    /// Encoding of returned data URLs; falls back to PNG when the browser
+    /// can't encode it.
+    pub format: OutputFormat,
+    /// Encoder quality in [0, 1] for lossy formats; `None` uses the browser default.
+    pub quality: Option<f32>,
+    /// CSS hex color that transparent pixels are composited onto for formats
+    /// without alpha (JPEG). White when unset. Other formats keep the
+    /// source's alpha.
+    pub background_color: Option<String>,
+    /// Per-pixel strength in [0, 1], row-major, multiplied with the global
+    /// strength. Any size is resampled to the model resolution.
+    pub strength_map: Option<Vec<f32>>,
+    /// Width of `strength_map`; inferred from the source or model size when unset.
+    pub strength_map_width: Option<u32>,
+    /// URL (or data URL) of a grayscale image stretched over the source:
+    /// white areas are stylized, black ones kept, gray ones blended, and
+    /// transparent ones count as black. Multiplied with `strength_map`.
+    pub mask: Option<String>,
+    /// Stylize where the mask is black instead, e.g. to style the
+    /// background of a subject mask.
+    pub invert_mask: bool,
+    /// Strength multipliers in [0, 1] for the red, green and blue channels,
+    /// on top of the global strength and any map or mask.
+    pub channel_strength: Option<[f32; 3]>,
+    /// `process_image_dual` only: a grayscale image like `mask` that scales
+    /// the mix per pixel, so white areas get the second style and black
+    /// ones the first.
+    pub mix_mask: Option<String>,
+    /// How much to spare salient (central, high-contrast) regions, in [0, 1]:
+    /// per-pixel strength is scaled by `1 - protect_subject * saliency`.
+    pub protect_subject: f32,
+    /// Unsharp-mask amount in [0, 2] applied to the stylized image before the
+    /// strength blend, for models whose output looks soft.
+    pub sharpen: f32,
+    /// Strength in [0, 1] of an edge-preserving denoiser applied to the
+    /// stylized image before sharpening, for models whose output is grainy.
+    pub denoise: f32,
+    /// Remap each channel of the stylized image to the source's value
+    /// distribution before the strength blend, keeping the photo's exposure
+    /// and palette while the style keeps its texture.
+    pub match_histogram: bool,
+    /// Color grade applied to the finished result in sRGB: the name of a LUT
+    /// added with `register_lut`, or a preset (`warm`, `cool`,
+    /// `teal_orange`, `bleach_bypass`, `faded_film`).
+    pub lut: Option<String>,
+    /// Also return the saliency map as `saliency_data_url`.
+    pub debug_saliency: bool,
+    /// Process in overlapping tiles at the largest resolution (up to the
+    /// source's) that fits this many milliseconds. The plan is announced as a
+    /// `"tile_plan"` event before processing starts.
+    pub time_budget_ms: Option<f64>,
+    /// Process in overlapping tiles at the source's resolution, capped only
+    /// by device memory. Implied by `time_budget_ms`.
+    pub tiled: bool,
+    /// Inference passes to average, each on the input shifted by up to 2 px
+    /// (seeded by `set_simulation_seed`), which suppresses checkerboard
+    /// artifacts. Capped at 4; 0 and 1 both run the input unshifted once.
+    pub passes: u32,
+    /// Further strengths in [0, 1] to return alongside the main result as
+    /// `variants`, each blended from the same inference and encoded in
+    /// `format`. At most 8.
+    pub strength_variants: Vec<f32>,
+    /// Space that resizing, pass averaging, blending and the strength
+    /// variants work in. `linear` decodes the source with the sRGB EOTF and
+    /// re-encodes just before the 8-bit conversion; the model still gets the
+    /// space its `model_color_space` declares.
+    pub working_space: ColorSpace,
+    /// Keep the photo's colors: the result takes only the luma of the
+    /// stylized image and the chroma of the original, before the strength
+    /// blend.
+    pub preserve_color: bool,
+    /// Aborting it stops processing with `Cancelled` at the next stage, tile
+    /// or pass, including a model download the call started. Each check
+    /// yields to the event loop first so the abort gets a chance to run.
+    #[serde(deserialize_with = "abort_signal")]
+    pub signal: Option<AbortSignal>,
+    /// Called as `on_progress(stage, percent)` when each stage (`decode`,
+    /// `preprocess`, `inference`, `postprocess`, `encode`) starts and before
+    /// every further tile or pass of inference. `percent` of the whole call,
+    /// 0-100, never goes down; calls that don't decode or encode skip those
+    /// stages. Anything the callback throws is ignored.
+    #[serde(deserialize_with = "js_function")]
+    pub on_progress: Option<js_sys::Function>,
+    /// Size of the returned image, resampled from the model output with
+    /// `resize_filter`. With only one of them set the other keeps the
+    /// output's aspect ratio; with neither the output size is returned.
+    pub target_width: Option<u32>,
+    pub target_height: Option<u32>,
+    /// `cpu` runs a model loaded on WebGPU on its CPU plan for this call.
+    /// Otherwise the model runs where `preferred_backend` had it loaded.
+    pub backend: Option<PreferredBackend>,
+}
+
+/// Passes the JS object through; only serde_wasm_bindgen can do that.
+fn abort_signal<'de, D: Deserializer<'de>>(de: D) -> Result<Option<AbortSignal>, D::Error> {
+    let value: JsValue = serde_wasm_bindgen::preserve::deserialize(de)?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    value
+        .dyn_into()
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom("signal must be an AbortSignal"))
+}
+
+fn js_function<'de, D: Deserializer<'de>>(de: D) -> Result<Option<js_sys::Function>, D::Error> {
+    let value: JsValue = serde_wasm_bindgen::preserve::deserialize(de)?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    value
+        .dyn_into()
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom("on_progress must be a function"))
+}
+
+impl ProcessOptions {
+    /// Calls `on_progress`, if set.
+    pub fn report_progress(&self, stage: Stage, percent: f32) {
+        if let Some(callback) = &self.on_progress {
+            let _ = callback.call2(
+                &JsValue::NULL,
+                &JsValue::from_str(stage.name()),
+                &JsValue::from_f64(percent as f64),
+            );
+        }
+    }
+
+    /// The parsed `background_color`.
+    pub fn background_rgb(&self) -> Result<[u8; 3], EngineError> {
+        match &self.background_color {
+            None => Ok([255, 255, 255]),
+            Some(color) => pipeline::parse_hex_color(color).map_err(|reason| {
+                EngineError::InvalidInput(format!("background_color: {}", reason))
+            }),
+        }
+    }
+
+    /// The validated `protect_subject` factor.
+    pub fn subject_protection(&self) -> Result<f32, EngineError> {
+        if !(0.0..=1.0).contains(&self.protect_subject) {
+            return Err(EngineError::InvalidInput(format!(
+                "protect_subject must be between 0 and 1, got {}",
+                self.protect_subject
+            )));
+        }
+        Ok(self.protect_subject)
+    }
+
+    /// The validated `sharpen` and `denoise` amounts.
+    pub fn postprocess(&self) -> Result<Postprocess, EngineError> {
+        Postprocess::new(self.sharpen, self.denoise).map_err(EngineError::InvalidInput)
+    }
+
+    /// The validated `orientation`.
+    pub fn source_orientation(&self) -> Result<Orientation, EngineError> {
+        match self.orientation {
+            None => Ok(Orientation::Normal),
+            Some(value) => Orientation::from_exif(value).ok_or_else(|| {
+                EngineError::InvalidInput(format!(
+                    "orientation must be an EXIF value from 1 to 8, got {}",
+                    value
+                ))
+            }),
+        }
+    }
+
+    /// The validated `upscale`.
+    pub fn upscale_factor(&self) -> Result<Option<u32>, EngineError> {
+        match self.upscale {
+            Some(factor) if !UPSCALE_FACTORS.contains(&factor) => {
+                Err(EngineError::InvalidInput(format!(
+                    "upscale must be one of {:?}, got {}",
+                    UPSCALE_FACTORS, factor
+                )))
+            }
+            factor => Ok(factor),
+        }
+    }
+
+    /// The validated `time_budget_ms`.
+    pub fn time_budget(&self) -> Result<Option<f64>, EngineError> {
+        match self.time_budget_ms {
+            Some(budget) if !(budget.is_finite() && budget > 0.0) => {
+                Err(EngineError::InvalidInput(format!(
+                    "time_budget_ms must be positive, got {}",
+                    budget
+                )))
+            }
+            budget => Ok(budget),
+        }
+    }
+
+    /// The validated `channel_strength`.
+    pub fn channel_strengths(&self) -> Result<Option<[f32; 3]>, EngineError> {
+        match self.channel_strength {
+            Some(channels) if channels.iter().any(|c| !(0.0..=1.0).contains(c)) => {
+                Err(EngineError::InvalidInput(format!(
+                    "channel_strength values must be between 0 and 1, got {:?}",
+                    channels
+                )))
+            }
+            channels => Ok(channels),
+        }
+    }
+
+    /// The validated `strength_variants`.
+    pub fn variant_strengths(&self) -> Result<&[f32], EngineError> {
+        if self.strength_variants.len() > MAX_STRENGTH_VARIANTS {
+            return Err(EngineError::InvalidInput(format!(
+                "at most {} strength_variants are supported, got {}",
+                MAX_STRENGTH_VARIANTS,
+                self.strength_variants.len()
+            )));
+        }
+        if let Some(strength) = self
+            .strength_variants
+            .iter()
+            .find(|s| !(0.0..=1.0).contains(*s))
+        {
+            return Err(EngineError::InvalidInput(format!(
+                "strength_variants must be between 0 and 1, got {}",
+                strength
+            )));
+        }
+        Ok(&self.strength_variants)
+    }
+
+    /// Checks `target_width` and `target_height`.
+    pub fn check_target_size(&self) -> Result<(), EngineError> {
+        for (name, value) in [
+            ("target_width", self.target_width),
+            ("target_height", self.target_height),
+        ] {
+            if let Some(value) = value.filter(|&v| v == 0 || v > MAX_TARGET_DIMENSION) {
+                return Err(EngineError::InvalidInput(format!(
+                    "{} must be between 1 and {}, got {}",
+                    name, MAX_TARGET_DIMENSION, value
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The size a `width` x `height` result is returned at.
+    pub fn output_size(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        let scaled = |length: u32, target: u32, reference: u32| {
+            ((length as f64 * target as f64 / reference as f64).round() as u32)
+                .clamp(1, MAX_TARGET_DIMENSION)
+        };
+        match (self.target_width, self.target_height) {
+            (Some(target_width), Some(target_height)) => (target_width, target_height),
+            (Some(target_width), None) => (target_width, scaled(height, target_width, width)),
+            (None, Some(target_height)) => (scaled(width, target_height, height), target_height),
+            (None, None) => (width, height),
+        }
+    }
+
+    /// Whether `signal` has been aborted.
+    pub fn cancelled(&self) -> bool {
+        self.signal.as_ref().is_some_and(|signal| signal.aborted())
+    }
+
+    /// Whether the output format can't store transparency.
+    pub fn needs_flattening(&self) -> bool {
+        self.format == OutputFormat::Jpeg
+    }
+}
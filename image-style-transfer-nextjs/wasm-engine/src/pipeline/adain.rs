@@ -0,0 +1,112 @@
+//! Adaptive instance normalization (Huang & Belongie, 2017): restyles
+//! content features with the per-channel statistics of a style image's
+//! features, so one encoder/decoder pair serves any style.
+
+use super::inference::{run_features, TractPlan};
+use super::tensor::{interleaved_to_planar, planar_to_interleaved};
+
+/// Keeps the standard deviation of flat channels away from zero.
+const EPSILON: f32 = 1e-5;
+
+/// Mean and standard deviation of each channel of a feature map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelStats {
+    pub mean: Vec<f32>,
+    pub std: Vec<f32>,
+}
+
+impl ChannelStats {
+    /// Statistics of planar `[channels, H, W]` data.
+    pub fn of_planar(features: &[f32], channels: usize) -> ChannelStats {
+        let plane = features.len() / channels.max(1);
+        let (mean, std) = features
+            .chunks_exact(plane.max(1))
+            .take(channels)
+            .map(|values| {
+                let mean = values.iter().sum::<f32>() / plane as f32;
+                let variance =
+                    values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / plane as f32;
+                (mean, (variance + EPSILON).sqrt())
+            })
+            .unzip();
+        ChannelStats { mean, std }
+    }
+
+    /// Statistics of an interleaved RGB tensor.
+    pub fn of_rgb(tensor: &[f32]) -> ChannelStats {
+        ChannelStats::of_planar(&interleaved_to_planar(tensor), 3)
+    }
+}
+
+/// Normalizes each channel of planar `content` and gives it `style`'s mean
+/// and deviation. `alpha` in [0, 1] trades the result back towards the
+/// content features.
+pub fn adain(content: &[f32], style: &ChannelStats, alpha: f32) -> Vec<f32> {
+    let channels = style.mean.len();
+    let own = ChannelStats::of_planar(content, channels);
+    let plane = content.len() / channels.max(1);
+    let alpha = alpha.clamp(0.0, 1.0);
+    content
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            let c = i / plane.max(1);
+            let restyled = (value - own.mean[c]) / own.std[c] * style.std[c] + style.mean[c];
+            alpha * restyled + (1.0 - alpha) * value
+        })
+        .collect()
+}
+
+/// AdaIN on the pixels themselves, for when no encoder can run: the
+/// content takes on the style's per-channel color statistics.
+pub fn match_pixel_stats(content: &[f32], style: &ChannelStats) -> Vec<f32> {
+    let restyled = adain(&interleaved_to_planar(content), style, 1.0);
+    planar_to_interleaved(&restyled)
+        .into_iter()
+        .map(|v| v.clamp(0.0, 1.0))
+        .collect()
+}
+
+/// Statistics of `encoder`'s features for an interleaved RGB image.
+pub fn feature_stats(
+    encoder: &TractPlan,
+    image: &[f32],
+    (width, height): (u32, u32),
+) -> Result<ChannelStats, String> {
+    let shape = [1, 3, height as usize, width as usize];
+    let (features, feature_shape) =
+        run_features(encoder, &interleaved_to_planar(image), shape).map_err(|e| e.to_string())?;
+    Ok(ChannelStats::of_planar(&features, feature_shape[1]))
+}
+
+/// Encodes interleaved RGB `content`, gives its features `style`'s
+/// statistics and decodes them back into an image of the same size.
+pub fn transfer(
+    encoder: &TractPlan,
+    decoder: &TractPlan,
+    content: &[f32],
+    (width, height): (u32, u32),
+    style: &ChannelStats,
+) -> Result<Vec<f32>, String> {
+    let shape = [1, 3, height as usize, width as usize];
+    let (features, feature_shape) =
+        run_features(encoder, &interleaved_to_planar(content), shape).map_err(|e| e.to_string())?;
+    if feature_shape[1] != style.mean.len() {
+        return Err(format!(
+            "the encoder produced {} channels but the style has {}",
+            feature_shape[1],
+            style.mean.len()
+        ));
+    }
+    let restyled = adain(&features, style, 1.0);
+    let feature_shape = [1, feature_shape[1], feature_shape[2], feature_shape[3]];
+    let (image, image_shape) =
+        run_features(decoder, &restyled, feature_shape).map_err(|e| e.to_string())?;
+    if image_shape != shape {
+        return Err(format!(
+            "decoder output shape {:?} doesn't match input shape {:?}",
+            image_shape, shape
+        ));
+    }
+    Ok(planar_to_interleaved(&image))
+}
@@ -0,0 +1,65 @@
+//! Inputs and statistics for `run_benchmark`.
+
+use serde::Serialize;
+
+use super::rng::XorShift64;
+
+/// Timed runs per backend when the caller doesn't say.
+pub const DEFAULT_BENCHMARK_ITERATIONS: u32 = 5;
+
+/// Most timed runs per backend, which keeps a benchmark from stalling the page.
+pub const MAX_BENCHMARK_ITERATIONS: u32 = 20;
+
+/// Repeated timings of one stage, in milliseconds.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct StageTiming {
+    pub median_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+impl StageTiming {
+    /// Summarizes `samples`; all zero when there are none.
+    pub fn from_samples(samples: &[f64]) -> StageTiming {
+        if samples.is_empty() {
+            return StageTiming::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let middle = sorted.len() / 2;
+        let median_ms = if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        };
+        StageTiming {
+            median_ms,
+            min_ms: sorted[0],
+            max_ms: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Opaque RGBA pixels with smooth gradients, hard edges and grain, so that
+/// resizing and inference do representative work. The same `seed` always
+/// gives the same image.
+pub fn synthetic_image(width: u32, height: u32, seed: u64) -> Vec<u8> {
+    let mut rng = XorShift64::new(seed);
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = (x as f32 / width as f32, y as f32 / height as f32);
+            // A checkerboard of 8 squares per side supplies the edges
+            let square = ((x * 8 / width.max(1)) + (y * 8 / height.max(1))) % 2;
+            let grain = (rng.next_f32() - 0.5) * 0.1;
+            let channel = |value: f32| ((value + grain).clamp(0.0, 1.0) * 255.0).round() as u8;
+            pixels.extend_from_slice(&[
+                channel(u),
+                channel(v),
+                channel(0.25 + 0.5 * square as f32),
+                255,
+            ]);
+        }
+    }
+    pixels
+}
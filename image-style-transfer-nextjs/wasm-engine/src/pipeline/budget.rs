@@ -0,0 +1,184 @@
+//! Memory budget enforcement for loaded models.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// A loaded model as seen by the eviction planner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResidentModel {
+    pub name: String,
+    /// What the runnable model holds, counted as its file size.
+    pub bytes: usize,
+    /// The downloaded file, when it is kept besides the runnable model and
+    /// can be dropped without unloading it.
+    pub retained_bytes: usize,
+    /// Logical clock value of the last load or inference; lower is older.
+    pub last_used: u64,
+}
+
+impl ResidentModel {
+    fn total_bytes(&self) -> usize {
+        self.bytes + self.retained_bytes
+    }
+}
+
+/// Caps on what loaded models may hold; 0 means no cap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResidencyLimits {
+    pub max_bytes: usize,
+    pub max_models: usize,
+}
+
+/// What [`plan_residency`] frees, in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EvictionPlan {
+    /// Models that keep running but drop their retained file.
+    pub drop_retained: Vec<String>,
+    /// Models to unload.
+    pub unload: Vec<String>,
+}
+
+/// Running totals of what eviction freed, for `get_stats`.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EvictionStats {
+    pub models_unloaded: u64,
+    /// Times a model's retained file was dropped while it stayed loaded.
+    pub retained_dropped: u64,
+    pub bytes_freed: u64,
+}
+
+/// Picks what to free, least recently used first, so that `resident` stays
+/// within `limits` once a model of `incoming_bytes` is added (`None` checks
+/// the resident models alone). Models named in `pinned` are never chosen.
+///
+/// Models over `max_models` are unloaded first. Bytes over `max_bytes` are
+/// then freed by dropping retained files, which costs nothing until the
+/// file is needed again, and only then by unloading models. Fails without
+/// freeing anything when the limits can't be met.
+pub fn plan_residency(
+    resident: &[ResidentModel],
+    incoming_bytes: Option<usize>,
+    limits: ResidencyLimits,
+    pinned: &[&str],
+) -> Result<EvictionPlan, String> {
+    let mut candidates: Vec<&ResidentModel> = resident
+        .iter()
+        .filter(|m| !pinned.contains(&m.name.as_str()))
+        .collect();
+    candidates.sort_by_key(|m| m.last_used);
+
+    let mut plan = EvictionPlan::default();
+    if limits.max_models > 0 {
+        let room = limits.max_models - usize::from(incoming_bytes.is_some());
+        let excess = resident.len().saturating_sub(room);
+        if excess > candidates.len() {
+            return Err(format!(
+                "{} models are in use but at most {} may be loaded",
+                resident.len() - candidates.len() + usize::from(incoming_bytes.is_some()),
+                limits.max_models
+            ));
+        }
+        plan.unload = candidates.drain(..excess).map(|m| m.name.clone()).collect();
+    }
+    if limits.max_bytes == 0 {
+        return Ok(plan);
+    }
+
+    let incoming_bytes = incoming_bytes.unwrap_or(0);
+    let mut remaining: Vec<ResidentModel> = resident
+        .iter()
+        .filter(|m| !plan.unload.contains(&m.name))
+        .cloned()
+        .collect();
+    let mut used: usize = remaining.iter().map(ResidentModel::total_bytes).sum();
+    for model in candidates.iter().filter(|m| m.retained_bytes > 0) {
+        if used + incoming_bytes <= limits.max_bytes {
+            break;
+        }
+        used -= model.retained_bytes;
+        plan.drop_retained.push(model.name.clone());
+        if let Some(kept) = remaining.iter_mut().find(|m| m.name == model.name) {
+            kept.retained_bytes = 0;
+        }
+    }
+    let unload = plan_evictions(&remaining, incoming_bytes, limits.max_bytes, pinned)?;
+    // Unloading frees the retained file too
+    plan.drop_retained.retain(|name| !unload.contains(name));
+    plan.unload.extend(unload);
+    Ok(plan)
+}
+
+/// Picks the models to evict, oldest first, so that `incoming_bytes` fits in
+/// `budget_bytes`, counting each with its retained file. Models named in
+/// `pinned` are never chosen.
+///
+/// Fails without evicting anything when the incoming model alone exceeds the
+/// budget or when the pinned models leave too little room.
+pub fn plan_evictions(
+    resident: &[ResidentModel],
+    incoming_bytes: usize,
+    budget_bytes: usize,
+    pinned: &[&str],
+) -> Result<Vec<String>, String> {
+    if incoming_bytes > budget_bytes {
+        return Err(format!(
+            "model needs {} bytes but the memory budget is only {} bytes",
+            incoming_bytes, budget_bytes
+        ));
+    }
+
+    let mut used: usize = resident.iter().map(ResidentModel::total_bytes).sum();
+    let mut candidates: Vec<&ResidentModel> = resident
+        .iter()
+        .filter(|m| !pinned.contains(&m.name.as_str()))
+        .collect();
+    candidates.sort_by_key(|m| m.last_used);
+
+    let mut evictions = Vec::new();
+    let mut candidates = candidates.into_iter();
+    while used + incoming_bytes > budget_bytes {
+        match candidates.next() {
+            Some(model) => {
+                used -= model.total_bytes();
+                evictions.push(model.name.clone());
+            }
+            None => {
+                return Err(format!(
+                    "models in use hold {} bytes; {} more do not fit in the {} byte budget",
+                    used, incoming_bytes, budget_bytes
+                ));
+            }
+        }
+    }
+    Ok(evictions)
+}
+
+/// Logical clock of model loads and inferences, the source of
+/// `ResidentModel::last_used`.
+#[derive(Clone, Debug, Default)]
+pub struct UsageClock {
+    now: u64,
+    last_used: HashMap<String, u64>,
+}
+
+impl UsageClock {
+    /// Marks `name` as the most recently used model.
+    pub fn touch(&mut self, name: &str) {
+        self.now += 1;
+        self.last_used.insert(name.to_string(), self.now);
+    }
+
+    pub fn forget(&mut self, name: &str) {
+        self.last_used.remove(name);
+    }
+
+    pub fn clear(&mut self) {
+        self.last_used.clear();
+    }
+
+    /// 0 for models never touched, which makes them the first to go.
+    pub fn last_used(&self, name: &str) -> u64 {
+        self.last_used.get(name).copied().unwrap_or(0)
+    }
+}
@@ -0,0 +1,195 @@
+//! LRU cache of stylized tensors, so switching back to a style or changing
+//! only the strength skips inference.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::InferencePath;
+
+/// Default capacity: a handful of 256x256 results.
+pub const DEFAULT_RESULT_CACHE_BYTES: usize = 8 * 1024 * 1024;
+
+/// FNV-1a over the bit patterns of a tensor.
+pub fn hash_tensor(tensor: &[f32]) -> u64 {
+    tensor
+        .iter()
+        .flat_map(|v| v.to_bits().to_le_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+/// Everything the stylized tensor depends on, the model file included.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub style: String,
+    /// The style's `version`, so results of an older model file never hit.
+    pub version: String,
+    pub input_hash: u64,
+    pub input_len: usize,
+    pub seed: u64,
+}
+
+impl CacheKey {
+    pub fn new(style: &str, version: &str, input_tensor: &[f32], seed: u64) -> Self {
+        CacheKey {
+            style: style.to_string(),
+            version: version.to_string(),
+            input_hash: hash_tensor(input_tensor),
+            input_len: input_tensor.len(),
+            seed,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CachedResult {
+    pub tensor: Vec<f32>,
+    pub path: InferencePath,
+}
+
+struct Entry {
+    result: CachedResult,
+    last_used: u64,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: usize,
+    pub capacity_bytes: usize,
+}
+
+pub struct ResultCache {
+    entries: HashMap<CacheKey, Entry>,
+    capacity_bytes: usize,
+    used_bytes: usize,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+fn entry_bytes(result: &CachedResult) -> usize {
+    result.tensor.len() * std::mem::size_of::<f32>()
+}
+
+impl ResultCache {
+    pub fn new(capacity_bytes: usize) -> Self {
+        ResultCache {
+            entries: HashMap::new(),
+            capacity_bytes,
+            used_bytes: 0,
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Looks up a result, counting the hit or miss.
+    pub fn get(&mut self, key: &CacheKey) -> Option<CachedResult> {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                Some(entry.result.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Stores a result, evicting least recently used entries to make room.
+    /// Results larger than the whole cache are not stored.
+    pub fn insert(&mut self, key: CacheKey, result: CachedResult) {
+        let bytes = entry_bytes(&result);
+        if bytes > self.capacity_bytes {
+            return;
+        }
+        self.remove(&key);
+        while self.used_bytes + bytes > self.capacity_bytes {
+            self.evict_oldest();
+        }
+
+        self.clock += 1;
+        self.used_bytes += bytes;
+        self.entries.insert(
+            key,
+            Entry {
+                result,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Changes the capacity, evicting as needed. Zero disables caching.
+    pub fn set_capacity(&mut self, capacity_bytes: usize) {
+        self.capacity_bytes = capacity_bytes;
+        while self.used_bytes > self.capacity_bytes {
+            self.evict_oldest();
+        }
+    }
+
+    /// Drops every result produced for `style`, e.g. after its model changed.
+    pub fn invalidate_style(&mut self, style: &str) {
+        let keys: Vec<CacheKey> = self
+            .entries
+            .keys()
+            .filter(|k| k.style == style)
+            .cloned()
+            .collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used_bytes = 0;
+    }
+
+    /// Zeroes the hit and miss counters, keeping the entries.
+    pub fn reset_counters(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+            bytes: self.used_bytes,
+            capacity_bytes: self.capacity_bytes,
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.used_bytes -= entry_bytes(&entry.result);
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        match oldest {
+            Some(key) => self.remove(&key),
+            None => self.used_bytes = 0,
+        }
+    }
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        ResultCache::new(DEFAULT_RESULT_CACHE_BYTES)
+    }
+}
@@ -0,0 +1,100 @@
+//! Decoding and encoding images without a DOM, for workers.
+//!
+//! Only PNG and JPEG are compiled in; the browser's decoders and encoders
+//! are used wherever a document exists.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder};
+
+use super::orientation::{exif_orientation, Orientation};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Quality browsers use for JPEG when none is given.
+pub const DEFAULT_JPEG_QUALITY: f32 = 0.92;
+
+/// RGBA pixels decoded from an image file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedImage {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Decodes a PNG or JPEG file into RGBA pixels, turned upright as its EXIF
+/// orientation says, like browsers do for `<img>`.
+pub fn decode_rgba(bytes: &[u8]) -> Result<DecodedImage, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+    let rgba = image.to_rgba8();
+    let (width, height) = (rgba.width(), rgba.height());
+    let orientation = exif_orientation(bytes).unwrap_or_default();
+    if orientation == Orientation::Normal {
+        return Ok(DecodedImage {
+            width,
+            height,
+            pixels: rgba.into_raw(),
+        });
+    }
+    let (pixels, width, height) = orientation.apply(rgba.as_raw(), width, height);
+    Ok(DecodedImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Encodes RGBA pixels as PNG.
+pub fn encode_png(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    PngEncoder::new(&mut out)
+        .write_image(pixels, width, height, ColorType::Rgba8)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+/// Encodes RGBA pixels as JPEG at `quality` in [0, 1], dropping alpha.
+pub fn encode_jpeg(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    quality: f32,
+) -> Result<Vec<u8>, String> {
+    let rgb: Vec<u8> = pixels
+        .chunks_exact(4)
+        .flat_map(|px| [px[0], px[1], px[2]])
+        .collect();
+    let quality = (quality.clamp(0.0, 1.0) * 100.0).round().max(1.0) as u8;
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, quality)
+        .write_image(&rgb, width, height, ColorType::Rgb8)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+/// Standard, padded base64.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// A base64 data URL of `bytes`.
+pub fn data_url(mime_type: &str, bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", mime_type, base64_encode(bytes))
+}
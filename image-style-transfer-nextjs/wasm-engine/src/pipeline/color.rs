@@ -0,0 +1,93 @@
+//! sRGB transfer functions and the space tensor math runs in.
+
+use serde::{Deserialize, Serialize};
+
+/// How tensor values encode light.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    /// Gamma-encoded, as canvases store pixels.
+    #[default]
+    Srgb,
+    /// Proportional to light, where averages and blends are physically right.
+    Linear,
+}
+
+/// The sRGB EOTF (IEC 61966-2-1), mirrored for the negative values of
+/// extended-range float canvases.
+pub fn srgb_to_linear(v: f32) -> f32 {
+    let magnitude = v.abs();
+    let linear = if magnitude <= 0.04045 {
+        magnitude / 12.92
+    } else {
+        ((magnitude + 0.055) / 1.055).powf(2.4)
+    };
+    linear.copysign(v)
+}
+
+/// Inverse of [`srgb_to_linear`].
+pub fn linear_to_srgb(v: f32) -> f32 {
+    let magnitude = v.abs();
+    let encoded = if magnitude <= 0.003_130_8 {
+        magnitude * 12.92
+    } else {
+        1.055 * magnitude.powf(1.0 / 2.4) - 0.055
+    };
+    encoded.copysign(v)
+}
+
+/// Re-encodes every value of `tensor` from `from` to `to`.
+pub fn convert(tensor: &mut [f32], from: ColorSpace, to: ColorSpace) {
+    match (from, to) {
+        (ColorSpace::Srgb, ColorSpace::Linear) => {
+            tensor.iter_mut().for_each(|v| *v = srgb_to_linear(*v))
+        }
+        (ColorSpace::Linear, ColorSpace::Srgb) => {
+            tensor.iter_mut().for_each(|v| *v = linear_to_srgb(*v))
+        }
+        _ => {}
+    }
+}
+
+/// Normalizes RGBA bytes to [0, 1] and linearizes the color channels; alpha
+/// stays as it is.
+pub fn rgba_to_linear(pixels: &[u8]) -> Vec<f32> {
+    let mut linear: Vec<f32> = pixels.iter().map(|&v| v as f32 / 255.0).collect();
+    linearize_rgba(&mut linear);
+    linear
+}
+
+/// Linearizes the color channels of float RGBA pixels in place.
+pub fn linearize_rgba(pixels: &mut [f32]) {
+    for px in pixels.chunks_exact_mut(4) {
+        px[..3].iter_mut().for_each(|v| *v = srgb_to_linear(*v));
+    }
+}
+
+/// BT.601 luma of a gamma-encoded RGB pixel.
+fn luma(rgb: &[f32]) -> f32 {
+    0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2]
+}
+
+/// Keeps the luma (Y') of `stylized` and the chroma (Cb, Cr) of `original`,
+/// both interleaved RGB in `space`, so only brushwork and shading change.
+/// With Cb and Cr fixed, swapping Y' shifts every channel of the original
+/// by the same amount. Y'CbCr is defined on gamma-encoded values, so linear
+/// tensors are encoded for the swap and decoded after it.
+pub fn preserve_chroma(original: &[f32], stylized: &mut [f32], space: ColorSpace) {
+    for (pixel, original) in stylized.chunks_exact_mut(3).zip(original.chunks_exact(3)) {
+        let (mut styled, mut source) = ([0.0; 3], [0.0; 3]);
+        styled.copy_from_slice(pixel);
+        source.copy_from_slice(original);
+        if space == ColorSpace::Linear {
+            styled = styled.map(linear_to_srgb);
+            source = source.map(linear_to_srgb);
+        }
+        let shift = luma(&styled) - luma(&source);
+        let mut kept = source.map(|channel| channel + shift);
+        if space == ColorSpace::Linear {
+            kept = kept.map(srgb_to_linear);
+        }
+        pixel.copy_from_slice(&kept);
+    }
+}
@@ -0,0 +1,161 @@
+//! Pre-compressed model files, for hosts that can't set `Content-Encoding`.
+
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelCompression {
+    None,
+    Gzip,
+    Brotli,
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Guesses the compression from magic bytes. Brotli streams have no magic
+/// number, so they have to be declared in the metadata.
+pub fn detect_compression(bytes: &[u8]) -> ModelCompression {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        ModelCompression::Gzip
+    } else {
+        ModelCompression::None
+    }
+}
+
+/// The compression a `.onnx.gz` or `.onnx.br` URL implies, ignoring any
+/// query string or fragment.
+pub fn compression_from_url(url: &str) -> Option<ModelCompression> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    if path.ends_with(".gz") {
+        Some(ModelCompression::Gzip)
+    } else if path.ends_with(".br") {
+        Some(ModelCompression::Brotli)
+    } else {
+        None
+    }
+}
+
+/// Decompresses a fetched model. `declared` comes from the metadata; when
+/// unset the format is sniffed. Uncompressed payloads are returned as-is.
+pub fn decompress_model(
+    bytes: Vec<u8>,
+    declared: Option<ModelCompression>,
+) -> Result<Vec<u8>, String> {
+    match declared.unwrap_or_else(|| detect_compression(&bytes)) {
+        ModelCompression::None => Ok(bytes),
+        ModelCompression::Gzip => {
+            let mut decompressed = Vec::with_capacity(bytes.len() * 2);
+            flate2::read::GzDecoder::new(&bytes[..])
+                .read_to_end(&mut decompressed)
+                .map_err(|e| format!("invalid gzip data: {}", e))?;
+            Ok(decompressed)
+        }
+        ModelCompression::Brotli => {
+            let mut decompressed = Vec::with_capacity(bytes.len() * 2);
+            brotli_decompressor::Decompressor::new(&bytes[..], 4096)
+                .read_to_end(&mut decompressed)
+                .map_err(|e| format!("invalid brotli data: {}", e))?;
+            Ok(decompressed)
+        }
+    }
+}
+
+enum Decoder {
+    /// Undeclared and too few bytes to sniff yet.
+    Sniffing,
+    None(Vec<u8>),
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Brotli(Box<brotli_decompressor::DecompressorWriter<Vec<u8>>>),
+    Failed(String),
+}
+
+/// Decompresses a model while it downloads, so that little is left to do
+/// once the last chunk arrives. Gives the same bytes as [`decompress_model`].
+pub struct StreamingDecompressor {
+    declared: Option<ModelCompression>,
+    decoder: Decoder,
+    /// Leading bytes of the download already fed to `decoder`.
+    consumed: usize,
+}
+
+impl StreamingDecompressor {
+    pub fn new(declared: Option<ModelCompression>) -> StreamingDecompressor {
+        StreamingDecompressor {
+            declared,
+            decoder: Decoder::Sniffing,
+            consumed: 0,
+        }
+    }
+
+    fn start(&mut self, compression: ModelCompression) {
+        self.decoder = match compression {
+            ModelCompression::None => Decoder::None(Vec::new()),
+            ModelCompression::Gzip => Decoder::Gzip(flate2::write::GzDecoder::new(Vec::new())),
+            ModelCompression::Brotli => Decoder::Brotli(Box::new(
+                brotli_decompressor::DecompressorWriter::new(Vec::new(), 4096),
+            )),
+        };
+    }
+
+    /// Decompresses the part of `received`, everything downloaded so far,
+    /// not seen yet. A `received` shorter than before means the download
+    /// started over, and so does decompression.
+    pub fn catch_up(&mut self, received: &[u8]) {
+        if received.len() < self.consumed {
+            *self = StreamingDecompressor::new(self.declared);
+        }
+        if let Decoder::Sniffing = self.decoder {
+            match self.declared {
+                Some(compression) => self.start(compression),
+                None if received.len() >= GZIP_MAGIC.len() => {
+                    self.start(detect_compression(received))
+                }
+                None => return,
+            }
+        }
+        let fresh = &received[self.consumed..];
+        let written = match &mut self.decoder {
+            Decoder::None(bytes) => {
+                bytes.extend_from_slice(fresh);
+                Ok(())
+            }
+            Decoder::Gzip(decoder) => decoder
+                .write_all(fresh)
+                .map_err(|e| format!("invalid gzip data: {}", e)),
+            Decoder::Brotli(decoder) => decoder
+                .write_all(fresh)
+                .map_err(|e| format!("invalid brotli data: {}", e)),
+            Decoder::Sniffing | Decoder::Failed(_) => Ok(()),
+        };
+        if let Err(reason) = written {
+            self.decoder = Decoder::Failed(reason);
+        }
+        self.consumed = received.len();
+    }
+
+    /// The decompressed model once `received` is the whole download.
+    pub fn finish(mut self, received: &[u8]) -> Result<Vec<u8>, String> {
+        self.catch_up(received);
+        if let Decoder::Sniffing = self.decoder {
+            // Too short to be gzip
+            self.start(ModelCompression::None);
+            self.catch_up(received);
+        }
+        match self.decoder {
+            Decoder::None(bytes) => Ok(bytes),
+            Decoder::Gzip(decoder) => decoder
+                .finish()
+                .map_err(|e| format!("invalid gzip data: {}", e)),
+            Decoder::Brotli(mut decoder) => match decoder.close() {
+                Ok(()) => decoder
+                    .into_inner()
+                    .map_err(|_| "invalid brotli data: stream ended early".to_string()),
+                Err(e) => Err(format!("invalid brotli data: {}", e)),
+            },
+            Decoder::Failed(reason) => Err(reason),
+            Decoder::Sniffing => unreachable!("started above"),
+        }
+    }
+}
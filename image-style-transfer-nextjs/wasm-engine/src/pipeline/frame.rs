@@ -0,0 +1,231 @@
+//! Converting WebCodecs `VideoFrame` pixel layouts to RGBA.
+//!
+//! `VideoFrame.copyTo` hands back the frame in its native format, one plane
+//! after another with the offsets and strides it reports; decoders and
+//! cameras mostly produce 4:2:0 YUV.
+
+/// The `VideoFrame.format` values the engine reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// Y, U and V planes, chroma subsampled 2x2.
+    I420,
+    /// `I420` with a full-resolution alpha plane.
+    I420A,
+    /// Y plane and one interleaved UV plane, chroma subsampled 2x2.
+    Nv12,
+    Rgba,
+    /// `Rgba` with the alpha byte unused.
+    Rgbx,
+    Bgra,
+    Bgrx,
+}
+
+impl PixelFormat {
+    pub fn parse(format: &str) -> Result<PixelFormat, String> {
+        match format {
+            "I420" => Ok(PixelFormat::I420),
+            "I420A" => Ok(PixelFormat::I420A),
+            "NV12" => Ok(PixelFormat::Nv12),
+            "RGBA" => Ok(PixelFormat::Rgba),
+            "RGBX" => Ok(PixelFormat::Rgbx),
+            "BGRA" => Ok(PixelFormat::Bgra),
+            "BGRX" => Ok(PixelFormat::Bgrx),
+            other => Err(format!("unsupported VideoFrame format {}", other)),
+        }
+    }
+
+    fn plane_count(self) -> usize {
+        match self {
+            PixelFormat::I420 => 3,
+            PixelFormat::I420A => 4,
+            PixelFormat::Nv12 => 2,
+            PixelFormat::Rgba | PixelFormat::Rgbx | PixelFormat::Bgra | PixelFormat::Bgrx => 1,
+        }
+    }
+}
+
+/// Where one plane starts in the copied buffer and how far apart its rows are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaneLayout {
+    pub offset: usize,
+    pub stride: usize,
+}
+
+/// The YUV to RGB matrix of `VideoColorSpace.matrix`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum YuvMatrix {
+    /// Standard definition video and most webcams.
+    #[default]
+    Bt601,
+    /// High definition video.
+    Bt709,
+}
+
+impl YuvMatrix {
+    /// `bt709` or anything else, which is treated as BT.601.
+    pub fn parse(matrix: Option<&str>) -> YuvMatrix {
+        match matrix {
+            Some("bt709") => YuvMatrix::Bt709,
+            _ => YuvMatrix::Bt601,
+        }
+    }
+
+    /// Red from V, green from U and V, blue from U.
+    fn coefficients(self) -> (f32, f32, f32, f32) {
+        match self {
+            YuvMatrix::Bt601 => (1.402, 0.344_136, 0.714_136, 1.772),
+            YuvMatrix::Bt709 => (1.5748, 0.187_324, 0.468_124, 1.8556),
+        }
+    }
+}
+
+/// How a frame's YUV samples map to RGB; RGB formats ignore it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameColor {
+    pub matrix: YuvMatrix,
+    /// Samples use 0-255 rather than video range (16-235, chroma 16-240).
+    pub full_range: bool,
+}
+
+/// Converts a frame copied in its native `format` to RGBA. Formats without
+/// alpha come out opaque.
+pub fn frame_to_rgba(
+    format: PixelFormat,
+    data: &[u8],
+    planes: &[PlaneLayout],
+    (width, height): (u32, u32),
+    color: FrameColor,
+) -> Result<Vec<u8>, String> {
+    let (width, height) = (width as usize, height as usize);
+    if planes.len() != format.plane_count() {
+        return Err(format!(
+            "{:?} has {} planes, got layouts for {}",
+            format,
+            format.plane_count(),
+            planes.len()
+        ));
+    }
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let plane_sizes: Vec<(usize, usize)> = match format {
+        PixelFormat::I420 => vec![
+            (width, height),
+            (chroma_width, chroma_height),
+            (chroma_width, chroma_height),
+        ],
+        PixelFormat::I420A => vec![
+            (width, height),
+            (chroma_width, chroma_height),
+            (chroma_width, chroma_height),
+            (width, height),
+        ],
+        PixelFormat::Nv12 => vec![(width, height), (chroma_width * 2, chroma_height)],
+        _ => vec![(width * 4, height)],
+    };
+    for (index, (plane, &(row_len, rows))) in planes.iter().zip(&plane_sizes).enumerate() {
+        let needed = match rows {
+            0 => 0,
+            rows => plane.offset + plane.stride * (rows - 1) + row_len,
+        };
+        if plane.stride < row_len || needed > data.len() {
+            return Err(format!(
+                "plane {} needs {} bytes with stride {}, the buffer has {}",
+                index,
+                needed,
+                plane.stride,
+                data.len()
+            ));
+        }
+    }
+    let row = |plane: usize, y: usize| &data[planes[plane].offset + planes[plane].stride * y..];
+
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    match format {
+        PixelFormat::Rgba | PixelFormat::Rgbx | PixelFormat::Bgra | PixelFormat::Bgrx => {
+            let swap = matches!(format, PixelFormat::Bgra | PixelFormat::Bgrx);
+            let opaque = matches!(format, PixelFormat::Rgbx | PixelFormat::Bgrx);
+            for y in 0..height {
+                for pixel in row(0, y)[..width * 4].chunks_exact(4) {
+                    let (r, b) = if swap {
+                        (pixel[2], pixel[0])
+                    } else {
+                        (pixel[0], pixel[2])
+                    };
+                    pixels.extend_from_slice(&[
+                        r,
+                        pixel[1],
+                        b,
+                        if opaque { 255 } else { pixel[3] },
+                    ]);
+                }
+            }
+        }
+        PixelFormat::I420 | PixelFormat::I420A | PixelFormat::Nv12 => {
+            for y in 0..height {
+                let luma = row(0, y);
+                for x in 0..width {
+                    let (u, v) = match format {
+                        PixelFormat::Nv12 => {
+                            let uv = row(1, y / 2);
+                            (uv[x / 2 * 2], uv[x / 2 * 2 + 1])
+                        }
+                        _ => (row(1, y / 2)[x / 2], row(2, y / 2)[x / 2]),
+                    };
+                    let [r, g, b] = yuv_to_rgb(luma[x], u, v, color);
+                    let alpha = match format {
+                        PixelFormat::I420A => row(3, y)[x],
+                        _ => 255,
+                    };
+                    pixels.extend_from_slice(&[r, g, b, alpha]);
+                }
+            }
+        }
+    }
+    Ok(pixels)
+}
+
+fn yuv_to_rgb(y: u8, u: u8, v: u8, color: FrameColor) -> [u8; 3] {
+    let (y, u, v) = (y as f32, u as f32 - 128.0, v as f32 - 128.0);
+    let (y, u, v) = if color.full_range {
+        (y, u, v)
+    } else {
+        (
+            (y - 16.0) * 255.0 / 219.0,
+            u * 255.0 / 224.0,
+            v * 255.0 / 224.0,
+        )
+    };
+    let (red_v, green_u, green_v, blue_u) = color.matrix.coefficients();
+    [y + red_v * v, y - green_u * u - green_v * v, y + blue_u * u]
+        .map(|channel| channel.round().clamp(0.0, 255.0) as u8)
+}
+
+/// Drops the frames of a live stream that were captured while the previous
+/// one was being stylized, so a slow model lowers the frame rate rather than
+/// letting the output fall further and further behind the camera.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FramePacer {
+    /// Frames captured before this timestamp (microseconds) are stale.
+    next_due: Option<f64>,
+    pub processed: u32,
+    pub skipped: u32,
+}
+
+impl FramePacer {
+    /// Whether the frame captured at `timestamp` should be stylized; counts
+    /// it as skipped when not.
+    pub fn admit(&mut self, timestamp: f64) -> bool {
+        match self.next_due {
+            Some(due) if timestamp < due => {
+                self.skipped += 1;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Records that the frame captured at `timestamp` took `elapsed_ms`.
+    pub fn finished(&mut self, timestamp: f64, elapsed_ms: f64) {
+        self.processed += 1;
+        self.next_due = Some(timestamp + elapsed_ms * 1000.0);
+    }
+}
@@ -0,0 +1,144 @@
+//! Golden-image regression harness.
+//!
+//! Runs the DOM-free pipeline over small synthetic inputs and digests the
+//! 8-bit output, so refactors of the pixel loops that change what users see
+//! fail a test instead of being noticed visually. Used by
+//! `tests/golden_harness_tests.rs` natively and in the browser, and by the
+//! hidden `debug_process_rgba_hash` export for checking a deployed build.
+//!
+//! To record new digests after an intended change, run
+//! `UPDATE_GOLDEN=1 cargo test --test golden_harness_tests -- --nocapture`
+//! and paste the printed table into the test.
+
+use xxhash_rust::xxh64::xxh64;
+
+use super::{
+    default_registry, load_plan, process_rgba, ModelKind, ModelMetadata, XorShift64,
+    DEFAULT_SIMULATION_SEED,
+};
+
+/// Width and height of every golden input.
+pub const GOLDEN_SIZE: u32 = 64;
+
+/// Style name that runs [`IDENTITY_MODEL`] instead of a simulated style.
+pub const IDENTITY_STYLE: &str = "identity_onnx";
+
+/// A one-node ONNX graph passing a `[1, 3, 64, 64]` input through unchanged.
+pub const IDENTITY_MODEL: &[u8] = include_bytes!("golden/identity.onnx");
+
+/// The synthetic images every style is run over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoldenInput {
+    Gradient,
+    Checkerboard,
+    /// Uniform noise from a fixed seed.
+    Noise,
+}
+
+impl GoldenInput {
+    pub const ALL: [GoldenInput; 3] = [
+        GoldenInput::Gradient,
+        GoldenInput::Checkerboard,
+        GoldenInput::Noise,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GoldenInput::Gradient => "gradient",
+            GoldenInput::Checkerboard => "checkerboard",
+            GoldenInput::Noise => "noise",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<GoldenInput> {
+        GoldenInput::ALL
+            .into_iter()
+            .find(|input| input.name() == name)
+    }
+
+    /// Opaque RGBA pixels, `GOLDEN_SIZE` square.
+    pub fn pixels(self) -> Vec<u8> {
+        let size = GOLDEN_SIZE;
+        let mut rng = XorShift64::new(0x9E37_79B9_7F4A_7C15);
+        let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let rgb = match self {
+                    GoldenInput::Gradient => {
+                        let scale = |v: u32| (v * 255 / (size - 1)) as u8;
+                        [scale(x), scale(y), scale((x + y) / 2)]
+                    }
+                    GoldenInput::Checkerboard => {
+                        if (x / 8 + y / 8).is_multiple_of(2) {
+                            [240, 200, 20]
+                        } else {
+                            [10, 30, 90]
+                        }
+                    }
+                    GoldenInput::Noise => {
+                        let bits = rng.next_u64();
+                        [bits as u8, (bits >> 8) as u8, (bits >> 16) as u8]
+                    }
+                };
+                pixels.extend_from_slice(&rgb);
+                pixels.push(255);
+            }
+        }
+        pixels
+    }
+}
+
+/// Names of the built-in styles with a simulated fallback, plus
+/// [`IDENTITY_STYLE`].
+pub fn golden_styles() -> Vec<String> {
+    default_registry()
+        .into_iter()
+        .filter(|metadata| metadata.simulated_style.is_some())
+        .map(|metadata| metadata.name)
+        .chain([IDENTITY_STYLE.to_string()])
+        .collect()
+}
+
+/// Full-strength output of `style` over `input`.
+pub fn golden_output(style: &str, input: GoldenInput) -> Result<Vec<u8>, String> {
+    let sized = |metadata: ModelMetadata| ModelMetadata {
+        input_width: GOLDEN_SIZE,
+        input_height: GOLDEN_SIZE,
+        ..metadata
+    };
+    let pixels = input.pixels();
+    if style == IDENTITY_STYLE {
+        let plan = load_plan(IDENTITY_MODEL).map_err(|e| e.to_string())?;
+        let metadata = sized(ModelMetadata {
+            name: IDENTITY_STYLE.to_string(),
+            kind: ModelKind::Onnx,
+            ..ModelMetadata::default()
+        });
+        return Ok(process_rgba(
+            &pixels,
+            &metadata,
+            Some(&plan),
+            1.0,
+            None,
+            DEFAULT_SIMULATION_SEED,
+        ));
+    }
+    let metadata = default_registry()
+        .into_iter()
+        .find(|metadata| metadata.name == style && metadata.simulated_style.is_some())
+        .ok_or_else(|| format!("'{}' is not a built-in simulated style", style))?;
+    Ok(process_rgba(
+        &pixels,
+        &sized(metadata),
+        None,
+        1.0,
+        None,
+        DEFAULT_SIMULATION_SEED,
+    ))
+}
+
+/// xxHash64 (seed 0) of the 8-bit output, so last-ulp float differences
+/// between native and wasm libm don't count as a change.
+pub fn golden_hash(style: &str, input: GoldenInput) -> Result<u64, String> {
+    golden_output(style, input).map(|output| xxh64(&output, 0))
+}
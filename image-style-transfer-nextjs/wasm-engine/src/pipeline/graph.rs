@@ -0,0 +1,790 @@
+//! The image ops feed-forward style networks are built from, as a flat
+//! graph lowered from ONNX so that it can run outside tract. The WebGPU
+//! backend executes it with compute shaders; [`Graph::run_reference`] is the
+//! CPU definition those shaders follow.
+
+#[cfg(feature = "backend-tract")]
+use std::collections::HashMap;
+
+#[cfg(feature = "backend-tract")]
+use tract_onnx::pb;
+#[cfg(feature = "backend-tract")]
+use tract_onnx::prelude::*;
+
+/// Index into a graph's values: 0 is the input image and op `i` writes
+/// value `i + 1`.
+pub type ValueId = usize;
+
+/// `[channels, height, width]` of a value; the batch is always 1.
+pub type Shape = [usize; 3];
+
+/// Padding as `[top, left, bottom, right]`.
+pub type Pads = [usize; 4];
+
+/// Either side of an [`Op::Binary`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    Value(ValueId),
+    /// One constant per channel.
+    Channel(Vec<f32>),
+    Scalar(f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryKind {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryKind {
+    Relu,
+    LeakyRelu(f32),
+    Sigmoid,
+    Tanh,
+    Clip(f32, f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadMode {
+    Constant(f32),
+    Reflect,
+    Edge,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    /// 2D convolution with `[out, in, kh, kw]` weights and one bias per
+    /// output channel.
+    Conv {
+        input: ValueId,
+        weights: Vec<f32>,
+        bias: Vec<f32>,
+        out_channels: usize,
+        kernel: (usize, usize),
+        stride: (usize, usize),
+        pads: Pads,
+    },
+    InstanceNorm {
+        input: ValueId,
+        scale: Vec<f32>,
+        bias: Vec<f32>,
+        epsilon: f32,
+    },
+    Unary {
+        input: ValueId,
+        kind: UnaryKind,
+    },
+    /// At least one side is a value; constants broadcast over it.
+    Binary {
+        lhs: Operand,
+        rhs: Operand,
+        kind: BinaryKind,
+    },
+    /// Nearest-neighbour upsampling by whole `(y, x)` factors.
+    Upsample {
+        input: ValueId,
+        scale: (usize, usize),
+    },
+    Pad {
+        input: ValueId,
+        pads: Pads,
+        mode: PadMode,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Graph {
+    pub ops: Vec<Op>,
+    pub output: ValueId,
+}
+
+impl UnaryKind {
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            UnaryKind::Relu => x.max(0.0),
+            UnaryKind::LeakyRelu(alpha) => {
+                if x < 0.0 {
+                    alpha * x
+                } else {
+                    x
+                }
+            }
+            UnaryKind::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            UnaryKind::Tanh => x.tanh(),
+            UnaryKind::Clip(min, max) => x.max(min).min(max),
+        }
+    }
+}
+
+impl BinaryKind {
+    pub fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            BinaryKind::Add => a + b,
+            BinaryKind::Sub => a - b,
+            BinaryKind::Mul => a * b,
+            BinaryKind::Div => a / b,
+        }
+    }
+}
+
+impl Op {
+    /// The shape this op produces from the shapes of earlier values.
+    pub fn output_shape(&self, shapes: &[Shape]) -> Result<Shape, String> {
+        let shape_of = |id: ValueId| {
+            shapes
+                .get(id)
+                .copied()
+                .ok_or_else(|| format!("value {} is used before it is computed", id))
+        };
+        match self {
+            Op::Conv {
+                input,
+                weights,
+                bias,
+                out_channels,
+                kernel: (kh, kw),
+                stride: (sy, sx),
+                pads,
+            } => {
+                let [c, h, w] = shape_of(*input)?;
+                if weights.len() != out_channels * c * kh * kw || bias.len() != *out_channels {
+                    return Err(format!(
+                        "convolution weights don't fit {} input channels",
+                        c
+                    ));
+                }
+                let (padded_h, padded_w) = (h + pads[0] + pads[2], w + pads[1] + pads[3]);
+                if padded_h < *kh || padded_w < *kw || *sy == 0 || *sx == 0 {
+                    return Err(format!(
+                        "a {}x{} kernel doesn't fit a {}x{} input",
+                        kw, kh, w, h
+                    ));
+                }
+                Ok([
+                    *out_channels,
+                    (padded_h - kh) / sy + 1,
+                    (padded_w - kw) / sx + 1,
+                ])
+            }
+            Op::InstanceNorm {
+                input, scale, bias, ..
+            } => {
+                let shape = shape_of(*input)?;
+                if scale.len() != shape[0] || bias.len() != shape[0] {
+                    return Err(format!(
+                        "instance norm parameters don't fit {} channels",
+                        shape[0]
+                    ));
+                }
+                Ok(shape)
+            }
+            Op::Unary { input, .. } => shape_of(*input),
+            Op::Binary { lhs, rhs, .. } => {
+                let shape = match (lhs, rhs) {
+                    (Operand::Value(id), _) | (_, Operand::Value(id)) => shape_of(*id)?,
+                    _ => return Err("an elementwise op needs a value operand".to_string()),
+                };
+                for operand in [lhs, rhs] {
+                    match operand {
+                        Operand::Value(id) if shape_of(*id)? != shape => {
+                            return Err(format!(
+                                "can't combine values of shapes {:?} and {:?}",
+                                shape,
+                                shape_of(*id)?
+                            ))
+                        }
+                        Operand::Channel(values) if values.len() != shape[0] => {
+                            return Err(format!(
+                                "{} per-channel constants for {} channels",
+                                values.len(),
+                                shape[0]
+                            ))
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(shape)
+            }
+            Op::Upsample {
+                input,
+                scale: (sy, sx),
+            } => {
+                let [c, h, w] = shape_of(*input)?;
+                Ok([c, h * sy, w * sx])
+            }
+            Op::Pad { input, pads, mode } => {
+                let [c, h, w] = shape_of(*input)?;
+                if *mode == PadMode::Reflect
+                    && (pads[0].max(pads[2]) >= h || pads[1].max(pads[3]) >= w)
+                {
+                    return Err(format!(
+                        "can't reflect-pad a {}x{} input by {:?}",
+                        w, h, pads
+                    ));
+                }
+                Ok([c, h + pads[0] + pads[2], w + pads[1] + pads[3]])
+            }
+        }
+    }
+}
+
+impl Graph {
+    /// The shape of every value for an input of `input` shape.
+    pub fn shapes(&self, input: Shape) -> Result<Vec<Shape>, String> {
+        let mut shapes = vec![input];
+        for op in &self.ops {
+            let shape = op.output_shape(&shapes)?;
+            shapes.push(shape);
+        }
+        if self.output >= shapes.len() {
+            return Err(format!("output value {} doesn't exist", self.output));
+        }
+        Ok(shapes)
+    }
+
+    /// Runs the graph on the CPU on a planar input of `shape`, returning the
+    /// planar output and its shape.
+    pub fn run_reference(&self, input: &[f32], shape: Shape) -> Result<(Vec<f32>, Shape), String> {
+        let shapes = self.shapes(shape)?;
+        if input.len() != shape.iter().product::<usize>() {
+            return Err(format!("{} values for a {:?} input", input.len(), shape));
+        }
+        let mut values: Vec<Vec<f32>> = vec![input.to_vec()];
+        for (i, op) in self.ops.iter().enumerate() {
+            let output = run_op(op, &values, &shapes, shapes[i + 1]);
+            values.push(output);
+        }
+        Ok((values.swap_remove(self.output), shapes[self.output]))
+    }
+}
+
+fn run_op(op: &Op, values: &[Vec<f32>], shapes: &[Shape], [oc, oh, ow]: Shape) -> Vec<f32> {
+    match op {
+        Op::Conv {
+            input,
+            weights,
+            bias,
+            kernel: (kh, kw),
+            stride: (sy, sx),
+            pads,
+            ..
+        } => {
+            let [ic, ih, iw] = shapes[*input];
+            let x = &values[*input];
+            let mut out = vec![0.0; oc * oh * ow];
+            for o in 0..oc {
+                for oy in 0..oh {
+                    for ox in 0..ow {
+                        let mut sum = bias[o];
+                        for i in 0..ic {
+                            for ky in 0..*kh {
+                                let y = (oy * sy + ky) as isize - pads[0] as isize;
+                                if y < 0 || y >= ih as isize {
+                                    continue;
+                                }
+                                for kx in 0..*kw {
+                                    let xx = (ox * sx + kx) as isize - pads[1] as isize;
+                                    if xx < 0 || xx >= iw as isize {
+                                        continue;
+                                    }
+                                    sum += weights[((o * ic + i) * kh + ky) * kw + kx]
+                                        * x[(i * ih + y as usize) * iw + xx as usize];
+                                }
+                            }
+                        }
+                        out[(o * oh + oy) * ow + ox] = sum;
+                    }
+                }
+            }
+            out
+        }
+        Op::InstanceNorm {
+            input,
+            scale,
+            bias,
+            epsilon,
+        } => {
+            let plane = oh * ow;
+            values[*input]
+                .chunks_exact(plane)
+                .enumerate()
+                .flat_map(|(c, values)| {
+                    let mean = values.iter().sum::<f32>() / plane as f32;
+                    let variance =
+                        values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / plane as f32;
+                    let inv_std = 1.0 / (variance + epsilon).sqrt();
+                    values
+                        .iter()
+                        .map(move |v| (v - mean) * inv_std * scale[c] + bias[c])
+                })
+                .collect()
+        }
+        Op::Unary { input, kind } => values[*input].iter().map(|&x| kind.apply(x)).collect(),
+        Op::Binary { lhs, rhs, kind } => {
+            let plane = oh * ow;
+            let operand = |operand: &Operand, i: usize| match operand {
+                Operand::Value(id) => values[*id][i],
+                Operand::Channel(channels) => channels[i / plane],
+                Operand::Scalar(value) => *value,
+            };
+            (0..oc * plane)
+                .map(|i| kind.apply(operand(lhs, i), operand(rhs, i)))
+                .collect()
+        }
+        Op::Upsample {
+            input,
+            scale: (sy, sx),
+        } => {
+            let [_, ih, iw] = shapes[*input];
+            let x = &values[*input];
+            (0..oc * oh * ow)
+                .map(|i| {
+                    let (c, y, xx) = (i / (oh * ow), i / ow % oh, i % ow);
+                    x[(c * ih + y / sy) * iw + xx / sx]
+                })
+                .collect()
+        }
+        Op::Pad { input, pads, mode } => {
+            let [_, ih, iw] = shapes[*input];
+            let x = &values[*input];
+            (0..oc * oh * ow)
+                .map(|i| {
+                    let (c, y, xx) = (i / (oh * ow), i / ow % oh, i % ow);
+                    let y = y as isize - pads[0] as isize;
+                    let xx = xx as isize - pads[1] as isize;
+                    let (y, xx) = match mode {
+                        PadMode::Constant(value) => {
+                            if y < 0 || xx < 0 || y >= ih as isize || xx >= iw as isize {
+                                return *value;
+                            }
+                            (y, xx)
+                        }
+                        PadMode::Reflect => (reflect(y, ih), reflect(xx, iw)),
+                        PadMode::Edge => {
+                            (y.clamp(0, ih as isize - 1), xx.clamp(0, iw as isize - 1))
+                        }
+                    };
+                    x[(c * ih + y as usize) * iw + xx as usize]
+                })
+                .collect()
+        }
+    }
+}
+
+/// Mirrors an out-of-range index back into `0..len` without repeating the
+/// edge, as ONNX `reflect` padding does.
+fn reflect(i: isize, len: usize) -> isize {
+    let last = len as isize - 1;
+    if i < 0 {
+        -i
+    } else if i > last {
+        2 * last - i
+    } else {
+        i
+    }
+}
+
+/// Reads an ONNX model into a [`Graph`]. Fails on any op, attribute or
+/// broadcast the graph can't express, naming it, so callers can fall back
+/// to a runtime that understands the whole file.
+#[cfg(feature = "backend-tract")]
+pub fn lower_onnx(model_bytes: &[u8]) -> Result<Graph, String> {
+    let proto = tract_onnx::onnx()
+        .proto_model_for_read(&mut std::io::Cursor::new(model_bytes))
+        .map_err(|e| format!("{:#}", e))?;
+    let graph = proto.graph.unwrap_or_default();
+
+    let mut lowering = Lowering::default();
+    for tensor in &graph.initializer {
+        let constant = load_constant(tensor)?;
+        lowering.constants.insert(tensor.name.clone(), constant);
+    }
+    let input = graph
+        .input
+        .iter()
+        .find(|input| !lowering.constants.contains_key(&input.name))
+        .ok_or("the graph has no input")?;
+    lowering.values.insert(input.name.clone(), 0);
+    for node in &graph.node {
+        lowering
+            .lower_node(node)
+            .map_err(|e| format!("node '{}' ({}): {}", node.name, node.op_type, e))?;
+    }
+    let output = graph.output.first().ok_or("the graph has no output")?;
+    let output = *lowering
+        .values
+        .get(&output.name)
+        .ok_or_else(|| format!("output '{}' isn't computed from the input", output.name))?;
+    Ok(Graph {
+        ops: lowering.ops,
+        output,
+    })
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn lower_onnx(_model_bytes: &[u8]) -> Result<Graph, String> {
+    Err("built without the backend-tract feature".to_string())
+}
+
+/// A constant tensor: its shape and values as f32.
+#[cfg(feature = "backend-tract")]
+type Constant = (Vec<usize>, Vec<f32>);
+
+#[cfg(feature = "backend-tract")]
+fn load_constant(tensor: &pb::TensorProto) -> Result<Constant, String> {
+    let loaded = tract_onnx::tensor::load_tensor(
+        &tract_onnx::data_resolver::FopenDataResolver,
+        tensor,
+        None,
+    )
+    .and_then(|t| t.cast_to::<f32>().map(|t| t.into_owned()))
+    .map_err(|e| format!("can't read constant '{}': {}", tensor.name, e))?;
+    let values = loaded
+        .try_as_plain_ram()
+        .and_then(|plain| plain.as_slice::<f32>())
+        .map_err(|e| e.to_string())?
+        .to_vec();
+    Ok((loaded.shape().to_vec(), values))
+}
+
+#[cfg(feature = "backend-tract")]
+#[derive(Default)]
+struct Lowering {
+    constants: HashMap<String, Constant>,
+    values: HashMap<String, ValueId>,
+    ops: Vec<Op>,
+}
+
+#[cfg(feature = "backend-tract")]
+impl Lowering {
+    fn lower_node(&mut self, node: &pb::NodeProto) -> Result<(), String> {
+        let output = node.output.first().ok_or("no output")?.clone();
+        let op = match node.op_type.as_str() {
+            "Constant" => {
+                let tensor = attribute(node, "value")
+                    .and_then(|a| a.t.as_ref())
+                    .ok_or("only tensor constants are supported")?;
+                let constant = load_constant(tensor)?;
+                self.constants.insert(output, constant);
+                return Ok(());
+            }
+            "Identity" | "Dropout" => {
+                let input = self.input_name(node, 0)?;
+                match (self.values.get(input), self.constants.get(input)) {
+                    (Some(&id), _) => {
+                        self.values.insert(output, id);
+                    }
+                    (None, Some(constant)) => {
+                        let constant = constant.clone();
+                        self.constants.insert(output, constant);
+                    }
+                    (None, None) => return Err(format!("unknown input '{}'", input)),
+                }
+                return Ok(());
+            }
+            "Conv" => self.conv(node)?,
+            "InstanceNormalization" => {
+                let (_, scale) = self.constant(node, 1)?;
+                let (_, bias) = self.constant(node, 2)?;
+                Op::InstanceNorm {
+                    input: self.value(node, 0)?,
+                    scale: scale.clone(),
+                    bias: bias.clone(),
+                    epsilon: float_attribute(node, "epsilon").unwrap_or(1e-5),
+                }
+            }
+            "BatchNormalization" => return self.batch_norm(node, output),
+            "Relu" => self.unary(node, UnaryKind::Relu)?,
+            "LeakyRelu" => self.unary(
+                node,
+                UnaryKind::LeakyRelu(float_attribute(node, "alpha").unwrap_or(0.01)),
+            )?,
+            "Sigmoid" => self.unary(node, UnaryKind::Sigmoid)?,
+            "Tanh" => self.unary(node, UnaryKind::Tanh)?,
+            "Clip" => {
+                // Opset 11 moved the bounds from attributes to inputs
+                let bound = |index: usize, name: &str, default: f32| match self
+                    .optional_constant(node, index)?
+                {
+                    Some((_, values)) => values.first().copied().ok_or("empty bound".to_string()),
+                    None => Ok(float_attribute(node, name).unwrap_or(default)),
+                };
+                let kind = UnaryKind::Clip(bound(1, "min", f32::MIN)?, bound(2, "max", f32::MAX)?);
+                self.unary(node, kind)?
+            }
+            "Add" => self.binary(node, BinaryKind::Add)?,
+            "Sub" => self.binary(node, BinaryKind::Sub)?,
+            "Mul" => self.binary(node, BinaryKind::Mul)?,
+            "Div" => self.binary(node, BinaryKind::Div)?,
+            "Upsample" | "Resize" => self.upsample(node)?,
+            "Pad" => self.pad(node)?,
+            other => return Err(format!("unsupported op {}", other)),
+        };
+        self.push(output, op);
+        Ok(())
+    }
+
+    fn push(&mut self, output: String, op: Op) {
+        self.ops.push(op);
+        self.values.insert(output, self.ops.len());
+    }
+
+    fn input_name<'a>(&self, node: &'a pb::NodeProto, index: usize) -> Result<&'a str, String> {
+        node.input
+            .get(index)
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| format!("missing input {}", index))
+    }
+
+    fn value(&self, node: &pb::NodeProto, index: usize) -> Result<ValueId, String> {
+        let name = self.input_name(node, index)?;
+        self.values
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("input '{}' isn't computed from the image", name))
+    }
+
+    fn constant(&self, node: &pb::NodeProto, index: usize) -> Result<&Constant, String> {
+        self.optional_constant(node, index)?
+            .ok_or_else(|| format!("missing input {}", index))
+    }
+
+    fn optional_constant(
+        &self,
+        node: &pb::NodeProto,
+        index: usize,
+    ) -> Result<Option<&Constant>, String> {
+        match node.input.get(index).filter(|name| !name.is_empty()) {
+            None => Ok(None),
+            Some(name) => self
+                .constants
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| format!("input '{}' must be a constant", name)),
+        }
+    }
+
+    fn operand(&self, node: &pb::NodeProto, index: usize) -> Result<Operand, String> {
+        let name = self.input_name(node, index)?;
+        if let Some(&id) = self.values.get(name) {
+            return Ok(Operand::Value(id));
+        }
+        let (shape, values) = self
+            .constants
+            .get(name)
+            .ok_or_else(|| format!("unknown input '{}'", name))?;
+        // Scalars, and [C, 1, 1] or [1, C, 1, 1] per-channel constants
+        let significant: Vec<usize> = shape.iter().copied().skip_while(|&d| d == 1).collect();
+        match significant.as_slice() {
+            [] => Ok(Operand::Scalar(values[0])),
+            [_, 1, 1] if shape.len() >= 3 => Ok(Operand::Channel(values.clone())),
+            _ => Err(format!("can't broadcast a constant of shape {:?}", shape)),
+        }
+    }
+
+    fn unary(&self, node: &pb::NodeProto, kind: UnaryKind) -> Result<Op, String> {
+        Ok(Op::Unary {
+            input: self.value(node, 0)?,
+            kind,
+        })
+    }
+
+    fn binary(&self, node: &pb::NodeProto, kind: BinaryKind) -> Result<Op, String> {
+        let (lhs, rhs) = (self.operand(node, 0)?, self.operand(node, 1)?);
+        if !matches!(lhs, Operand::Value(_)) && !matches!(rhs, Operand::Value(_)) {
+            return Err("both operands are constants".to_string());
+        }
+        Ok(Op::Binary { lhs, rhs, kind })
+    }
+
+    fn conv(&self, node: &pb::NodeProto) -> Result<Op, String> {
+        if int_attribute(node, "group").unwrap_or(1) != 1 {
+            return Err("grouped convolutions are not supported".to_string());
+        }
+        if ints_attribute(node, "dilations").iter().any(|&d| d != 1) {
+            return Err("dilated convolutions are not supported".to_string());
+        }
+        match string_attribute(node, "auto_pad").as_deref() {
+            None | Some("NOTSET") | Some("VALID") => {}
+            Some(other) => return Err(format!("auto_pad {} is not supported", other)),
+        }
+        let (shape, weights) = self.constant(node, 1)?;
+        let [out_channels, _, kh, kw] = shape[..] else {
+            return Err(format!("expected 4D weights, got {:?}", shape));
+        };
+        let bias = match self.optional_constant(node, 2)? {
+            Some((_, bias)) => bias.clone(),
+            None => vec![0.0; out_channels],
+        };
+        let strides = ints_attribute(node, "strides");
+        let stride = match strides[..] {
+            [] => (1, 1),
+            [sy, sx] => (sy as usize, sx as usize),
+            _ => return Err(format!("expected 2 strides, got {:?}", strides)),
+        };
+        Ok(Op::Conv {
+            input: self.value(node, 0)?,
+            weights: weights.clone(),
+            bias,
+            out_channels,
+            kernel: (kh, kw),
+            stride,
+            pads: spatial_pads(&ints_attribute(node, "pads"), 2)?,
+        })
+    }
+
+    /// Folds the statistics into a per-channel multiply and add.
+    fn batch_norm(&mut self, node: &pb::NodeProto, output: String) -> Result<(), String> {
+        let epsilon = float_attribute(node, "epsilon").unwrap_or(1e-5);
+        let (_, scale) = self.constant(node, 1)?;
+        let (_, bias) = self.constant(node, 2)?;
+        let (_, mean) = self.constant(node, 3)?;
+        let (_, variance) = self.constant(node, 4)?;
+        let factor: Vec<f32> = scale
+            .iter()
+            .zip(variance)
+            .map(|(s, v)| s / (v + epsilon).sqrt())
+            .collect();
+        let offset: Vec<f32> = bias
+            .iter()
+            .zip(mean)
+            .zip(&factor)
+            .map(|((b, m), f)| b - m * f)
+            .collect();
+        let scaled = Op::Binary {
+            lhs: Operand::Value(self.value(node, 0)?),
+            rhs: Operand::Channel(factor),
+            kind: BinaryKind::Mul,
+        };
+        self.ops.push(scaled);
+        let shifted = Op::Binary {
+            lhs: Operand::Value(self.ops.len()),
+            rhs: Operand::Channel(offset),
+            kind: BinaryKind::Add,
+        };
+        self.push(output, shifted);
+        Ok(())
+    }
+
+    fn upsample(&self, node: &pb::NodeProto) -> Result<Op, String> {
+        let mode = string_attribute(node, "mode").unwrap_or_else(|| "nearest".to_string());
+        if mode != "nearest" {
+            return Err(format!("{} interpolation is not supported", mode));
+        }
+        // Upsample and opset 10 Resize take scales second, later Resize third
+        let index = if node.op_type == "Resize" && node.input.len() > 2 {
+            2
+        } else {
+            1
+        };
+        let scales = match self.optional_constant(node, index)? {
+            Some((_, scales)) if !scales.is_empty() => scales.clone(),
+            _ => {
+                let scales: Vec<f32> = attribute(node, "scales")
+                    .map(|a| a.floats.clone())
+                    .unwrap_or_default();
+                if scales.is_empty() {
+                    return Err("only constant scales are supported".to_string());
+                }
+                scales
+            }
+        };
+        let whole = |scale: f32| (scale >= 1.0 && scale.fract() == 0.0).then_some(scale as usize);
+        match scales[..] {
+            [n, c, sy, sx] if n == 1.0 && c == 1.0 => match (whole(sy), whole(sx)) {
+                (Some(sy), Some(sx)) => Ok(Op::Upsample {
+                    input: self.value(node, 0)?,
+                    scale: (sy, sx),
+                }),
+                _ => Err(format!(
+                    "scales {:?} aren't whole upsampling factors",
+                    scales
+                )),
+            },
+            _ => Err(format!("scales {:?} aren't spatial", scales)),
+        }
+    }
+
+    fn pad(&self, node: &pb::NodeProto) -> Result<Op, String> {
+        // Opset 11 moved pads and the value from attributes to inputs
+        let pads: Vec<i64> = match self.optional_constant(node, 1)? {
+            Some((_, pads)) => pads.iter().map(|&p| p as i64).collect(),
+            None => ints_attribute(node, "pads"),
+        };
+        let value = match self.optional_constant(node, 2)? {
+            Some((_, value)) => value.first().copied().unwrap_or(0.0),
+            None => float_attribute(node, "value").unwrap_or(0.0),
+        };
+        let mode = match string_attribute(node, "mode").as_deref() {
+            None | Some("constant") => PadMode::Constant(value),
+            Some("reflect") => PadMode::Reflect,
+            Some("edge") => PadMode::Edge,
+            Some(other) => return Err(format!("{} padding is not supported", other)),
+        };
+        Ok(Op::Pad {
+            input: self.value(node, 0)?,
+            pads: spatial_pads(&pads, 4)?,
+            mode,
+        })
+    }
+}
+
+/// `[top, left, bottom, right]` from ONNX pads over `rank` dimensions, of
+/// which only the last two may be padded.
+#[cfg(feature = "backend-tract")]
+fn spatial_pads(pads: &[i64], rank: usize) -> Result<Pads, String> {
+    if pads.is_empty() {
+        return Ok([0; 4]);
+    }
+    if pads.len() != 2 * rank || pads.iter().any(|&p| p < 0) {
+        return Err(format!("unsupported pads {:?}", pads));
+    }
+    let (begin, end) = pads.split_at(rank);
+    if begin[..rank - 2]
+        .iter()
+        .chain(&end[..rank - 2])
+        .any(|&p| p != 0)
+    {
+        return Err(format!(
+            "only height and width can be padded, got {:?}",
+            pads
+        ));
+    }
+    Ok([
+        begin[rank - 2] as usize,
+        begin[rank - 1] as usize,
+        end[rank - 2] as usize,
+        end[rank - 1] as usize,
+    ])
+}
+
+#[cfg(feature = "backend-tract")]
+fn attribute<'a>(node: &'a pb::NodeProto, name: &str) -> Option<&'a pb::AttributeProto> {
+    node.attribute.iter().find(|a| a.name == name)
+}
+
+#[cfg(feature = "backend-tract")]
+fn float_attribute(node: &pb::NodeProto, name: &str) -> Option<f32> {
+    attribute(node, name).map(|a| a.f)
+}
+
+#[cfg(feature = "backend-tract")]
+fn int_attribute(node: &pb::NodeProto, name: &str) -> Option<i64> {
+    attribute(node, name).map(|a| a.i)
+}
+
+#[cfg(feature = "backend-tract")]
+fn ints_attribute(node: &pb::NodeProto, name: &str) -> Vec<i64> {
+    attribute(node, name)
+        .map(|a| a.ints.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "backend-tract")]
+fn string_attribute(node: &pb::NodeProto, name: &str) -> Option<String> {
+    attribute(node, name).map(|a| String::from_utf8_lossy(&a.s).into_owned())
+}
@@ -0,0 +1,53 @@
+//! Side-by-side strength comparisons.
+
+/// The most strengths one grid may hold.
+pub const MAX_GRID_CELLS: usize = 9;
+
+/// Rejects empty, oversized or non-finite strength lists and clamps the rest
+/// to [0, 1].
+pub fn grid_strengths(strengths: &[f32]) -> Result<Vec<f32>, String> {
+    if strengths.is_empty() {
+        return Err("at least one strength is required".to_string());
+    }
+    if strengths.len() > MAX_GRID_CELLS {
+        return Err(format!(
+            "{} strengths requested; a grid holds at most {}",
+            strengths.len(),
+            MAX_GRID_CELLS
+        ));
+    }
+    if let Some(bad) = strengths.iter().find(|s| !s.is_finite()) {
+        return Err(format!("strength {} is not a number", bad));
+    }
+    Ok(strengths.iter().map(|s| s.clamp(0.0, 1.0)).collect())
+}
+
+/// `(columns, rows)` for `count` cells. `columns` of 0 picks the smallest
+/// square that fits; larger values are capped at `count`.
+pub fn grid_dimensions(count: usize, columns: u32) -> (u32, u32) {
+    let count = count.max(1) as u32;
+    let columns = match columns {
+        0 => (1..).find(|c| c * c >= count).unwrap_or(1),
+        columns => columns.min(count),
+    };
+    (columns, count.div_ceil(columns))
+}
+
+/// Lays out equally sized RGBA `cells` row by row, `columns` per row. Slots
+/// past the last cell stay transparent.
+pub fn compose_grid(cells: &[Vec<u8>], cell_width: u32, cell_height: u32, columns: u32) -> Vec<u8> {
+    let (columns, rows) = grid_dimensions(cells.len(), columns);
+    let (cell_width, cell_height) = (cell_width as usize, cell_height as usize);
+    let row_bytes = cell_width * 4;
+    let grid_row_bytes = row_bytes * columns as usize;
+    let mut out = vec![0u8; grid_row_bytes * cell_height * rows as usize];
+    for (index, cell) in cells.iter().enumerate() {
+        let (column, row) = (index % columns as usize, index / columns as usize);
+        let origin = row * cell_height * grid_row_bytes + column * row_bytes;
+        for (y, line) in cell.chunks_exact(row_bytes).take(cell_height).enumerate() {
+            let start = origin + y * grid_row_bytes;
+            out[start..start + row_bytes].copy_from_slice(line);
+        }
+    }
+    out
+}
@@ -0,0 +1,125 @@
+//! Transferring a low-resolution stylization onto the full-resolution source
+//! with a fast guided filter (He & Sun, 2015).
+//!
+//! Around every low-resolution pixel the stylized result is fitted as a
+//! linear function of the input, per channel. The fitted coefficients are
+//! smooth, so they survive bilinear upsampling, and applying them to the
+//! full-resolution input brings its edges and fine detail into the result.
+
+/// Window radius, in low-resolution pixels, of the local linear fits.
+pub const GUIDED_RADIUS: u32 = 2;
+
+/// Regularization of the fits: larger values flatten them towards the
+/// stylized window mean where the input barely varies.
+pub const GUIDED_EPSILON: f32 = 1e-4;
+
+/// Means over the `(2 * radius + 1)` square window around each pixel of a
+/// single-channel image, shrunk at the borders, via a summed-area table.
+fn box_mean(values: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let mut table = vec![0.0f64; (width + 1) * (height + 1)];
+    for y in 0..height {
+        let mut row = 0.0f64;
+        for x in 0..width {
+            row += values[y * width + x] as f64;
+            table[(y + 1) * (width + 1) + x + 1] = table[y * (width + 1) + x + 1] + row;
+        }
+    }
+    let mut means = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let (y0, y1) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+        for x in 0..width {
+            let (x0, x1) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+            let sum = table[y1 * (width + 1) + x1]
+                - table[y0 * (width + 1) + x1]
+                - table[y1 * (width + 1) + x0]
+                + table[y0 * (width + 1) + x0];
+            means.push((sum / ((x1 - x0) * (y1 - y0)) as f64) as f32);
+        }
+    }
+    means
+}
+
+/// For each of `high` positions along an axis, the two of `low` positions
+/// around it and the weight of the second, aligning pixel centers.
+fn bilinear_taps(low: usize, high: usize) -> Vec<(usize, usize, f32)> {
+    let scale = low as f32 / high as f32;
+    (0..high)
+        .map(|i| {
+            let position = ((i as f32 + 0.5) * scale - 0.5).clamp(0.0, (low - 1) as f32);
+            let first = position.floor() as usize;
+            (first, (first + 1).min(low - 1), position - first as f32)
+        })
+        .collect()
+}
+
+/// Upsamples `stylized`, made from `input` (both interleaved RGB at
+/// `low_size`), to `high_size` by following `high_input`, the same source at
+/// that size, which is turned into the result in place. All three must be in
+/// the same color space.
+pub fn guided_upsample(
+    input: &[f32],
+    stylized: &[f32],
+    (low_width, low_height): (u32, u32),
+    mut high_input: Vec<f32>,
+    (high_width, high_height): (u32, u32),
+) -> Vec<f32> {
+    let (w, h) = (low_width as usize, low_height as usize);
+    let radius = GUIDED_RADIUS as usize;
+    // Interleaved per-channel (a, b) of `stylized ≈ a * input + b`
+    let mut slopes = vec![0.0f32; w * h * 3];
+    let mut offsets = vec![0.0f32; w * h * 3];
+    for c in 0..3 {
+        let guide: Vec<f32> = input.iter().skip(c).step_by(3).copied().collect();
+        let target: Vec<f32> = stylized.iter().skip(c).step_by(3).copied().collect();
+        let squares: Vec<f32> = guide.iter().map(|g| g * g).collect();
+        let products: Vec<f32> = guide.iter().zip(&target).map(|(g, t)| g * t).collect();
+        let mean_guide = box_mean(&guide, w, h, radius);
+        let mean_target = box_mean(&target, w, h, radius);
+        let mean_squares = box_mean(&squares, w, h, radius);
+        let mean_products = box_mean(&products, w, h, radius);
+        let mut a = Vec::with_capacity(w * h);
+        let mut b = Vec::with_capacity(w * h);
+        for i in 0..w * h {
+            let variance = mean_squares[i] - mean_guide[i] * mean_guide[i];
+            let covariance = mean_products[i] - mean_guide[i] * mean_target[i];
+            let slope = covariance / (variance.max(0.0) + GUIDED_EPSILON);
+            a.push(slope);
+            b.push(mean_target[i] - slope * mean_guide[i]);
+        }
+        // Averaging the fits of every window covering a pixel smooths them
+        for (i, (a, b)) in box_mean(&a, w, h, radius)
+            .into_iter()
+            .zip(box_mean(&b, w, h, radius))
+            .enumerate()
+        {
+            slopes[i * 3 + c] = a;
+            offsets[i * 3 + c] = b;
+        }
+    }
+
+    // Sampled bilinearly per pixel rather than resized whole, so only the
+    // full-resolution tensor itself is ever allocated at that size
+    let x_taps = bilinear_taps(w, high_width as usize);
+    let y_taps = bilinear_taps(h, high_height as usize);
+    let sample = |map: &[f32],
+                  (y0, y1, fy): (usize, usize, f32),
+                  (x0, x1, fx): (usize, usize, f32),
+                  c: usize| {
+        let at = |y: usize, x: usize| map[(y * w + x) * 3 + c];
+        let top = at(y0, x0) + (at(y0, x1) - at(y0, x0)) * fx;
+        let bottom = at(y1, x0) + (at(y1, x1) - at(y1, x0)) * fx;
+        top + (bottom - top) * fy
+    };
+    for (row, &y_tap) in high_input
+        .chunks_exact_mut(high_width as usize * 3)
+        .zip(&y_taps)
+    {
+        for (pixel, &x_tap) in row.chunks_exact_mut(3).zip(&x_taps) {
+            for (c, value) in pixel.iter_mut().enumerate() {
+                *value =
+                    sample(&slopes, y_tap, x_tap, c) * *value + sample(&offsets, y_tap, x_tap, c);
+            }
+        }
+    }
+    high_input
+}
@@ -0,0 +1,348 @@
+//! ONNX inference through tract, when built with the `backend-tract` feature.
+//!
+//! Without it `TractPlan` can't be constructed and `load_plan` always fails,
+//! so callers fall through to the other backends without any cfg of their own.
+
+#[cfg(feature = "backend-tract")]
+use tract_onnx::prelude::*;
+#[cfg(feature = "backend-tract")]
+use tract_onnx::tract_core::floats::FloatPrecisionTranslator;
+#[cfg(feature = "backend-tract")]
+use tract_onnx::tract_core::internal::{ensure, DimLike};
+
+#[cfg(feature = "backend-tract")]
+use super::normalize::{model_input, model_output};
+#[cfg(feature = "backend-tract")]
+use super::quantize::{Quantization, QuantizedType};
+use super::shapes::DeclaredShape;
+use super::ModelMetadata;
+
+#[cfg(feature = "backend-tract")]
+pub type TractPlan = Arc<TypedSimplePlan>;
+
+#[cfg(not(feature = "backend-tract"))]
+pub enum TractPlan {}
+
+/// Parses ONNX bytes and optimizes them into a runnable plan.
+#[cfg(feature = "backend-tract")]
+pub fn load_plan(model_bytes: &[u8]) -> TractResult<TractPlan> {
+    tract_onnx::onnx()
+        .model_for_read(&mut std::io::Cursor::new(model_bytes))?
+        .into_optimized()?
+        .into_runnable()
+}
+
+/// `load_plan` with every f32 op converted to f16, which halves the
+/// weights' memory and speeds up memory-bound convolutions. The plan takes
+/// and returns f16 tensors; `run_plan` and `run_features` cast for it. Fails
+/// when an op can't be converted, leaving the caller to fall back to f32.
+#[cfg(feature = "backend-tract")]
+pub fn load_half_plan(model_bytes: &[u8]) -> TractResult<TractPlan> {
+    decluttered(model_bytes, true)?
+        .into_optimized()?
+        .into_runnable()
+}
+
+/// `load_plan`, or `load_half_plan` when `half`, without the optimization
+/// pass that takes most of the loading time: runnable at once but slower,
+/// until [`Unoptimized::optimize`] gives the plan it would have been.
+#[cfg(feature = "backend-tract")]
+pub fn load_unoptimized_plan(model_bytes: &[u8], half: bool) -> TractResult<TractPlan> {
+    decluttered(model_bytes, half)?.into_runnable()
+}
+
+#[cfg(feature = "backend-tract")]
+fn decluttered(model_bytes: &[u8], half: bool) -> TractResult<TypedModel> {
+    let mut model = tract_onnx::onnx()
+        .model_for_read(&mut std::io::Cursor::new(model_bytes))?
+        .into_typed()?
+        .into_decluttered()?;
+    if half {
+        model.transform(&FloatPrecisionTranslator::new(
+            DatumType::F32,
+            DatumType::F16,
+        ))?;
+    }
+    Ok(model)
+}
+
+/// A copy of an unoptimized plan's graph, to be optimized later without
+/// holding on to the plan.
+#[cfg(feature = "backend-tract")]
+pub struct Unoptimized(TypedModel);
+
+#[cfg(feature = "backend-tract")]
+impl Unoptimized {
+    pub fn of(plan: &TractPlan) -> Unoptimized {
+        Unoptimized(plan.model().clone())
+    }
+
+    pub fn optimize(self) -> Result<TractPlan, String> {
+        self.0
+            .into_optimized()
+            .and_then(|model| model.into_runnable())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Casts a float input to f16 for plans from `load_half_plan`.
+#[cfg(feature = "backend-tract")]
+fn plan_precision(plan: &TractPlan, input: Tensor) -> TractResult<Tensor> {
+    let expected = plan.model().input_fact(0)?.datum_type;
+    if expected == DatumType::F16 && input.datum_type() == DatumType::F32 {
+        input.cast_to::<f16>().map(|cast| cast.into_owned())
+    } else {
+        Ok(input)
+    }
+}
+
+/// The declared shapes of the plan's first input and output.
+#[cfg(feature = "backend-tract")]
+pub fn plan_shapes(plan: &TractPlan) -> TractResult<(DeclaredShape, DeclaredShape)> {
+    let declared = |fact: &TypedFact| -> DeclaredShape {
+        fact.shape.iter().map(|dim| dim.to_usize().ok()).collect()
+    };
+    let model = plan.model();
+    Ok((
+        declared(model.input_fact(0)?),
+        declared(model.output_fact(0)?),
+    ))
+}
+
+/// Rough bytes a plan holds once optimized: its constants, weights
+/// included, plus every intermediate tensor whose shape is known. An upper
+/// bound, since tract frees activations as soon as they are consumed.
+#[cfg(feature = "backend-tract")]
+pub fn plan_size_estimate(plan: &TractPlan) -> usize {
+    plan.model()
+        .nodes()
+        .iter()
+        .flat_map(|node| &node.outputs)
+        .map(|output| {
+            let fact = &output.fact;
+            match &fact.konst {
+                Some(tensor) => tensor.len() * tensor.datum_type().size_of(),
+                None => fact.shape.as_concrete().map_or(0, |shape| {
+                    shape.iter().product::<usize>() * fact.datum_type.size_of()
+                }),
+            }
+        })
+        .sum()
+}
+
+/// Checks that the element types of the plan's image input and output are
+/// what `metadata.quantization` declares: 8-bit when it is set, else f32. A
+/// quantized model may still return floats.
+#[cfg(feature = "backend-tract")]
+pub fn check_quantization(plan: &TractPlan, metadata: &ModelMetadata) -> Result<(), String> {
+    let model = plan.model();
+    // Half-precision plans still take and return floats
+    let datum_type = |fact: TractResult<&TypedFact>| -> Result<DatumType, String> {
+        fact.map(|fact| match fact.datum_type.unquantized() {
+            DatumType::F16 => DatumType::F32,
+            datum_type => datum_type,
+        })
+        .map_err(|e| e.to_string())
+    };
+    let (input, output) = (
+        datum_type(model.input_fact(0))?,
+        datum_type(model.output_fact(0))?,
+    );
+    let declared = match metadata.quantization.map(|q| q.dtype) {
+        None => DatumType::F32,
+        Some(QuantizedType::Uint8) => DatumType::U8,
+        Some(QuantizedType::Int8) => DatumType::I8,
+    };
+    if input != declared {
+        return Err(match metadata.quantization {
+            None => format!(
+                "'{}' takes a {:?} input; describe its 8-bit encoding with quantization",
+                metadata.name, input
+            ),
+            Some(_) => format!(
+                "'{}' takes a {:?} input, but its quantization declares {:?}",
+                metadata.name, input, declared
+            ),
+        });
+    }
+    if output != declared && output != DatumType::F32 {
+        return Err(format!(
+            "'{}' returns a {:?} output, expected {:?} or F32",
+            metadata.name, output, declared
+        ));
+    }
+    Ok(())
+}
+
+/// Rounds `values` to `quantization`'s 8-bit encoding.
+#[cfg(feature = "backend-tract")]
+fn quantized_tensor(
+    shape: &[usize],
+    values: &[f32],
+    quantization: Quantization,
+) -> TractResult<Tensor> {
+    let quantize = |&value: &f32| quantization.quantize(value);
+    match quantization.dtype {
+        QuantizedType::Uint8 => Tensor::from_shape(
+            shape,
+            &values.iter().map(|v| quantize(v) as u8).collect::<Vec<_>>(),
+        ),
+        QuantizedType::Int8 => Tensor::from_shape(
+            shape,
+            &values.iter().map(|v| quantize(v) as i8).collect::<Vec<_>>(),
+        ),
+    }
+}
+
+/// Runs `plan` on a single interleaved image tensor sized for `metadata`.
+///
+/// The model sees `[1, 3, H, W]` data (`[1, H, W, 3]` for NHWC models),
+/// normalized as `metadata` asks and quantized if it declares so, and must
+/// produce the same shape; the result is dequantized, mapped back from its
+/// `output_range` to [0, 1] and converted back to the interleaved layout.
+#[cfg(feature = "backend-tract")]
+pub fn run_plan(
+    plan: &TractPlan,
+    input_tensor: &[f32],
+    metadata: &ModelMetadata,
+) -> TractResult<Vec<f32>> {
+    let layout = metadata.layout;
+    let input_shape = layout.image_shape(
+        3,
+        metadata.input_height as usize,
+        metadata.input_width as usize,
+    );
+    let normalized = model_input(input_tensor, metadata).into_owned();
+    let planar = layout.from_interleaved(normalized);
+    let input = match metadata.quantization {
+        Some(quantization) => quantized_tensor(&input_shape, &planar, quantization)?,
+        None => plan_precision(plan, Tensor::from_shape(&input_shape, &planar)?)?,
+    };
+
+    let outputs = plan.run(tvec!(input.into()))?;
+    ensure!(
+        outputs[0].shape() == input_shape,
+        "model output shape {:?} doesn't match input shape {:?}",
+        outputs[0].shape(),
+        input_shape
+    );
+    let dequantized: Vec<f32>;
+    let float_output;
+    let output = match (outputs[0].datum_type().unquantized(), metadata.quantization) {
+        (DatumType::U8, Some(quantization)) => {
+            dequantized = outputs[0]
+                .try_as_plain_ram()?
+                .as_slice::<u8>()?
+                .iter()
+                .map(|&value| quantization.dequantize(value as i32))
+                .collect();
+            &dequantized
+        }
+        (DatumType::I8, Some(quantization)) => {
+            dequantized = outputs[0]
+                .try_as_plain_ram()?
+                .as_slice::<i8>()?
+                .iter()
+                .map(|&value| quantization.dequantize(value as i32))
+                .collect();
+            &dequantized
+        }
+        _ => {
+            float_output = outputs[0].cast_to::<f32>()?;
+            float_output.try_as_plain_ram()?.as_slice::<f32>()?
+        }
+    };
+
+    let mut output = layout.interleaved(output);
+    model_output(&mut output, metadata);
+    Ok(output)
+}
+
+/// Runs `plan` on a rank-4 image tensor of `shape`, planar `[1, C, H, W]`
+/// unless the model is NHWC, and returns its first output with that
+/// output's shape, whatever it is. Used for the encoder and decoder halves
+/// of AdaIN models and for segmentation.
+#[cfg(feature = "backend-tract")]
+pub fn run_features(
+    plan: &TractPlan,
+    planar: &[f32],
+    shape: [usize; 4],
+) -> TractResult<(Vec<f32>, Vec<usize>)> {
+    let input = plan_precision(plan, Tensor::from_shape(&shape, planar)?)?;
+    let outputs = plan.run(tvec!(input.into()))?;
+    ensure!(
+        outputs[0].rank() == 4,
+        "expected a [1, C, H, W] output, got {:?}",
+        outputs[0].shape()
+    );
+    Ok((
+        outputs[0]
+            .cast_to::<f32>()?
+            .try_as_plain_ram()?
+            .as_slice::<f32>()?
+            .to_vec(),
+        outputs[0].shape().to_vec(),
+    ))
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn load_plan(_model_bytes: &[u8]) -> Result<TractPlan, String> {
+    Err("built without the backend-tract feature".to_string())
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn load_half_plan(_model_bytes: &[u8]) -> Result<TractPlan, String> {
+    Err("built without the backend-tract feature".to_string())
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn load_unoptimized_plan(_model_bytes: &[u8], _half: bool) -> Result<TractPlan, String> {
+    Err("built without the backend-tract feature".to_string())
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub struct Unoptimized;
+
+#[cfg(not(feature = "backend-tract"))]
+impl Unoptimized {
+    pub fn of(plan: &TractPlan) -> Unoptimized {
+        match *plan {}
+    }
+
+    pub fn optimize(self) -> Result<TractPlan, String> {
+        Err("built without the backend-tract feature".to_string())
+    }
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn plan_shapes(plan: &TractPlan) -> Result<(DeclaredShape, DeclaredShape), String> {
+    match *plan {}
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn plan_size_estimate(plan: &TractPlan) -> usize {
+    match *plan {}
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn check_quantization(plan: &TractPlan, _metadata: &ModelMetadata) -> Result<(), String> {
+    match *plan {}
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn run_plan(
+    plan: &TractPlan,
+    _input_tensor: &[f32],
+    _metadata: &ModelMetadata,
+) -> Result<Vec<f32>, String> {
+    match *plan {}
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn run_features(
+    plan: &TractPlan,
+    _planar: &[f32],
+    _shape: [usize; 4],
+) -> Result<(Vec<f32>, Vec<usize>), String> {
+    match *plan {}
+}
@@ -0,0 +1,221 @@
+//! Summarizing an ONNX model without making it runnable, for debugging models
+//! tract can't load, and summarizing the plans of models it did load.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+#[cfg(feature = "backend-tract")]
+use tract_onnx::pb;
+#[cfg(feature = "backend-tract")]
+use tract_onnx::prelude::*;
+
+use super::inference::TractPlan;
+
+/// A graph input or output as declared in the ONNX file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TensorSummary {
+    pub name: String,
+    /// Concrete sizes, or the symbol name for symbolic dimensions.
+    pub shape: Vec<String>,
+    /// ONNX element type name, e.g. `"FLOAT"`.
+    pub dtype: String,
+}
+
+/// Why the graph could not be optimized.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OptimizeFailure {
+    pub message: String,
+    /// The node tract reported the failure against, when it named one.
+    pub node: Option<String>,
+}
+
+/// What [`inspect_model`] found.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ModelInspection {
+    /// Version of the default (`ai.onnx`) operator set.
+    pub opset: Option<i64>,
+    pub inputs: Vec<TensorSummary>,
+    pub outputs: Vec<TensorSummary>,
+    /// Node count per operator type.
+    pub ops: BTreeMap<String, usize>,
+    /// Total number of values across all initializers.
+    pub parameter_count: u64,
+    /// `None` when the graph optimizes cleanly.
+    pub optimize_error: Option<OptimizeFailure>,
+}
+
+/// The inputs and outputs of a loaded plan, as tract typed them.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlanFacts {
+    pub inputs: Vec<TensorSummary>,
+    pub outputs: Vec<TensorSummary>,
+}
+
+/// The name in the first `#12 "name"` node reference of a tract error.
+pub fn failing_node(message: &str) -> Option<String> {
+    message.match_indices('#').find_map(|(at, _)| {
+        let rest = &message[at + 1..];
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let quoted = rest[digits..].strip_prefix(" \"").filter(|_| digits > 0)?;
+        quoted.find('"').map(|end| quoted[..end].to_string())
+    })
+}
+
+/// Parses ONNX bytes and summarizes the graph. Only parse errors fail;
+/// optimization errors are reported in [`ModelInspection::optimize_error`].
+#[cfg(feature = "backend-tract")]
+pub fn inspect_model(model_bytes: &[u8]) -> Result<ModelInspection, String> {
+    let onnx = tract_onnx::onnx();
+    let proto = onnx
+        .proto_model_for_read(&mut std::io::Cursor::new(model_bytes))
+        .map_err(|e| format!("{:#}", e))?;
+    let graph = proto.graph.clone().unwrap_or_default();
+
+    let initializers: Vec<&str> = graph.initializer.iter().map(|t| t.name.as_str()).collect();
+    let mut ops = BTreeMap::new();
+    for node in &graph.node {
+        *ops.entry(node.op_type.clone()).or_insert(0) += 1;
+    }
+    let parameter_count = graph
+        .initializer
+        .iter()
+        .map(|t| t.dims.iter().map(|&d| d.max(0) as u64).product::<u64>())
+        .sum();
+
+    let optimize_error = onnx
+        .model_for_proto_model(&proto)
+        .and_then(|model| model.into_optimized())
+        .err()
+        .map(|e| {
+            let message = format!("{:#}", e);
+            OptimizeFailure {
+                node: failing_node(&message),
+                message,
+            }
+        });
+
+    Ok(ModelInspection {
+        opset: proto
+            .opset_import
+            .iter()
+            .find(|set| set.domain.is_empty() || set.domain == "ai.onnx")
+            .map(|set| set.version),
+        inputs: graph
+            .input
+            .iter()
+            .filter(|info| !initializers.contains(&info.name.as_str()))
+            .map(summarize)
+            .collect(),
+        outputs: graph.output.iter().map(summarize).collect(),
+        ops,
+        parameter_count,
+        optimize_error,
+    })
+}
+
+#[cfg(feature = "backend-tract")]
+fn summarize(info: &pb::ValueInfoProto) -> TensorSummary {
+    use pb::tensor_shape_proto::dimension::Value;
+
+    let tensor = info.r#type.as_ref().and_then(|t| match &t.value {
+        Some(pb::type_proto::Value::TensorType(tensor)) => Some(tensor),
+        _ => None,
+    });
+    let shape = tensor
+        .and_then(|tensor| tensor.shape.as_ref())
+        .map(|shape| {
+            shape
+                .dim
+                .iter()
+                .map(|dim| match &dim.value {
+                    Some(Value::DimValue(size)) => size.to_string(),
+                    Some(Value::DimParam(symbol)) if !symbol.is_empty() => symbol.clone(),
+                    _ => "?".to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let dtype = tensor
+        .and_then(|tensor| pb::tensor_proto::DataType::try_from(tensor.elem_type).ok())
+        .map_or("UNDEFINED", |dtype| dtype.as_str_name())
+        .to_string();
+    TensorSummary {
+        name: info.name.clone(),
+        shape,
+        dtype,
+    }
+}
+
+/// What tract settled on for the plan's inputs and outputs, which is what
+/// inference actually sees; the element types of a half precision plan, for
+/// one, are `"FLOAT16"` whatever the file declares.
+#[cfg(feature = "backend-tract")]
+pub fn plan_facts(plan: &TractPlan) -> PlanFacts {
+    let model = plan.model();
+    let summarize = |outlet: &OutletId| {
+        let name = model
+            .outlet_label(*outlet)
+            .unwrap_or(&model.node(outlet.node).name)
+            .to_string();
+        match model.outlet_fact(*outlet) {
+            Ok(fact) => TensorSummary {
+                name,
+                shape: fact.shape.iter().map(|dim| dim.to_string()).collect(),
+                dtype: onnx_type_name(fact.datum_type),
+            },
+            Err(_) => TensorSummary {
+                name,
+                shape: Vec::new(),
+                dtype: "UNDEFINED".to_string(),
+            },
+        }
+    };
+    PlanFacts {
+        inputs: model
+            .input_outlets()
+            .unwrap_or_default()
+            .iter()
+            .map(summarize)
+            .collect(),
+        outputs: model
+            .output_outlets()
+            .unwrap_or_default()
+            .iter()
+            .map(summarize)
+            .collect(),
+    }
+}
+
+/// The ONNX name of a tract element type, so plan facts read like
+/// [`inspect_model`]'s.
+#[cfg(feature = "backend-tract")]
+fn onnx_type_name(datum_type: DatumType) -> String {
+    let name = match datum_type.unquantized() {
+        DatumType::Bool => "BOOL",
+        DatumType::U8 => "UINT8",
+        DatumType::U16 => "UINT16",
+        DatumType::U32 => "UINT32",
+        DatumType::U64 => "UINT64",
+        DatumType::I8 => "INT8",
+        DatumType::I16 => "INT16",
+        DatumType::I32 => "INT32",
+        DatumType::I64 => "INT64",
+        DatumType::F16 => "FLOAT16",
+        DatumType::F32 => "FLOAT",
+        DatumType::F64 => "DOUBLE",
+        DatumType::String => "STRING",
+        other => return format!("{:?}", other),
+    };
+    name.to_string()
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn plan_facts(plan: &TractPlan) -> PlanFacts {
+    match *plan {}
+}
+
+#[cfg(not(feature = "backend-tract"))]
+pub fn inspect_model(_model_bytes: &[u8]) -> Result<ModelInspection, String> {
+    Err("built without the backend-tract feature".to_string())
+}
@@ -0,0 +1,29 @@
+//! Checking downloaded model files against the SHA-256 their metadata
+//! declares. The digest itself comes from the browser's SubtleCrypto.
+
+/// Checks `sha256` is a hex SHA-256 digest: 64 hex digits in either case.
+pub fn validate_sha256(sha256: &str) -> Result<(), String> {
+    if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("sha256 must be 64 hex digits, got '{}'", sha256));
+    }
+    Ok(())
+}
+
+/// Lowercase hex of `bytes`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Compares a computed `digest` with the declared hex `expected`.
+pub fn verify_digest(digest: &[u8], expected: &str) -> Result<(), String> {
+    let actual = to_hex(digest);
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(format!(
+            "expected sha256 {}, got {}",
+            expected.to_ascii_lowercase(),
+            actual
+        ))
+    }
+}
@@ -0,0 +1,60 @@
+//! Averaging inference over slightly shifted copies of the input, which
+//! cancels the checkerboard artifacts fast style transfer models produce.
+
+use super::rng::XorShift64;
+
+/// The most inference passes one call may run.
+pub const MAX_PASSES: u32 = 4;
+
+/// Largest shift, in pixels, along either axis.
+pub const MAX_JITTER: i32 = 2;
+
+/// `(dx, dy)` for each of `passes` passes. The first pass is never shifted,
+/// so a single pass is exactly the unjittered pipeline; the others are
+/// distinct non-zero shifts drawn from `seed`.
+pub fn jitter_offsets(passes: u32, seed: u64) -> Vec<(i32, i32)> {
+    let mut rng = XorShift64::new(seed);
+    let span = (2 * MAX_JITTER + 1) as u64;
+    let mut offsets = vec![(0, 0)];
+    while offsets.len() < passes.clamp(1, MAX_PASSES) as usize {
+        let offset = (
+            (rng.next_u64() % span) as i32 - MAX_JITTER,
+            (rng.next_u64() % span) as i32 - MAX_JITTER,
+        );
+        if !offsets.contains(&offset) {
+            offsets.push(offset);
+        }
+    }
+    offsets
+}
+
+/// Moves an interleaved RGB tensor by `(dx, dy)` pixels, replicating edge
+/// pixels into the uncovered border so it doesn't darken.
+pub fn shift_tensor(tensor: &[f32], width: u32, height: u32, (dx, dy): (i32, i32)) -> Vec<f32> {
+    let (width, height) = (width as i32, height as i32);
+    let mut out = Vec::with_capacity(tensor.len());
+    for y in 0..height {
+        let source_y = (y - dy).clamp(0, height - 1);
+        for x in 0..width {
+            let source_x = (x - dx).clamp(0, width - 1);
+            let index = ((source_y * width + source_x) * 3) as usize;
+            out.extend_from_slice(&tensor[index..index + 3]);
+        }
+    }
+    out
+}
+
+/// Adds `tensor` to the running `sum`.
+pub fn accumulate(sum: &mut [f32], tensor: &[f32]) {
+    for (total, value) in sum.iter_mut().zip(tensor) {
+        *total += value;
+    }
+}
+
+/// Turns a sum of `passes` tensors into their mean.
+pub fn finish_average(sum: &mut [f32], passes: usize) {
+    let scale = 1.0 / passes as f32;
+    for value in sum {
+        *value *= scale;
+    }
+}
@@ -0,0 +1,69 @@
+//! Fitting a source into a fixed model input without stretching it.
+
+/// Where a source sits inside a model input it was fitted into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Letterbox {
+    /// Model input size.
+    pub model_width: u32,
+    pub model_height: u32,
+    /// The source's rectangle within it; the rest is padding.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Letterbox {
+    /// Fits `source_width` x `source_height` inside the model input, keeping
+    /// its aspect ratio, centered.
+    pub fn fit(
+        (source_width, source_height): (u32, u32),
+        (model_width, model_height): (u32, u32),
+    ) -> Letterbox {
+        let scale = (model_width as f64 / source_width as f64)
+            .min(model_height as f64 / source_height as f64);
+        let width = ((source_width as f64 * scale).round() as u32).clamp(1, model_width);
+        let height = ((source_height as f64 * scale).round() as u32).clamp(1, model_height);
+        Letterbox {
+            model_width,
+            model_height,
+            x: (model_width - width) / 2,
+            y: (model_height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the source fills the whole input.
+    pub fn is_full(&self) -> bool {
+        (self.width, self.height) == (self.model_width, self.model_height)
+    }
+
+    /// Places an interleaved RGB tensor of the source's size in the model
+    /// input, repeating its edge pixels into the padding so the model sees
+    /// no artificial border.
+    pub fn pad(&self, tensor: &[f32]) -> Vec<f32> {
+        let mut padded = Vec::with_capacity((self.model_width * self.model_height * 3) as usize);
+        for y in 0..self.model_height {
+            let sy = y.saturating_sub(self.y).min(self.height - 1);
+            for x in 0..self.model_width {
+                let sx = x.saturating_sub(self.x).min(self.width - 1);
+                let i = ((sy * self.width + sx) * 3) as usize;
+                padded.extend_from_slice(&tensor[i..i + 3]);
+            }
+        }
+        padded
+    }
+
+    /// Cuts the source's rectangle back out of a model-sized RGB tensor.
+    pub fn crop(&self, tensor: &[f32]) -> Vec<f32> {
+        let row = (self.width * 3) as usize;
+        (self.y..self.y + self.height)
+            .flat_map(|y| {
+                let start = ((y * self.model_width + self.x) * 3) as usize;
+                &tensor[start..start + row]
+            })
+            .copied()
+            .collect()
+    }
+}
@@ -0,0 +1,183 @@
+//! 3D color lookup tables: parsing Adobe/Resolve `.cube` files, built-in
+//! grading presets, and applying either to sRGB tensors.
+
+/// Largest `LUT_3D_SIZE` accepted; 65 is the largest grading tools export.
+pub const MAX_LUT_SIZE: usize = 65;
+
+/// Grid size of the built-in presets.
+const PRESET_SIZE: usize = 17;
+
+/// Names accepted by [`Lut3d::preset`].
+pub const LUT_PRESETS: [&str; 5] = ["warm", "cool", "teal_orange", "bleach_bypass", "faded_film"];
+
+/// Rec. 709 luma of an sRGB color.
+fn luma([r, g, b]: [f32; 3]) -> f32 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// A `size`³ grid of output colors, indexed with red varying fastest as in
+/// `.cube` files, over inputs from `domain_min` to `domain_max`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut3d {
+    pub size: usize,
+    pub table: Vec<[f32; 3]>,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+}
+
+impl Lut3d {
+    /// Samples `grade` on a `size`³ grid over [0, 1].
+    pub fn from_fn(size: usize, grade: impl Fn([f32; 3]) -> [f32; 3]) -> Lut3d {
+        let step = 1.0 / (size - 1) as f32;
+        let table = (0..size * size * size)
+            .map(|i| {
+                let (r, g, b) = (i % size, i / size % size, i / (size * size));
+                grade([r as f32 * step, g as f32 * step, b as f32 * step])
+            })
+            .collect();
+        Lut3d {
+            size,
+            table,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+        }
+    }
+
+    /// Parses the text of a `.cube` file. `TITLE` and comments are ignored;
+    /// 1D LUTs aren't supported.
+    pub fn parse_cube(text: &str) -> Result<Lut3d, String> {
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+        let triple = |fields: &[&str], line: usize| -> Result<[f32; 3], String> {
+            match fields {
+                [r, g, b] => {
+                    let parse = |field: &str| {
+                        field
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|v| v.is_finite())
+                            .ok_or_else(|| format!("line {}: '{}' is not a number", line, field))
+                    };
+                    Ok([parse(r)?, parse(g)?, parse(b)?])
+                }
+                _ => Err(format!("line {}: expected 3 values", line)),
+            }
+        };
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.first().copied() {
+                None | Some("TITLE") => {}
+                Some("LUT_1D_SIZE") => return Err("1D LUTs aren't supported".to_string()),
+                Some("LUT_3D_SIZE") => {
+                    let parsed = fields
+                        .get(1)
+                        .and_then(|field| field.parse::<usize>().ok())
+                        .filter(|size| (2..=MAX_LUT_SIZE).contains(size))
+                        .ok_or_else(|| {
+                            format!(
+                                "line {}: LUT_3D_SIZE must be from 2 to {}",
+                                number, MAX_LUT_SIZE
+                            )
+                        })?;
+                    size = Some(parsed);
+                }
+                Some("DOMAIN_MIN") => domain_min = triple(&fields[1..], number)?,
+                Some("DOMAIN_MAX") => domain_max = triple(&fields[1..], number)?,
+                Some(_) => table.push(triple(&fields, number)?),
+            }
+        }
+        let size = size.ok_or("missing LUT_3D_SIZE")?;
+        if table.len() != size * size * size {
+            return Err(format!(
+                "expected {} entries for LUT_3D_SIZE {}, got {}",
+                size * size * size,
+                size,
+                table.len()
+            ));
+        }
+        if (0..3).any(|c| domain_max[c] <= domain_min[c]) {
+            return Err(format!(
+                "DOMAIN_MAX {:?} must exceed DOMAIN_MIN {:?}",
+                domain_max, domain_min
+            ));
+        }
+        Ok(Lut3d {
+            size,
+            table,
+            domain_min,
+            domain_max,
+        })
+    }
+
+    /// A built-in grade by name, one of [`LUT_PRESETS`].
+    pub fn preset(name: &str) -> Option<Lut3d> {
+        let grade: fn([f32; 3]) -> [f32; 3] = match name {
+            "warm" => |[r, g, b]| [r * 1.06 + 0.02, g * 1.01, b * 0.9],
+            "cool" => |[r, g, b]| [r * 0.92, g, b * 1.06 + 0.02],
+            // Shadows toward teal, highlights toward orange
+            "teal_orange" => |rgb| {
+                let shift = (luma(rgb) - 0.5) * 0.16;
+                [rgb[0] + shift, rgb[1] + shift * 0.2, rgb[2] - shift]
+            },
+            // Half desaturated, with the contrast of a skipped bleach bath
+            "bleach_bypass" => |rgb| {
+                let y = luma(rgb);
+                rgb.map(|v| {
+                    let mixed = v * 0.5 + y * 0.5;
+                    (mixed - 0.5) * 1.25 + 0.5
+                })
+            },
+            // Lifted blacks and lowered whites
+            "faded_film" => |rgb| rgb.map(|v| 0.06 + v * 0.86),
+            _ => return None,
+        };
+        Some(Lut3d::from_fn(PRESET_SIZE, |rgb| {
+            grade(rgb).map(|v| v.clamp(0.0, 1.0))
+        }))
+    }
+
+    fn at(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.table[(b * self.size + g) * self.size + r]
+    }
+
+    /// The graded color of one sRGB pixel, interpolating trilinearly.
+    pub fn lookup(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        let mut base = [0usize; 3];
+        let mut next = [0usize; 3];
+        let mut fraction = [0.0f32; 3];
+        for c in 0..3 {
+            let span = self.domain_max[c] - self.domain_min[c];
+            let position = ((rgb[c] - self.domain_min[c]) / span * last).clamp(0.0, last);
+            base[c] = position.floor() as usize;
+            next[c] = (base[c] + 1).min(self.size - 1);
+            fraction[c] = position - base[c] as f32;
+        }
+        let lerp = |a: [f32; 3], b: [f32; 3], t: f32| [0, 1, 2].map(|c| a[c] + (b[c] - a[c]) * t);
+        let along_r =
+            |g: usize, b: usize| lerp(self.at(base[0], g, b), self.at(next[0], g, b), fraction[0]);
+        let near = lerp(
+            along_r(base[1], base[2]),
+            along_r(next[1], base[2]),
+            fraction[1],
+        );
+        let far = lerp(
+            along_r(base[1], next[2]),
+            along_r(next[1], next[2]),
+            fraction[1],
+        );
+        lerp(near, far, fraction[2])
+    }
+
+    /// Grades an interleaved sRGB tensor in place.
+    pub fn apply(&self, tensor: &mut [f32]) {
+        for pixel in tensor.chunks_exact_mut(3) {
+            let graded = self.lookup([pixel[0], pixel[1], pixel[2]]);
+            pixel.copy_from_slice(&graded);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Model registry entries.
+
+use serde::{Deserialize, Serialize};
+
+use super::color::ColorSpace;
+use super::compression::ModelCompression;
+use super::normalize::ValueRange;
+use super::quantize::Quantization;
+use super::simulated::SimulatedStyleConfig;
+use super::suggest::StyleAffinity;
+use super::tensor::TensorLayout;
+
+/// What backs a registry entry.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    /// An ONNX file fetched from `model_url`, with the simulated filter as fallback.
+    #[default]
+    Onnx,
+    /// A filter implemented by a JavaScript callback; nothing is downloaded.
+    JsFilter,
+    /// Only ever runs its `simulated_style`; nothing is downloaded.
+    Simulated,
+    /// Arbitrary style transfer with a style image given per call: a VGG
+    /// encoder at `model_url` and a decoder at `decoder_url`, with AdaIN
+    /// between them. Falls back to matching the style's pixel statistics.
+    Adain,
+    /// Finds people for `process_with_segmentation` rather than styling:
+    /// RGB in, person probability out. Falls back to the saliency heuristic.
+    Segmentation,
+    /// Enlarges results for the `upscale` option rather than styling: RGB
+    /// in, RGB `upscale` times larger out. Falls back to a bicubic resize.
+    SuperResolution,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ModelMetadata {
+    pub name: String,
+    pub size_mb: f32,
+    pub input_width: u32,
+    pub input_height: u32,
+    pub input_channels: u32,
+    pub model_url: String,
+    /// The decoder of an `adain` entry; unused by other kinds.
+    pub decoder_url: String,
+    /// Identifies the file at `model_url` in the persistent model cache and
+    /// the result cache; change it whenever that file changes. A registry
+    /// update that changes it unloads the model, so its next use downloads
+    /// the new file.
+    pub version: String,
+    /// How the file at `model_url` is compressed; unset sniffs the payload.
+    pub compression: Option<ModelCompression>,
+    /// Hex SHA-256 of the file at `model_url` as served, before any
+    /// decompression. Downloads and cached copies that don't match are
+    /// rejected with `IntegrityError`; unset skips the check.
+    pub sha256: Option<String>,
+    pub description: String,
+    pub kind: ModelKind,
+    /// Filter used when no ONNX plan can run. Entries without one pass the
+    /// input through unchanged in that case.
+    pub simulated_style: Option<SimulatedStyleConfig>,
+    /// Weights used by `suggest_styles`; `None` scores neutrally.
+    pub style_affinity: Option<StyleAffinity>,
+    /// Space the model was trained on, which its input is converted to and
+    /// its output converted back from.
+    pub model_color_space: ColorSpace,
+    /// Range the model's input spans, e.g. `"0_255"` for models trained on
+    /// raw pixel values.
+    pub input_range: ValueRange,
+    /// Per-channel RGB mean subtracted from the input after scaling it to
+    /// `input_range`, in that range; `IMAGENET_MEAN` for torchvision models.
+    pub mean: [f32; 3],
+    /// Per-channel RGB standard deviation the input is divided by after
+    /// subtracting `mean`.
+    pub std: [f32; 3],
+    /// Range the model's output spans, e.g. `"-1_1"` for tanh outputs; it is
+    /// mapped back to [0, 1] before conversion to pixels.
+    pub output_range: ValueRange,
+    /// Memory order of the model's image input and output: `"nchw"` as most
+    /// ONNX exports, or `"nhwc"` for models converted from TensorFlow.
+    pub layout: TensorLayout,
+    /// The 8-bit encoding of the image input and output of a style whose
+    /// graph takes and returns quantized tensors. Int8 models that take and
+    /// return floats around quantized internals, as QDQ exports do, need
+    /// none.
+    pub quantization: Option<Quantization>,
+    /// How many times larger a `super_resolution` entry's output is than its
+    /// input, 2 or 4; unused by other kinds.
+    pub upscale: u32,
+}
+
+impl Default for ModelMetadata {
+    fn default() -> Self {
+        ModelMetadata {
+            name: String::new(),
+            size_mb: 0.0,
+            input_width: 256,
+            input_height: 256,
+            input_channels: 3,
+            model_url: String::new(),
+            decoder_url: String::new(),
+            version: String::new(),
+            compression: None,
+            sha256: None,
+            description: String::new(),
+            kind: ModelKind::Onnx,
+            simulated_style: None,
+            style_affinity: None,
+            model_color_space: ColorSpace::Srgb,
+            input_range: ValueRange::Unit,
+            mean: [0.0; 3],
+            std: [1.0; 3],
+            output_range: ValueRange::Unit,
+            layout: TensorLayout::Nchw,
+            quantization: None,
+            upscale: 0,
+        }
+    }
+}
+
+fn builtin(name: &str, size_mb: f32, description: &str) -> ModelMetadata {
+    ModelMetadata {
+        name: name.to_string(),
+        size_mb,
+        model_url: format!("/models/{}.onnx", name),
+        description: description.to_string(),
+        simulated_style: SimulatedStyleConfig::preset(name),
+        style_affinity: StyleAffinity::preset(name),
+        ..ModelMetadata::default()
+    }
+}
+
+/// The segmentation model `process_with_segmentation` registers when the
+/// registry has none: a small portrait model (e.g. MediaPipe's selfie
+/// segmentation exported to ONNX) that deployments may place at
+/// `/models/portrait_segmentation.onnx`.
+pub fn portrait_segmentation() -> ModelMetadata {
+    ModelMetadata {
+        kind: ModelKind::Segmentation,
+        ..builtin(
+            "portrait_segmentation",
+            0.5,
+            "Separates people from the background",
+        )
+    }
+}
+
+/// The super-resolution model the `upscale` option registers when the
+/// registry has none for `factor`: a compact ESRGAN-style network (e.g.
+/// Real-ESRGAN's general model exported to ONNX) that deployments may place
+/// at `/models/super_resolution_x2.onnx` or `_x4.onnx`. It runs on 128x128
+/// tiles of the result.
+pub fn super_resolution(factor: u32) -> ModelMetadata {
+    ModelMetadata {
+        input_width: 128,
+        input_height: 128,
+        kind: ModelKind::SuperResolution,
+        upscale: factor,
+        ..builtin(
+            &format!("super_resolution_x{}", factor),
+            4.8,
+            "Enlarges results while restoring fine detail",
+        )
+    }
+}
+
+/// The styles every engine starts with.
+pub fn default_registry() -> Vec<ModelMetadata> {
+    vec![
+        builtin(
+            "van_gogh_starry_night",
+            2.4,
+            "Neural style transfer trained on Van Gogh's masterpiece",
+        ),
+        builtin(
+            "picasso_cubist",
+            2.1,
+            "Geometric abstraction in revolutionary cubist style",
+        ),
+        builtin(
+            "cyberpunk_neon",
+            2.8,
+            "Futuristic digital enhancement with neon aesthetics",
+        ),
+        builtin(
+            "monet_water_lilies",
+            2.3,
+            "Impressionist technique capturing light and atmosphere",
+        ),
+        builtin(
+            "anime_studio_ghibli",
+            2.6,
+            "Studio Ghibli inspired animation transformation",
+        ),
+        // 16:9 and simulation-only, so nothing can assume square inputs
+        ModelMetadata {
+            input_width: 384,
+            input_height: 216,
+            model_url: String::new(),
+            kind: ModelKind::Simulated,
+            ..builtin(
+                "cinematic_widescreen",
+                0.0,
+                "Widescreen film look with teal shadows and warm highlights",
+            )
+        },
+    ]
+}
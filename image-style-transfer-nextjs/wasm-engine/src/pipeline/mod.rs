@@ -0,0 +1,201 @@
+//! The DOM-free image pipeline.
+//!
+//! Everything under this module works on plain slices and never touches
+//! `wasm_bindgen` or `web_sys`, so it builds and is tested on native targets
+//! as well as wasm32. `lib.rs` is only the browser adapter around it.
+
+pub mod adain;
+pub mod benchmark;
+pub mod budget;
+pub mod cache;
+pub mod codec;
+pub mod color;
+pub mod compression;
+pub mod frame;
+#[doc(hidden)]
+pub mod golden;
+pub mod graph;
+pub mod grid;
+pub mod guided;
+pub mod inference;
+pub mod inspect;
+pub mod integrity;
+pub mod jitter;
+pub mod letterbox;
+pub mod lut;
+pub mod metadata;
+pub mod normalize;
+pub mod orientation;
+pub mod pool;
+pub mod postprocess;
+pub mod prefetch;
+pub mod progress;
+pub mod quantize;
+pub mod registry;
+pub mod resize;
+pub mod resume;
+pub mod retry;
+pub mod rng;
+pub mod saliency;
+pub mod segmentation;
+pub mod shapes;
+pub mod simd;
+pub mod simulated;
+pub mod strength;
+pub mod suggest;
+pub mod temporal;
+pub mod tensor;
+pub mod tiling;
+pub mod upscale;
+
+pub use color::ColorSpace;
+pub use compression::{
+    compression_from_url, decompress_model, detect_compression, ModelCompression,
+    StreamingDecompressor,
+};
+pub use grid::{compose_grid, grid_dimensions, grid_strengths, MAX_GRID_CELLS};
+pub use inference::{
+    load_plan, plan_shapes, plan_size_estimate, run_features, run_plan, TractPlan,
+};
+pub use inspect::{inspect_model, plan_facts, ModelInspection, PlanFacts};
+pub use letterbox::Letterbox;
+pub use metadata::{
+    default_registry, portrait_segmentation, super_resolution, ModelKind, ModelMetadata,
+};
+pub use normalize::ValueRange;
+pub use resize::{resize_rgba, resize_rgba_f32, ResizeFilter};
+pub use rng::{XorShift64, DEFAULT_SIMULATION_SEED};
+pub use saliency::saliency_map;
+pub use shapes::{check_model_shapes, corrected_metadata, ShapeMismatch};
+pub use simulated::{simulate_style, SimulatedStyleConfig};
+pub use strength::StrengthMap;
+pub use suggest::{rank_styles, ImageStats, StyleAffinity, Suggestion};
+pub use temporal::TemporalBlend;
+pub use tensor::{
+    alpha_channel, blend_channels_into, blend_tensors, blend_tensors_into, blend_tensors_per_pixel,
+    flatten_alpha, float_rgba_to_tensor, interleaved_to_planar, parse_hex_color,
+    planar_to_interleaved, rgba_to_tensor, tensor_to_rgba, tensor_to_rgba_into, TensorLayout,
+    ToneMap,
+};
+pub use tiling::{plan_tiles, TileBudget, TilePlan};
+
+/// Which path produced a stylized tensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InferencePath {
+    Onnx,
+    /// The ONNX model lowered to WebGPU compute shaders.
+    WebGpu,
+    Simulated,
+}
+
+/// Output of [`stylize`].
+pub struct Stylized {
+    pub tensor: Vec<f32>,
+    pub path: InferencePath,
+    /// Why ONNX inference was skipped or failed, when it was attempted.
+    pub onnx_error: Option<String>,
+}
+
+/// Runs the model when a plan is available, falling back to the simulated
+/// filter seeded with `seed`.
+pub fn stylize(
+    input_tensor: &[f32],
+    metadata: &ModelMetadata,
+    plan: Option<&TractPlan>,
+    seed: u64,
+) -> Stylized {
+    let mut onnx_error = None;
+    if let Some(plan) = plan {
+        match run_plan(plan, input_tensor, metadata) {
+            Ok(tensor) => {
+                return Stylized {
+                    tensor,
+                    path: InferencePath::Onnx,
+                    onnx_error,
+                };
+            }
+            Err(e) => onnx_error = Some(e.to_string()),
+        }
+    }
+
+    let tensor = match &metadata.simulated_style {
+        Some(config) => simulate_style(input_tensor, config, metadata.input_width, seed),
+        None => input_tensor.to_vec(),
+    };
+    Stylized {
+        tensor,
+        path: InferencePath::Simulated,
+        onnx_error,
+    }
+}
+
+/// Applies `strength` to a stylized tensor; full strength skips the blend entirely.
+///
+/// `strength_map` holds one value per pixel at the tensor's resolution and is
+/// multiplied with `strength` before blending, so any later mode-specific
+/// blending sees the combined per-pixel strength. `space` is the space both
+/// tensors are in.
+pub fn apply_strength(
+    input_tensor: &[f32],
+    stylized: Vec<f32>,
+    strength: f32,
+    strength_map: Option<&[f32]>,
+    space: ColorSpace,
+) -> Vec<f32> {
+    if strength_map.is_none() && strength >= 1.0 {
+        return stylized;
+    }
+    let mut blended = Vec::with_capacity(stylized.len());
+    blend_tensors_into(
+        input_tensor,
+        &stylized,
+        strength,
+        strength_map,
+        space,
+        &mut blended,
+    );
+    blended
+}
+
+/// Like [`apply_strength`], writing into `out` so that blending one stylized
+/// tensor at several strengths can share a buffer.
+pub fn apply_strength_into(
+    input_tensor: &[f32],
+    stylized: &[f32],
+    strength: f32,
+    strength_map: Option<&[f32]>,
+    space: ColorSpace,
+    out: &mut Vec<f32>,
+) {
+    if strength_map.is_none() && strength >= 1.0 {
+        out.clear();
+        out.extend_from_slice(stylized);
+    } else {
+        blend_tensors_into(input_tensor, stylized, strength, strength_map, space, out);
+    }
+}
+
+/// Full pipeline over RGBA pixels already resized to the model resolution.
+pub fn process_rgba(
+    pixels: &[u8],
+    metadata: &ModelMetadata,
+    plan: Option<&TractPlan>,
+    strength: f32,
+    strength_map: Option<&StrengthMap>,
+    seed: u64,
+) -> Vec<u8> {
+    let input_tensor = rgba_to_tensor(pixels);
+    let stylized = stylize(&input_tensor, metadata, plan, seed);
+    let map = strength_map.map(|map| map.resampled(metadata.input_width, metadata.input_height));
+    let blended = apply_strength(
+        &input_tensor,
+        stylized.tensor,
+        strength,
+        map.as_deref(),
+        ColorSpace::Srgb,
+    );
+    tensor_to_rgba(
+        &blended,
+        (metadata.input_width * metadata.input_height) as usize,
+    )
+}
@@ -0,0 +1,100 @@
+//! Scaling the engine's [0, 1] tensors into the values a model was trained
+//! on, and its outputs back.
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use super::ModelMetadata;
+
+/// The span of values a model's tensors use for black to white.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValueRange {
+    /// [0, 1], as the engine's own tensors.
+    #[default]
+    #[serde(rename = "0_1")]
+    Unit,
+    /// [-1, 1], common for GAN-style generators.
+    #[serde(rename = "-1_1")]
+    Signed,
+    /// [0, 255], as raw pixel bytes.
+    #[serde(rename = "0_255")]
+    Bytes,
+}
+
+impl ValueRange {
+    /// Maps a value in [0, 1] onto this range.
+    pub fn expand(self, value: f32) -> f32 {
+        match self {
+            ValueRange::Unit => value,
+            ValueRange::Signed => value * 2.0 - 1.0,
+            ValueRange::Bytes => value * 255.0,
+        }
+    }
+
+    /// Inverse of [`expand`](Self::expand).
+    pub fn compress(self, value: f32) -> f32 {
+        match self {
+            ValueRange::Unit => value,
+            ValueRange::Signed => (value + 1.0) * 0.5,
+            ValueRange::Bytes => value / 255.0,
+        }
+    }
+}
+
+/// The mean and standard deviation of ImageNet per RGB channel, in [0, 1],
+/// which torchvision-trained models expect subtracted and divided out.
+pub const IMAGENET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+pub const IMAGENET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// Whether `metadata`'s model takes the engine's [0, 1] tensors unchanged.
+pub fn is_identity(metadata: &ModelMetadata) -> bool {
+    metadata.input_range == ValueRange::Unit
+        && metadata.mean == [0.0; 3]
+        && metadata.std == [1.0; 3]
+}
+
+/// Checks that `mean` and `std` can be applied.
+pub fn validate(metadata: &ModelMetadata) -> Result<(), String> {
+    if metadata.mean.iter().any(|m| !m.is_finite()) {
+        return Err(format!(
+            "'{}' has a non-finite mean {:?}",
+            metadata.name, metadata.mean
+        ));
+    }
+    if metadata.std.iter().any(|s| !s.is_finite() || *s <= 0.0) {
+        return Err(format!(
+            "'{}' needs a positive std per channel, got {:?}",
+            metadata.name, metadata.std
+        ));
+    }
+    Ok(())
+}
+
+/// An interleaved RGB `tensor` in [0, 1] as `metadata`'s model expects it:
+/// scaled to its `input_range`, then `(value - mean) / std` per channel with
+/// `mean` and `std` given in that range. Borrowed when nothing changes.
+pub fn model_input<'a>(tensor: &'a [f32], metadata: &ModelMetadata) -> Cow<'a, [f32]> {
+    if is_identity(metadata) {
+        return Cow::Borrowed(tensor);
+    }
+    let (range, mean, std) = (metadata.input_range, metadata.mean, metadata.std);
+    Cow::Owned(
+        tensor
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| (range.expand(value) - mean[i % 3]) / std[i % 3])
+            .collect(),
+    )
+}
+
+/// Maps a model's output from its `output_range` back to [0, 1] in place,
+/// before anything clamps it.
+pub fn model_output(tensor: &mut [f32], metadata: &ModelMetadata) {
+    let range = metadata.output_range;
+    if range != ValueRange::Unit {
+        tensor
+            .iter_mut()
+            .for_each(|value| *value = range.compress(*value));
+    }
+}
@@ -0,0 +1,170 @@
+//! EXIF orientation: reading the tag from JPEG and PNG files, and turning
+//! stored pixels upright.
+
+/// How stored pixels map to the upright image, numbered as the EXIF
+/// Orientation tag (0x0112).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// 1: stored upright.
+    #[default]
+    Normal,
+    /// 2: mirrored left to right.
+    FlipHorizontal,
+    /// 3: upside down.
+    Rotate180,
+    /// 4: mirrored top to bottom.
+    FlipVertical,
+    /// 5: mirrored along the top-left to bottom-right diagonal.
+    Transpose,
+    /// 6: needs a quarter turn clockwise, as phones held upright store it.
+    Rotate90,
+    /// 7: mirrored along the top-right to bottom-left diagonal.
+    Transverse,
+    /// 8: needs a quarter turn counterclockwise.
+    Rotate270,
+}
+
+const ORIENTATION_TAG: u16 = 0x0112;
+
+impl Orientation {
+    /// The orientation for an EXIF tag value; `None` outside 1–8.
+    pub fn from_exif(value: u16) -> Option<Orientation> {
+        Some(match value {
+            1 => Orientation::Normal,
+            2 => Orientation::FlipHorizontal,
+            3 => Orientation::Rotate180,
+            4 => Orientation::FlipVertical,
+            5 => Orientation::Transpose,
+            6 => Orientation::Rotate90,
+            7 => Orientation::Transverse,
+            8 => Orientation::Rotate270,
+            _ => return None,
+        })
+    }
+
+    /// Whether the upright image has width and height swapped.
+    pub fn swaps_sides(self) -> bool {
+        matches!(
+            self,
+            Orientation::Transpose
+                | Orientation::Rotate90
+                | Orientation::Transverse
+                | Orientation::Rotate270
+        )
+    }
+
+    /// Turns `width` x `height` RGBA pixels upright, returning them with
+    /// their new width and height.
+    pub fn apply(self, pixels: &[u8], width: u32, height: u32) -> (Vec<u8>, u32, u32) {
+        if self == Orientation::Normal {
+            return (pixels.to_vec(), width, height);
+        }
+        let (w, h) = (width as usize, height as usize);
+        let (out_width, out_height) = if self.swaps_sides() { (h, w) } else { (w, h) };
+        let mut upright = Vec::with_capacity(pixels.len());
+        for y in 0..out_height {
+            for x in 0..out_width {
+                let (sx, sy) = match self {
+                    Orientation::Normal => (x, y),
+                    Orientation::FlipHorizontal => (w - 1 - x, y),
+                    Orientation::Rotate180 => (w - 1 - x, h - 1 - y),
+                    Orientation::FlipVertical => (x, h - 1 - y),
+                    Orientation::Transpose => (y, x),
+                    Orientation::Rotate90 => (y, h - 1 - x),
+                    Orientation::Transverse => (w - 1 - y, h - 1 - x),
+                    Orientation::Rotate270 => (w - 1 - y, x),
+                };
+                let i = (sy * w + sx) * 4;
+                upright.extend_from_slice(&pixels[i..i + 4]);
+            }
+        }
+        (upright, out_width as u32, out_height as u32)
+    }
+}
+
+/// The orientation recorded in a JPEG's APP1 Exif segment or a PNG's eXIf
+/// chunk; `None` when there is none or it can't be read.
+pub fn exif_orientation(bytes: &[u8]) -> Option<Orientation> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        jpeg_exif(bytes).and_then(tiff_orientation)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_exif(bytes).and_then(tiff_orientation)
+    } else {
+        None
+    }
+}
+
+/// The TIFF payload of the first Exif APP1 segment before the image data.
+fn jpeg_exif(bytes: &[u8]) -> Option<&[u8]> {
+    let mut at = 2;
+    while at + 4 <= bytes.len() {
+        if bytes[at] != 0xFF {
+            return None;
+        }
+        let marker = bytes[at + 1];
+        // Start of scan: no metadata segments follow
+        if marker == 0xDA {
+            return None;
+        }
+        let length = u16::from_be_bytes([bytes[at + 2], bytes[at + 3]]) as usize;
+        let segment = bytes.get(at + 4..at + 2 + length)?;
+        if marker == 0xE1 {
+            if let Some(tiff) = segment.strip_prefix(b"Exif\0\0") {
+                return Some(tiff);
+            }
+        }
+        at += 2 + length;
+    }
+    None
+}
+
+/// The TIFF payload of the eXIf chunk.
+fn png_exif(bytes: &[u8]) -> Option<&[u8]> {
+    let mut at = 8;
+    while at + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[at..at + 4].try_into().ok()?) as usize;
+        let kind = &bytes[at + 4..at + 8];
+        let data = bytes.get(at + 8..(at + 8).checked_add(length)?)?;
+        match kind {
+            b"eXIf" => return Some(data),
+            b"IDAT" | b"IEND" => return None,
+            _ => at += 12 + length,
+        }
+    }
+    None
+}
+
+/// Reads the Orientation entry of IFD0 from a TIFF header in either byte order.
+fn tiff_orientation(tiff: &[u8]) -> Option<Orientation> {
+    let little_endian = match tiff.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| -> Option<u16> {
+        let bytes = tiff.get(at..at + 2)?.try_into().ok()?;
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |at: usize| -> Option<u32> {
+        let bytes = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    if u16_at(2)? != 42 {
+        return None;
+    }
+    let ifd = u32_at(4)? as usize;
+    let entries = u16_at(ifd)? as usize;
+    (0..entries)
+        .map(|entry| ifd + 2 + entry * 12)
+        .find(|&entry| u16_at(entry) == Some(ORIENTATION_TAG))
+        .and_then(|entry| u16_at(entry + 8))
+        .and_then(Orientation::from_exif)
+}
@@ -0,0 +1,65 @@
+//! Working buffers kept between processing calls, so repeated processing at
+//! the same resolution (slider drags, video frames) reuses its allocations.
+
+/// Resolutions whose buffers are kept; older ones are dropped.
+pub const POOLED_RESOLUTIONS: usize = 4;
+
+/// The blend tensor and output pixels of one result.
+#[derive(Default, Debug)]
+pub struct Buffers {
+    pub blend: Vec<f32>,
+    pub pixels: Vec<u8>,
+}
+
+impl Buffers {
+    fn byte_len(&self) -> usize {
+        self.blend.capacity() * std::mem::size_of::<f32>() + self.pixels.capacity()
+    }
+}
+
+/// Buffers keyed by the width and height they were last used at, most
+/// recently returned last.
+#[derive(Default, Debug)]
+pub struct BufferPool {
+    entries: Vec<((u32, u32), Buffers)>,
+}
+
+impl BufferPool {
+    /// The buffers last used at `size`, or empty ones. Their contents are
+    /// stale; the `_into` functions clear them before writing.
+    pub fn take(&mut self, size: (u32, u32)) -> Buffers {
+        match self.entries.iter().position(|(key, _)| *key == size) {
+            Some(index) => self.entries.remove(index).1,
+            None => Buffers::default(),
+        }
+    }
+
+    /// Keeps `buffers` for the next call at `size`, dropping the least
+    /// recently used resolution beyond `POOLED_RESOLUTIONS`. Buffers can be
+    /// given back separately: of each kind, the one with more capacity is kept.
+    pub fn give(&mut self, size: (u32, u32), mut buffers: Buffers) {
+        let pooled = self.take(size);
+        if pooled.blend.capacity() > buffers.blend.capacity() {
+            buffers.blend = pooled.blend;
+        }
+        if pooled.pixels.capacity() > buffers.pixels.capacity() {
+            buffers.pixels = pooled.pixels;
+        }
+        self.entries.push((size, buffers));
+        if self.entries.len() > POOLED_RESOLUTIONS {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Bytes held across every resolution.
+    pub fn byte_len(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|(_, buffers)| buffers.byte_len())
+            .sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,170 @@
+//! Cleaning up a model's output before the strength blend: a light
+//! edge-preserving denoiser, unsharp-mask sharpening, and histogram matching
+//! to the source.
+
+/// Largest `sharpen` amount accepted; beyond it halos dominate.
+pub const MAX_SHARPEN: f32 = 2.0;
+
+/// Standard deviation, in pixels, of the blur the unsharp mask subtracts.
+pub const SHARPEN_SIGMA: f32 = 1.0;
+
+/// Window radius, in pixels, of the denoiser.
+pub const DENOISE_RADIUS: usize = 2;
+
+/// How different two colors may be, as RGB distance in [0, 1] units, before
+/// the denoiser stops averaging them; edges above it are kept.
+pub const DENOISE_RANGE_SIGMA: f32 = 0.1;
+
+/// What to apply to a stylized tensor, both off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Postprocess {
+    /// Unsharp-mask amount in [0, `MAX_SHARPEN`].
+    pub sharpen: f32,
+    /// Denoiser strength in [0, 1]: how far pixels move to the denoised value.
+    pub denoise: f32,
+}
+
+impl Postprocess {
+    /// Checks the amounts are in range.
+    pub fn new(sharpen: f32, denoise: f32) -> Result<Postprocess, String> {
+        if !(0.0..=MAX_SHARPEN).contains(&sharpen) {
+            return Err(format!(
+                "sharpen must be between 0 and {}, got {}",
+                MAX_SHARPEN, sharpen
+            ));
+        }
+        if !(0.0..=1.0).contains(&denoise) {
+            return Err(format!("denoise must be between 0 and 1, got {}", denoise));
+        }
+        Ok(Postprocess { sharpen, denoise })
+    }
+
+    pub fn is_noop(&self) -> bool {
+        self.sharpen == 0.0 && self.denoise == 0.0
+    }
+
+    /// Denoises, then sharpens, so the noise isn't sharpened with the detail.
+    pub fn apply(&self, tensor: &mut [f32], size: (u32, u32)) {
+        if self.denoise > 0.0 {
+            denoise(tensor, size, self.denoise);
+        }
+        if self.sharpen > 0.0 {
+            sharpen(tensor, size, self.sharpen);
+        }
+    }
+}
+
+/// Normalized Gaussian weights for offsets `-radius..=radius`.
+fn gaussian_kernel(sigma: f32, radius: usize) -> Vec<f32> {
+    let weights: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let d = i as f32 - radius as f32;
+            (-d * d / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+/// Separable Gaussian blur of an interleaved RGB tensor, repeating edge pixels.
+fn gaussian_blur(tensor: &[f32], (width, height): (u32, u32), sigma: f32) -> Vec<f32> {
+    let (w, h) = (width as usize, height as usize);
+    let radius = (sigma * 2.0).ceil() as usize;
+    let kernel = gaussian_kernel(sigma, radius);
+    let pass = |input: &[f32], horizontal: bool| {
+        let mut output = vec![0.0f32; input.len()];
+        for y in 0..h {
+            for x in 0..w {
+                for (k, weight) in kernel.iter().enumerate() {
+                    let (sx, sy) = if horizontal {
+                        ((x + k).saturating_sub(radius).min(w - 1), y)
+                    } else {
+                        (x, (y + k).saturating_sub(radius).min(h - 1))
+                    };
+                    let (out, src) = ((y * w + x) * 3, (sy * w + sx) * 3);
+                    for c in 0..3 {
+                        output[out + c] += input[src + c] * weight;
+                    }
+                }
+            }
+        }
+        output
+    };
+    pass(&pass(tensor, true), false)
+}
+
+/// Unsharp mask in place: adds `amount` times the difference from a
+/// Gaussian blur, clamped to [0, 1].
+pub fn sharpen(tensor: &mut [f32], size: (u32, u32), amount: f32) {
+    let blurred = gaussian_blur(tensor, size, SHARPEN_SIGMA);
+    for (value, blur) in tensor.iter_mut().zip(blurred) {
+        *value = (*value + amount * (*value - blur)).clamp(0.0, 1.0);
+    }
+}
+
+/// Bilateral filter in place: each pixel moves `strength` of the way to a
+/// Gaussian-weighted mean of its neighbours of similar color, which smooths
+/// noise and grain but not edges.
+pub fn denoise(tensor: &mut [f32], (width, height): (u32, u32), strength: f32) {
+    let (w, h) = (width as usize, height as usize);
+    let radius = DENOISE_RADIUS;
+    let spatial = gaussian_kernel(radius as f32 / 2.0, radius);
+    let range = -0.5 / (DENOISE_RANGE_SIGMA * DENOISE_RANGE_SIGMA);
+    let source = tensor.to_vec();
+    for y in 0..h {
+        for x in 0..w {
+            let at = (y * w + x) * 3;
+            let center = &source[at..at + 3];
+            let mut sum = [0.0f32; 3];
+            let mut total = 0.0f32;
+            for (ky, wy) in spatial.iter().enumerate() {
+                let sy = (y + ky).saturating_sub(radius).min(h - 1);
+                for (kx, wx) in spatial.iter().enumerate() {
+                    let sx = (x + kx).saturating_sub(radius).min(w - 1);
+                    let other = &source[(sy * w + sx) * 3..(sy * w + sx) * 3 + 3];
+                    let distance: f32 = center
+                        .iter()
+                        .zip(other)
+                        .map(|(a, b)| (a - b) * (a - b))
+                        .sum();
+                    let weight = wy * wx * (distance * range).exp();
+                    for c in 0..3 {
+                        sum[c] += other[c] * weight;
+                    }
+                    total += weight;
+                }
+            }
+            for c in 0..3 {
+                tensor[at + c] += (sum[c] / total - center[c]) * strength;
+            }
+        }
+    }
+}
+
+/// Per channel, remaps `stylized` so its values are distributed like
+/// `reference`'s, keeping their order: each value takes the reference value
+/// at its own quantile. Both are interleaved RGB; their sizes may differ.
+/// Equal values stay equal, so flat areas don't pick up noise.
+pub fn match_histogram(stylized: &mut [f32], reference: &[f32]) {
+    if reference.len() < 3 {
+        return;
+    }
+    for c in 0..3 {
+        let sorted = |tensor: &[f32]| {
+            let mut values: Vec<f32> = tensor.iter().skip(c).step_by(3).copied().collect();
+            values.sort_by(f32::total_cmp);
+            values
+        };
+        let (own, target) = (sorted(stylized), sorted(reference));
+        let (n, m) = (own.len() as f32, target.len());
+        for value in stylized.iter_mut().skip(c).step_by(3) {
+            let below = own.partition_point(|&v| v < *value);
+            let through = own.partition_point(|&v| v <= *value);
+            let quantile = (below + through) as f32 / (2.0 * n);
+            let position = (quantile * m as f32 - 0.5).clamp(0.0, (m - 1) as f32);
+            let first = position.floor() as usize;
+            let next = (first + 1).min(m - 1);
+            *value = target[first] + (target[next] - target[first]) * (position - first as f32);
+        }
+    }
+}
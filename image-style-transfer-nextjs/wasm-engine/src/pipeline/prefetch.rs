@@ -0,0 +1,99 @@
+//! The order `prefetch_models` downloads models in.
+
+use serde::Deserialize;
+
+/// How soon a prefetch should run relative to the others queued.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefetchPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+struct Waiting {
+    name: String,
+    priority: PrefetchPriority,
+    /// Queueing order, for first-come first-served among equal priorities.
+    sequence: u64,
+}
+
+/// Prefetches waiting to start and those running.
+#[derive(Default)]
+pub struct PrefetchQueue {
+    waiting: Vec<Waiting>,
+    running: Vec<String>,
+    next_sequence: u64,
+}
+
+impl PrefetchQueue {
+    /// Queues `name`, or raises the priority it is already queued at.
+    /// Returns false when it is already running.
+    pub fn push(&mut self, name: &str, priority: PrefetchPriority) -> bool {
+        if self.is_running(name) {
+            return false;
+        }
+        match self.waiting.iter_mut().find(|w| w.name == name) {
+            Some(waiting) => waiting.priority = waiting.priority.max(priority),
+            None => {
+                self.waiting.push(Waiting {
+                    name: name.to_string(),
+                    priority,
+                    sequence: self.next_sequence,
+                });
+                self.next_sequence += 1;
+            }
+        }
+        true
+    }
+
+    /// Moves the most urgent waiting prefetch, the earliest queued among
+    /// equals, to running, unless `limit` already are.
+    pub fn start(&mut self, limit: usize) -> Option<String> {
+        if self.running.len() >= limit {
+            return None;
+        }
+        let index = self
+            .waiting
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, w)| (w.priority, std::cmp::Reverse(w.sequence)))?
+            .0;
+        let name = self.waiting.remove(index).name;
+        self.running.push(name.clone());
+        Some(name)
+    }
+
+    /// Marks a running prefetch as done.
+    pub fn finish(&mut self, name: &str) {
+        self.running.retain(|running| running != name);
+    }
+
+    /// Drops `name` from the waiting prefetches; returns whether it was there.
+    pub fn cancel(&mut self, name: &str) -> bool {
+        let before = self.waiting.len();
+        self.waiting.retain(|w| w.name != name);
+        self.waiting.len() != before
+    }
+
+    /// Drops every waiting prefetch; running ones carry on.
+    pub fn clear(&mut self) {
+        self.waiting.clear();
+    }
+
+    pub fn is_running(&self, name: &str) -> bool {
+        self.running.iter().any(|running| running == name)
+    }
+
+    /// Waiting prefetches in the order they would start.
+    pub fn waiting(&self) -> Vec<String> {
+        let mut waiting: Vec<&Waiting> = self.waiting.iter().collect();
+        waiting.sort_by_key(|w| (std::cmp::Reverse(w.priority), w.sequence));
+        waiting.into_iter().map(|w| w.name.clone()).collect()
+    }
+
+    pub fn running(&self) -> &[String] {
+        &self.running
+    }
+}
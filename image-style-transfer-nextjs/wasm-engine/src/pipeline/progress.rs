@@ -0,0 +1,63 @@
+//! Turning pipeline stages into an overall percentage for progress bars.
+
+/// The stages a processing call reports, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Decode,
+    /// Loading the model if needed and drawing the source at its size.
+    Preprocess,
+    Inference,
+    /// Blending, masking and scaling the stylized pixels.
+    Postprocess,
+    Encode,
+}
+
+impl Stage {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Decode => "decode",
+            Stage::Preprocess => "preprocess",
+            Stage::Inference => "inference",
+            Stage::Postprocess => "postprocess",
+            Stage::Encode => "encode",
+        }
+    }
+
+    /// Percent of the call done when the stage starts; inference, by far the
+    /// slowest, gets most of the range.
+    fn start(self) -> f32 {
+        match self {
+            Stage::Decode => 0.0,
+            Stage::Preprocess => 5.0,
+            Stage::Inference => 10.0,
+            Stage::Postprocess => 90.0,
+            Stage::Encode => 95.0,
+        }
+    }
+}
+
+/// Inference steps (tiles times passes) a call runs and how many are done.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub steps: u32,
+    pub done: u32,
+}
+
+impl Progress {
+    pub fn new(steps: u32) -> Progress {
+        Progress { steps, done: 0 }
+    }
+
+    /// Percent of the call done at `stage`, counting finished steps within
+    /// inference.
+    pub fn percent(&self, stage: Stage) -> f32 {
+        match stage {
+            Stage::Inference if self.steps > 0 => {
+                let share = self.done.min(self.steps) as f32 / self.steps as f32;
+                Stage::Inference.start()
+                    + share * (Stage::Postprocess.start() - Stage::Inference.start())
+            }
+            stage => stage.start(),
+        }
+    }
+}
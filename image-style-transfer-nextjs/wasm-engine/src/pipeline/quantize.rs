@@ -0,0 +1,86 @@
+//! Models whose image input and output are 8-bit tensors, as fully
+//! quantized ONNX exports have: quantizing the normalized input and
+//! dequantizing the output around the plan.
+//!
+//! Models quantized only inside the graph (QDQ or QOperator nodes between
+//! float input and output) need none of this; tract dequantizes them itself.
+
+use serde::{Deserialize, Serialize};
+
+/// Element type of a quantized tensor.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantizedType {
+    #[default]
+    Uint8,
+    Int8,
+}
+
+impl QuantizedType {
+    /// Smallest and largest representable values.
+    pub fn range(self) -> (i32, i32) {
+        match self {
+            QuantizedType::Uint8 => (0, 255),
+            QuantizedType::Int8 => (-128, 127),
+        }
+    }
+}
+
+/// Affine 8-bit encoding of a model's image tensors: a quantized value `q`
+/// stands for `(q - zero_point) * scale`. The default reads raw pixel bytes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct Quantization {
+    pub dtype: QuantizedType,
+    pub input_scale: f32,
+    pub input_zero_point: i32,
+    pub output_scale: f32,
+    pub output_zero_point: i32,
+}
+
+impl Default for Quantization {
+    fn default() -> Self {
+        Quantization {
+            dtype: QuantizedType::Uint8,
+            input_scale: 1.0 / 255.0,
+            input_zero_point: 0,
+            output_scale: 1.0 / 255.0,
+            output_zero_point: 0,
+        }
+    }
+}
+
+impl Quantization {
+    /// Checks the scales are positive and the zero points representable.
+    pub fn validate(&self) -> Result<(), String> {
+        let (min, max) = self.dtype.range();
+        for (scale, zero_point, side) in [
+            (self.input_scale, self.input_zero_point, "input"),
+            (self.output_scale, self.output_zero_point, "output"),
+        ] {
+            if !scale.is_finite() || scale <= 0.0 {
+                return Err(format!("{}_scale must be positive, got {}", side, scale));
+            }
+            if !(min..=max).contains(&zero_point) {
+                return Err(format!(
+                    "{}_zero_point must be in [{}, {}] for {:?}, got {}",
+                    side, min, max, self.dtype, zero_point
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rounds a normalized input value to the nearest representable one.
+    pub fn quantize(&self, value: f32) -> i32 {
+        let (min, max) = self.dtype.range();
+        ((value / self.input_scale).round() as i32)
+            .saturating_add(self.input_zero_point)
+            .clamp(min, max)
+    }
+
+    /// The real value an output element stands for.
+    pub fn dequantize(&self, value: i32) -> f32 {
+        (value - self.output_zero_point) as f32 * self.output_scale
+    }
+}
@@ -0,0 +1,379 @@
+//! Validation and editing rules for the model registry.
+
+use serde::{Deserialize, Serialize};
+
+use super::compression::ModelCompression;
+use super::metadata::{ModelKind, ModelMetadata};
+use super::simulated::SimulatedStyleConfig;
+use super::suggest::StyleAffinity;
+
+/// Checks that an entry could actually be used by the engine.
+pub fn validate_metadata(metadata: &ModelMetadata) -> Result<(), String> {
+    validate_local_metadata(metadata)?;
+    if matches!(
+        metadata.kind,
+        ModelKind::Onnx | ModelKind::Segmentation | ModelKind::SuperResolution
+    ) && metadata.model_url.is_empty()
+    {
+        return Err(format!(
+            "'{}' is an ONNX model without a model_url",
+            metadata.name
+        ));
+    }
+    if metadata.kind == ModelKind::Adain
+        && (metadata.model_url.is_empty() || metadata.decoder_url.is_empty())
+    {
+        return Err(format!(
+            "'{}' is an AdaIN model without both a model_url and a decoder_url",
+            metadata.name
+        ));
+    }
+    Ok(())
+}
+
+/// `validate_metadata` for an ONNX model whose bytes the caller supplies,
+/// which needs no URLs.
+pub fn validate_local_metadata(metadata: &ModelMetadata) -> Result<(), String> {
+    if metadata.name.is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    if metadata.input_width == 0 || metadata.input_height == 0 {
+        return Err(format!(
+            "'{}' needs a non-zero input size, got {}x{}",
+            metadata.name, metadata.input_width, metadata.input_height
+        ));
+    }
+    if metadata.input_channels != 3 {
+        return Err(format!(
+            "'{}' must take 3 input channels, got {}",
+            metadata.name, metadata.input_channels
+        ));
+    }
+    if !metadata.size_mb.is_finite() || metadata.size_mb < 0.0 {
+        return Err(format!("'{}' has an invalid size_mb", metadata.name));
+    }
+    if metadata.kind == ModelKind::SuperResolution
+        && !super::upscale::UPSCALE_FACTORS.contains(&metadata.upscale)
+    {
+        return Err(format!(
+            "'{}' must upscale by one of {:?}, got {}",
+            metadata.name,
+            super::upscale::UPSCALE_FACTORS,
+            metadata.upscale
+        ));
+    }
+    if let Some(sha256) = &metadata.sha256 {
+        super::integrity::validate_sha256(sha256)
+            .map_err(|reason| format!("'{}': {}", metadata.name, reason))?;
+    }
+    super::normalize::validate(metadata)?;
+    if let Some(quantization) = &metadata.quantization {
+        quantization
+            .validate()
+            .map_err(|reason| format!("'{}' quantization: {}", metadata.name, reason))?;
+    }
+    Ok(())
+}
+
+/// A partial update for an existing entry. Absent fields are left alone;
+/// the name and kind can't be changed this way.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct MetadataPatch {
+    pub description: Option<String>,
+    pub model_url: Option<String>,
+    pub decoder_url: Option<String>,
+    pub version: Option<String>,
+    pub compression: Option<ModelCompression>,
+    pub sha256: Option<String>,
+    pub size_mb: Option<f32>,
+    pub input_width: Option<u32>,
+    pub input_height: Option<u32>,
+    pub input_channels: Option<u32>,
+    pub simulated_style: Option<SimulatedStyleConfig>,
+    pub style_affinity: Option<StyleAffinity>,
+}
+
+impl MetadataPatch {
+    /// Whether applying the patch would change the input shape of `metadata`.
+    pub fn changes_shape(&self, metadata: &ModelMetadata) -> bool {
+        self.input_width.is_some_and(|w| w != metadata.input_width)
+            || self
+                .input_height
+                .is_some_and(|h| h != metadata.input_height)
+            || self
+                .input_channels
+                .is_some_and(|c| c != metadata.input_channels)
+    }
+
+    /// Returns the patched copy of `metadata`, validated.
+    pub fn apply(self, metadata: &ModelMetadata) -> Result<ModelMetadata, String> {
+        let mut patched = metadata.clone();
+        if let Some(description) = self.description {
+            patched.description = description;
+        }
+        if let Some(model_url) = self.model_url {
+            patched.model_url = model_url;
+        }
+        if let Some(decoder_url) = self.decoder_url {
+            patched.decoder_url = decoder_url;
+        }
+        if let Some(version) = self.version {
+            patched.version = version;
+        }
+        if let Some(compression) = self.compression {
+            patched.compression = Some(compression);
+        }
+        if let Some(sha256) = self.sha256 {
+            patched.sha256 = Some(sha256);
+        }
+        if let Some(size_mb) = self.size_mb {
+            patched.size_mb = size_mb;
+        }
+        if let Some(width) = self.input_width {
+            patched.input_width = width;
+        }
+        if let Some(height) = self.input_height {
+            patched.input_height = height;
+        }
+        if let Some(channels) = self.input_channels {
+            patched.input_channels = channels;
+        }
+        if let Some(config) = self.simulated_style {
+            patched.simulated_style = Some(config);
+        }
+        if let Some(affinity) = self.style_affinity {
+            patched.style_affinity = Some(affinity);
+        }
+        validate_metadata(&patched)?;
+        Ok(patched)
+    }
+}
+
+/// A downloadable entry whose `version` a registry update changed.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct VersionChange {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// The entries of `after` with a `model_url` whose `version` differs from
+/// that of the same name in `before`. Any change counts, not just a newer
+/// version, so that rolling a registry back rolls the models back too.
+pub fn version_changes(before: &[ModelMetadata], after: &[ModelMetadata]) -> Vec<VersionChange> {
+    after
+        .iter()
+        .filter(|m| !m.model_url.is_empty())
+        .filter_map(|m| {
+            let old = before.iter().find(|old| old.name == m.name)?;
+            (old.version != m.version).then(|| VersionChange {
+                name: m.name.clone(),
+                from: old.version.clone(),
+                to: m.version.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Why one entry of an import was skipped.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RejectedEntry {
+    pub index: usize,
+    pub name: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    pub rejected: Vec<RejectedEntry>,
+}
+
+/// Builds the registry resulting from importing `entries` into `current`.
+///
+/// With `replace` the result holds only the accepted entries; otherwise they
+/// are appended and names already present count as duplicates. `is_loaded`
+/// marks models whose input shape is locked by an optimized plan, and
+/// `is_js_filter` names that have a registered callback. Bad entries are
+/// reported individually and never abort the import.
+pub fn import_entries(
+    current: &[ModelMetadata],
+    entries: Vec<serde_json::Value>,
+    replace: bool,
+    is_loaded: impl Fn(&str) -> bool,
+    is_js_filter: impl Fn(&str) -> bool,
+) -> (Vec<ModelMetadata>, ImportReport) {
+    let mut registry = if replace {
+        Vec::new()
+    } else {
+        current.to_vec()
+    };
+    let mut report = ImportReport::default();
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let name = entry
+            .get("name")
+            .and_then(|n| n.as_str())
+            .map(str::to_string);
+        let mut reject = |reason: String| {
+            report.rejected.push(RejectedEntry {
+                index,
+                name: name.clone(),
+                reason,
+            });
+        };
+
+        let metadata = match parse_entry(entry) {
+            Ok(metadata) => metadata,
+            Err(reason) => {
+                reject(reason);
+                continue;
+            }
+        };
+        if registry.iter().any(|m| m.name == metadata.name) {
+            reject(format!("duplicate name '{}'", metadata.name));
+            continue;
+        }
+        if let Err(reason) = check_replacement(&metadata, current, &is_loaded, &is_js_filter) {
+            reject(reason);
+            continue;
+        }
+
+        report.imported.push(metadata.name.clone());
+        registry.push(metadata);
+    }
+
+    (registry, report)
+}
+
+/// What merging a manifest into the registry did, by entry name.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct MergeReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    /// Entries identical to the ones already registered.
+    pub unchanged: Vec<String>,
+    pub rejected: Vec<RejectedEntry>,
+}
+
+/// The entries of a registry manifest: either a JSON array of entries or an
+/// object with one under `models`.
+pub fn manifest_entries(manifest: serde_json::Value) -> Result<Vec<serde_json::Value>, String> {
+    match manifest {
+        serde_json::Value::Array(entries) => Ok(entries),
+        serde_json::Value::Object(mut object) => match object.remove("models") {
+            Some(serde_json::Value::Array(entries)) => Ok(entries),
+            _ => Err("an object manifest needs a \"models\" array".to_string()),
+        },
+        _ => Err("a manifest must be an array of entries or have a \"models\" array".to_string()),
+    }
+}
+
+/// Builds the registry resulting from merging the manifest `entries` into
+/// `current`: new names are appended and existing ones replaced in place,
+/// so styles missing from the manifest are kept. A JS filter can't be turned
+/// into another kind this way. `is_loaded` and `is_js_filter` are as for
+/// [`import_entries`].
+pub fn merge_entries(
+    current: &[ModelMetadata],
+    entries: Vec<serde_json::Value>,
+    is_loaded: impl Fn(&str) -> bool,
+    is_js_filter: impl Fn(&str) -> bool,
+) -> (Vec<ModelMetadata>, MergeReport) {
+    let mut registry = current.to_vec();
+    let mut report = MergeReport::default();
+    let mut seen = Vec::new();
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let name = entry
+            .get("name")
+            .and_then(|n| n.as_str())
+            .map(str::to_string);
+        let mut reject = |reason: String| {
+            report.rejected.push(RejectedEntry {
+                index,
+                name: name.clone(),
+                reason,
+            });
+        };
+
+        let metadata = match parse_entry(entry) {
+            Ok(metadata) => metadata,
+            Err(reason) => {
+                reject(reason);
+                continue;
+            }
+        };
+        if seen.contains(&metadata.name) {
+            reject(format!("duplicate name '{}'", metadata.name));
+            continue;
+        }
+        seen.push(metadata.name.clone());
+        if let Err(reason) = check_replacement(&metadata, current, &is_loaded, &is_js_filter) {
+            reject(reason);
+            continue;
+        }
+
+        match registry.iter_mut().find(|m| m.name == metadata.name) {
+            Some(existing) if *existing == metadata => report.unchanged.push(metadata.name),
+            Some(existing)
+                if existing.kind == ModelKind::JsFilter && metadata.kind != ModelKind::JsFilter =>
+            {
+                reject(format!(
+                    "'{}' is a JS filter; remove it first",
+                    metadata.name
+                ))
+            }
+            Some(existing) => {
+                report.updated.push(metadata.name.clone());
+                *existing = metadata;
+            }
+            None => {
+                report.added.push(metadata.name.clone());
+                registry.push(metadata);
+            }
+        }
+    }
+
+    (registry, report)
+}
+
+fn parse_entry(entry: serde_json::Value) -> Result<ModelMetadata, String> {
+    let metadata: ModelMetadata =
+        serde_json::from_value(entry).map_err(|e| format!("malformed entry: {}", e))?;
+    validate_metadata(&metadata)?;
+    Ok(metadata)
+}
+
+/// Whether `metadata` may take the place of the entry of its name in
+/// `current`, or be added when there is none.
+fn check_replacement(
+    metadata: &ModelMetadata,
+    current: &[ModelMetadata],
+    is_loaded: impl Fn(&str) -> bool,
+    is_js_filter: impl Fn(&str) -> bool,
+) -> Result<(), String> {
+    if metadata.kind == ModelKind::JsFilter && !is_js_filter(&metadata.name) {
+        return Err(format!(
+            "'{}' is a JS filter; register it with register_js_filter",
+            metadata.name
+        ));
+    }
+    if is_loaded(&metadata.name) {
+        let locked = current.iter().find(|m| m.name == metadata.name);
+        if locked.is_some_and(|m| {
+            (m.input_width, m.input_height, m.input_channels)
+                != (
+                    metadata.input_width,
+                    metadata.input_height,
+                    metadata.input_channels,
+                )
+        }) {
+            return Err(format!(
+                "'{}' is loaded; unload it before changing its input shape",
+                metadata.name
+            ));
+        }
+    }
+    Ok(())
+}
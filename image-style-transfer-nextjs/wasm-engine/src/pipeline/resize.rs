@@ -0,0 +1,199 @@
+//! Resizing source pixels to the model resolution.
+//!
+//! Done in Rust rather than with `drawImage` so the result doesn't depend on
+//! the browser's resampler.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeFilter {
+    Nearest,
+    #[default]
+    Bilinear,
+    /// Catmull-Rom: sharper than bilinear, with less ringing than Lanczos3.
+    Bicubic,
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    fn support(self) -> f32 {
+        match self {
+            ResizeFilter::Nearest => 0.5,
+            ResizeFilter::Bilinear => 1.0,
+            ResizeFilter::Bicubic => 2.0,
+            ResizeFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ResizeFilter::Nearest => unreachable!("nearest doesn't use weights"),
+            ResizeFilter::Bilinear => (1.0 - x).max(0.0),
+            // Keys' cubic convolution with a = -0.5
+            ResizeFilter::Bicubic if x < 1.0 => (1.5 * x - 2.5) * x * x + 1.0,
+            ResizeFilter::Bicubic if x < 2.0 => ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0,
+            ResizeFilter::Bicubic => 0.0,
+            ResizeFilter::Lanczos3 if x < 3.0 => sinc(x) * sinc(x / 3.0),
+            ResizeFilter::Lanczos3 => 0.0,
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * std::f32::consts::PI;
+        x.sin() / x
+    }
+}
+
+/// Scales `width` x `height` down, keeping the aspect ratio, so neither side
+/// exceeds `max_dimension`. 0 means no limit.
+pub fn fit_within(width: u32, height: u32, max_dimension: u32) -> (u32, u32) {
+    if max_dimension == 0 || (width <= max_dimension && height <= max_dimension) {
+        return (width, height);
+    }
+    let scale = max_dimension as f64 / width.max(height) as f64;
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+// Sources are drawn at most this many times the model size; Rust resamples
+// the rest of the way
+const MAX_WORKING_SCALE: u32 = 4;
+
+/// Size to draw a `width` x `height` source at before resizing to the model
+/// input: within `max_dimension` (0 means no limit) and never more than
+/// `MAX_WORKING_SCALE` times the model's larger side, so huge photos don't
+/// have to be read back whole.
+pub fn working_size(
+    width: u32,
+    height: u32,
+    (model_width, model_height): (u32, u32),
+    max_dimension: u32,
+) -> (u32, u32) {
+    let model_cap = model_width
+        .max(model_height)
+        .saturating_mul(MAX_WORKING_SCALE);
+    let cap = match max_dimension {
+        0 => model_cap,
+        max => max.min(model_cap),
+    };
+    fit_within(width, height, cap)
+}
+
+/// Source taps and normalized weights for each output position along one axis.
+fn axis_taps(src_len: u32, dst_len: u32, filter: ResizeFilter) -> Vec<Vec<(usize, f32)>> {
+    let scale = src_len as f32 / dst_len as f32;
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            if filter == ResizeFilter::Nearest {
+                let index = (center as u32).min(src_len - 1) as usize;
+                return vec![(index, 1.0)];
+            }
+
+            // Widen the kernel when shrinking so every source pixel contributes
+            let filter_scale = scale.max(1.0);
+            let support = filter.support() * filter_scale;
+            let first = (center - support).floor().max(0.0) as u32;
+            let last = ((center + support).ceil() as u32).min(src_len);
+            let mut taps: Vec<(usize, f32)> = (first..last)
+                .map(|j| {
+                    let distance = (j as f32 + 0.5 - center) / filter_scale;
+                    (j as usize, filter.weight(distance))
+                })
+                .filter(|&(_, w)| w != 0.0)
+                .collect();
+            let total: f32 = taps.iter().map(|&(_, w)| w).sum();
+            if total == 0.0 {
+                return vec![((center as u32).min(src_len - 1) as usize, 1.0)];
+            }
+            for tap in &mut taps {
+                tap.1 /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+/// Separable resize of interleaved 4-channel pixels. Samples are converted
+/// to f32 as the horizontal pass reads them, so `u8` input is never copied
+/// whole.
+fn resize_f32<T: Copy + Into<f32>>(
+    pixels: &[T],
+    src_width: u32,
+    src_height: u32,
+    dst_width: u32,
+    dst_height: u32,
+    filter: ResizeFilter,
+) -> Vec<f32> {
+    let x_taps = axis_taps(src_width, dst_width, filter);
+    let y_taps = axis_taps(src_height, dst_height, filter);
+
+    let mut horizontal = vec![0.0f32; (dst_width * src_height * 4) as usize];
+    for y in 0..src_height as usize {
+        let row = &pixels[y * src_width as usize * 4..(y + 1) * src_width as usize * 4];
+        for (x, taps) in x_taps.iter().enumerate() {
+            let out = &mut horizontal[(y * dst_width as usize + x) * 4..][..4];
+            for &(j, w) in taps {
+                for c in 0..4 {
+                    out[c] += row[j * 4 + c].into() * w;
+                }
+            }
+        }
+    }
+
+    let mut resized = vec![0.0f32; (dst_width * dst_height * 4) as usize];
+    for (y, taps) in y_taps.iter().enumerate() {
+        for x in 0..dst_width as usize {
+            let out = &mut resized[(y * dst_width as usize + x) * 4..][..4];
+            for &(j, w) in taps {
+                let src = &horizontal[(j * dst_width as usize + x) * 4..][..4];
+                for c in 0..4 {
+                    out[c] += src[c] * w;
+                }
+            }
+        }
+    }
+    resized
+}
+
+/// Resizes RGBA bytes. Same-size input is returned unchanged.
+pub fn resize_rgba(
+    pixels: &[u8],
+    src_width: u32,
+    src_height: u32,
+    dst_width: u32,
+    dst_height: u32,
+    filter: ResizeFilter,
+) -> Vec<u8> {
+    if (src_width, src_height) == (dst_width, dst_height) {
+        return pixels.to_vec();
+    }
+    resize_f32(pixels, src_width, src_height, dst_width, dst_height, filter)
+        .into_iter()
+        .map(|v| v.round().clamp(0.0, 255.0) as u8)
+        .collect()
+}
+
+/// Resizes float RGBA pixels. Lanczos can ring slightly past the input range;
+/// tone mapping takes care of that afterwards.
+pub fn resize_rgba_f32(
+    pixels: &[f32],
+    src_width: u32,
+    src_height: u32,
+    dst_width: u32,
+    dst_height: u32,
+    filter: ResizeFilter,
+) -> Vec<f32> {
+    if (src_width, src_height) == (dst_width, dst_height) {
+        return pixels.to_vec();
+    }
+    resize_f32(pixels, src_width, src_height, dst_width, dst_height, filter)
+}
@@ -0,0 +1,142 @@
+//! Bookkeeping for resuming interrupted downloads with HTTP Range requests.
+
+/// Bytes received so far for a download that may be resumed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PartialDownload {
+    pub bytes: Vec<u8>,
+    /// Full length of the file, when the server said.
+    pub total_len: Option<usize>,
+    /// Validator of the file the bytes came from; sent as `If-Range`.
+    pub etag: Option<String>,
+    /// The server advertised `Accept-Ranges: bytes`.
+    pub resumable: bool,
+}
+
+/// A parsed `Content-Range: bytes start-end/total` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentRange {
+    pub start: usize,
+    pub end: usize,
+    /// `None` for `*`.
+    pub total: Option<usize>,
+}
+
+pub fn parse_content_range(header: &str) -> Option<ContentRange> {
+    let (range, total) = header.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+    if start > end || total.is_some_and(|total| end >= total) {
+        return None;
+    }
+    Some(ContentRange { start, end, total })
+}
+
+/// Headers of a response, as far as resuming cares.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResponseInfo<'a> {
+    pub status: u16,
+    pub content_length: Option<usize>,
+    pub content_range: Option<&'a str>,
+    pub etag: Option<&'a str>,
+    pub accept_ranges: Option<&'a str>,
+    pub content_encoding: Option<&'a str>,
+}
+
+impl ResponseInfo<'_> {
+    /// Whether the browser decodes the body. `Content-Length` and byte ranges
+    /// then count encoded bytes, which the body stream never shows us.
+    pub fn is_encoded(&self) -> bool {
+        self.content_encoding
+            .is_some_and(|encoding| !encoding.trim().eq_ignore_ascii_case("identity"))
+    }
+}
+
+impl PartialDownload {
+    /// The `Range` header for the next request, if there is anything to resume.
+    pub fn range_header(&self) -> Option<String> {
+        (self.resumable && !self.bytes.is_empty()).then(|| format!("bytes={}-", self.bytes.len()))
+    }
+
+    /// Prepares for the body of `response`, which is appended to `bytes`
+    /// afterwards.
+    ///
+    /// A 200 starts over. A 206 must continue exactly where `bytes` ends and
+    /// come from the same file; otherwise the buffer is dropped so the next
+    /// attempt downloads everything again, and an error is returned.
+    ///
+    /// Encoded responses are neither length-checked nor resumed.
+    pub fn begin(&mut self, response: ResponseInfo) -> Result<(), String> {
+        let etag = response.etag.map(str::to_string);
+        if response.status == 206 {
+            let check = || {
+                if self.bytes.is_empty() {
+                    return Err("unexpected partial response".to_string());
+                }
+                if response.is_encoded() {
+                    return Err("partial response with a Content-Encoding".to_string());
+                }
+                if self.etag.is_some() && etag.is_some() && self.etag != etag {
+                    return Err("file changed since the download started".to_string());
+                }
+                let range = response
+                    .content_range
+                    .and_then(parse_content_range)
+                    .ok_or_else(|| "missing or invalid Content-Range".to_string())?;
+                if range.start != self.bytes.len() {
+                    return Err(format!(
+                        "resumed at byte {} instead of {}",
+                        range.start,
+                        self.bytes.len()
+                    ));
+                }
+                if range.total.is_some()
+                    && self.total_len.is_some()
+                    && range.total != self.total_len
+                {
+                    return Err("file length changed since the download started".to_string());
+                }
+                Ok(range.total)
+            };
+            return match check() {
+                Ok(total) => {
+                    self.total_len = total.or(self.total_len);
+                    Ok(())
+                }
+                Err(reason) => {
+                    *self = PartialDownload::default();
+                    Err(reason)
+                }
+            };
+        }
+
+        let encoded = response.is_encoded();
+        let total_len = response.content_length.filter(|_| !encoded);
+        *self = PartialDownload {
+            bytes: Vec::with_capacity(total_len.unwrap_or(0)),
+            total_len,
+            etag,
+            resumable: !encoded
+                && response
+                    .accept_ranges
+                    .is_some_and(|ranges| ranges.split(',').any(|unit| unit.trim() == "bytes")),
+        };
+        Ok(())
+    }
+
+    /// Hands over the assembled file, checking it against the advertised length.
+    pub fn complete(&mut self) -> Result<Vec<u8>, String> {
+        let download = std::mem::take(self);
+        match download.total_len {
+            Some(total) if total != download.bytes.len() => Err(format!(
+                "received {} bytes, expected {}",
+                download.bytes.len(),
+                total
+            )),
+            _ => Ok(download.bytes),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! Retry policy for model downloads.
+
+/// Whether an HTTP status is worth retrying: timeouts, rate limiting and
+/// server errors. Anything else (404, 403, ...) won't change on a retry.
+pub fn is_retryable_status(status: u16) -> bool {
+    status == 408 || status == 429 || (500..600).contains(&status)
+}
+
+/// Delay before retrying after failed attempt number `attempt` (1-based):
+/// exponential backoff from `base_ms`, scaled by a jitter factor in
+/// [0.5, 1.5) drawn from `jitter` in [0, 1).
+pub fn backoff_delay_ms(base_ms: u32, attempt: u32, jitter: f64) -> f64 {
+    let exponent = attempt.saturating_sub(1).min(16);
+    base_ms as f64 * 2f64.powi(exponent as i32) * (0.5 + jitter.clamp(0.0, 1.0))
+}
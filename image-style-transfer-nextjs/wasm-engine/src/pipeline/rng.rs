@@ -0,0 +1,36 @@
+//! Small deterministic PRNG for anything stochastic in the pipeline.
+
+/// Seed used when the caller never picks one.
+pub const DEFAULT_SIMULATION_SEED: u64 = 0x5EED_5EED_5EED_5EED;
+
+/// xorshift64* generator. Not cryptographic, just reproducible across platforms.
+#[derive(Clone, Debug)]
+pub struct XorShift64 {
+    state: u64,
+}
+
+impl XorShift64 {
+    pub fn new(seed: u64) -> Self {
+        // A zero state would only ever produce zeros
+        let state = if seed == 0 {
+            DEFAULT_SIMULATION_SEED
+        } else {
+            seed
+        };
+        XorShift64 { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform value in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
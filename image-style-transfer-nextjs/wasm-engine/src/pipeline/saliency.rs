@@ -0,0 +1,79 @@
+//! A cheap, deterministic saliency proxy used to protect subjects from
+//! heavy stylization.
+
+/// Per-pixel saliency in [0, 1] for an interleaved RGB tensor.
+///
+/// Local contrast (luminance against a box-blurred surround) is combined with
+/// a center prior, since subjects tend to be both distinct and central.
+pub fn saliency_map(tensor: &[f32], width: u32, height: u32) -> Vec<f32> {
+    let (w, h) = (width as usize, height as usize);
+    let luminance: Vec<f32> = tensor
+        .chunks_exact(3)
+        .map(|px| 0.2126 * px[0] + 0.7152 * px[1] + 0.0722 * px[2])
+        .collect();
+
+    // Summed-area table for O(1) box means
+    let mut integral = vec![0.0f64; (w + 1) * (h + 1)];
+    for y in 0..h {
+        let mut row = 0.0f64;
+        for x in 0..w {
+            row += luminance[y * w + x] as f64;
+            integral[(y + 1) * (w + 1) + x + 1] = integral[y * (w + 1) + x + 1] + row;
+        }
+    }
+    let radius = (w.max(h) / 8).max(1);
+
+    let mut contrast = Vec::with_capacity(w * h);
+    for y in 0..h {
+        let (y0, y1) = (y.saturating_sub(radius), (y + radius + 1).min(h));
+        for x in 0..w {
+            let (x0, x1) = (x.saturating_sub(radius), (x + radius + 1).min(w));
+            let sum = integral[y1 * (w + 1) + x1]
+                - integral[y0 * (w + 1) + x1]
+                - integral[y1 * (w + 1) + x0]
+                + integral[y0 * (w + 1) + x0];
+            let mean = sum / ((x1 - x0) * (y1 - y0)) as f64;
+            contrast.push((luminance[y * w + x] as f64 - mean).abs() as f32);
+        }
+    }
+    let max_contrast = contrast.iter().copied().fold(0.0f32, f32::max);
+
+    let mut saliency = Vec::with_capacity(w * h);
+    for y in 0..h {
+        for x in 0..w {
+            // Normalized distance from the center, 1.0 at the edge midpoints
+            let dx = (x as f32 + 0.5) / w as f32 * 2.0 - 1.0;
+            let dy = (y as f32 + 0.5) / h as f32 * 2.0 - 1.0;
+            let center = (-(dx * dx + dy * dy) / (2.0 * 0.45 * 0.45)).exp();
+            let local = if max_contrast > 0.0 {
+                contrast[y * w + x] / max_contrast
+            } else {
+                0.0
+            };
+            saliency.push(center * (0.5 + 0.5 * local));
+        }
+    }
+
+    let max = saliency.iter().copied().fold(0.0f32, f32::max);
+    if max > 0.0 {
+        saliency.iter_mut().for_each(|s| *s /= max);
+    }
+    saliency
+}
+
+/// Multiplies `strengths` by `1 - protection * saliency`, per pixel.
+pub fn attenuate_salient(strengths: &mut [f32], saliency: &[f32], protection: f32) {
+    for (strength, &s) in strengths.iter_mut().zip(saliency) {
+        *strength *= 1.0 - protection * s;
+    }
+}
+
+/// Grayscale RGBA rendering of a [0, 1] map, for debugging.
+pub fn map_to_rgba(map: &[f32]) -> Vec<u8> {
+    map.iter()
+        .flat_map(|&v| {
+            let v = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+            [v, v, v, 255]
+        })
+        .collect()
+}
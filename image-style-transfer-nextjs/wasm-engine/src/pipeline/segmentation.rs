@@ -0,0 +1,43 @@
+//! Reading person segmentation model output as a per-pixel strength.
+
+/// Probability that each pixel shows a person, row-major, with its size,
+/// from a `[1, C, H, W]` output: a single channel of probabilities, or of
+/// logits when any value falls outside [0, 1], or two channels of
+/// background and person scores, which are softmaxed.
+pub fn person_probability(
+    output: &[f32],
+    shape: &[usize],
+) -> Result<(Vec<f32>, (u32, u32)), String> {
+    let &[1, channels, height, width] = shape else {
+        return Err(format!("expected a [1, C, H, W] output, got {:?}", shape));
+    };
+    let plane = width * height;
+    if output.len() != channels * plane || plane == 0 {
+        return Err(format!(
+            "output has {} values, which is not {:?}",
+            output.len(),
+            shape
+        ));
+    }
+    let probability = match channels {
+        1 if output.iter().all(|v| (0.0..=1.0).contains(v)) => output.to_vec(),
+        1 => output.iter().map(|&v| 1.0 / (1.0 + (-v).exp())).collect(),
+        2 => output[..plane]
+            .iter()
+            .zip(&output[plane..])
+            .map(|(&background, &person)| 1.0 / (1.0 + (background - person).exp()))
+            .collect(),
+        _ => return Err(format!("expected 1 or 2 output channels, got {}", channels)),
+    };
+    Ok((probability, (width as u32, height as u32)))
+}
+
+/// Strength for a pixel given its person probability: people are kept and
+/// the background stylized, or the other way round with `invert`.
+pub fn subject_strength(person: f32, invert: bool) -> f32 {
+    if invert {
+        person
+    } else {
+        1.0 - person
+    }
+}
@@ -0,0 +1,144 @@
+//! Checking a model graph's declared shapes against its registry entry.
+
+use std::fmt;
+
+use super::resize::fit_within;
+use super::tensor::TensorLayout;
+use super::ModelMetadata;
+
+/// A tensor shape as declared by a graph; `None` is a symbolic dimension.
+pub type DeclaredShape = Vec<Option<usize>>;
+
+/// Sides of dynamic inputs are rounded down to a multiple of this, so the
+/// strided downsampling and upsampling of style networks gives back the
+/// input size.
+pub const DYNAMIC_SIZE_MULTIPLE: u32 = 8;
+
+/// How a declared shape differs from the `[1, C, H, W]` (or, for NHWC
+/// models, `[1, H, W, C]`) the metadata implies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    /// `"input"` or `"output"`.
+    pub tensor: &'static str,
+    pub expected: [usize; 4],
+    pub actual: DeclaredShape,
+    /// The first mismatched dimension, or `None` when the rank is wrong.
+    pub dimension: Option<&'static str>,
+}
+
+impl fmt::Display for ShapeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "model {} is {} but the metadata expects {:?}",
+            self.tensor,
+            format_shape(&self.actual),
+            self.expected
+        )?;
+        match self.dimension {
+            Some(dimension) => write!(f, " ({} differs)", dimension),
+            None => write!(f, " (rank {} instead of 4)", self.actual.len()),
+        }
+    }
+}
+
+/// `[1, 3, ?, ?]`-style rendering of a declared shape.
+pub fn format_shape(shape: &[Option<usize>]) -> String {
+    let dims: Vec<String> = shape
+        .iter()
+        .map(|dim| dim.map_or("?".to_string(), |dim| dim.to_string()))
+        .collect();
+    format!("[{}]", dims.join(", "))
+}
+
+fn compare(
+    tensor: &'static str,
+    expected: [usize; 4],
+    actual: &[Option<usize>],
+    layout: TensorLayout,
+) -> Result<(), ShapeMismatch> {
+    let mismatch = |dimension| ShapeMismatch {
+        tensor,
+        expected,
+        actual: actual.to_vec(),
+        dimension,
+    };
+    if actual.len() != 4 {
+        return Err(mismatch(None));
+    }
+    // Symbolic dimensions accept whatever the metadata says
+    match (0..4).find(|&i| actual[i].is_some_and(|dim| dim != expected[i])) {
+        Some(i) => Err(mismatch(Some(layout.dimension_names()[i]))),
+        None => Ok(()),
+    }
+}
+
+/// Checks a graph's input and output against `metadata`: both must be
+/// `[1, C, H, W]` (`[1, H, W, C]` for NHWC models), with 3 output channels
+/// and the input's height and width.
+pub fn check_model_shapes(
+    input: &[Option<usize>],
+    output: &[Option<usize>],
+    metadata: &ModelMetadata,
+) -> Result<(), ShapeMismatch> {
+    let (width, height) = (
+        metadata.input_width as usize,
+        metadata.input_height as usize,
+    );
+    let layout = metadata.layout;
+    compare(
+        "input",
+        layout.image_shape(metadata.input_channels as usize, height, width),
+        input,
+        layout,
+    )?;
+    compare(
+        "output",
+        layout.image_shape(3, height, width),
+        output,
+        layout,
+    )
+}
+
+/// `metadata` with its input size and channels taken from the graph, when the
+/// graph's input is a concrete `[1, C, H, W]` (`[1, H, W, C]` for NHWC
+/// models). The corrected entry still has to pass [`check_model_shapes`].
+pub fn corrected_metadata(
+    input: &[Option<usize>],
+    metadata: &ModelMetadata,
+) -> Option<ModelMetadata> {
+    let dims = match (metadata.layout, input) {
+        (TensorLayout::Nchw, &[batch, channels, height, width]) => [batch, channels, height, width],
+        (TensorLayout::Nhwc, &[batch, height, width, channels]) => [batch, channels, height, width],
+        _ => return None,
+    };
+    match dims {
+        [Some(1), Some(channels), Some(height), Some(width)] => Some(ModelMetadata {
+            input_channels: channels as u32,
+            input_height: height as u32,
+            input_width: width as u32,
+            ..metadata.clone()
+        }),
+        _ => None,
+    }
+}
+
+/// Whether a declared image input leaves its height and width symbolic, so
+/// the model runs at whatever size it is given.
+pub fn has_dynamic_size(input: &[Option<usize>], layout: TensorLayout) -> bool {
+    matches!(
+        (layout, input),
+        (TensorLayout::Nchw, &[_, _, None, None]) | (TensorLayout::Nhwc, &[_, None, None, _])
+    )
+}
+
+/// The size a model with a dynamic input runs a `width` x `height` image at:
+/// its own, within `max_side` (0 means no limit), with both sides rounded
+/// down to a multiple of [`DYNAMIC_SIZE_MULTIPLE`].
+pub fn dynamic_input_size(width: u32, height: u32, max_side: u32) -> (u32, u32) {
+    let (width, height) = fit_within(width, height, max_side);
+    let round = |side: u32| {
+        (side / DYNAMIC_SIZE_MULTIPLE * DYNAMIC_SIZE_MULTIPLE).max(DYNAMIC_SIZE_MULTIPLE)
+    };
+    (round(width), round(height))
+}
@@ -0,0 +1,212 @@
+//! 128-bit wasm SIMD versions of the per-pixel loops in `tensor`.
+//!
+//! They exist when the crate is built with the `simd128` feature for a
+//! target with `simd128` enabled (`RUSTFLAGS="-C target-feature=+simd128"`).
+//! Each one handles as much of its input as fills whole vectors and returns
+//! how much that was; the caller's scalar loop finishes the rest, which in
+//! other builds is all of it. Browsers without SIMD can't instantiate such a
+//! module at all, so the app picks between the two builds when it loads.
+
+use super::color::ColorSpace;
+
+/// Whether the vectorized loops are compiled in.
+pub const ENABLED: bool = cfg!(all(
+    feature = "simd128",
+    target_arch = "wasm32",
+    target_feature = "simd128"
+));
+
+#[cfg(all(
+    feature = "simd128",
+    target_arch = "wasm32",
+    target_feature = "simd128"
+))]
+mod imp {
+    use core::arch::wasm32::*;
+
+    use super::ColorSpace;
+
+    const GAMMA: f32 = 2.2;
+
+    fn load(values: &[f32]) -> v128 {
+        debug_assert!(values.len() >= 4);
+        // SAFETY: v128_load has no alignment requirement and 16 bytes are in bounds
+        unsafe { v128_load(values.as_ptr() as *const v128) }
+    }
+
+    fn store(value: v128, out: &mut Vec<f32>) {
+        let mut lanes = [0.0f32; 4];
+        // SAFETY: `lanes` is 16 bytes
+        unsafe { v128_store(lanes.as_mut_ptr() as *mut v128, value) };
+        out.extend_from_slice(&lanes);
+    }
+
+    /// Bytes of RGBA pixels consumed; pushes their normalized RGB.
+    pub fn rgba_to_tensor(pixels: &[u8], tensor: &mut Vec<f32>) -> usize {
+        let scale = f32x4_splat(255.0);
+        let chunks = pixels.chunks_exact(16);
+        let done = pixels.len() - chunks.remainder().len();
+        for chunk in chunks {
+            // SAFETY: the chunk is 16 bytes
+            let bytes = unsafe { v128_load(chunk.as_ptr() as *const v128) };
+            let (low, high) = (
+                u16x8_extend_low_u8x16(bytes),
+                u16x8_extend_high_u8x16(bytes),
+            );
+            // One pixel per vector, divided rather than multiplied by the
+            // reciprocal so the values match the scalar loop exactly
+            let [p0, p1, p2, p3] = [
+                u32x4_extend_low_u16x8(low),
+                u32x4_extend_high_u16x8(low),
+                u32x4_extend_low_u16x8(high),
+                u32x4_extend_high_u16x8(high),
+            ]
+            .map(|pixel| f32x4_div(f32x4_convert_u32x4(pixel), scale));
+            // Drop the alphas: r0 g0 b0 r1 | g1 b1 r2 g2 | b2 r3 g3 b3
+            store(i32x4_shuffle::<0, 1, 2, 4>(p0, p1), tensor);
+            store(i32x4_shuffle::<1, 2, 4, 5>(p1, p2), tensor);
+            store(i32x4_shuffle::<2, 4, 5, 6>(p2, p3), tensor);
+        }
+        done
+    }
+
+    /// Pixels written to the front of `pixels` as opaque RGBA.
+    pub fn tensor_to_rgba(tensor: &[f32], pixel_count: usize, pixels: &mut [u8]) -> usize {
+        let groups = pixel_count.min(tensor.len() / 3) / 4;
+        let (zero, scale) = (f32x4_splat(0.0), f32x4_splat(255.0));
+        // Truncates like `as u8`, with NaN becoming 0
+        let quantize = |values: &[f32]| {
+            let scaled = f32x4_min(f32x4_max(f32x4_mul(load(values), scale), zero), scale);
+            i32x4_trunc_sat_f32x4(scaled)
+        };
+        // r0 g0 b0 _ r1 g1 b1 _ ..., the gaps zeroed by out of range indices
+        let spread = u8x16(0, 1, 2, 16, 3, 4, 5, 16, 6, 7, 8, 16, 9, 10, 11, 16);
+        let alpha = u32x4_splat(0xff00_0000);
+        for (group, out) in pixels[..groups * 16].chunks_exact_mut(16).enumerate() {
+            let values = &tensor[group * 12..group * 12 + 12];
+            let rgb = u8x16_narrow_i16x8(
+                i16x8_narrow_i32x4(quantize(&values[0..4]), quantize(&values[4..8])),
+                i16x8_narrow_i32x4(quantize(&values[8..12]), zero),
+            );
+            let rgba = v128_or(i8x16_swizzle(rgb, spread), alpha);
+            // SAFETY: the chunk is 16 bytes
+            unsafe { v128_store(out.as_mut_ptr() as *mut v128, rgba) };
+        }
+        groups * 4
+    }
+
+    /// Values of the blend consumed; pushes their results. Per-pixel
+    /// strengths are left to the scalar loop.
+    pub fn blend(
+        original: &[f32],
+        stylized: &[f32],
+        strength: f32,
+        space: ColorSpace,
+        out: &mut Vec<f32>,
+    ) -> usize {
+        let len = original.len().min(stylized.len()) / 4 * 4;
+        let (zero, one) = (f32x4_splat(0.0), f32x4_splat(1.0));
+        let (keep, take) = (f32x4_splat(1.0 - strength), f32x4_splat(strength));
+        for i in (0..len).step_by(4) {
+            let (orig, style) = (load(&original[i..]), load(&stylized[i..]));
+            let blended = match space {
+                ColorSpace::Linear => f32x4_add(f32x4_mul(orig, keep), f32x4_mul(style, take)),
+                ColorSpace::Srgb => {
+                    let mixed = f32x4_add(
+                        f32x4_mul(pow(orig, GAMMA), keep),
+                        f32x4_mul(pow(style, GAMMA), take),
+                    );
+                    pow(mixed, 1.0 / GAMMA)
+                }
+            };
+            // Pseudo-min/max keep NaN and -0.0 the way f32::clamp does
+            store(f32x4_pmin(f32x4_pmax(blended, zero), one), out);
+        }
+        len
+    }
+
+    /// `x^exponent` as `exp2(exponent * log2(x))`, to about 1e-6 of `powf`
+    /// relatively. Zero stays zero and negative bases give NaN.
+    fn pow(x: v128, exponent: f32) -> v128 {
+        let zero = f32x4_splat(0.0);
+        let positive = exp2(f32x4_mul(log2(x), f32x4_splat(exponent)));
+        let nonpositive = v128_bitselect(zero, f32x4_splat(f32::NAN), f32x4_eq(x, zero));
+        v128_bitselect(positive, nonpositive, f32x4_gt(x, zero))
+    }
+
+    fn log2(x: v128) -> v128 {
+        let one = f32x4_splat(1.0);
+        // x = m * 2^e with m in [1, 2), then moved into [sqrt(1/2), sqrt(2))
+        let exponent = i32x4_sub(u32x4_shr(x, 23), i32x4_splat(127));
+        let mantissa = v128_or(
+            v128_and(x, i32x4_splat(0x007f_ffff)),
+            i32x4_splat(0x3f80_0000),
+        );
+        let high = f32x4_gt(mantissa, f32x4_splat(std::f32::consts::SQRT_2));
+        let m = v128_bitselect(f32x4_mul(mantissa, f32x4_splat(0.5)), mantissa, high);
+        // `high` lanes are -1
+        let e = f32x4_convert_i32x4(i32x4_sub(exponent, high));
+        // ln(m) = 2 atanh(t) for t = (m - 1) / (m + 1), |t| < 0.172
+        let t = f32x4_div(f32x4_sub(m, one), f32x4_add(m, one));
+        let t2 = f32x4_mul(t, t);
+        let series = [1.0 / 7.0, 1.0 / 5.0, 1.0 / 3.0, 1.0]
+            .iter()
+            .fold(f32x4_splat(1.0 / 9.0), |sum, &c| {
+                f32x4_add(f32x4_mul(sum, t2), f32x4_splat(c))
+            });
+        let ln = f32x4_mul(f32x4_mul(t, series), f32x4_splat(2.0));
+        f32x4_add(f32x4_mul(ln, f32x4_splat(std::f32::consts::LOG2_E)), e)
+    }
+
+    fn exp2(y: v128) -> v128 {
+        // Keeps 2^n a normal float
+        let y = f32x4_min(f32x4_max(y, f32x4_splat(-126.0)), f32x4_splat(127.0));
+        let n = f32x4_nearest(y);
+        let f = f32x4_mul(f32x4_sub(y, n), f32x4_splat(std::f32::consts::LN_2));
+        // e^f for |f| <= ln(2) / 2
+        let taylor = [
+            1.0 / 720.0,
+            1.0 / 120.0,
+            1.0 / 24.0,
+            1.0 / 6.0,
+            1.0 / 2.0,
+            1.0,
+            1.0,
+        ]
+        .iter()
+        .fold(f32x4_splat(1.0 / 5040.0), |sum, &c| {
+            f32x4_add(f32x4_mul(sum, f), f32x4_splat(c))
+        });
+        let scale = i32x4_shl(i32x4_add(i32x4_trunc_sat_f32x4(n), i32x4_splat(127)), 23);
+        f32x4_mul(taylor, scale)
+    }
+}
+
+#[cfg(not(all(
+    feature = "simd128",
+    target_arch = "wasm32",
+    target_feature = "simd128"
+)))]
+mod imp {
+    use super::ColorSpace;
+
+    pub fn rgba_to_tensor(_pixels: &[u8], _tensor: &mut Vec<f32>) -> usize {
+        0
+    }
+
+    pub fn tensor_to_rgba(_tensor: &[f32], _pixel_count: usize, _pixels: &mut [u8]) -> usize {
+        0
+    }
+
+    pub fn blend(
+        _original: &[f32],
+        _stylized: &[f32],
+        _strength: f32,
+        _space: ColorSpace,
+        _out: &mut Vec<f32>,
+    ) -> usize {
+        0
+    }
+}
+
+pub use imp::{blend, rgba_to_tensor, tensor_to_rgba};
@@ -0,0 +1,211 @@
+//! Hand-written filters used when no ONNX model can run.
+//!
+//! Every simulated style is a [`SimulatedStyleConfig`]; the built-in styles
+//! are just presets of it. Stages run in a fixed order per value: channel
+//! gain, swirl, glow, soft light, offset, block fragmentation, grain,
+//! quantization, tone split, and a final clamp to [0, 1]. A stage whose
+//! amount is zero is skipped entirely.
+
+use serde::{Deserialize, Serialize};
+
+use super::rng::XorShift64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SimulatedStyleConfig {
+    /// Multiplier per RGB channel.
+    pub channel_gain: [f32; 3],
+    /// Spatial frequency of the sin(x) + cos(y) swirl.
+    pub swirl_frequency: f32,
+    pub swirl_amplitude: f32,
+    /// Diagonal |sin((x + y) * frequency)| glow.
+    pub glow_frequency: f32,
+    pub glow_amplitude: f32,
+    /// How much of the glow each RGB channel receives.
+    pub glow_channels: [f32; 3],
+    /// Slow sinusoid over the pixel index, scaled by `soft_light_amount`.
+    pub soft_light_frequency: f32,
+    pub soft_light_amount: f32,
+    /// Constant added after the additive stages.
+    pub offset: f32,
+    /// Checkerboard block size in pixels; 0 disables fragmentation.
+    pub block_size: u32,
+    /// Gain for blocks on the checkerboard diagonal.
+    pub block_edge_gain: f32,
+    /// Gain and offset for the remaining blocks.
+    pub block_fill_gain: f32,
+    pub block_fill_offset: f32,
+    /// Amplitude of uniform noise drawn from the seeded generator.
+    pub grain: f32,
+    /// Number of levels values are rounded to; 0 disables quantization.
+    pub quantization_levels: u32,
+    /// Values above the threshold get `highlight_gain`, the rest `shadow_gain`.
+    pub highlight_threshold: f32,
+    pub highlight_gain: f32,
+    pub shadow_gain: f32,
+}
+
+impl Default for SimulatedStyleConfig {
+    /// The neutral config: every stage is a no-op apart from the final clamp.
+    fn default() -> Self {
+        SimulatedStyleConfig {
+            channel_gain: [1.0, 1.0, 1.0],
+            swirl_frequency: 0.0,
+            swirl_amplitude: 0.0,
+            glow_frequency: 0.0,
+            glow_amplitude: 0.0,
+            glow_channels: [1.0, 1.0, 1.0],
+            soft_light_frequency: 0.0,
+            soft_light_amount: 0.0,
+            offset: 0.0,
+            block_size: 0,
+            block_edge_gain: 1.0,
+            block_fill_gain: 1.0,
+            block_fill_offset: 0.0,
+            grain: 0.0,
+            quantization_levels: 0,
+            highlight_threshold: 0.5,
+            highlight_gain: 1.0,
+            shadow_gain: 1.0,
+        }
+    }
+}
+
+impl SimulatedStyleConfig {
+    /// The preset for one of the built-in styles.
+    pub fn preset(style_name: &str) -> Option<Self> {
+        let neutral = SimulatedStyleConfig::default();
+        let config = match style_name {
+            // Van Gogh's swirling brushstrokes and color enhancement
+            "van_gogh_starry_night" => SimulatedStyleConfig {
+                channel_gain: [1.4, 1.2, 1.1],
+                swirl_frequency: 0.02,
+                swirl_amplitude: 0.1,
+                offset: 0.1,
+                ..neutral
+            },
+            // Geometric fragmentation and high contrast
+            "picasso_cubist" => SimulatedStyleConfig {
+                block_size: 16,
+                block_edge_gain: 2.0,
+                block_fill_gain: 0.6,
+                block_fill_offset: 0.2,
+                ..neutral
+            },
+            // Neon glow on red/magenta and blue/cyan, green pulled down
+            "cyberpunk_neon" => SimulatedStyleConfig {
+                channel_gain: [1.3, 0.8, 1.5],
+                glow_frequency: 0.01,
+                glow_amplitude: 0.2,
+                glow_channels: [1.0, 0.0, 1.0],
+                ..neutral
+            },
+            // Impressionist soft brushwork
+            "monet_water_lilies" => SimulatedStyleConfig {
+                channel_gain: [1.1, 1.1, 1.1],
+                soft_light_frequency: 0.001,
+                soft_light_amount: 0.05,
+                ..neutral
+            },
+            // Anime color saturation and cel-shading
+            "anime_studio_ghibli" => SimulatedStyleConfig {
+                quantization_levels: 6,
+                highlight_threshold: 0.5,
+                highlight_gain: 1.3,
+                shadow_gain: 0.9,
+                ..neutral
+            },
+            // Teal/orange grade with a horizontal swirl
+            "cinematic_widescreen" => SimulatedStyleConfig {
+                channel_gain: [1.1, 1.0, 0.9],
+                swirl_frequency: 0.05,
+                swirl_amplitude: 0.05,
+                highlight_threshold: 0.6,
+                highlight_gain: 1.1,
+                shadow_gain: 0.85,
+                ..neutral
+            },
+            _ => return None,
+        };
+        Some(config)
+    }
+
+    fn apply(
+        &self,
+        pixel: f32,
+        channel: usize,
+        x: usize,
+        y: usize,
+        position: usize,
+        rng: &mut XorShift64,
+    ) -> f32 {
+        let mut value = pixel * self.channel_gain[channel];
+
+        if self.swirl_amplitude != 0.0 {
+            let swirl_x = (x as f32 * self.swirl_frequency).sin() * self.swirl_amplitude;
+            let swirl_y = (y as f32 * self.swirl_frequency).cos() * self.swirl_amplitude;
+            value = value + swirl_x + swirl_y;
+        }
+        if self.glow_amplitude != 0.0 && self.glow_channels[channel] != 0.0 {
+            let glow =
+                ((x as f32 + y as f32) * self.glow_frequency).sin().abs() * self.glow_amplitude;
+            value += glow * self.glow_channels[channel];
+        }
+        if self.soft_light_amount != 0.0 {
+            value += self.soft_light_amount
+                * (1.0 + (position as f32 * self.soft_light_frequency).sin());
+        }
+        if self.offset != 0.0 {
+            value += self.offset;
+        }
+        if self.block_size > 0 {
+            let block_size = self.block_size as usize;
+            let block_x = (x / block_size) * block_size;
+            let block_y = (y / block_size) * block_size;
+            if (block_x + block_y).is_multiple_of(block_size * 2) {
+                value *= self.block_edge_gain;
+            } else {
+                value = value * self.block_fill_gain + self.block_fill_offset;
+            }
+        }
+        if self.grain != 0.0 {
+            value += (rng.next_f32() - 0.5) * self.grain;
+        }
+        if self.quantization_levels > 0 {
+            let levels = self.quantization_levels as f32;
+            value = (value * levels).round() / levels;
+        }
+        if value > self.highlight_threshold {
+            value *= self.highlight_gain;
+        } else {
+            value *= self.shadow_gain;
+        }
+
+        value.clamp(0.0, 1.0)
+    }
+}
+
+/// Applies `config` to an interleaved RGB tensor `width` pixels wide.
+///
+/// The output is a pure function of the input, config, width and `seed`: any
+/// stochastic element draws from the generator seeded here.
+pub fn simulate_style(
+    input_tensor: &[f32],
+    config: &SimulatedStyleConfig,
+    width: u32,
+    seed: u64,
+) -> Vec<f32> {
+    let mut rng = XorShift64::new(seed);
+
+    input_tensor
+        .iter()
+        .enumerate()
+        .map(|(i, &pixel)| {
+            let channel = i % 3;
+            let position = i / 3;
+            let x = position % width as usize;
+            let y = position / width as usize;
+            config.apply(pixel, channel, x, y, position, &mut rng)
+        })
+        .collect()
+}
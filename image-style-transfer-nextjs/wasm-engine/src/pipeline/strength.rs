@@ -0,0 +1,109 @@
+//! Spatially varying strength.
+
+/// Per-pixel strength values in row-major order, at any resolution.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrengthMap {
+    values: Vec<f32>,
+    width: u32,
+    height: u32,
+}
+
+impl StrengthMap {
+    pub fn new(values: Vec<f32>, width: u32, height: u32) -> Result<StrengthMap, String> {
+        if width == 0 || height == 0 || values.len() != (width * height) as usize {
+            return Err(format!(
+                "strength map has {} values, which is not {}x{}",
+                values.len(),
+                width,
+                height
+            ));
+        }
+        Ok(StrengthMap {
+            values,
+            width,
+            height,
+        })
+    }
+
+    /// Reads a grayscale mask from RGBA pixels: white styles fully, black not
+    /// at all, and transparent pixels count as black so cutouts work as
+    /// masks. `invert` swaps the two.
+    pub fn from_mask(
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        invert: bool,
+    ) -> Result<StrengthMap, String> {
+        let values = pixels
+            .chunks_exact(4)
+            .map(|px| {
+                let luma = 0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32;
+                let value = luma * px[3] as f32 / (255.0 * 255.0);
+                if invert {
+                    1.0 - value
+                } else {
+                    value
+                }
+            })
+            .collect();
+        StrengthMap::new(values, width, height)
+    }
+
+    /// Builds a map whose width isn't known: an exact match for one of the
+    /// `candidates` sizes wins, otherwise the aspect ratio of the first
+    /// candidate is assumed.
+    pub fn infer(values: Vec<f32>, candidates: &[(u32, u32)]) -> Result<StrengthMap, String> {
+        if let Some(&(width, height)) = candidates
+            .iter()
+            .find(|(w, h)| (w * h) as usize == values.len())
+        {
+            return StrengthMap::new(values, width, height);
+        }
+        let &(width, height) = candidates
+            .first()
+            .ok_or_else(|| "no size to infer the strength map shape from".to_string())?;
+        let aspect = width as f64 / height as f64;
+        let inferred_width = ((values.len() as f64 * aspect).sqrt().round() as u32).max(1);
+        let inferred_height = values.len() as u32 / inferred_width;
+        StrengthMap::new(values, inferred_width, inferred_height)
+    }
+
+    /// The map with every value passed through `map`.
+    pub fn map_values(&self, map: impl Fn(f32) -> f32) -> StrengthMap {
+        StrengthMap {
+            values: self.values.iter().map(|&v| map(v)).collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Bilinearly resamples to `width` x `height`, clamping values to [0, 1].
+    pub fn resampled(&self, width: u32, height: u32) -> Vec<f32> {
+        let sample = |x: u32, y: u32| self.values[(y * self.width + x) as usize].clamp(0.0, 1.0);
+        if (width, height) == (self.width, self.height) {
+            return (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| sample(x, y))
+                .collect();
+        }
+
+        // Pixel centers map onto pixel centers
+        let scale_x = self.width as f32 / width as f32;
+        let scale_y = self.height as f32 / height as f32;
+        let mut out = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            let sy = ((y as f32 + 0.5) * scale_y - 0.5).clamp(0.0, (self.height - 1) as f32);
+            let (y0, fy) = (sy.floor() as u32, sy.fract());
+            let y1 = (y0 + 1).min(self.height - 1);
+            for x in 0..width {
+                let sx = ((x as f32 + 0.5) * scale_x - 0.5).clamp(0.0, (self.width - 1) as f32);
+                let (x0, fx) = (sx.floor() as u32, sx.fract());
+                let x1 = (x0 + 1).min(self.width - 1);
+                let top = sample(x0, y0) * (1.0 - fx) + sample(x1, y0) * fx;
+                let bottom = sample(x0, y1) * (1.0 - fx) + sample(x1, y1) * fx;
+                out.push(top * (1.0 - fy) + bottom * fy);
+            }
+        }
+        out
+    }
+}
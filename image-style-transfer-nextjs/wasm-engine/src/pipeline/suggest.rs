@@ -0,0 +1,194 @@
+//! Style suggestions from cheap image statistics.
+
+use serde::{Deserialize, Serialize};
+
+use super::metadata::ModelMetadata;
+
+/// Summary statistics of a (small) image.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImageStats {
+    /// Mean Rec. 709 luminance in [0, 1].
+    pub mean_luminance: f32,
+    /// Mean HSV saturation in [0, 1].
+    pub mean_saturation: f32,
+    /// Fraction of pixels whose Sobel gradient marks an edge.
+    pub edge_density: f32,
+    /// Saturation-weighted share of warm (red to yellow) hues among
+    /// all colored pixels, in [0, 1]; 0.5 when the image is gray.
+    pub warm_share: f32,
+}
+
+// Sobel magnitude (on [0, 1] luminance) above which a pixel counts as an edge
+const EDGE_THRESHOLD: f32 = 0.25;
+
+impl ImageStats {
+    pub fn from_rgba(pixels: &[u8], width: u32, height: u32) -> ImageStats {
+        let (width, height) = (width as usize, height as usize);
+        let pixel_count = (width * height).max(1) as f32;
+        let mut luminance = Vec::with_capacity(width * height);
+        let (mut saturation_sum, mut warm, mut cool) = (0.0f32, 0.0f32, 0.0f32);
+
+        for px in pixels.chunks_exact(4) {
+            let (r, g, b) = (
+                px[0] as f32 / 255.0,
+                px[1] as f32 / 255.0,
+                px[2] as f32 / 255.0,
+            );
+            luminance.push(0.2126 * r + 0.7152 * g + 0.0722 * b);
+
+            let max = r.max(g).max(b);
+            let min = r.min(g).min(b);
+            let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+            saturation_sum += saturation;
+            if max > min {
+                let hue = if max == r {
+                    60.0 * ((g - b) / (max - min)).rem_euclid(6.0)
+                } else if max == g {
+                    60.0 * ((b - r) / (max - min) + 2.0)
+                } else {
+                    60.0 * ((r - g) / (max - min) + 4.0)
+                };
+                if !(75.0..330.0).contains(&hue) {
+                    warm += saturation;
+                } else if (150.0..270.0).contains(&hue) {
+                    cool += saturation;
+                }
+            }
+        }
+
+        let mut edges = 0usize;
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                let l = |dx: usize, dy: usize| luminance[(y + dy - 1) * width + x + dx - 1];
+                let gx = l(2, 0) + 2.0 * l(2, 1) + l(2, 2) - l(0, 0) - 2.0 * l(0, 1) - l(0, 2);
+                let gy = l(0, 2) + 2.0 * l(1, 2) + l(2, 2) - l(0, 0) - 2.0 * l(1, 0) - l(2, 0);
+                if (gx * gx + gy * gy).sqrt() > EDGE_THRESHOLD {
+                    edges += 1;
+                }
+            }
+        }
+
+        ImageStats {
+            mean_luminance: luminance.iter().sum::<f32>() / pixel_count,
+            mean_saturation: saturation_sum / pixel_count,
+            edge_density: edges as f32 / pixel_count,
+            warm_share: if warm + cool > 0.0 {
+                warm / (warm + cool)
+            } else {
+                0.5
+            },
+        }
+    }
+}
+
+/// How much a style likes each image trait. Positive weights favor bright,
+/// saturated, detailed or warm images, negative ones the opposite; features
+/// are centered so each term contributes at most its weight.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct StyleAffinity {
+    pub brightness: f32,
+    pub saturation: f32,
+    pub edge_density: f32,
+    pub warmth: f32,
+    /// Added to every score, e.g. to promote a house style.
+    pub bias: f32,
+}
+
+impl StyleAffinity {
+    /// Heuristics for the built-in styles.
+    pub fn preset(name: &str) -> Option<StyleAffinity> {
+        let (brightness, saturation, edge_density, warmth) = match name {
+            "van_gogh_starry_night" => (-0.4, 0.3, 0.4, -0.4),
+            "picasso_cubist" => (0.0, -0.2, 0.7, 0.2),
+            "cyberpunk_neon" => (-0.9, -0.5, 0.2, -0.3),
+            "monet_water_lilies" => (0.6, 0.2, -0.7, -0.1),
+            "anime_studio_ghibli" => (0.5, 0.5, -0.2, 0.3),
+            _ => return None,
+        };
+        Some(StyleAffinity {
+            brightness,
+            saturation,
+            edge_density,
+            warmth,
+            bias: 0.0,
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub name: String,
+    pub score: f32,
+    pub reasons: Vec<String>,
+}
+
+// A term has to move the score this much to be worth mentioning
+const REASON_THRESHOLD: f32 = 0.05;
+
+fn score(stats: &ImageStats, affinity: &StyleAffinity) -> Suggestion {
+    // Each feature in [-1, 1]; edge density rarely exceeds ~0.3 in photos
+    let terms = [
+        (
+            affinity.brightness,
+            stats.mean_luminance * 2.0 - 1.0,
+            ("bright image", "dark image"),
+        ),
+        (
+            affinity.saturation,
+            stats.mean_saturation * 2.0 - 1.0,
+            ("vivid colors", "muted colors"),
+        ),
+        (
+            affinity.edge_density,
+            (stats.edge_density / 0.3).min(1.0) * 2.0 - 1.0,
+            ("lots of fine detail", "smooth, low-detail areas"),
+        ),
+        (
+            affinity.warmth,
+            stats.warm_share * 2.0 - 1.0,
+            ("warm palette", "cool palette"),
+        ),
+    ];
+
+    let mut contributions: Vec<(f32, String)> = terms
+        .iter()
+        .map(|&(weight, feature, (high, low))| {
+            let label = if feature >= 0.0 { high } else { low };
+            (weight * feature, label.to_string())
+        })
+        .collect();
+    let score = affinity.bias + contributions.iter().map(|(c, _)| c).sum::<f32>();
+
+    contributions.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let reasons = contributions
+        .into_iter()
+        .filter(|(c, _)| *c >= REASON_THRESHOLD)
+        .map(|(_, label)| label)
+        .collect();
+    Suggestion {
+        name: String::new(),
+        score,
+        reasons,
+    }
+}
+
+/// Scores every registry entry against `stats`, best first. Entries without
+/// a `style_affinity` score neutrally.
+pub fn rank_styles(
+    stats: &ImageStats,
+    registry: &[ModelMetadata],
+    top_n: usize,
+) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = registry
+        .iter()
+        .map(|metadata| Suggestion {
+            name: metadata.name.clone(),
+            ..score(stats, &metadata.style_affinity.unwrap_or_default())
+        })
+        .collect();
+    // Stable sort keeps registry order between equal scores
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(top_n);
+    suggestions
+}
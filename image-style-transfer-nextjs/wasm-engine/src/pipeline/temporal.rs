@@ -0,0 +1,146 @@
+//! Damping frame-to-frame flicker when stylizing a sequence.
+
+/// Frames over which the blend ramps up to its full weight.
+pub const RAMP_FRAMES: u32 = 4;
+
+/// Exponential moving average over a sequence's stylized tensors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemporalBlend {
+    /// Weight of the previous frame, in [0, 1).
+    blend_previous: f32,
+    /// Mean per-channel input change at which a pixel stops taking anything
+    /// from the previous frame; `None` blends every pixel alike.
+    motion_threshold: Option<f32>,
+    previous: Option<Vec<f32>>,
+    /// The input `previous` was stylized from, to measure motion against.
+    previous_input: Option<Vec<f32>>,
+    size: (u32, u32),
+    /// Frames blended since the last reset.
+    frames: u32,
+    last_effective: f32,
+}
+
+impl TemporalBlend {
+    /// `blend_previous` must be finite; it is clamped to [0, 0.95] so new
+    /// frames always show through.
+    pub fn new(blend_previous: f32) -> Result<TemporalBlend, String> {
+        if !blend_previous.is_finite() {
+            return Err(format!("blend_previous {} is not a number", blend_previous));
+        }
+        Ok(TemporalBlend {
+            blend_previous: blend_previous.clamp(0.0, 0.95),
+            motion_threshold: None,
+            previous: None,
+            previous_input: None,
+            size: (0, 0),
+            frames: 0,
+            last_effective: 0.0,
+        })
+    }
+
+    /// Fades the previous frame out of pixels whose input changed, by up to
+    /// `threshold` (mean absolute change per channel, in [0, 1]), so moving
+    /// subjects don't leave ghosts while static areas stay smoothed.
+    pub fn with_motion_threshold(mut self, threshold: f32) -> Result<TemporalBlend, String> {
+        if !threshold.is_finite() || threshold <= 0.0 {
+            return Err(format!("motion threshold {} must be positive", threshold));
+        }
+        self.motion_threshold = Some(threshold);
+        Ok(self)
+    }
+
+    /// The weight the previous frame gets after `frames` frames: none for
+    /// the first, then ramping linearly to `blend_previous`.
+    pub fn effective_blend(&self, frames: u32) -> f32 {
+        self.blend_previous * frames.min(RAMP_FRAMES) as f32 / RAMP_FRAMES as f32
+    }
+
+    /// Blends `stylized` (at `size`) with the previous result and remembers
+    /// the outcome. A size change starts over.
+    pub fn apply(&mut self, stylized: Vec<f32>, size: (u32, u32)) -> Vec<f32> {
+        self.blend(stylized, None, size)
+    }
+
+    /// [`apply`](Self::apply) for an RGB `stylized` tensor made from `input`,
+    /// weighting each pixel down by how much its input moved when a motion
+    /// threshold is set.
+    pub fn apply_with_input(
+        &mut self,
+        stylized: Vec<f32>,
+        input: &[f32],
+        size: (u32, u32),
+    ) -> Vec<f32> {
+        self.blend(stylized, Some(input), size)
+    }
+
+    fn blend(
+        &mut self,
+        mut stylized: Vec<f32>,
+        input: Option<&[f32]>,
+        size: (u32, u32),
+    ) -> Vec<f32> {
+        if size != self.size {
+            self.reset();
+            self.size = size;
+        }
+        let weight = match &self.previous {
+            Some(previous) if previous.len() == stylized.len() => {
+                let weight = self.effective_blend(self.frames);
+                let moved = match (self.motion_threshold, input, &self.previous_input) {
+                    (Some(threshold), Some(input), Some(previous_input))
+                        if input.len() == stylized.len() && previous_input.len() == input.len() =>
+                    {
+                        Some((threshold, input, previous_input))
+                    }
+                    _ => None,
+                };
+                for (index, (pixel, previous)) in stylized
+                    .chunks_exact_mut(3)
+                    .zip(previous.chunks_exact(3))
+                    .enumerate()
+                {
+                    let weight = match moved {
+                        Some((threshold, input, previous_input)) => {
+                            let channels = index * 3..index * 3 + 3;
+                            let change = input[channels.clone()]
+                                .iter()
+                                .zip(&previous_input[channels])
+                                .map(|(now, before)| (now - before).abs())
+                                .sum::<f32>()
+                                / 3.0;
+                            weight * (1.0 - (change / threshold).min(1.0))
+                        }
+                        None => weight,
+                    };
+                    for (value, previous) in pixel.iter_mut().zip(previous) {
+                        *value = *value * (1.0 - weight) + previous * weight;
+                    }
+                }
+                weight
+            }
+            _ => 0.0,
+        };
+        self.previous = Some(stylized.clone());
+        self.previous_input = input.map(<[f32]>::to_vec);
+        self.frames += 1;
+        self.last_effective = weight;
+        stylized
+    }
+
+    /// The weight used by the latest [`apply`](Self::apply).
+    pub fn last_effective_blend(&self) -> f32 {
+        self.last_effective
+    }
+
+    /// Frames blended since the last reset.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.previous_input = None;
+        self.frames = 0;
+        self.last_effective = 0.0;
+    }
+}
@@ -0,0 +1,342 @@
+//! Conversions between canvas pixels and normalized tensors, plus strength blending.
+
+use serde::{Deserialize, Serialize};
+
+use super::color::ColorSpace;
+use super::simd;
+
+/// How float (possibly HDR) pixel values are brought into [0, 1].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMap {
+    /// Clip to [0, 1], which is what 8-bit canvases already do.
+    #[default]
+    Clamp,
+    /// `v / (1 + v)`.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+impl ToneMap {
+    pub fn apply(self, v: f32) -> f32 {
+        let v = v.max(0.0);
+        match self {
+            ToneMap::Clamp => v.min(1.0),
+            ToneMap::Reinhard => v / (1.0 + v),
+            ToneMap::Aces => ((v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14)).min(1.0),
+        }
+    }
+}
+
+/// Converts RGBA bytes into a normalized, interleaved RGB tensor (alpha is dropped).
+pub fn rgba_to_tensor(pixels: &[u8]) -> Vec<f32> {
+    let mut tensor = Vec::with_capacity(pixels.len() / 4 * 3);
+    let done = simd::rgba_to_tensor(pixels, &mut tensor);
+    for px in pixels[done..].chunks_exact(4) {
+        tensor.push(px[0] as f32 / 255.0);
+        tensor.push(px[1] as f32 / 255.0);
+        tensor.push(px[2] as f32 / 255.0);
+    }
+    tensor
+}
+
+/// Converts float RGBA pixels (1.0 = SDR white) into a normalized, interleaved
+/// RGB tensor, tone mapping each channel. Alpha is dropped.
+pub fn float_rgba_to_tensor(pixels: &[f32], tone_map: ToneMap) -> Vec<f32> {
+    let mut tensor = Vec::with_capacity(pixels.len() / 4 * 3);
+    for px in pixels.chunks_exact(4) {
+        tensor.push(tone_map.apply(px[0]));
+        tensor.push(tone_map.apply(px[1]));
+        tensor.push(tone_map.apply(px[2]));
+    }
+    tensor
+}
+
+/// Memory order of an image tensor handed in or out by callers, or taken and
+/// produced by a model.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TensorLayout {
+    /// Planar `[1, C, H, W]`, as ONNX image models take it.
+    #[default]
+    Nchw,
+    /// Interleaved `[1, H, W, C]`, as the engine works internally and
+    /// TensorFlow exports take it.
+    Nhwc,
+}
+
+impl TensorLayout {
+    pub fn parse(name: &str) -> Result<TensorLayout, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "nchw" => Ok(TensorLayout::Nchw),
+            "nhwc" => Ok(TensorLayout::Nhwc),
+            _ => Err(format!(
+                "unknown layout '{}', expected \"nchw\" or \"nhwc\"",
+                name
+            )),
+        }
+    }
+
+    /// The shape of a single `channels` x `height` x `width` image.
+    pub fn image_shape(self, channels: usize, height: usize, width: usize) -> [usize; 4] {
+        match self {
+            TensorLayout::Nchw => [1, channels, height, width],
+            TensorLayout::Nhwc => [1, height, width, channels],
+        }
+    }
+
+    /// Names of the dimensions of [`image_shape`](Self::image_shape).
+    pub fn dimension_names(self) -> [&'static str; 4] {
+        match self {
+            TensorLayout::Nchw => ["batch", "channels", "height", "width"],
+            TensorLayout::Nhwc => ["batch", "height", "width", "channels"],
+        }
+    }
+
+    /// Copies an RGB tensor in this layout into the interleaved one.
+    pub fn interleaved(self, tensor: &[f32]) -> Vec<f32> {
+        match self {
+            TensorLayout::Nchw => planar_to_interleaved(tensor),
+            TensorLayout::Nhwc => tensor.to_vec(),
+        }
+    }
+
+    /// Reorders a rank-4 image tensor of `shape` in this layout, with any
+    /// number of channels, into NCHW, returning it with its NCHW shape.
+    pub fn to_nchw(self, tensor: Vec<f32>, shape: &[usize]) -> (Vec<f32>, Vec<usize>) {
+        match (self, shape) {
+            (TensorLayout::Nhwc, &[batch, height, width, channels]) => {
+                let plane = height * width;
+                let image = plane * channels;
+                let planar = (0..tensor.len())
+                    .map(|i| {
+                        let (n, c, p) = (i / image, i % image / plane, i % plane);
+                        tensor[n * image + p * channels + c]
+                    })
+                    .collect();
+                (planar, vec![batch, channels, height, width])
+            }
+            _ => (tensor, shape.to_vec()),
+        }
+    }
+
+    /// Converts a tensor in this layout to the interleaved one in place,
+    /// reusing `scratch` (which ends up holding the old contents).
+    pub fn to_interleaved(self, tensor: &mut Vec<f32>, scratch: &mut Vec<f32>) {
+        if self == TensorLayout::Nchw {
+            let pixel_count = tensor.len() / 3;
+            scratch.clear();
+            scratch.extend((0..pixel_count * 3).map(|i| tensor[(i % 3) * pixel_count + i / 3]));
+            std::mem::swap(tensor, scratch);
+        }
+    }
+
+    /// Converts an interleaved tensor to this layout.
+    pub fn from_interleaved(self, tensor: Vec<f32>) -> Vec<f32> {
+        match self {
+            TensorLayout::Nchw => interleaved_to_planar(&tensor),
+            TensorLayout::Nhwc => tensor,
+        }
+    }
+}
+
+/// Reorders an interleaved (HWC) RGB tensor into planes (CHW), the layout
+/// ONNX models expect for `[1, 3, H, W]` inputs.
+pub fn interleaved_to_planar(tensor: &[f32]) -> Vec<f32> {
+    let pixel_count = tensor.len() / 3;
+    let mut planar = vec![0.0; pixel_count * 3];
+    for (i, px) in tensor.chunks_exact(3).enumerate() {
+        for (c, &value) in px.iter().enumerate() {
+            planar[c * pixel_count + i] = value;
+        }
+    }
+    planar
+}
+
+/// Inverse of [`interleaved_to_planar`].
+pub fn planar_to_interleaved(planar: &[f32]) -> Vec<f32> {
+    let pixel_count = planar.len() / 3;
+    (0..pixel_count * 3)
+        .map(|i| planar[(i % 3) * pixel_count + i / 3])
+        .collect()
+}
+
+/// Converts an interleaved RGB tensor in [0, 1] back into opaque RGBA bytes.
+pub fn tensor_to_rgba(tensor: &[f32], pixel_count: usize) -> Vec<u8> {
+    let mut pixels = Vec::new();
+    tensor_to_rgba_into(tensor, pixel_count, &mut pixels);
+    pixels
+}
+
+/// Like [`tensor_to_rgba`], reusing `pixels`' allocation.
+pub fn tensor_to_rgba_into(tensor: &[f32], pixel_count: usize, pixels: &mut Vec<u8>) {
+    pixels.clear();
+    pixels.resize(pixel_count * 4, 0);
+    let done = simd::tensor_to_rgba(tensor, pixel_count, pixels);
+    for (i, out) in pixels.chunks_exact_mut(4).enumerate().skip(done) {
+        out[0] = (tensor[i * 3] * 255.0).clamp(0.0, 255.0) as u8;
+        out[1] = (tensor[i * 3 + 1] * 255.0).clamp(0.0, 255.0) as u8;
+        out[2] = (tensor[i * 3 + 2] * 255.0).clamp(0.0, 255.0) as u8;
+        out[3] = 255;
+    }
+}
+
+/// Parses a CSS hex color (`#rgb` or `#rrggbb`).
+pub fn parse_hex_color(color: &str) -> Result<[u8; 3], String> {
+    let hex = color
+        .strip_prefix('#')
+        .filter(|hex| hex.is_ascii())
+        .ok_or_else(|| format!("'{}' is not a #rgb or #rrggbb color", color))?;
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    let rgb = match hex.len() {
+        3 => (0..3)
+            .map(|i| channel(&hex[i..i + 1]).map(|v| v * 17))
+            .collect::<Option<Vec<u8>>>(),
+        6 => (0..3)
+            .map(|i| channel(&hex[i * 2..i * 2 + 2]))
+            .collect::<Option<Vec<u8>>>(),
+        _ => None,
+    };
+    match rgb {
+        Some(rgb) => Ok([rgb[0], rgb[1], rgb[2]]),
+        None => Err(format!("'{}' is not a #rgb or #rrggbb color", color)),
+    }
+}
+
+/// The alpha channel of RGBA bytes, one byte per pixel, or `None` when every
+/// pixel is opaque.
+pub fn alpha_channel(pixels: &[u8]) -> Option<Vec<u8>> {
+    let alpha: Vec<u8> = pixels.chunks_exact(4).map(|px| px[3]).collect();
+    alpha.iter().any(|&a| a < 255).then_some(alpha)
+}
+
+/// [`alpha_channel`] of float RGBA pixels, where 1.0 is opaque.
+pub fn float_alpha_channel(pixels: &[f32]) -> Option<Vec<u8>> {
+    let alpha: Vec<u8> = pixels
+        .chunks_exact(4)
+        .map(|px| (px[3].clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect();
+    alpha.iter().any(|&a| a < 255).then_some(alpha)
+}
+
+/// Writes `alpha`, one byte per pixel, into RGBA pixels.
+pub fn restore_alpha(pixels: &mut [u8], alpha: &[u8]) {
+    for (px, &a) in pixels.chunks_exact_mut(4).zip(alpha) {
+        px[3] = a;
+    }
+}
+
+/// Composites RGBA pixels over an opaque background, leaving them opaque.
+pub fn flatten_alpha(pixels: &mut [u8], background: [u8; 3]) {
+    for px in pixels.chunks_exact_mut(4) {
+        let alpha = px[3] as u32;
+        for (channel, &bg) in px[..3].iter_mut().zip(&background) {
+            *channel = ((*channel as u32 * alpha + bg as u32 * (255 - alpha) + 127) / 255) as u8;
+        }
+        px[3] = 255;
+    }
+}
+
+/// Blends the original and stylized tensors in gamma space.
+///
+/// `strength` of 0.0 returns the original, 1.0 the stylized tensor. The output
+/// length is the shorter of the two inputs.
+pub fn blend_tensors(original: &[f32], stylized: &[f32], strength: f32) -> Vec<f32> {
+    let mut out = Vec::new();
+    blend_tensors_into(
+        original,
+        stylized,
+        strength,
+        None,
+        ColorSpace::Srgb,
+        &mut out,
+    );
+    out
+}
+
+/// Like [`blend_tensors`], with one strength per pixel (every 3 values).
+pub fn blend_tensors_per_pixel(original: &[f32], stylized: &[f32], strengths: &[f32]) -> Vec<f32> {
+    original
+        .iter()
+        .zip(stylized)
+        .enumerate()
+        .map(|(i, (&orig, &style))| blend_value(orig, style, strengths[i / 3]))
+        .collect()
+}
+
+/// Like [`blend_tensors`], or [`blend_tensors_per_pixel`] with `strengths`
+/// scaled by `strength`, reusing `out`'s allocation. Tensors in linear
+/// `space` are interpolated directly instead of through the gamma curve.
+pub fn blend_tensors_into(
+    original: &[f32],
+    stylized: &[f32],
+    strength: f32,
+    strengths: Option<&[f32]>,
+    space: ColorSpace,
+    out: &mut Vec<f32>,
+) {
+    let blend = match space {
+        ColorSpace::Srgb => blend_value,
+        ColorSpace::Linear => lerp_value,
+    };
+    out.clear();
+    let pairs = original.iter().zip(stylized);
+    match strengths {
+        Some(strengths) => out.extend(
+            pairs
+                .enumerate()
+                .map(|(i, (&orig, &style))| blend(orig, style, strengths[i / 3] * strength)),
+        ),
+        None => {
+            let done = simd::blend(original, stylized, strength, space, out);
+            out.extend(
+                pairs
+                    .skip(done)
+                    .map(|(&orig, &style)| blend(orig, style, strength)),
+            )
+        }
+    }
+}
+
+/// Like [`blend_tensors_into`], with each value's strength also scaled by
+/// the entry of `channels` (red, green, blue) for its channel, e.g. to style
+/// a blue sky fully and skin tones lightly.
+pub fn blend_channels_into(
+    original: &[f32],
+    stylized: &[f32],
+    strength: f32,
+    strengths: Option<&[f32]>,
+    channels: [f32; 3],
+    space: ColorSpace,
+    out: &mut Vec<f32>,
+) {
+    let blend = match space {
+        ColorSpace::Srgb => blend_value,
+        ColorSpace::Linear => lerp_value,
+    };
+    out.clear();
+    out.extend(
+        original
+            .iter()
+            .zip(stylized)
+            .enumerate()
+            .map(|(i, (&orig, &style))| {
+                let pixel = strengths.map_or(1.0, |strengths| strengths[i / 3]);
+                blend(orig, style, strength * pixel * channels[i % 3])
+            }),
+    );
+}
+
+fn lerp_value(orig: f32, style: f32, strength: f32) -> f32 {
+    (orig * (1.0 - strength) + style * strength).clamp(0.0, 1.0)
+}
+
+fn blend_value(orig: f32, style: f32, strength: f32) -> f32 {
+    // Apply proper blending with gamma correction for better visual results
+    let gamma = 2.2;
+    let orig_gamma = orig.powf(gamma);
+    let style_gamma = style.powf(gamma);
+    let blended_gamma = orig_gamma * (1.0 - strength) + style_gamma * strength;
+    blended_gamma.powf(1.0 / gamma).clamp(0.0, 1.0)
+}
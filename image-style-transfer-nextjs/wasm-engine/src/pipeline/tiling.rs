@@ -0,0 +1,252 @@
+//! Tiled full-resolution processing: planning within a time budget, and
+//! splitting and feather-blending the tiles.
+
+use serde::Serialize;
+
+/// Tile sizes tried for models with symbolic height and width.
+const DYNAMIC_TILE_SIZES: [u32; 4] = [256, 384, 512, 768];
+
+/// Each output dimension shrinks by this factor until the plan fits the budget.
+const SHRINK_STEP: f64 = 0.8;
+
+/// What a plan has to fit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileBudget {
+    pub source_width: u32,
+    pub source_height: u32,
+    /// The model's input size, at which `tile_ms` was measured.
+    pub model_width: u32,
+    pub model_height: u32,
+    /// Whether the model accepts other tile sizes than its input size.
+    pub dynamic: bool,
+    /// Measured cost of one inference at the model's input size.
+    pub tile_ms: f64,
+    /// `navigator.deviceMemory`, in GB, when the browser exposes it.
+    pub device_memory_gb: Option<f64>,
+    pub time_budget_ms: f64,
+}
+
+/// How an image will be split, and what that is expected to cost.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct TilePlan {
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Pixels shared with each neighbouring tile.
+    pub overlap: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub tile_count: u32,
+    pub output_width: u32,
+    pub output_height: u32,
+    pub estimated_ms: f64,
+}
+
+/// Longest output side worth attempting with `device_memory_gb` of RAM.
+pub fn max_output_side(device_memory_gb: Option<f64>) -> u32 {
+    match device_memory_gb {
+        Some(gb) if gb <= 1.0 => 2048,
+        Some(gb) if gb <= 4.0 => 4096,
+        Some(_) => 8192,
+        None => 4096,
+    }
+}
+
+/// Largest dynamic tile side worth attempting with `device_memory_gb` of RAM,
+/// which is also the largest size a dynamic model runs a whole image at.
+pub fn max_tile_side(device_memory_gb: Option<f64>) -> u32 {
+    match device_memory_gb {
+        Some(gb) if gb <= 1.0 => 384,
+        Some(gb) if gb <= 2.0 => 512,
+        _ => 768,
+    }
+}
+
+/// Tiles of `tile` pixels overlapping by `overlap` needed to cover `length`.
+pub fn tile_count(length: u32, tile: u32, overlap: u32) -> u32 {
+    if length <= tile {
+        1
+    } else {
+        (length - overlap).div_ceil(tile - overlap)
+    }
+}
+
+fn plan_for(budget: &TileBudget, output: (u32, u32), tile: (u32, u32)) -> TilePlan {
+    let overlap = tile.0.min(tile.1) / 8;
+    let columns = tile_count(output.0, tile.0, overlap);
+    let rows = tile_count(output.1, tile.1, overlap);
+    // Inference cost grows with tile area
+    let area_ratio =
+        (tile.0 as f64 * tile.1 as f64) / (budget.model_width as f64 * budget.model_height as f64);
+    TilePlan {
+        tile_width: tile.0,
+        tile_height: tile.1,
+        overlap,
+        columns,
+        rows,
+        tile_count: columns * rows,
+        output_width: output.0,
+        output_height: output.1,
+        estimated_ms: budget.tile_ms * area_ratio * (columns * rows) as f64,
+    }
+}
+
+/// Picks the largest output resolution (never above the source) whose
+/// estimated cost fits `time_budget_ms`, split into as few tiles as possible.
+/// When even a single tile doesn't fit, a single-tile plan is returned.
+pub fn plan_tiles(budget: &TileBudget) -> TilePlan {
+    let tiles: Vec<(u32, u32)> = if budget.dynamic {
+        let max_side = max_tile_side(budget.device_memory_gb);
+        DYNAMIC_TILE_SIZES
+            .iter()
+            .filter(|&&side| side <= max_side)
+            .map(|&side| (side, side))
+            .collect()
+    } else {
+        vec![(budget.model_width, budget.model_height)]
+    };
+
+    let (source_width, source_height) = (
+        budget.source_width.max(1) as f64,
+        budget.source_height.max(1) as f64,
+    );
+    let longest = source_width.max(source_height);
+    let mut scale = (max_output_side(budget.device_memory_gb) as f64 / longest).min(1.0);
+    loop {
+        let output = (
+            ((source_width * scale).round() as u32).max(1),
+            ((source_height * scale).round() as u32).max(1),
+        );
+        let plans: Vec<TilePlan> = tiles
+            .iter()
+            .map(|&tile| plan_for(budget, output, tile))
+            .collect();
+        // Fewer tiles means fewer seams, so prefer that among plans that fit
+        let fitting = plans
+            .iter()
+            .filter(|plan| plan.estimated_ms <= budget.time_budget_ms)
+            .min_by_key(|plan| plan.tile_count);
+        if let Some(plan) = fitting {
+            return *plan;
+        }
+        if let Some(plan) = plans.iter().find(|plan| plan.tile_count == 1) {
+            return *plan;
+        }
+        scale *= SHRINK_STEP;
+    }
+}
+
+/// Where each tile starts along an axis of `length`, for the `tile_count`
+/// tiles a plan has there. The last tile ends flush with the edge, so it
+/// may overlap its neighbour by more than `overlap`; a tile longer than the
+/// axis starts at 0 and hangs over the end.
+pub fn tile_origins(length: u32, tile: u32, overlap: u32) -> Vec<u32> {
+    let count = tile_count(length, tile, overlap);
+    if count == 1 {
+        return vec![0];
+    }
+    let mut origins: Vec<u32> = (0..count - 1).map(|i| i * (tile - overlap)).collect();
+    origins.push(length - tile);
+    origins
+}
+
+/// Feather weights along one axis of the tile at `index` among `origins`:
+/// a linear ramp across each overlap with a neighbour, 1 elsewhere, so that
+/// overlapping weights sum to about 1 and seams fade instead of cutting.
+pub fn axis_weights(origins: &[u32], index: usize, tile: u32) -> Vec<f32> {
+    let start = origins[index];
+    let before = match index {
+        0 => 0,
+        _ => (origins[index - 1] + tile).saturating_sub(start),
+    };
+    let after = origins
+        .get(index + 1)
+        .map_or(0, |&next| (start + tile).saturating_sub(next));
+    let ramp = |distance: u32, overlap: u32| match overlap {
+        0 => 1.0,
+        _ => ((distance as f32 + 0.5) / overlap as f32).min(1.0),
+    };
+    (0..tile)
+        .map(|i| ramp(i, before).min(ramp(tile - 1 - i, after)))
+        .collect()
+}
+
+/// Copies a `size` tile at `origin` out of an interleaved RGB tensor,
+/// repeating the edge pixels where the tile hangs over the image.
+pub fn extract_tile(
+    tensor: &[f32],
+    (width, height): (u32, u32),
+    (x0, y0): (u32, u32),
+    (tile_width, tile_height): (u32, u32),
+) -> Vec<f32> {
+    let mut tile = Vec::with_capacity((tile_width * tile_height * 3) as usize);
+    for y in 0..tile_height {
+        let sy = (y0 + y).min(height - 1) as usize;
+        for x in 0..tile_width {
+            let sx = (x0 + x).min(width - 1) as usize;
+            let i = (sy * width as usize + sx) * 3;
+            tile.extend_from_slice(&tensor[i..i + 3]);
+        }
+    }
+    tile
+}
+
+/// Weighted sum of stylized tiles over the full output.
+pub struct TileBlender {
+    width: u32,
+    height: u32,
+    sum: Vec<f32>,
+    weight: Vec<f32>,
+}
+
+impl TileBlender {
+    pub fn new(width: u32, height: u32) -> TileBlender {
+        let pixels = (width * height) as usize;
+        TileBlender {
+            width,
+            height,
+            sum: vec![0.0; pixels * 3],
+            weight: vec![0.0; pixels],
+        }
+    }
+
+    /// Adds a stylized tile at `origin`, weighted by the product of its
+    /// column and row weights. Pixels hanging over the image are dropped.
+    pub fn add(
+        &mut self,
+        tile: &[f32],
+        (x0, y0): (u32, u32),
+        column_weights: &[f32],
+        row_weights: &[f32],
+    ) {
+        let tile_width = column_weights.len();
+        for (y, &row_weight) in row_weights.iter().enumerate() {
+            let oy = y0 as usize + y;
+            if oy >= self.height as usize {
+                break;
+            }
+            for (x, &column_weight) in column_weights.iter().enumerate() {
+                let ox = x0 as usize + x;
+                if ox >= self.width as usize {
+                    break;
+                }
+                let weight = row_weight * column_weight;
+                let out = oy * self.width as usize + ox;
+                let src = (y * tile_width + x) * 3;
+                for c in 0..3 {
+                    self.sum[out * 3 + c] += tile[src + c] * weight;
+                }
+                self.weight[out] += weight;
+            }
+        }
+    }
+
+    /// The blended tensor; pixels no tile covered stay black.
+    pub fn finish(mut self) -> Vec<f32> {
+        for (px, &weight) in self.sum.chunks_exact_mut(3).zip(&self.weight) {
+            if weight > 0.0 {
+                px.iter_mut().for_each(|v| *v /= weight);
+            }
+        }
+        self.sum
+    }
+}
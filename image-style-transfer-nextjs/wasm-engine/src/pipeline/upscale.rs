@@ -0,0 +1,68 @@
+//! Enlarging results with a super-resolution model, one model-sized tile at
+//! a time so any result size fits its input.
+
+use super::tiling::{axis_weights, extract_tile, tile_origins, TileBlender};
+
+/// Factors the `upscale` option accepts.
+pub const UPSCALE_FACTORS: [u32; 2] = [2, 4];
+
+/// Input pixels shared by neighbouring tiles, feathered to hide seams.
+pub const UPSCALE_OVERLAP: u32 = 8;
+
+/// Checks that a model turned a `tile` into one `factor` times larger, given
+/// its output shape in NCHW order.
+pub fn check_output(
+    shape: &[usize],
+    (tile_width, tile_height): (u32, u32),
+    factor: u32,
+) -> Result<(), String> {
+    let expected = [
+        1,
+        3,
+        (tile_height * factor) as usize,
+        (tile_width * factor) as usize,
+    ];
+    if shape != expected {
+        return Err(format!(
+            "expected a {:?} output for a {}x{} tile, got {:?}",
+            expected, tile_width, tile_height, shape
+        ));
+    }
+    Ok(())
+}
+
+/// Enlarges an interleaved RGB `tensor` of `size` by `factor`, running
+/// `upscale_tile` on overlapping tiles of `tile` size and feathering the
+/// enlarged tiles together. `upscale_tile` gets interleaved RGB and returns
+/// it `factor` times larger in each dimension.
+pub fn upscale_tiled<E>(
+    tensor: &[f32],
+    (width, height): (u32, u32),
+    (tile_width, tile_height): (u32, u32),
+    factor: u32,
+    mut upscale_tile: impl FnMut(&[f32]) -> Result<Vec<f32>, E>,
+) -> Result<Vec<f32>, E> {
+    let overlap = UPSCALE_OVERLAP.min(tile_width.min(tile_height) / 4);
+    let xs = tile_origins(width, tile_width, overlap);
+    let ys = tile_origins(height, tile_height, overlap);
+    // Weights are laid out in output pixels, where the tiles also overlap
+    // `factor` times as much
+    let scaled = |origins: &[u32]| origins.iter().map(|o| o * factor).collect::<Vec<_>>();
+    let (out_xs, out_ys) = (scaled(&xs), scaled(&ys));
+    let mut blender = TileBlender::new(width * factor, height * factor);
+    for (row, &y0) in ys.iter().enumerate() {
+        let row_weights = axis_weights(&out_ys, row, tile_height * factor);
+        for (column, &x0) in xs.iter().enumerate() {
+            let column_weights = axis_weights(&out_xs, column, tile_width * factor);
+            let tile = extract_tile(tensor, (width, height), (x0, y0), (tile_width, tile_height));
+            let enlarged = upscale_tile(&tile)?;
+            blender.add(
+                &enlarged,
+                (out_xs[column], out_ys[row]),
+                &column_weights,
+                &row_weights,
+            );
+        }
+    }
+    Ok(blender.finish())
+}
@@ -0,0 +1,253 @@
+//! Downloading and parsing models in idle time, before they are asked for.
+//!
+//! Prefetches run as tasks detached from the engine, which stays free for
+//! other calls meanwhile, and leave their results here for the engine to
+//! pick up when the model is loaded.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::config::EngineConfig;
+use crate::download::{self, DownloadWatch};
+use crate::model_store::ModelStore;
+use crate::pipeline::prefetch::{PrefetchPriority, PrefetchQueue};
+use crate::pipeline::resume::PartialDownload;
+use crate::pipeline::{self, ModelCompression, StreamingDecompressor, TractPlan};
+use crate::scope;
+
+/// What a prefetch needs to know about its registry entry.
+#[derive(Clone)]
+pub struct PrefetchRequest {
+    pub url: String,
+    pub version: String,
+    pub compression: Option<ModelCompression>,
+    pub sha256: Option<String>,
+}
+
+/// A prefetched model: its decompressed bytes and, when tract could parse
+/// them as the config asks, the optimized plan.
+pub struct Prefetched {
+    pub version: String,
+    pub bytes: Vec<u8>,
+    pub plan: Option<TractPlan>,
+}
+
+#[derive(Default)]
+pub struct Prefetcher {
+    queue: PrefetchQueue,
+    requests: HashMap<String, PrefetchRequest>,
+    done: HashMap<String, Result<Prefetched, String>>,
+    // Resolved when the prefetch of that name finishes
+    waiters: HashMap<String, Vec<js_sys::Function>>,
+    // Downloads the engine is making for a caller; no prefetch starts meanwhile
+    foreground: u32,
+    // As of the latest prefetch_models call
+    config: EngineConfig,
+}
+
+pub type SharedPrefetcher = Rc<RefCell<Prefetcher>>;
+
+/// Queues a prefetch of `name` and starts what the limit allows.
+pub fn enqueue(
+    shared: &SharedPrefetcher,
+    name: &str,
+    request: PrefetchRequest,
+    priority: PrefetchPriority,
+    config: &EngineConfig,
+) {
+    {
+        let mut prefetcher = shared.borrow_mut();
+        prefetcher.config = config.clone();
+        if prefetcher.done.contains_key(name) || !prefetcher.queue.push(name, priority) {
+            return;
+        }
+        prefetcher.requests.insert(name.to_string(), request);
+    }
+    pump(shared);
+}
+
+/// The prefetch of `name`, waiting for it if it is running. A prefetch that
+/// hasn't started is cancelled instead, as is one of another `version`.
+pub async fn take(
+    shared: &SharedPrefetcher,
+    name: &str,
+    version: &str,
+) -> Option<Result<Prefetched, String>> {
+    if shared.borrow().queue.is_running(name) {
+        let promise = js_sys::Promise::new(&mut |resolve, _| {
+            shared
+                .borrow_mut()
+                .waiters
+                .entry(name.to_string())
+                .or_default()
+                .push(resolve);
+        });
+        let _ = JsFuture::from(promise).await;
+    }
+    forget(shared, name).filter(|result| result.as_ref().map_or(true, |p| p.version == version))
+}
+
+/// Cancels or discards the prefetch of `name`, returning its result if it
+/// had finished. A running prefetch completes but its result is dropped.
+pub fn forget(shared: &SharedPrefetcher, name: &str) -> Option<Result<Prefetched, String>> {
+    let mut prefetcher = shared.borrow_mut();
+    prefetcher.queue.cancel(name);
+    prefetcher.requests.remove(name);
+    prefetcher.done.remove(name)
+}
+
+/// Cancels every waiting prefetch and drops every finished one.
+pub fn clear(shared: &SharedPrefetcher) {
+    let mut prefetcher = shared.borrow_mut();
+    prefetcher.queue.clear();
+    prefetcher.requests.clear();
+    prefetcher.done.clear();
+}
+
+/// `{ waiting, running, ready }` names, for `get_prefetch_status`.
+pub fn status(shared: &SharedPrefetcher) -> serde_json::Value {
+    let prefetcher = shared.borrow();
+    let mut ready: Vec<&String> = prefetcher.done.keys().collect();
+    ready.sort();
+    serde_json::json!({
+        "waiting": prefetcher.queue.waiting(),
+        "running": prefetcher.queue.running(),
+        "ready": ready,
+    })
+}
+
+/// Held while the engine downloads a model for a caller; prefetches that
+/// haven't started wait until no such download is left.
+pub struct Foreground(SharedPrefetcher);
+
+impl Foreground {
+    pub fn begin(shared: &SharedPrefetcher) -> Foreground {
+        shared.borrow_mut().foreground += 1;
+        Foreground(shared.clone())
+    }
+}
+
+impl Drop for Foreground {
+    fn drop(&mut self) {
+        self.0.borrow_mut().foreground -= 1;
+        pump(&self.0);
+    }
+}
+
+/// Starts waiting prefetches, most urgent first, while fewer than
+/// `prefetch_concurrency` run and no foreground download is in progress.
+fn pump(shared: &SharedPrefetcher) {
+    loop {
+        let started = {
+            let mut prefetcher = shared.borrow_mut();
+            if prefetcher.foreground > 0 {
+                return;
+            }
+            let limit = prefetcher.config.prefetch_concurrency.max(1) as usize;
+            let Some(name) = prefetcher.queue.start(limit) else {
+                return;
+            };
+            let config = prefetcher.config.clone();
+            match prefetcher.requests.get(&name) {
+                Some(request) => (name, request.clone(), config),
+                None => {
+                    prefetcher.queue.finish(&name);
+                    continue;
+                }
+            }
+        };
+        let shared = shared.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let (name, request, config) = started;
+            scope::idle().await;
+            let result = prefetch(&name, &request, &config).await;
+            let waiters = {
+                let mut prefetcher = shared.borrow_mut();
+                prefetcher.queue.finish(&name);
+                // Forgotten meanwhile, e.g. by a new version of the entry
+                if prefetcher.requests.remove(&name).is_some() {
+                    prefetcher.done.insert(name.clone(), result);
+                }
+                prefetcher.waiters.remove(&name).unwrap_or_default()
+            };
+            for resolve in waiters {
+                let _ = resolve.call0(&JsValue::NULL);
+            }
+            pump(&shared);
+        });
+    }
+}
+
+/// Reads the model from the persistent cache or downloads it (storing it
+/// there), then decompresses and parses it.
+async fn prefetch(
+    name: &str,
+    request: &PrefetchRequest,
+    config: &EngineConfig,
+) -> Result<Prefetched, String> {
+    let compression = request
+        .compression
+        .or_else(|| pipeline::compression_from_url(&request.url));
+    let store = if config.persistent_model_cache {
+        ModelStore::open().await.ok()
+    } else {
+        None
+    };
+    let mut raw = None;
+    if let Some(store) = &store {
+        if let Ok(Some(cached)) = store.get(name, &request.version).await {
+            let verified = match &request.sha256 {
+                Some(expected) => download::verify_sha256(name, &cached, expected)
+                    .await
+                    .is_ok(),
+                None => true,
+            };
+            raw = verified.then_some(cached);
+        }
+    }
+    let raw = match raw {
+        Some(raw) => raw,
+        None => {
+            // Decompressed below together with cached copies
+            let mut decoder = StreamingDecompressor::new(Some(ModelCompression::None));
+            let fetched = download::fetch_model(
+                &request.url,
+                config,
+                &mut PartialDownload::default(),
+                &mut decoder,
+                &DownloadWatch::default(),
+                |_, _, _| {},
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            if let Some(expected) = &request.sha256 {
+                download::verify_sha256(name, &fetched, expected)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            if let Some(store) = &store {
+                let _ = store.put(name, &request.version, &fetched).await;
+            }
+            fetched
+        }
+    };
+    let bytes = pipeline::decompress_model(raw, compression)
+        .map_err(|reason| format!("Cannot decompress '{}': {}", name, reason))?;
+
+    // Parsing is the slow part, so it waits for idle time of its own
+    scope::idle().await;
+    let plan = if config.half_precision {
+        pipeline::inference::load_half_plan(&bytes).ok()
+    } else {
+        pipeline::load_plan(&bytes).ok()
+    };
+    Ok(Prefetched {
+        version: request.version.clone(),
+        bytes,
+        plan,
+    })
+}
@@ -0,0 +1,170 @@
+//! Reporting panics and failed operations to a JavaScript callback.
+//!
+//! Each engine keeps its own reporter and the context of its operation in
+//! progress. The panic hook is global, so it reports to the most recently set
+//! reporter, with the context of the most recently started operation.
+
+use std::cell::{Cell, RefCell};
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::result::Backend;
+
+/// What the engine was doing when an error happened.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ErrorContext {
+    /// Increases with every reported operation, across engines.
+    pub operation_id: u64,
+    pub operation: &'static str,
+    pub model: Option<String>,
+    pub input_width: Option<u32>,
+    pub input_height: Option<u32>,
+    pub backend: Option<Backend>,
+}
+
+thread_local! {
+    static PANIC_REPORTER: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    // Operations in progress on any engine, oldest first
+    static LIVE_OPERATIONS: RefCell<Vec<ErrorContext>> = const { RefCell::new(Vec::new()) };
+    static NEXT_OPERATION_ID: Cell<u64> = const { Cell::new(1) };
+    // Rejections of the reporter's promise end here
+    static IGNORE: Closure<dyn FnMut(JsValue)> = Closure::new(|_: JsValue| {});
+}
+
+/// An engine's reporter and the operation it is tracking.
+#[derive(Default)]
+pub struct Reporter {
+    callback: Option<js_sys::Function>,
+    operation: Option<ErrorContext>,
+}
+
+impl Reporter {
+    /// Also makes `callback` the one panics go to.
+    pub fn set_callback(&mut self, callback: Option<js_sys::Function>) {
+        PANIC_REPORTER.with(|r| *r.borrow_mut() = callback.clone());
+        self.callback = callback;
+    }
+
+    /// Drops this engine's callback; the panic hook keeps whatever it has.
+    pub fn clear_callback(&mut self) {
+        self.callback = None;
+    }
+
+    /// Starts tracking `operation`. Returns false, changing nothing, when an
+    /// operation is already being tracked; only the outermost one reports.
+    pub fn begin(&mut self, operation: &'static str, model: Option<&str>) -> bool {
+        if self.operation.is_some() {
+            return false;
+        }
+        let context = ErrorContext {
+            operation_id: NEXT_OPERATION_ID.with(|id| id.replace(id.get() + 1)),
+            operation,
+            model: model.map(str::to_string),
+            ..ErrorContext::default()
+        };
+        LIVE_OPERATIONS.with(|live| live.borrow_mut().push(context.clone()));
+        self.operation = Some(context);
+        true
+    }
+
+    /// Adds details to the operation in progress, if any.
+    pub fn update(&mut self, f: impl FnOnce(&mut ErrorContext)) {
+        let Some(context) = self.operation.as_mut() else {
+            return;
+        };
+        f(context);
+        LIVE_OPERATIONS.with(|live| {
+            if let Some(live) = live
+                .borrow_mut()
+                .iter_mut()
+                .find(|live| live.operation_id == context.operation_id)
+            {
+                *live = context.clone();
+            }
+        });
+    }
+
+    /// Ends the operation started by `begin` (when it returned true),
+    /// reporting `result` if it failed.
+    pub fn finish<T>(&mut self, started: bool, result: Result<T, JsValue>) -> Result<T, JsValue> {
+        if !started {
+            return result;
+        }
+        if let Some(context) = self.operation.take() {
+            LIVE_OPERATIONS.with(|live| {
+                live.borrow_mut()
+                    .retain(|live| live.operation_id != context.operation_id)
+            });
+            if let (Err(error), Some(callback)) = (&result, &self.callback) {
+                report(callback, error, Some(&context));
+            }
+        }
+        result
+    }
+}
+
+impl Drop for Reporter {
+    // A dropped engine's pending operation must not outlive it
+    fn drop(&mut self) {
+        let _ = self.finish(true, Ok(()));
+    }
+}
+
+/// Installs a panic hook that logs the message and location to the console
+/// and forwards them to the reporter. Safe to call repeatedly.
+#[cfg(target_arch = "wasm32")]
+pub fn install_panic_hook() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        std::panic::set_hook(Box::new(|info| {
+            console_error_panic_hook::hook(info);
+            let message = match info.location() {
+                Some(location) => format!(
+                    "panicked at {}:{}:{}: {}",
+                    location.file(),
+                    location.line(),
+                    location.column(),
+                    info.payload_as_str().unwrap_or("Box<dyn Any>")
+                ),
+                None => format!(
+                    "panicked: {}",
+                    info.payload_as_str().unwrap_or("Box<dyn Any>")
+                ),
+            };
+            let error = js_sys::Error::new(&message);
+            error.set_name("Panic");
+            let _ = js_sys::Reflect::set(&error, &"code".into(), &"Panic".into());
+
+            // try_borrow: a panic can happen while either is borrowed
+            let Some(reporter) =
+                PANIC_REPORTER.with(|r| r.try_borrow().ok().and_then(|r| r.clone()))
+            else {
+                return;
+            };
+            let context = LIVE_OPERATIONS
+                .with(|live| live.try_borrow().ok().and_then(|live| live.last().cloned()));
+            report(&reporter, &error.into(), context.as_ref());
+        }));
+    });
+}
+
+/// Calls the reporter with `(error, context)` in a microtask: the engine is
+/// still borrowed here, so a synchronous call couldn't use it, and whatever
+/// the reporter throws only rejects a promise nobody awaits.
+fn report(reporter: &js_sys::Function, error: &JsValue, context: Option<&ErrorContext>) {
+    let context = context
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap_or(JsValue::NULL);
+
+    let call = reporter.bind2(&JsValue::NULL, error, &context);
+    let resolved = js_sys::Promise::resolve(&JsValue::UNDEFINED);
+    let chained = js_sys::Reflect::get(&resolved, &"then".into()).and_then(|then| {
+        then.unchecked_into::<js_sys::Function>()
+            .call1(&resolved, &call)
+    });
+    if let Ok(chained) = chained {
+        let _ = IGNORE.with(|ignore| chained.unchecked_into::<js_sys::Promise>().catch(ignore));
+    }
+}
@@ -0,0 +1,180 @@
+//! What the processing entry points report back besides the pixels.
+
+use serde::Serialize;
+
+use crate::options::OutputFormat;
+use crate::pipeline::benchmark::StageTiming;
+use crate::pipeline::InferencePath;
+
+/// What actually produced the stylized pixels.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Onnx,
+    #[serde(rename = "webgpu")]
+    WebGpu,
+    Simulated,
+    JsFilter,
+}
+
+impl From<InferencePath> for Backend {
+    fn from(path: InferencePath) -> Self {
+        match path {
+            InferencePath::Onnx => Backend::Onnx,
+            InferencePath::WebGpu => Backend::WebGpu,
+            InferencePath::Simulated => Backend::Simulated,
+        }
+    }
+}
+
+/// What a loaded ONNX model runs on.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelRuntime {
+    /// tract, inside the wasm module (`backend-tract`).
+    Tract,
+    /// The JS runtime registered with `set_external_backend` (`backend-ort-web`).
+    External,
+    /// Compute shaders on the WebGPU device, with tract as the fallback.
+    #[serde(rename = "webgpu")]
+    WebGpu,
+    /// No runtime could load it; the simulated filter stands in.
+    Simulated,
+}
+
+/// Whether the engine has a usable WebGPU device, as reported by `get_stats`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebGpuState {
+    /// No device was ever acquired.
+    Never,
+    Available,
+    /// The device was lost and hasn't been reacquired.
+    Lost,
+}
+
+/// Where a registry entry is in being loaded, as `get_model_state` reports
+/// it: `{ state }`, plus `error` when it failed.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ModelState {
+    NotLoaded,
+    Downloading,
+    /// Downloaded and being handed to a runtime.
+    Parsing,
+    /// Loaded, or a style with nothing to load.
+    Ready,
+    /// The last load failed; loading again may still succeed.
+    Failed {
+        error: String,
+    },
+}
+
+/// Wall-clock milliseconds spent in each stage.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Timings {
+    pub decode_ms: f64,
+    /// Drawing the source and resizing it to the model input.
+    pub resize_ms: f64,
+    /// Converting the resized pixels to the normalized tensor.
+    pub tensor_ms: f64,
+    /// `resize_ms` and `tensor_ms` together.
+    pub preprocess_ms: f64,
+    /// All inference passes together.
+    pub inference_ms: f64,
+    /// Each inference pass; more than one only with the `passes` option.
+    pub pass_ms: Vec<f64>,
+    /// The strength blend alone; `postprocess_ms` includes it.
+    pub blend_ms: f64,
+    pub postprocess_ms: f64,
+    pub encode_ms: f64,
+    /// Blending and encoding each of `variants`, in order.
+    pub variant_ms: Vec<f64>,
+    pub total_ms: f64,
+}
+
+/// The single result shape returned by every data-URL-producing entry point.
+#[derive(Serialize, Clone, Debug)]
+pub struct ProcessResult {
+    pub data_url: String,
+    pub format: OutputFormat,
+    pub mime_type: &'static str,
+    /// The requested format wasn't supported and PNG was used instead.
+    pub format_fallback: bool,
+    pub width: u32,
+    pub height: u32,
+    pub backend: Backend,
+    /// Shorthand for `backend == "simulated"`.
+    pub simulated: bool,
+    pub from_cache: bool,
+    /// Output size over source size; below 1 when the source was shrunk.
+    pub downscale_factor: f32,
+    pub timings: Timings,
+    /// Grayscale saliency map at model resolution, when `debug_saliency` is set.
+    pub saliency_data_url: Option<String>,
+    /// One per `strength_variants` option, in order.
+    pub variants: Vec<StrengthVariant>,
+}
+
+/// The result blended at one of the `strength_variants`.
+#[derive(Serialize, Clone, Debug)]
+pub struct StrengthVariant {
+    pub strength: f32,
+    /// `None` when encoding this variant failed.
+    pub data_url: Option<String>,
+    /// Why encoding failed; the other variants are unaffected.
+    pub error: Option<String>,
+}
+
+/// What `process_sequence_frame` returns.
+#[derive(Serialize, Clone, Debug)]
+pub struct SequenceFrame {
+    #[serde(flatten)]
+    pub result: ProcessResult,
+    /// Position in the sequence, counting from 0 at the last reset.
+    pub frame_index: u32,
+    /// Weight the previous frame actually got, after the ramp-in.
+    pub effective_blend: f32,
+}
+
+/// What `stream_video` resolves with once the stream stops.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct StreamSummary {
+    pub frames_processed: u32,
+    /// Frames dropped because they arrived while another was being stylized.
+    pub frames_skipped: u32,
+}
+
+/// What `run_benchmark` resolves with.
+#[derive(Serialize, Clone, Debug)]
+pub struct BenchmarkReport {
+    pub style: String,
+    /// Size of the synthetic source, twice the model input per side.
+    pub source_width: u32,
+    pub source_height: u32,
+    /// Timed runs per backend, after one untimed warm-up run.
+    pub iterations: u32,
+    /// Whether this build runs the CPU kernels with wasm SIMD.
+    pub simd: bool,
+    /// Threads tract's matrix multiplications run on; 1 without a pool.
+    pub threads: usize,
+    pub backends: Vec<BackendBenchmark>,
+    /// The backend with the lowest median total among those that ran the
+    /// style itself, leaving out the simulated fallback.
+    pub fastest: Option<Backend>,
+}
+
+/// One backend's share of a `BenchmarkReport`.
+#[derive(Serialize, Clone, Debug)]
+pub struct BackendBenchmark {
+    pub backend: Backend,
+    /// Resizing the source and converting it to a tensor.
+    pub preprocess: StageTiming,
+    pub inference: StageTiming,
+    /// Blending, converting back to pixels and resizing to the source size.
+    pub postprocess: StageTiming,
+    /// Sum of the three medians.
+    pub total_ms: f64,
+    /// Why the backend failed; its timings are then empty.
+    pub error: Option<String>,
+}
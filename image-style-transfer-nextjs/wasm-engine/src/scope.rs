@@ -0,0 +1,127 @@
+//! The global scope the engine runs in: a window or a Web Worker.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    Crypto, Document, HtmlCanvasElement, IdbFactory, Performance, RequestInit, Window,
+    WorkerGlobalScope,
+};
+
+use crate::error::EngineError;
+
+/// The APIs both globals have, for code that shouldn't care which it got.
+pub enum Scope {
+    Window(Window),
+    Worker(WorkerGlobalScope),
+}
+
+impl Scope {
+    /// `None` outside a browser (e.g. a worklet).
+    pub fn current() -> Option<Scope> {
+        let global = js_sys::global();
+        match global.dyn_ref::<Window>() {
+            Some(window) => Some(Scope::Window(window.clone())),
+            None => global
+                .dyn_into::<WorkerGlobalScope>()
+                .ok()
+                .map(Scope::Worker),
+        }
+    }
+
+    pub fn fetch_with_str_and_init(&self, url: &str, init: &RequestInit) -> js_sys::Promise {
+        match self {
+            Scope::Window(window) => window.fetch_with_str_and_init(url, init),
+            Scope::Worker(worker) => worker.fetch_with_str_and_init(url, init),
+        }
+    }
+
+    pub fn set_timeout(&self, handler: &js_sys::Function, timeout_ms: i32) -> Result<i32, JsValue> {
+        match self {
+            Scope::Window(window) => {
+                window.set_timeout_with_callback_and_timeout_and_arguments_0(handler, timeout_ms)
+            }
+            Scope::Worker(worker) => {
+                worker.set_timeout_with_callback_and_timeout_and_arguments_0(handler, timeout_ms)
+            }
+        }
+    }
+
+    pub fn clear_timeout(&self, handle: i32) {
+        match self {
+            Scope::Window(window) => window.clear_timeout_with_handle(handle),
+            Scope::Worker(worker) => worker.clear_timeout_with_handle(handle),
+        }
+    }
+
+    pub fn performance(&self) -> Option<Performance> {
+        match self {
+            Scope::Window(window) => window.performance(),
+            Scope::Worker(worker) => worker.performance(),
+        }
+    }
+
+    pub fn crypto(&self) -> Result<Crypto, JsValue> {
+        match self {
+            Scope::Window(window) => window.crypto(),
+            Scope::Worker(worker) => worker.crypto(),
+        }
+    }
+
+    /// `None` where storage is blocked as well as where it doesn't exist.
+    pub fn indexed_db(&self) -> Option<IdbFactory> {
+        match self {
+            Scope::Window(window) => window.indexed_db(),
+            Scope::Worker(worker) => worker.indexed_db(),
+        }
+        .ok()
+        .flatten()
+    }
+}
+
+/// Resolves on a fresh task, after events already queued (such as an abort)
+/// have been handled. Returns at once outside a browser.
+pub async fn yield_to_event_loop() {
+    let Some(scope) = Scope::current() else {
+        return;
+    };
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = scope.set_timeout(&resolve, 0);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Resolves when the browser next has idle time, or on a fresh task where
+/// there's no `requestIdleCallback` (workers, Safari). Returns at once
+/// outside a browser.
+pub async fn idle() {
+    if Scope::current().is_none() {
+        return;
+    }
+    let global = js_sys::global();
+    let request = js_sys::Reflect::get(&global, &"requestIdleCallback".into())
+        .ok()
+        .and_then(|request| request.dyn_into::<js_sys::Function>().ok());
+    let Some(request) = request else {
+        return yield_to_event_loop().await;
+    };
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = request.call1(&global, &resolve);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// The page's document; workers have none.
+pub fn document() -> Option<Document> {
+    web_sys::window().and_then(|window| window.document())
+}
+
+/// A detached `<canvas>`, or `InvalidInput` in a worker instead of a panic.
+pub fn create_canvas() -> Result<HtmlCanvasElement, JsValue> {
+    let document = document().ok_or_else(|| {
+        EngineError::InvalidInput("This needs a DOM canvas, which workers don't have".to_string())
+    })?;
+    document
+        .create_element("canvas")?
+        .dyn_into::<HtmlCanvasElement>()
+        .map_err(JsValue::from)
+}
@@ -0,0 +1,450 @@
+//! Already-decoded DOM elements (or, in workers, pixels) accepted as
+//! pipeline input.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement, HtmlVideoElement, ImageBitmap,
+    OffscreenCanvas, OffscreenCanvasRenderingContext2d, RequestInit, Response,
+};
+
+use crate::error::{EngineError, ImageSourceKind};
+use crate::pipeline::codec::{self, DecodedImage};
+use crate::pipeline::orientation::Orientation;
+use crate::pipeline::{self, ColorSpace, ResizeFilter, ToneMap};
+use crate::scope::{self, Scope};
+
+/// Something that can be drawn straight onto the model-sized canvas.
+pub enum ElementSource {
+    Image(HtmlImageElement),
+    Video(HtmlVideoElement),
+    Canvas(HtmlCanvasElement),
+    /// Drawn as-is, so a bitmap decoded with `imageOrientation: 'from-image'`
+    /// keeps its orientation.
+    Bitmap(ImageBitmap),
+    /// An image decoded in Rust, where there is no `<img>` to decode it.
+    Pixels(DecodedImage),
+}
+
+impl ElementSource {
+    /// Detects the element type and checks that it has pixels to draw.
+    pub fn from_js(source: &JsValue) -> Result<ElementSource, EngineError> {
+        let source = if let Some(image) = source.dyn_ref::<HtmlImageElement>() {
+            ElementSource::Image(image.clone())
+        } else if let Some(video) = source.dyn_ref::<HtmlVideoElement>() {
+            ElementSource::Video(video.clone())
+        } else if let Some(canvas) = source.dyn_ref::<HtmlCanvasElement>() {
+            ElementSource::Canvas(canvas.clone())
+        } else if let Some(bitmap) = source.dyn_ref::<ImageBitmap>() {
+            ElementSource::Bitmap(bitmap.clone())
+        } else {
+            return Err(EngineError::InvalidInput(
+                "Source must be an <img>, <video> or <canvas> element or an ImageBitmap"
+                    .to_string(),
+            ));
+        };
+
+        let (width, height) = source.dimensions();
+        if width == 0 || height == 0 {
+            if let ElementSource::Bitmap(_) = source {
+                return Err(EngineError::InvalidInput(
+                    "ImageBitmap has been closed".to_string(),
+                ));
+            }
+            return Err(EngineError::InvalidInput(format!(
+                "{} has no pixels yet ({}x{}); wait until it has loaded",
+                source.describe(),
+                width,
+                height
+            )));
+        }
+        Ok(source)
+    }
+
+    /// Intrinsic size of the current frame.
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            ElementSource::Image(image) => (image.natural_width(), image.natural_height()),
+            ElementSource::Video(video) => (video.video_width(), video.video_height()),
+            ElementSource::Canvas(canvas) => (canvas.width(), canvas.height()),
+            // A closed bitmap reports 0x0
+            ElementSource::Bitmap(bitmap) => (bitmap.width(), bitmap.height()),
+            ElementSource::Pixels(image) => (image.width, image.height),
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ElementSource::Image(_) => "Image element",
+            ElementSource::Video(_) => "Video element",
+            ElementSource::Canvas(_) => "Canvas element",
+            ElementSource::Bitmap(_) => "ImageBitmap",
+            ElementSource::Pixels(_) => "Decoded image",
+        }
+    }
+
+    /// Draws the source scaled to `width` x `height` at the origin.
+    pub fn draw(
+        &self,
+        ctx: &CanvasRenderingContext2d,
+        width: u32,
+        height: u32,
+    ) -> Result<(), JsValue> {
+        let (dw, dh) = (width as f64, height as f64);
+        match self {
+            ElementSource::Image(image) => {
+                ctx.draw_image_with_html_image_element_and_dw_and_dh(image, 0.0, 0.0, dw, dh)
+            }
+            ElementSource::Video(video) => {
+                ctx.draw_image_with_html_video_element_and_dw_and_dh(video, 0.0, 0.0, dw, dh)
+            }
+            ElementSource::Canvas(canvas) => {
+                ctx.draw_image_with_html_canvas_element_and_dw_and_dh(canvas, 0.0, 0.0, dw, dh)
+            }
+            ElementSource::Bitmap(bitmap) => ctx
+                .draw_image_with_image_bitmap_and_dw_and_dh(bitmap, 0.0, 0.0, dw, dh)
+                .map_err(|_| {
+                    EngineError::InvalidInput("ImageBitmap was closed before drawing".to_string())
+                        .into()
+                }),
+            ElementSource::Pixels(image) => {
+                let pixels = pipeline::resize_rgba(
+                    &image.pixels,
+                    image.width,
+                    image.height,
+                    width,
+                    height,
+                    ResizeFilter::default(),
+                );
+                let image_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(
+                    wasm_bindgen::Clamped(&pixels[..]),
+                    width,
+                    height,
+                )?;
+                ctx.put_image_data(&image_data, 0.0, 0.0)
+            }
+        }
+    }
+
+    /// The source's pixels turned upright by `orientation`, at full size.
+    pub fn oriented(&self, orientation: Orientation) -> Result<ElementSource, JsValue> {
+        let (width, height) = self.dimensions();
+        let (pixels, width, height) =
+            orientation.apply(&self.sample_rgba(width, height)?, width, height);
+        Ok(ElementSource::Pixels(DecodedImage {
+            pixels,
+            width,
+            height,
+        }))
+    }
+
+    /// The source's RGBA pixels scaled to `width` x `height`, read through a
+    /// DOM canvas where there is a document and an `OffscreenCanvas` in
+    /// workers, which can only draw bitmaps.
+    pub fn sample_rgba(&self, width: u32, height: u32) -> Result<Vec<u8>, JsValue> {
+        let (w, h) = (width as f64, height as f64);
+        if let ElementSource::Pixels(image) = self {
+            return Ok(pipeline::resize_rgba(
+                &image.pixels,
+                image.width,
+                image.height,
+                width,
+                height,
+                ResizeFilter::default(),
+            ));
+        }
+        if scope::document().is_some() {
+            let canvas = scope::create_canvas()?;
+            canvas.set_width(width);
+            canvas.set_height(height);
+            let ctx = context_2d(&canvas, false)?;
+            self.draw(&ctx, width, height)?;
+            return Ok(ctx
+                .get_image_data(0.0, 0.0, w, h)
+                .map_err(map_tainted_canvas_error)?
+                .data()
+                .0);
+        }
+        let ElementSource::Bitmap(bitmap) = self else {
+            return Err(EngineError::InvalidInput(format!(
+                "{} can't be read in a worker; pass an ImageBitmap",
+                self.describe()
+            ))
+            .into());
+        };
+        let canvas = OffscreenCanvas::new(width, height)?;
+        let ctx: OffscreenCanvasRenderingContext2d = canvas
+            .get_context("2d")?
+            .ok_or_else(|| {
+                JsValue::from(EngineError::InvalidInput(
+                    "OffscreenCanvas has no 2d context".to_string(),
+                ))
+            })?
+            .dyn_into()?;
+        ctx.draw_image_with_image_bitmap_and_dw_and_dh(bitmap, 0.0, 0.0, w, h)
+            .map_err(|_| {
+                JsValue::from(EngineError::InvalidInput(
+                    "ImageBitmap was closed before drawing".to_string(),
+                ))
+            })?;
+        Ok(ctx
+            .get_image_data(0.0, 0.0, w, h)
+            .map_err(map_tainted_canvas_error)?
+            .data()
+            .0)
+    }
+
+    /// Releases the source's pixels; only bitmaps hold any.
+    pub fn close(&self) {
+        if let ElementSource::Bitmap(bitmap) = self {
+            bitmap.close();
+        }
+    }
+}
+
+/// Decodes a data URL (or any same-origin/CORS URL) into an image element.
+/// Fails with `DecodeFailed` when the browser can't decode it and with
+/// `InvalidInput` when it decodes to no pixels.
+pub async fn load_image(image_data_url: &str) -> Result<HtmlImageElement, JsValue> {
+    let img = HtmlImageElement::new()?;
+    img.set_cross_origin(Some("anonymous"));
+
+    let img_promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let img_clone = img.clone();
+        let resolve_clone = resolve.clone();
+        let reject_clone = reject.clone();
+
+        let onload = Closure::wrap(Box::new(move || {
+            resolve_clone.call0(&JsValue::NULL).unwrap();
+        }) as Box<dyn FnMut()>);
+
+        let decode_failed = EngineError::decode_failed(image_data_url);
+        let onerror = Closure::wrap(Box::new(move || {
+            let error: JsValue = decode_failed.clone().into();
+            reject_clone.call1(&JsValue::NULL, &error).unwrap();
+        }) as Box<dyn FnMut()>);
+
+        img_clone.set_onload(Some(onload.as_ref().unchecked_ref()));
+        img_clone.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+
+        onload.forget();
+        onerror.forget();
+    });
+
+    img.set_src(image_data_url);
+    wasm_bindgen_futures::JsFuture::from(img_promise).await?;
+    if img.natural_width() == 0 || img.natural_height() == 0 {
+        // SVGs without width and height attributes decode like this
+        return Err(EngineError::InvalidInput(format!(
+            "Image decoded to {}x{} pixels",
+            img.natural_width(),
+            img.natural_height()
+        ))
+        .into());
+    }
+    Ok(img)
+}
+
+/// Decodes an image URL into a source: an `<img>` where there is a
+/// document, otherwise pixels fetched and decoded in Rust (PNG and JPEG).
+pub async fn load_source(url: &str) -> Result<ElementSource, JsValue> {
+    if scope::document().is_some() {
+        return load_image(url).await.map(ElementSource::Image);
+    }
+    decode_image(url).await.map(ElementSource::Pixels)
+}
+
+/// Fetches `url` (data URLs included) and decodes it without the DOM.
+async fn decode_image(url: &str) -> Result<DecodedImage, JsValue> {
+    let scope = Scope::current().ok_or_else(|| {
+        EngineError::InvalidInput("Decoding images needs a window or worker".to_string())
+    })?;
+    let decode_failed = || JsValue::from(EngineError::decode_failed(url));
+    // Data URLs only fail to fetch when they are malformed
+    let fetch_failed = |status: Option<u16>| match ImageSourceKind::of(url) {
+        ImageSourceKind::DataUrl => decode_failed(),
+        kind => JsValue::from(EngineError::FetchFailed {
+            message: match status {
+                Some(status) => format!(
+                    "Fetching the image from {} failed with HTTP {}",
+                    kind.description(),
+                    status
+                ),
+                None => format!("Fetching the image from {} failed", kind.description()),
+            },
+            status,
+        }),
+    };
+    let response: Response =
+        JsFuture::from(scope.fetch_with_str_and_init(url, &RequestInit::new()))
+            .await
+            .and_then(|response| response.dyn_into())
+            .map_err(|_| fetch_failed(None))?;
+    if !response.ok() {
+        return Err(fetch_failed(Some(response.status())));
+    }
+    let buffer = JsFuture::from(response.array_buffer()?).await?;
+    let image = codec::decode_rgba(&js_sys::Uint8Array::new(&buffer).to_vec())
+        .map_err(|_| decode_failed())?;
+    if image.width == 0 || image.height == 0 {
+        return Err(EngineError::InvalidInput(format!(
+            "Image decoded to {}x{} pixels",
+            image.width, image.height
+        ))
+        .into());
+    }
+    Ok(image)
+}
+
+/// Gets the 2d context, asking for float16 storage when `high_dynamic_range`
+/// is set. Browsers without float canvases silently give an 8-bit one.
+pub fn context_2d(
+    canvas: &HtmlCanvasElement,
+    high_dynamic_range: bool,
+) -> Result<CanvasRenderingContext2d, JsValue> {
+    let context = if high_dynamic_range {
+        let settings = js_sys::Object::new();
+        js_sys::Reflect::set(&settings, &"colorType".into(), &"float16".into())?;
+        canvas.get_context_with_context_options("2d", &settings)?
+    } else {
+        canvas.get_context("2d")?
+    };
+    context
+        .ok_or_else(|| {
+            JsValue::from(EngineError::InvalidInput(
+                "Canvas has no 2d context".to_string(),
+            ))
+        })?
+        .dyn_into::<CanvasRenderingContext2d>()
+        .map_err(JsValue::from)
+}
+
+/// Source pixels resized to the model input, not yet normalized, so the
+/// two steps can be timed apart.
+pub enum Resized {
+    /// 8-bit sRGB.
+    Bytes(Vec<u8>),
+    /// Float RGBA in the working space, still to be tone mapped.
+    Floats(Vec<f32>, ToneMap),
+}
+
+impl Resized {
+    /// The normalized RGB tensor, with the alpha channel unless every pixel
+    /// is opaque.
+    pub fn into_tensor(self) -> (Vec<f32>, Option<Vec<u8>>) {
+        match self {
+            Resized::Bytes(pixels) => (
+                pipeline::rgba_to_tensor(&pixels),
+                pipeline::alpha_channel(&pixels),
+            ),
+            Resized::Floats(pixels, tone_map) => (
+                pipeline::float_rgba_to_tensor(&pixels, tone_map),
+                pipeline::tensor::float_alpha_channel(&pixels),
+            ),
+        }
+    }
+}
+
+/// Reads the canvas back and resizes it to `model_size` with `filter`,
+/// ready for [`Resized::into_tensor`] to normalize in `space`.
+///
+/// 8-bit data goes through `rgba_to_tensor` unchanged; float data (only
+/// requested when `tone_map` isn't `Clamp`) is tone mapped into [0, 1].
+/// For a linear `space` the pixels are linearized before resizing, and
+/// tone mapping sees linear values.
+pub fn read_resized(
+    ctx: &CanvasRenderingContext2d,
+    (width, height): (u32, u32),
+    (model_width, model_height): (u32, u32),
+    tone_map: ToneMap,
+    filter: ResizeFilter,
+    space: ColorSpace,
+) -> Result<Resized, JsValue> {
+    let (w, h) = (width as f64, height as f64);
+    let image_data = if tone_map == ToneMap::Clamp {
+        ctx.get_image_data(0.0, 0.0, w, h)
+    } else {
+        // ImageDataSettings isn't in web-sys yet
+        let settings = js_sys::Object::new();
+        js_sys::Reflect::set(&settings, &"pixelFormat".into(), &"rgba-float16".into())?;
+        js_sys::Reflect::get(ctx, &"getImageData".into())?
+            .dyn_into::<js_sys::Function>()?
+            .apply(
+                ctx,
+                &js_sys::Array::of5(&0.into(), &0.into(), &w.into(), &h.into(), &settings),
+            )
+            .and_then(|data| data.dyn_into::<web_sys::ImageData>())
+    }
+    .map_err(map_tainted_canvas_error)?;
+
+    let data = js_sys::Reflect::get(&image_data, &"data".into())?;
+    if data.is_instance_of::<js_sys::Uint8ClampedArray>() {
+        return Ok(resize_pixels(
+            &image_data.data().0,
+            (width, height),
+            (model_width, model_height),
+            filter,
+            space,
+        ));
+    }
+    let mut floats = js_sys::Float32Array::new(&data).to_vec();
+    if space == ColorSpace::Linear {
+        pipeline::color::linearize_rgba(&mut floats);
+    }
+    let pixels =
+        pipeline::resize_rgba_f32(&floats, width, height, model_width, model_height, filter);
+    Ok(Resized::Floats(pixels, tone_map))
+}
+
+/// The 8-bit half of [`read_resized`]: resizes RGBA bytes to `model_size`,
+/// linearizing them first for a linear `space`. Never tone mapped, only
+/// clipped after resampling.
+pub fn resize_pixels(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    (model_width, model_height): (u32, u32),
+    filter: ResizeFilter,
+    space: ColorSpace,
+) -> Resized {
+    if space == ColorSpace::Srgb {
+        return Resized::Bytes(pipeline::resize_rgba(
+            pixels,
+            width,
+            height,
+            model_width,
+            model_height,
+            filter,
+        ));
+    }
+    let linear = pipeline::color::rgba_to_linear(pixels);
+    Resized::Floats(
+        pipeline::resize_rgba_f32(&linear, width, height, model_width, model_height, filter),
+        ToneMap::Clamp,
+    )
+}
+
+/// [`resize_pixels`] and [`Resized::into_tensor`] in one.
+pub fn pixels_to_tensor(
+    pixels: &[u8],
+    size: (u32, u32),
+    model_size: (u32, u32),
+    filter: ResizeFilter,
+    space: ColorSpace,
+) -> (Vec<f32>, Option<Vec<u8>>) {
+    resize_pixels(pixels, size, model_size, filter, space).into_tensor()
+}
+
+/// Turns the browser's opaque exception for reading a tainted canvas into a
+/// `SecurityError` that explains the usual cause.
+pub fn map_tainted_canvas_error(error: JsValue) -> JsValue {
+    match error.dyn_ref::<web_sys::DomException>() {
+        Some(exception) if exception.name() == "SecurityError" => {
+            EngineError::SecurityError(
+                "Source pixels can't be read because it is cross-origin; serve it with CORS headers and set crossOrigin=\"anonymous\"".to_string(),
+            )
+            .into()
+        }
+        _ => error,
+    }
+}
@@ -0,0 +1,78 @@
+//! Running tract's matrix multiplications on rayon's global pool, with the
+//! `threads` feature.
+//!
+//! In the browser the pool is wasm-bindgen-rayon's: `initThreadPool(n)`
+//! starts Web Workers instantiating this module on its shared memory, and
+//! `use_thread_pool` then moves tract onto them. That needs a build with
+//! wasm atomics (see `build-wasm.sh`), a cross-origin isolated page, and the
+//! engine itself running in a worker: waiting for the pool blocks, which a
+//! window's main thread isn't allowed to do.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tract_linalg::multithread::{set_default_executor, Executor};
+use wasm_bindgen::prelude::*;
+
+use crate::error::EngineError;
+
+#[cfg(target_arch = "wasm32")]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+/// Size of the pool `use_thread_pool` gave tract; 0 before.
+static POOL_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Threads tract runs on, or 0 when it has no pool.
+pub fn pool_threads() -> usize {
+    POOL_THREADS.load(Ordering::Relaxed)
+}
+
+/// Runs tract's matrix multiplications on rayon's global pool from then
+/// on. In the browser, call it once the promise of `initThreadPool` has
+/// resolved: before that there is no pool to run on.
+#[wasm_bindgen]
+pub fn use_thread_pool() -> Result<(), JsValue> {
+    check_environment()?;
+    POOL_THREADS.store(rayon::current_num_threads(), Ordering::Relaxed);
+    set_default_executor(Executor::RayonGlobal);
+    Ok(())
+}
+
+/// Starts rayon's global pool with `threads` OS threads (0 for one per
+/// logical core), as wasm-bindgen-rayon's `initThreadPool` does in the
+/// browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init_thread_pool(threads: usize) -> Result<(), JsValue> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+        .map_err(|e| {
+            EngineError::InvalidInput(format!("Cannot start {} threads: {}", threads, e)).into()
+        })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn check_environment() -> Result<(), EngineError> {
+    use wasm_bindgen::JsCast;
+
+    let global = js_sys::global();
+    let isolated = js_sys::Reflect::get(&global, &"crossOriginIsolated".into())
+        .map(|isolated| isolated.is_truthy())
+        .unwrap_or(false);
+    if !isolated {
+        return Err(EngineError::InvalidInput(
+            "Threads need a cross-origin isolated page (COOP and COEP headers)".to_string(),
+        ));
+    }
+    if global.dyn_ref::<web_sys::Window>().is_some() {
+        return Err(EngineError::InvalidInput(
+            "Threads need the engine to run in a Web Worker; the main thread can't wait for them"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn check_environment() -> Result<(), EngineError> {
+    Ok(())
+}
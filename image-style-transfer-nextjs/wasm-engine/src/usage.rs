@@ -0,0 +1,60 @@
+//! Per-model usage counters reported by `get_stats`.
+
+use serde::Serialize;
+
+use crate::result::Backend;
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ModelUsage {
+    /// Completed downloads; already-loaded and download-free models don't count.
+    pub times_loaded: u32,
+    pub last_load_ms: f64,
+    /// Successful inferences, cache hits included.
+    pub inference_count: u64,
+    pub cache_hits: u64,
+    pub onnx_count: u64,
+    pub simulated_count: u64,
+    pub js_filter_count: u64,
+    /// Time spent producing stylized tensors; cache hits add (almost) nothing.
+    pub total_inference_ms: f64,
+    pub last_inference_ms: f64,
+    /// Failed loads and processing calls.
+    pub error_count: u64,
+    /// Unix time in milliseconds of the last load, inference or error.
+    pub last_used: Option<f64>,
+}
+
+impl ModelUsage {
+    pub fn record_load(&mut self, duration_ms: f64, now: f64) {
+        self.times_loaded += 1;
+        self.last_load_ms = duration_ms;
+        self.last_used = Some(now);
+    }
+
+    pub fn record_inference(
+        &mut self,
+        backend: Backend,
+        from_cache: bool,
+        duration_ms: f64,
+        now: f64,
+    ) {
+        self.inference_count += 1;
+        if from_cache {
+            self.cache_hits += 1;
+        }
+        match backend {
+            // The model itself ran, wherever it ran
+            Backend::Onnx | Backend::WebGpu => self.onnx_count += 1,
+            Backend::Simulated => self.simulated_count += 1,
+            Backend::JsFilter => self.js_filter_count += 1,
+        }
+        self.total_inference_ms += duration_ms;
+        self.last_inference_ms = duration_ms;
+        self.last_used = Some(now);
+    }
+
+    pub fn record_error(&mut self, now: f64) {
+        self.error_count += 1;
+        self.last_used = Some(now);
+    }
+}
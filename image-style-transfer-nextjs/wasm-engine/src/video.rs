@@ -0,0 +1,128 @@
+//! Reading and creating WebCodecs `VideoFrame`s, and the insertable streams
+//! that carry them to and from a `MediaStreamTrack`.
+//!
+//! web-sys only exposes these behind `web_sys_unstable_apis`, so the few
+//! members the engine uses are bound here.
+
+use js_sys::{Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::error::EngineError;
+use crate::pipeline::codec::DecodedImage;
+use crate::pipeline::frame::{self, FrameColor, PixelFormat, PlaneLayout, YuvMatrix};
+
+#[wasm_bindgen]
+extern "C" {
+    pub type VideoFrame;
+    #[wasm_bindgen(catch, constructor, js_class = "VideoFrame")]
+    fn new_with_buffer(data: &Uint8Array, init: &JsValue) -> Result<VideoFrame, JsValue>;
+    #[wasm_bindgen(method, getter)]
+    fn format(this: &VideoFrame) -> Option<String>;
+    #[wasm_bindgen(method, getter, js_name = visibleRect)]
+    fn visible_rect(this: &VideoFrame) -> JsValue;
+    #[wasm_bindgen(method, getter, js_name = colorSpace)]
+    fn color_space(this: &VideoFrame) -> JsValue;
+    #[wasm_bindgen(method, getter)]
+    pub fn timestamp(this: &VideoFrame) -> f64;
+    #[wasm_bindgen(method, getter)]
+    pub fn duration(this: &VideoFrame) -> Option<f64>;
+    #[wasm_bindgen(method, catch, js_name = allocationSize)]
+    fn allocation_size(this: &VideoFrame, options: &JsValue) -> Result<f64, JsValue>;
+    #[wasm_bindgen(method, catch, js_name = copyTo)]
+    fn copy_to(
+        this: &VideoFrame,
+        destination: &Uint8Array,
+        options: &JsValue,
+    ) -> Result<js_sys::Promise, JsValue>;
+    #[wasm_bindgen(method)]
+    pub fn close(this: &VideoFrame);
+
+    pub type MediaStreamTrackProcessor;
+    #[wasm_bindgen(method, getter)]
+    pub fn readable(this: &MediaStreamTrackProcessor) -> web_sys::ReadableStream;
+
+    pub type MediaStreamTrackGenerator;
+    #[wasm_bindgen(method, getter)]
+    pub fn writable(this: &MediaStreamTrackGenerator) -> web_sys::WritableStream;
+}
+
+fn get(value: &JsValue, key: &str) -> JsValue {
+    Reflect::get(value, &key.into()).unwrap_or(JsValue::UNDEFINED)
+}
+
+/// The visible pixels of `frame` as RGBA. Formats `frame::frame_to_rgba`
+/// knows are copied as they are and converted here; any other format is
+/// left to the browser to convert, which not every browser can.
+pub async fn read_frame(frame: &VideoFrame) -> Result<DecodedImage, JsValue> {
+    // A closed frame has no visible rect
+    let rect = frame.visible_rect();
+    if rect.is_null() || rect.is_undefined() {
+        return Err(EngineError::InvalidInput("VideoFrame has been closed".to_string()).into());
+    }
+    let dimension = |key: &str| get(&rect, key).as_f64().unwrap_or(0.0) as u32;
+    let (width, height) = (dimension("width"), dimension("height"));
+
+    let native = frame
+        .format()
+        .and_then(|format| PixelFormat::parse(&format).ok());
+    let options = Object::new();
+    if native.is_none() {
+        Reflect::set(&options, &"format".into(), &"RGBA".into())?;
+    }
+    let unreadable = |e: JsValue| {
+        JsValue::from(EngineError::InvalidInput(format!(
+            "Cannot read a {} VideoFrame: {}",
+            frame.format().unwrap_or_else(|| "opaque".to_string()),
+            crate::js_filter::describe_js_error(&e)
+        )))
+    };
+    let size = frame.allocation_size(&options).map_err(unreadable)?;
+    let buffer = Uint8Array::new_with_length(size as u32);
+    let copied = frame.copy_to(&buffer, &options).map_err(unreadable)?;
+    let layouts = JsFuture::from(copied).await.map_err(unreadable)?;
+    let planes: Vec<PlaneLayout> = js_sys::Array::from(&layouts)
+        .iter()
+        .map(|layout| PlaneLayout {
+            offset: get(&layout, "offset").as_f64().unwrap_or(0.0) as usize,
+            stride: get(&layout, "stride").as_f64().unwrap_or(0.0) as usize,
+        })
+        .collect();
+
+    let color_space = frame.color_space();
+    let color = FrameColor {
+        matrix: YuvMatrix::parse(get(&color_space, "matrix").as_string().as_deref()),
+        full_range: get(&color_space, "fullRange").as_bool().unwrap_or(false),
+    };
+    let pixels = frame::frame_to_rgba(
+        native.unwrap_or(PixelFormat::Rgba),
+        &buffer.to_vec(),
+        &planes,
+        (width, height),
+        color,
+    )
+    .map_err(|reason| EngineError::InvalidInput(format!("Cannot read VideoFrame: {}", reason)))?;
+    Ok(DecodedImage {
+        pixels,
+        width,
+        height,
+    })
+}
+
+/// A new RGBA frame holding `pixels`.
+pub fn rgba_frame(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    timestamp: f64,
+    duration: Option<f64>,
+) -> Result<VideoFrame, JsValue> {
+    let init = Object::new();
+    Reflect::set(&init, &"format".into(), &"RGBA".into())?;
+    Reflect::set(&init, &"codedWidth".into(), &width.into())?;
+    Reflect::set(&init, &"codedHeight".into(), &height.into())?;
+    Reflect::set(&init, &"timestamp".into(), &timestamp.into())?;
+    if let Some(duration) = duration {
+        Reflect::set(&init, &"duration".into(), &duration.into())?;
+    }
+    VideoFrame::new_with_buffer(&Uint8Array::from(pixels), &init)
+}
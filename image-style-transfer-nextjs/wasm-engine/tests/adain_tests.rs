@@ -0,0 +1,70 @@
+use style_transfer_wasm::pipeline::adain::{adain, match_pixel_stats, ChannelStats};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-3,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_channel_stats_are_per_plane() {
+    let stats = ChannelStats::of_planar(&[1.0, 3.0, 5.0, 5.0], 2);
+    assert_close(stats.mean[0], 2.0);
+    assert_close(stats.std[0], 1.0);
+    assert_close(stats.mean[1], 5.0);
+    // Flat channels keep a small deviation rather than zero
+    assert!(stats.std[1] > 0.0 && stats.std[1] < 0.01);
+
+    let rgb = ChannelStats::of_rgb(&[0.0, 0.5, 1.0, 1.0, 0.5, 0.0]);
+    assert_close(rgb.mean[0], 0.5);
+    assert_close(rgb.mean[1], 0.5);
+    assert_close(rgb.std[2], 0.5);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_adain_takes_the_style_statistics() {
+    let content: Vec<f32> = (0..32)
+        .map(|i| (i % 16) as f32 * 0.25 + (i / 16) as f32)
+        .collect();
+    let style = ChannelStats {
+        mean: vec![-3.0, 10.0],
+        std: vec![0.5, 4.0],
+    };
+    let restyled = adain(&content, &style, 1.0);
+    let stats = ChannelStats::of_planar(&restyled, 2);
+    for c in 0..2 {
+        assert_close(stats.mean[c], style.mean[c]);
+        assert_close(stats.std[c], style.std[c]);
+    }
+
+    // alpha 0 leaves the content alone and 0.5 lands halfway
+    assert_eq!(adain(&content, &style, 0.0), content);
+    let halfway = adain(&content, &style, 0.5);
+    for ((&h, &c), &r) in halfway.iter().zip(&content).zip(&restyled) {
+        assert_close(h, (c + r) / 2.0);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_pixel_fallback_matches_colors_within_range() {
+    let content: Vec<f32> = (0..48).map(|i| (i % 7) as f32 / 6.0).collect();
+    let style = ChannelStats {
+        mean: vec![0.9, 0.2, 0.5],
+        std: vec![0.4, 0.05, 0.1],
+    };
+    let matched = match_pixel_stats(&content, &style);
+    assert_eq!(matched.len(), content.len());
+    assert!(matched.iter().all(|v| (0.0..=1.0).contains(v)));
+    let stats = ChannelStats::of_rgb(&matched);
+    assert_close(stats.mean[1], 0.2);
+    assert!(stats.mean[0] > 0.7);
+}
@@ -0,0 +1,35 @@
+use style_transfer_wasm::pipeline::benchmark::{synthetic_image, StageTiming};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_stage_timing_summarizes_samples() {
+    let odd = StageTiming::from_samples(&[4.0, 1.0, 9.0]);
+    assert_eq!(
+        odd,
+        StageTiming {
+            median_ms: 4.0,
+            min_ms: 1.0,
+            max_ms: 9.0
+        }
+    );
+    let even = StageTiming::from_samples(&[3.0, 1.0, 2.0, 8.0]);
+    assert_eq!(even.median_ms, 2.5);
+    assert_eq!((even.min_ms, even.max_ms), (1.0, 8.0));
+    assert_eq!(StageTiming::from_samples(&[]), StageTiming::default());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_synthetic_image_is_opaque_and_reproducible() {
+    let pixels = synthetic_image(48, 32, 7);
+    assert_eq!(pixels.len(), 48 * 32 * 4);
+    assert!(pixels.chunks_exact(4).all(|p| p[3] == 255));
+    assert_eq!(pixels, synthetic_image(48, 32, 7));
+    assert_ne!(pixels, synthetic_image(48, 32, 8));
+    // The checkerboard gives both blue levels
+    assert!(pixels.chunks_exact(4).any(|p| p[2] < 100));
+    assert!(pixels.chunks_exact(4).any(|p| p[2] > 160));
+}
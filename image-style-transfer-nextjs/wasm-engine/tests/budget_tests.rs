@@ -0,0 +1,147 @@
+use style_transfer_wasm::pipeline::budget::{
+    plan_evictions, plan_residency, EvictionPlan, ResidencyLimits, ResidentModel, UsageClock,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn resident(name: &str, bytes: usize, last_used: u64) -> ResidentModel {
+    ResidentModel {
+        name: name.to_string(),
+        bytes,
+        retained_bytes: 0,
+        last_used,
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_nothing_is_evicted_when_the_model_fits() {
+    let loaded = vec![resident("a", 40, 1), resident("b", 40, 2)];
+    assert_eq!(plan_evictions(&loaded, 20, 100, &[]), Ok(vec![]));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_least_recently_used_models_are_evicted_first() {
+    let loaded = vec![
+        resident("a", 40, 3),
+        resident("b", 40, 1),
+        resident("c", 40, 2),
+    ];
+    assert_eq!(
+        plan_evictions(&loaded, 60, 120, &[]),
+        Ok(vec!["b".to_string(), "c".to_string()])
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_pinned_model_is_never_evicted() {
+    let loaded = vec![resident("a", 40, 1), resident("b", 40, 2)];
+    assert_eq!(
+        plan_evictions(&loaded, 40, 80, &["a"]),
+        Ok(vec!["b".to_string()])
+    );
+    assert!(plan_evictions(&loaded, 50, 80, &["a"]).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_model_larger_than_budget_is_an_error() {
+    let loaded = vec![resident("a", 10, 1)];
+    assert!(plan_evictions(&loaded, 101, 100, &[]).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_usage_clock_keeps_a_preloaded_model() {
+    // "a" is used, then "b" is preloaded without running inference
+    let mut clock = UsageClock::default();
+    clock.touch("a");
+    clock.touch("b");
+    assert!(clock.last_used("b") > clock.last_used("a"));
+    assert_eq!(clock.last_used("never"), 0);
+
+    let loaded: Vec<ResidentModel> = ["a", "b"]
+        .iter()
+        .map(|name| resident(name, 40, clock.last_used(name)))
+        .collect();
+    assert_eq!(
+        plan_evictions(&loaded, 40, 80, &[]),
+        Ok(vec!["a".to_string()])
+    );
+
+    clock.forget("a");
+    assert_eq!(clock.last_used("a"), 0);
+}
+
+fn retaining(name: &str, bytes: usize, retained_bytes: usize, last_used: u64) -> ResidentModel {
+    ResidentModel {
+        retained_bytes,
+        ..resident(name, bytes, last_used)
+    }
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_retained_files_are_dropped_before_models() {
+    let loaded = vec![
+        retaining("a", 40, 40, 1),
+        retaining("b", 40, 40, 2),
+        resident("c", 40, 3),
+    ];
+    let limits = ResidencyLimits {
+        max_bytes: 200,
+        max_models: 0,
+    };
+    // 200 used; dropping a's file makes room for 40 more
+    assert_eq!(
+        plan_residency(&loaded, Some(40), limits, &[]),
+        Ok(EvictionPlan {
+            drop_retained: names(&["a"]),
+            unload: vec![],
+        })
+    );
+    // Both files aren't enough for 100, so a goes and b's file with it
+    assert_eq!(
+        plan_residency(&loaded, Some(100), limits, &[]),
+        Ok(EvictionPlan {
+            drop_retained: names(&["b"]),
+            unload: names(&["a"]),
+        })
+    );
+    assert_eq!(
+        plan_residency(&loaded, None, limits, &[]),
+        Ok(EvictionPlan::default())
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_model_cap_unloads_least_recently_used() {
+    let loaded = vec![
+        resident("a", 10, 3),
+        resident("b", 10, 1),
+        resident("c", 10, 2),
+    ];
+    let limits = ResidencyLimits {
+        max_bytes: 0,
+        max_models: 2,
+    };
+    assert_eq!(
+        plan_residency(&loaded, Some(10), limits, &[])
+            .unwrap()
+            .unload,
+        names(&["b", "c"])
+    );
+    assert_eq!(
+        plan_residency(&loaded, None, limits, &[]).unwrap().unload,
+        names(&["b"])
+    );
+    assert!(plan_residency(&loaded, Some(10), limits, &["b", "c"]).is_err());
+}
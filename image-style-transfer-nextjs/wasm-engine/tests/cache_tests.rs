@@ -0,0 +1,82 @@
+use style_transfer_wasm::pipeline::cache::{CacheKey, CachedResult, ResultCache};
+use style_transfer_wasm::pipeline::InferencePath;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn result(len: usize) -> CachedResult {
+    CachedResult { tensor: vec![0.5; len], path: InferencePath::Simulated }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_key_depends_on_content_style_version_and_seed() {
+    let input = vec![0.1f32, 0.2, 0.3];
+    let key = CacheKey::new("a", "", &input, 1);
+    assert_eq!(key, CacheKey::new("a", "", &input.clone(), 1));
+    assert_ne!(key, CacheKey::new("b", "", &input, 1));
+    assert_ne!(key, CacheKey::new("a", "", &input, 2));
+    assert_ne!(key, CacheKey::new("a", "2", &input, 1));
+    assert_ne!(key, CacheKey::new("a", "", &[0.1, 0.2, 0.30001], 1));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_hits_and_misses_are_counted() {
+    let mut cache = ResultCache::new(1024);
+    let key = CacheKey::new("a", "", &[1.0], 0);
+    assert!(cache.get(&key).is_none());
+    cache.insert(key.clone(), result(4));
+    assert_eq!(cache.get(&key).unwrap().tensor.len(), 4);
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries, stats.bytes), (1, 1, 1, 16));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_least_recently_used_is_evicted() {
+    // Room for exactly two 4-value results
+    let mut cache = ResultCache::new(32);
+    let keys: Vec<CacheKey> = (0..3).map(|i| CacheKey::new("a", "", &[i as f32], 0)).collect();
+    cache.insert(keys[0].clone(), result(4));
+    cache.insert(keys[1].clone(), result(4));
+    cache.get(&keys[0]);
+    cache.insert(keys[2].clone(), result(4));
+
+    assert!(cache.get(&keys[0]).is_some());
+    assert!(cache.get(&keys[1]).is_none());
+    assert!(cache.get(&keys[2]).is_some());
+    assert_eq!(cache.stats().bytes, 32);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_capacity_and_invalidation() {
+    let mut cache = ResultCache::new(64);
+    cache.insert(CacheKey::new("a", "", &[0.0], 0), result(4));
+    cache.insert(CacheKey::new("b", "", &[0.0], 0), result(4));
+    cache.insert(CacheKey::new("big", "", &[0.0], 0), result(100));
+    assert_eq!(cache.stats().entries, 2);
+
+    cache.invalidate_style("a");
+    assert_eq!(cache.stats().entries, 1);
+
+    cache.set_capacity(0);
+    assert_eq!(cache.stats().entries, 0);
+    assert_eq!(cache.stats().bytes, 0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_reset_counters_keeps_entries() {
+    let mut cache = ResultCache::new(1024);
+    let key = CacheKey::new("a", "", &[1.0], 0);
+    cache.get(&key);
+    cache.insert(key.clone(), result(4));
+    cache.get(&key);
+    cache.reset_counters();
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (0, 0, 1));
+}
@@ -0,0 +1,75 @@
+use style_transfer_wasm::pipeline::codec::{
+    base64_encode, data_url, decode_rgba, encode_jpeg, encode_png,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn gradient(width: u32, height: u32) -> Vec<u8> {
+    (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            [(x * 255 / width) as u8, (y * 255 / height) as u8, 128, 255]
+        })
+        .collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_base64_matches_rfc_4648_vectors() {
+    let vectors = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+    for (input, expected) in vectors {
+        assert_eq!(base64_encode(input.as_bytes()), expected);
+    }
+    assert_eq!(base64_encode(&[0xfb, 0xff]), "+/8=");
+    assert_eq!(data_url("image/png", b"foo"), "data:image/png;base64,Zm9v");
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_png_round_trips_exactly() {
+    let mut pixels = gradient(7, 5);
+    // Alpha survives PNG
+    pixels[3] = 10;
+    let decoded = decode_rgba(&encode_png(&pixels, 7, 5).unwrap()).unwrap();
+    assert_eq!((decoded.width, decoded.height), (7, 5));
+    assert_eq!(decoded.pixels, pixels);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_jpeg_is_opaque_and_close() {
+    let pixels = gradient(32, 32);
+    let encoded = encode_jpeg(&pixels, 32, 32, 0.92).unwrap();
+    assert_eq!(&encoded[..2], &[0xff, 0xd8]);
+    let decoded = decode_rgba(&encoded).unwrap();
+    assert_eq!((decoded.width, decoded.height), (32, 32));
+    let max_error = decoded
+        .pixels
+        .iter()
+        .zip(&pixels)
+        .map(|(&a, &b)| (a as i32 - b as i32).abs())
+        .max()
+        .unwrap();
+    assert!(max_error <= 12, "max error {}", max_error);
+    assert!(decoded.pixels.chunks_exact(4).all(|px| px[3] == 255));
+
+    // Lower quality gives a smaller file
+    let small = encode_jpeg(&pixels, 32, 32, 0.1).unwrap();
+    assert!(small.len() < encoded.len());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_decode_rejects_other_bytes() {
+    assert!(decode_rgba(b"").is_err());
+    assert!(decode_rgba(b"<svg xmlns='http://www.w3.org/2000/svg'/>").is_err());
+}
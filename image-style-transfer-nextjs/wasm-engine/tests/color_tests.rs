@@ -0,0 +1,161 @@
+use style_transfer_wasm::pipeline;
+use style_transfer_wasm::pipeline::color::{
+    convert, linear_to_srgb, rgba_to_linear, srgb_to_linear, ColorSpace,
+};
+use style_transfer_wasm::ProcessOptions;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_transfer_functions_round_trip() {
+    for i in 0..=1000 {
+        let v = i as f32 / 1000.0;
+        let encoded = linear_to_srgb(srgb_to_linear(v));
+        assert!((encoded - v).abs() < 1e-5, "{} came back as {}", v, encoded);
+        let linear = srgb_to_linear(linear_to_srgb(v));
+        assert!((linear - v).abs() < 1e-5, "{} came back as {}", v, linear);
+    }
+    // Extended-range values mirror around zero
+    for v in [-0.5, -0.01, 1.5] {
+        assert!((linear_to_srgb(srgb_to_linear(v)) - v).abs() < 1e-5);
+    }
+    assert_eq!(srgb_to_linear(-0.5), -srgb_to_linear(0.5));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_transfer_functions_are_piecewise_srgb() {
+    assert_eq!(srgb_to_linear(0.0), 0.0);
+    assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+    // The linear toe, where a pure 2.2 power would be far darker
+    assert!((srgb_to_linear(0.04) - 0.04 / 12.92).abs() < 1e-7);
+    assert!((srgb_to_linear(0.5) - 0.214_041_14).abs() < 1e-6);
+    assert!((linear_to_srgb(0.18) - 0.461_356_1).abs() < 1e-6);
+    // Both segments meet at the knee
+    let below = srgb_to_linear(0.040_45);
+    let above = srgb_to_linear(0.040_450_1);
+    assert!((above - below).abs() < 1e-6);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_convert_between_spaces() {
+    let original = vec![0.0, 0.2, 0.5, 0.8, 1.0, 0.731];
+    let mut tensor = original.clone();
+    convert(&mut tensor, ColorSpace::Srgb, ColorSpace::Srgb);
+    assert_eq!(tensor, original);
+    convert(&mut tensor, ColorSpace::Srgb, ColorSpace::Linear);
+    assert!(tensor[2] < 0.25);
+    convert(&mut tensor, ColorSpace::Linear, ColorSpace::Srgb);
+    for (v, expected) in tensor.iter().zip(&original) {
+        assert!((v - expected).abs() < 1e-5);
+    }
+
+    let linear = rgba_to_linear(&[255, 128, 0, 51]);
+    assert!((linear[0] - 1.0).abs() < 1e-6);
+    assert!((linear[1] - srgb_to_linear(128.0 / 255.0)).abs() < 1e-7);
+    assert_eq!(linear[3], 0.2);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_linear_blending_interpolates_light() {
+    // Half red over half green: linear light gives a brighter, less muddy mix
+    let red = vec![1.0, 0.0, 0.0];
+    let green = vec![0.0, 1.0, 0.0];
+    let mut linear_red = red.clone();
+    let mut linear_green = green.clone();
+    convert(&mut linear_red, ColorSpace::Srgb, ColorSpace::Linear);
+    convert(&mut linear_green, ColorSpace::Srgb, ColorSpace::Linear);
+    let mut mixed =
+        pipeline::apply_strength(&linear_red, linear_green, 0.5, None, ColorSpace::Linear);
+    assert_eq!(mixed, [0.5, 0.5, 0.0]);
+    convert(&mut mixed, ColorSpace::Linear, ColorSpace::Srgb);
+    assert!((mixed[0] - linear_to_srgb(0.5)).abs() < 1e-6);
+
+    // The sRGB-space blend approximates this with a 2.2 power
+    let gamma = pipeline::apply_strength(&red, green, 0.5, None, ColorSpace::Srgb);
+    assert!((gamma[0] - mixed[0]).abs() < 0.01);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_working_space_option() {
+    let options: ProcessOptions =
+        serde_json::from_value(serde_json::json!({ "working_space": "linear" })).unwrap();
+    assert_eq!(options.working_space, ColorSpace::Linear);
+    assert!(
+        serde_json::from_value::<ProcessOptions>(serde_json::json!({ "working_space": "p3" }))
+            .is_err()
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_preserved_color_keeps_the_original_chroma() {
+    use style_transfer_wasm::pipeline::color::preserve_chroma;
+
+    let luma = |rgb: &[f32]| 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+    let original = [0.8, 0.3, 0.2, 0.1, 0.4, 0.9];
+    let stylized = [0.2, 0.6, 0.9, 0.5, 0.5, 0.5];
+    let mut kept = stylized;
+    preserve_chroma(&original, &mut kept, ColorSpace::Srgb);
+    for ((kept, original), stylized) in kept
+        .chunks_exact(3)
+        .zip(original.chunks_exact(3))
+        .zip(stylized.chunks_exact(3))
+    {
+        assert!((luma(kept) - luma(stylized)).abs() < 1e-6);
+        // Same color differences as the original, so the same Cb and Cr
+        assert!(((kept[0] - kept[1]) - (original[0] - original[1])).abs() < 1e-6);
+        assert!(((kept[2] - kept[1]) - (original[2] - original[1])).abs() < 1e-6);
+    }
+
+    // Linear tensors swap luma on their gamma-encoded values
+    let mut linear_original = original;
+    let mut linear_kept = stylized;
+    convert(&mut linear_original, ColorSpace::Srgb, ColorSpace::Linear);
+    convert(&mut linear_kept, ColorSpace::Srgb, ColorSpace::Linear);
+    preserve_chroma(&linear_original, &mut linear_kept, ColorSpace::Linear);
+    convert(&mut linear_kept, ColorSpace::Linear, ColorSpace::Srgb);
+    for (linear, srgb) in linear_kept.iter().zip(&kept) {
+        assert!((linear - srgb).abs() < 1e-5);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_channel_strengths_scale_each_channel() {
+    let original = [0.0; 6];
+    let stylized = [1.0; 6];
+    let mut blended = Vec::new();
+    pipeline::blend_channels_into(
+        &original,
+        &stylized,
+        1.0,
+        None,
+        [0.0, 0.5, 1.0],
+        ColorSpace::Linear,
+        &mut blended,
+    );
+    assert_eq!(blended, [0.0, 0.5, 1.0, 0.0, 0.5, 1.0]);
+
+    // The map and global strength multiply in
+    pipeline::blend_channels_into(
+        &original,
+        &stylized,
+        0.5,
+        Some(&[1.0, 0.0]),
+        [0.0, 0.5, 1.0],
+        ColorSpace::Linear,
+        &mut blended,
+    );
+    assert_eq!(blended, [0.0, 0.25, 0.5, 0.0, 0.0, 0.0]);
+
+    let options: ProcessOptions =
+        serde_json::from_value(serde_json::json!({ "channel_strength": [1.0, 0.3, 1.5] }))
+            .unwrap();
+    assert!(options.channel_strengths().is_err());
+}
@@ -0,0 +1,205 @@
+//! Fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+#[cfg(feature = "backend-tract")]
+use tract_onnx::pb;
+
+/// A single-node ONNX graph applying `op_type` to a float input `input`,
+/// declared with `input_shape` and `output_shape` (`None` is symbolic).
+#[cfg(feature = "backend-tract")]
+pub fn onnx_model(
+    op_type: &str,
+    input_shape: &[Option<i64>],
+    output_shape: &[Option<i64>],
+) -> Vec<u8> {
+    typed_onnx_model(
+        op_type,
+        pb::tensor_proto::DataType::Float,
+        input_shape,
+        output_shape,
+    )
+}
+
+/// `onnx_model` with input and output of `elem_type`.
+#[cfg(feature = "backend-tract")]
+pub fn typed_onnx_model(
+    op_type: &str,
+    elem_type: pb::tensor_proto::DataType,
+    input_shape: &[Option<i64>],
+    output_shape: &[Option<i64>],
+) -> Vec<u8> {
+    use pb::tensor_shape_proto::{dimension, Dimension};
+    use prost::Message;
+
+    let value_info = |name: &str, shape: &[Option<i64>]| pb::ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(pb::TypeProto {
+            value: Some(pb::type_proto::Value::TensorType(pb::type_proto::Tensor {
+                elem_type: elem_type as i32,
+                shape: Some(pb::TensorShapeProto {
+                    dim: shape
+                        .iter()
+                        .enumerate()
+                        .map(|(i, dim)| Dimension {
+                            value: Some(match dim {
+                                Some(dim) => dimension::Value::DimValue(*dim),
+                                None => dimension::Value::DimParam(format!("d{}", i)),
+                            }),
+                            ..Default::default()
+                        })
+                        .collect(),
+                }),
+            })),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    pb::ModelProto {
+        ir_version: 7,
+        opset_import: vec![pb::OperatorSetIdProto {
+            domain: String::new(),
+            version: 13,
+        }],
+        graph: Some(pb::GraphProto {
+            name: "fixture".to_string(),
+            node: vec![pb::NodeProto {
+                op_type: op_type.to_string(),
+                input: vec!["input".to_string()],
+                output: vec!["output".to_string()],
+                ..Default::default()
+            }],
+            input: vec![value_info("input", input_shape)],
+            output: vec![value_info("output", output_shape)],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+    .encode_to_vec()
+}
+
+#[cfg(feature = "backend-tract")]
+pub fn ints(name: &str, values: &[i64]) -> pb::AttributeProto {
+    pb::AttributeProto {
+        name: name.to_string(),
+        r#type: pb::attribute_proto::AttributeType::Ints as i32,
+        ints: values.to_vec(),
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "backend-tract")]
+pub fn string(name: &str, value: &str) -> pb::AttributeProto {
+    pb::AttributeProto {
+        name: name.to_string(),
+        r#type: pb::attribute_proto::AttributeType::String as i32,
+        s: value.as_bytes().to_vec(),
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "backend-tract")]
+pub fn floats(name: &str, dims: &[i64], values: Vec<f32>) -> pb::TensorProto {
+    pb::TensorProto {
+        name: name.to_string(),
+        dims: dims.to_vec(),
+        data_type: pb::tensor_proto::DataType::Float as i32,
+        float_data: values,
+        ..Default::default()
+    }
+}
+
+/// A scalar uint8 initializer, such as a zero point.
+#[cfg(feature = "backend-tract")]
+pub fn uint8(name: &str, value: u8) -> pb::TensorProto {
+    pb::TensorProto {
+        name: name.to_string(),
+        data_type: pb::tensor_proto::DataType::Uint8 as i32,
+        int32_data: vec![value as i32],
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "backend-tract")]
+pub fn int64s(name: &str, values: &[i64]) -> pb::TensorProto {
+    pb::TensorProto {
+        name: name.to_string(),
+        dims: vec![values.len() as i64],
+        data_type: pb::tensor_proto::DataType::Int64 as i32,
+        int64_data: values.to_vec(),
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "backend-tract")]
+pub fn node(
+    op_type: &str,
+    inputs: &[&str],
+    output: &str,
+    attribute: Vec<pb::AttributeProto>,
+) -> pb::NodeProto {
+    pb::NodeProto {
+        name: output.to_string(),
+        op_type: op_type.to_string(),
+        input: inputs.iter().map(|i| i.to_string()).collect(),
+        output: vec![output.to_string()],
+        attribute,
+        ..Default::default()
+    }
+}
+
+/// A `[1, 3, side, side]` graph of `nodes` from `"input"` to `"output"`.
+#[cfg(feature = "backend-tract")]
+pub fn graph_model(
+    side: i64,
+    nodes: Vec<pb::NodeProto>,
+    initializer: Vec<pb::TensorProto>,
+) -> Vec<u8> {
+    use pb::tensor_shape_proto::{dimension, Dimension};
+    use prost::Message;
+
+    let value_info = |name: &str| pb::ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(pb::TypeProto {
+            value: Some(pb::type_proto::Value::TensorType(pb::type_proto::Tensor {
+                elem_type: pb::tensor_proto::DataType::Float as i32,
+                shape: Some(pb::TensorShapeProto {
+                    dim: [1, 3, side, side]
+                        .iter()
+                        .map(|&dim| Dimension {
+                            value: Some(dimension::Value::DimValue(dim)),
+                            ..Default::default()
+                        })
+                        .collect(),
+                }),
+            })),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    pb::ModelProto {
+        ir_version: 7,
+        opset_import: vec![pb::OperatorSetIdProto {
+            domain: String::new(),
+            version: 13,
+        }],
+        graph: Some(pb::GraphProto {
+            name: "fixture".to_string(),
+            node: nodes,
+            initializer,
+            input: vec![value_info("input")],
+            output: vec![value_info("output")],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+    .encode_to_vec()
+}
+
+/// Deterministic values in [-scale, scale] for weights and inputs.
+#[cfg(feature = "backend-tract")]
+pub fn ramp(len: usize, scale: f32) -> Vec<f32> {
+    (0..len)
+        .map(|i| ((i * 37 % 23) as f32 / 11.0 - 1.0) * scale)
+        .collect()
+}
@@ -0,0 +1,153 @@
+use std::io::Write;
+
+use style_transfer_wasm::pipeline::{self, ModelCompression};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_gzip_is_sniffed_and_decompressed() {
+    let model: Vec<u8> = (0..4096).map(|i| (i % 7) as u8).collect();
+    let compressed = gzip(&model);
+    assert!(compressed.len() < model.len());
+    assert_eq!(
+        pipeline::detect_compression(&compressed),
+        ModelCompression::Gzip
+    );
+    assert_eq!(pipeline::detect_compression(&model), ModelCompression::None);
+
+    assert_eq!(
+        pipeline::decompress_model(compressed.clone(), None).unwrap(),
+        model
+    );
+    assert_eq!(
+        pipeline::decompress_model(compressed.clone(), Some(ModelCompression::Gzip)).unwrap(),
+        model
+    );
+    // Declaring "none" skips sniffing
+    assert_eq!(
+        pipeline::decompress_model(compressed.clone(), Some(ModelCompression::None)).unwrap(),
+        compressed
+    );
+}
+
+fn brotli(bytes: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    brotli::BrotliCompress(&mut &bytes[..], &mut compressed, &Default::default()).unwrap();
+    compressed
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_declared_brotli_is_decompressed() {
+    let model: Vec<u8> = (0..4096).map(|i| (i % 7) as u8).collect();
+    let compressed = brotli(&model);
+    assert!(compressed.len() < model.len());
+    // No magic number, so it has to be declared
+    assert_eq!(
+        pipeline::detect_compression(&compressed),
+        ModelCompression::None
+    );
+    assert_eq!(
+        pipeline::decompress_model(compressed, Some(ModelCompression::Brotli)).unwrap(),
+        model
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_bad_payloads_fail() {
+    let mut truncated = gzip(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    truncated.truncate(12);
+    assert!(pipeline::decompress_model(truncated, None).is_err());
+    assert!(pipeline::decompress_model(vec![1, 2, 3], Some(ModelCompression::Gzip)).is_err());
+    let mut truncated = brotli(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    truncated.truncate(truncated.len() / 2);
+    assert!(pipeline::decompress_model(truncated, Some(ModelCompression::Brotli)).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_compression_metadata_field() {
+    let metadata: pipeline::ModelMetadata = serde_json::from_value(serde_json::json!({
+        "name": "gz",
+        "model_url": "/models/gz.onnx.gz",
+        "compression": "gzip",
+    }))
+    .unwrap();
+    assert_eq!(metadata.compression, Some(ModelCompression::Gzip));
+    assert_eq!(pipeline::default_registry()[0].compression, None);
+}
+
+/// Feeds `compressed` to a streaming decompressor `chunk` bytes at a time,
+/// the way a download grows.
+fn stream(
+    compressed: &[u8],
+    chunk: usize,
+    declared: Option<ModelCompression>,
+) -> Result<Vec<u8>, String> {
+    let mut decoder = pipeline::StreamingDecompressor::new(declared);
+    let mut received = Vec::new();
+    for piece in compressed.chunks(chunk) {
+        received.extend_from_slice(piece);
+        decoder.catch_up(&received);
+    }
+    decoder.finish(&received)
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_streaming_matches_whole_file_decompression() {
+    let model: Vec<u8> = (0..20_000).map(|i| (i * 31 % 251) as u8).collect();
+    for chunk in [1, 7, 1000, 100_000] {
+        assert_eq!(stream(&gzip(&model), chunk, None).unwrap(), model);
+        assert_eq!(
+            stream(&brotli(&model), chunk, Some(ModelCompression::Brotli)).unwrap(),
+            model
+        );
+        assert_eq!(stream(&model, chunk, None).unwrap(), model);
+    }
+    assert_eq!(stream(&[9], 1, None).unwrap(), vec![9]);
+    assert!(stream(&[], 1, None).unwrap().is_empty());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_streaming_starts_over_with_the_download() {
+    let model: Vec<u8> = (0..4096).map(|i| (i % 13) as u8).collect();
+    let compressed = gzip(&model);
+    let mut decoder = pipeline::StreamingDecompressor::new(None);
+    decoder.catch_up(&compressed[..compressed.len() / 2]);
+    // The server sent the whole file again
+    decoder.catch_up(&compressed[..10]);
+    assert_eq!(decoder.finish(&compressed).unwrap(), model);
+
+    let mut truncated = brotli(&model);
+    truncated.truncate(truncated.len() / 2);
+    assert!(stream(&truncated, 64, Some(ModelCompression::Brotli)).is_err());
+    assert!(stream(&[0x1f, 0x8b, 0, 1, 2, 3], 2, None).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_compression_is_implied_by_the_url() {
+    use pipeline::compression_from_url;
+
+    assert_eq!(
+        compression_from_url("/models/candy.onnx.gz"),
+        Some(ModelCompression::Gzip)
+    );
+    assert_eq!(
+        compression_from_url("https://cdn.example.com/mosaic.onnx.br?v=3#top"),
+        Some(ModelCompression::Brotli)
+    );
+    assert_eq!(compression_from_url("/models/candy.onnx"), None);
+    assert_eq!(compression_from_url("/models/candy.onnx?format=.gz"), None);
+}
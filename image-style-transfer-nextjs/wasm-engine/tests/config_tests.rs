@@ -0,0 +1,60 @@
+use serde_json::json;
+use style_transfer_wasm::config::{EngineConfig, LogLevel, PreferredBackend};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn object(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    value.as_object().unwrap().clone()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_config_round_trips_through_json() {
+    let config = EngineConfig {
+        default_strength: 0.4,
+        preferred_style: Some("monet_water_lilies".to_string()),
+        strict_mode: true,
+        log_level: LogLevel::Warn,
+        max_input_dimension: 2048,
+        preferred_backend: PreferredBackend::Cpu,
+        retain_model_bytes: false,
+        download_attempts: 5,
+        download_retry_delay_ms: 250,
+        download_timeout_ms: 0,
+        trust_model_shapes: true,
+        persistent_model_cache: false,
+        half_precision: true,
+        deferred_optimization: true,
+        prefetch_concurrency: 2,
+    };
+    let stored = object(serde_json::to_value(&config).unwrap());
+    assert_eq!(EngineConfig::default().merged(&stored), Ok(config));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_config_ignores_unknown_fields() {
+    let stored = object(json!({ "log_level": "debug", "added_in_v9": { "anything": true } }));
+    let merged = EngineConfig::default().merged(&stored).unwrap();
+    assert_eq!(merged.log_level, LogLevel::Debug);
+    assert_eq!(merged.default_strength, 1.0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_config_reports_every_rejected_field() {
+    let stored = object(json!({
+        "default_strength": 3.0,
+        "strict_mode": "yes",
+        "preferred_backend": "cpu",
+        "download_attempts": 0,
+        "prefetch_concurrency": 0,
+    }));
+    let rejected = EngineConfig::default().merged(&stored).unwrap_err();
+    assert_eq!(rejected.len(), 4);
+    assert!(rejected.iter().any(|r| r.starts_with("download_attempts")));
+    assert!(rejected.iter().any(|r| r.starts_with("prefetch_concurrency")));
+    assert!(rejected.iter().any(|r| r.starts_with("default_strength")));
+    assert!(rejected.iter().any(|r| r.starts_with("strict_mode")));
+}
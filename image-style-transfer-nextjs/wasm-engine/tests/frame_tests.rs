@@ -0,0 +1,121 @@
+use style_transfer_wasm::pipeline::frame::{
+    frame_to_rgba, FrameColor, FramePacer, PixelFormat, PlaneLayout, YuvMatrix,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn plane(offset: usize, stride: usize) -> PlaneLayout {
+    PlaneLayout { offset, stride }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_video_range_gray_maps_to_full_range() {
+    // 2x2 I420: black and white luma, neutral chroma
+    let data = [16, 235, 235, 16, 128, 128];
+    let planes = [plane(0, 2), plane(4, 1), plane(5, 1)];
+    let pixels = frame_to_rgba(
+        PixelFormat::I420,
+        &data,
+        &planes,
+        (2, 2),
+        FrameColor::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        pixels,
+        vec![0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 255]
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_chroma_is_shared_by_each_2x2_block() {
+    // Full-range red in BT.601 and BT.709, as I420 and as NV12
+    for (matrix, (y, u, v)) in [
+        (YuvMatrix::Bt601, (76, 85, 255)),
+        (YuvMatrix::Bt709, (54, 99, 255)),
+    ] {
+        let color = FrameColor {
+            matrix,
+            full_range: true,
+        };
+        let i420 = frame_to_rgba(
+            PixelFormat::I420,
+            &[y, y, y, y, u, v],
+            &[plane(0, 2), plane(4, 1), plane(5, 1)],
+            (2, 2),
+            color,
+        )
+        .unwrap();
+        let nv12 = frame_to_rgba(
+            PixelFormat::Nv12,
+            &[y, y, y, y, u, v],
+            &[plane(0, 2), plane(4, 2)],
+            (2, 2),
+            color,
+        )
+        .unwrap();
+        assert_eq!(i420, nv12);
+        for pixel in i420.chunks_exact(4) {
+            assert!(pixel[0] >= 250, "{:?} {:?}", matrix, pixel);
+            assert!(pixel[1] <= 5 && pixel[2] <= 5, "{:?} {:?}", matrix, pixel);
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_bgrx_rows_follow_offset_and_stride() {
+    // One padding byte up front and four after each 1-pixel row
+    let data = [0, 1, 2, 3, 9, 0, 0, 0, 0, 4, 5, 6, 9];
+    let pixels = frame_to_rgba(
+        PixelFormat::Bgrx,
+        &data,
+        &[plane(1, 8)],
+        (1, 2),
+        FrameColor::default(),
+    )
+    .unwrap();
+    assert_eq!(pixels, vec![3, 2, 1, 255, 6, 5, 4, 255]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_short_buffers_and_unknown_formats_are_rejected() {
+    let short = frame_to_rgba(
+        PixelFormat::I420,
+        &[0; 5],
+        &[plane(0, 2), plane(4, 1), plane(5, 1)],
+        (2, 2),
+        FrameColor::default(),
+    );
+    assert!(short.unwrap_err().contains("plane 2"));
+    let missing = frame_to_rgba(
+        PixelFormat::Nv12,
+        &[0; 6],
+        &[plane(0, 2)],
+        (2, 2),
+        FrameColor::default(),
+    );
+    assert!(missing.is_err());
+    assert!(PixelFormat::parse("I444").is_err());
+    assert_eq!(PixelFormat::parse("NV12"), Ok(PixelFormat::Nv12));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_frames_captured_during_inference_are_skipped() {
+    // 30 fps camera, 50 ms per stylized frame
+    let mut pacer = FramePacer::default();
+    let mut stylized = Vec::new();
+    for timestamp in (0..10).map(|frame| frame as f64 * 33_333.0) {
+        if pacer.admit(timestamp) {
+            stylized.push(timestamp as u32 / 33_333);
+            pacer.finished(timestamp, 50.0);
+        }
+    }
+    assert_eq!(stylized, vec![0, 2, 4, 6, 8]);
+    assert_eq!((pacer.processed, pacer.skipped), (5, 5));
+}
@@ -0,0 +1,95 @@
+//! Golden digests of the full pipeline over the synthetic inputs in
+//! `pipeline::golden`. See that module for how to regenerate them.
+
+use style_transfer_wasm::pipeline::golden::{
+    golden_hash, golden_styles, GoldenInput, IDENTITY_STYLE,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const GOLDEN: &[(&str, &str, u64)] = &[
+    ("van_gogh_starry_night", "gradient", 0x42a7075bb2b94586),
+    ("van_gogh_starry_night", "checkerboard", 0x6bef944580017bb0),
+    ("van_gogh_starry_night", "noise", 0x8779a7cacd9485f3),
+    ("picasso_cubist", "gradient", 0xedd0af27703d86f9),
+    ("picasso_cubist", "checkerboard", 0x6dd18c3fe37868a4),
+    ("picasso_cubist", "noise", 0xfbfcd5bb672bb066),
+    ("cyberpunk_neon", "gradient", 0x0be82cb0f2880f26),
+    ("cyberpunk_neon", "checkerboard", 0x4ecf773cd9c61018),
+    ("cyberpunk_neon", "noise", 0x14bf7b2dbd3030ed),
+    ("monet_water_lilies", "gradient", 0x6331a4e1e259b22c),
+    ("monet_water_lilies", "checkerboard", 0x23513acd3d727cff),
+    ("monet_water_lilies", "noise", 0x0b0f20e28f3cdf2a),
+    ("anime_studio_ghibli", "gradient", 0xdc72503e14fc0cd2),
+    ("anime_studio_ghibli", "checkerboard", 0x2e01d065677286df),
+    ("anime_studio_ghibli", "noise", 0x9a703b68c43b2f5b),
+    ("cinematic_widescreen", "gradient", 0x1c9228da98818dae),
+    ("cinematic_widescreen", "checkerboard", 0x7df9c27dda465e27),
+    ("cinematic_widescreen", "noise", 0x53b7e0392e2be593),
+    ("identity_onnx", "gradient", 0x8e0c9f96e7f681fd),
+    ("identity_onnx", "checkerboard", 0xf8b13c43caee87d0),
+    ("identity_onnx", "noise", 0x06e412db5ae90e6b),
+];
+
+fn regenerating() -> bool {
+    std::env::var("UPDATE_GOLDEN").is_ok_and(|value| value == "1")
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_pipeline_golden_hashes() {
+    let mut actual = Vec::new();
+    for style in golden_styles() {
+        if style == IDENTITY_STYLE && !cfg!(feature = "backend-tract") {
+            continue;
+        }
+        for input in GoldenInput::ALL {
+            actual.push((
+                style.clone(),
+                input.name(),
+                golden_hash(&style, input).unwrap(),
+            ));
+        }
+    }
+
+    if regenerating() {
+        for (style, input, hash) in &actual {
+            println!("    ({:?}, {:?}, 0x{:016x}),", style, input, hash);
+        }
+        return;
+    }
+    for (style, input, hash) in &actual {
+        let expected = GOLDEN
+            .iter()
+            .find(|(s, i, _)| s == style && i == input)
+            .unwrap_or_else(|| panic!("no golden hash for {} over {}", style, input));
+        assert_eq!(
+            *hash, expected.2,
+            "output of {} over {} changed",
+            style, input
+        );
+    }
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_identity_model_passes_pixels_through() {
+    use style_transfer_wasm::pipeline::golden::golden_output;
+
+    for input in GoldenInput::ALL {
+        assert_eq!(
+            golden_output(IDENTITY_STYLE, input).unwrap(),
+            input.pixels()
+        );
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_unknown_styles_are_rejected() {
+    assert!(golden_hash("no_such_style", GoldenInput::Noise).is_err());
+    assert_eq!(GoldenInput::parse("noise"), Some(GoldenInput::Noise));
+    assert_eq!(GoldenInput::parse("static"), None);
+}
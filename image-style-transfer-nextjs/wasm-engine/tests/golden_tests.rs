@@ -0,0 +1,166 @@
+//! Golden values for the simulated fallback styles and the resize filters.
+//!
+//! Each style is run over a fixed 256x256 gradient, and each filter over a
+//! checkerboard, and the quantized output is hashed. A failure here means a
+//! refactor changed what users see when no ONNX model is available, or how
+//! sources are resampled; update the constant only if that was intended.
+
+use style_transfer_wasm::pipeline;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const SIZE: usize = 256;
+
+fn gradient() -> Vec<f32> {
+    let mut tensor = Vec::with_capacity(SIZE * SIZE * 3);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            tensor.push(x as f32 / (SIZE - 1) as f32);
+            tensor.push(y as f32 / (SIZE - 1) as f32);
+            tensor.push(((x + y) % SIZE) as f32 / (SIZE - 1) as f32);
+        }
+    }
+    tensor
+}
+
+// FNV-1a over the 8-bit output, so last-ulp differences between native and
+// wasm libm don't count as a change
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn simulated_hash(style: &str, seed: u64) -> u64 {
+    let config = pipeline::SimulatedStyleConfig::preset(style).unwrap();
+    let output = pipeline::simulate_style(&gradient(), &config, SIZE as u32, seed);
+    fnv1a(&pipeline::tensor_to_rgba(&output, SIZE * SIZE))
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_simulated_golden_hashes() {
+    let expected = [
+        ("van_gogh_starry_night", 0x8ac6e8b91a618ef9),
+        ("picasso_cubist", 0x2fe4cb92791b9095),
+        ("cyberpunk_neon", 0x15eb61f2f9bfff92),
+        ("monet_water_lilies", 0x93f7e069a3734841),
+        ("anime_studio_ghibli", 0x694af8c2f04f1af1),
+        ("cinematic_widescreen", 0x8ac0cdd1affeb29c),
+    ];
+    for (style, hash) in expected {
+        assert_eq!(
+            simulated_hash(style, pipeline::DEFAULT_SIMULATION_SEED),
+            hash,
+            "simulated output for {} changed",
+            style
+        );
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_simulated_output_is_repeatable() {
+    for style in ["van_gogh_starry_night", "anime_studio_ghibli"] {
+        assert_eq!(simulated_hash(style, 42), simulated_hash(style, 42));
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_grain_follows_seed() {
+    let config = pipeline::SimulatedStyleConfig {
+        grain: 0.2,
+        ..pipeline::SimulatedStyleConfig::preset("monet_water_lilies").unwrap()
+    };
+    let input = gradient();
+    let run = |seed| pipeline::simulate_style(&input, &config, SIZE as u32, seed);
+    assert_eq!(run(1), run(1));
+    assert_ne!(run(1), run(2));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_xorshift_sequence_is_stable() {
+    let mut rng = pipeline::XorShift64::new(1);
+    let first: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+    let mut again = pipeline::XorShift64::new(1);
+    assert_eq!(first, (0..3).map(|_| again.next_u64()).collect::<Vec<_>>());
+    assert_eq!(first[0], 0x47e4_ce4b_896c_dd1d);
+
+    let value = pipeline::XorShift64::new(99).next_f32();
+    assert!((0.0..1.0).contains(&value));
+}
+
+fn checkerboard(width: u32, height: u32, cell: u32) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let on = (x / cell + y / cell).is_multiple_of(2);
+            pixels.extend_from_slice(if on { &[240, 200, 20, 255] } else { &[10, 30, 90, 255] });
+        }
+    }
+    pixels
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_resize_filter_golden_hashes() {
+    let pixels = checkerboard(64, 48, 3);
+    let expected = [
+        (pipeline::ResizeFilter::Nearest, 0x404b9a11ebac57d2),
+        (pipeline::ResizeFilter::Bilinear, 0xf138ba75f6db1cb5),
+        (pipeline::ResizeFilter::Bicubic, 0xb8c7a5841f91d32b),
+        (pipeline::ResizeFilter::Lanczos3, 0xe4a48d5dac80eb40),
+    ];
+    for (filter, hash) in expected {
+        let resized = pipeline::resize_rgba(&pixels, 64, 48, 25, 19, filter);
+        assert_eq!(resized.len(), 25 * 19 * 4);
+        assert_eq!(fnv1a(&resized), hash, "{:?} resize output changed", filter);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_working_size_is_capped_by_the_model() {
+    use pipeline::resize::working_size;
+
+    // A 12 MP photo for a 256x256 model is drawn at 1024 on its long side
+    assert_eq!(working_size(4000, 3000, (256, 256), 0), (1024, 768));
+    assert_eq!(working_size(4000, 3000, (256, 256), 512), (512, 384));
+    assert_eq!(working_size(640, 480, (256, 256), 0), (640, 480));
+
+    // Converting while resizing gives the same bytes as resizing floats
+    let pixels = checkerboard(64, 48, 3);
+    let floats: Vec<f32> = pixels.iter().map(|&v| v as f32).collect();
+    let from_bytes =
+        pipeline::resize_rgba(&pixels, 64, 48, 25, 19, pipeline::ResizeFilter::Lanczos3);
+    let from_floats =
+        pipeline::resize_rgba_f32(&floats, 64, 48, 25, 19, pipeline::ResizeFilter::Lanczos3);
+    let rounded: Vec<u8> = from_floats
+        .iter()
+        .map(|v| v.round().clamp(0.0, 255.0) as u8)
+        .collect();
+    assert_eq!(from_bytes, rounded);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tiny_sources_are_upscaled() {
+    use pipeline::resize::working_size;
+
+    assert_eq!(working_size(1, 1, (256, 256), 0), (1, 1));
+    let pixel = [200, 100, 50, 255];
+    for filter in [
+        pipeline::ResizeFilter::Nearest,
+        pipeline::ResizeFilter::Bilinear,
+        pipeline::ResizeFilter::Bicubic,
+        pipeline::ResizeFilter::Lanczos3,
+    ] {
+        let resized = pipeline::resize_rgba(&pixel, 1, 1, 256, 256, filter);
+        assert!(resized.chunks(4).all(|px| px == pixel), "{:?}", filter);
+        let floats = pipeline::resize_rgba_f32(&[0.5; 8], 2, 1, 256, 3, filter);
+        assert!(floats.iter().all(|v| (v - 0.5).abs() < 1e-6), "{:?}", filter);
+    }
+}
@@ -0,0 +1,167 @@
+mod common;
+
+use style_transfer_wasm::pipeline::graph::{BinaryKind, Graph, Op, Operand, PadMode, UnaryKind};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_lowered_graph_matches_tract() {
+    use common::*;
+    use style_transfer_wasm::pipeline::{self, graph::lower_onnx};
+
+    let nodes = vec![
+        node(
+            "Conv",
+            &["input", "w1", "b1"],
+            "c1",
+            vec![ints("pads", &[1, 1, 1, 1])],
+        ),
+        node(
+            "InstanceNormalization",
+            &["c1", "scale", "shift"],
+            "n1",
+            vec![],
+        ),
+        node("Relu", &["n1"], "r1", vec![]),
+        node(
+            "Resize",
+            &["r1", "", "scales"],
+            "up",
+            vec![string("mode", "nearest")],
+        ),
+        node(
+            "Pad",
+            &["up", "pads"],
+            "padded",
+            vec![string("mode", "reflect")],
+        ),
+        node(
+            "Conv",
+            &["padded", "w2", "b2"],
+            "c2",
+            vec![ints("strides", &[2, 2])],
+        ),
+        node("Add", &["c2", "input"], "residual", vec![]),
+        node("Mul", &["residual", "gain"], "scaled", vec![]),
+        node("Sigmoid", &["scaled"], "output", vec![]),
+    ];
+    let initializer = vec![
+        floats("w1", &[4, 3, 3, 3], ramp(4 * 3 * 9, 0.3)),
+        floats("b1", &[4], vec![0.1, -0.2, 0.0, 0.3]),
+        floats("scale", &[4], vec![1.0, 0.5, 2.0, 1.5]),
+        floats("shift", &[4], vec![0.0, 0.1, -0.1, 0.2]),
+        floats("scales", &[4], vec![1.0, 1.0, 2.0, 2.0]),
+        int64s("pads", &[0, 0, 1, 1, 0, 0, 1, 1]),
+        floats("w2", &[3, 4, 3, 3], ramp(3 * 4 * 9, 0.2)),
+        floats("b2", &[3], vec![0.05, 0.0, -0.05]),
+        floats("gain", &[1, 3, 1, 1], vec![1.5, 0.5, -1.0]),
+    ];
+    let bytes = graph_model(8, nodes, initializer);
+
+    let graph = lower_onnx(&bytes).unwrap();
+    assert_eq!(graph.ops.len(), 9);
+    let input = ramp(3 * 8 * 8, 1.0);
+    let (ours, shape) = graph.run_reference(&input, [3, 8, 8]).unwrap();
+    assert_eq!(shape, [3, 8, 8]);
+
+    let plan = pipeline::load_plan(&bytes).unwrap();
+    let (theirs, their_shape) = pipeline::run_features(&plan, &input, [1, 3, 8, 8]).unwrap();
+    assert_eq!(their_shape, vec![1, 3, 8, 8]);
+    let max_error = ours
+        .iter()
+        .zip(&theirs)
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0, f32::max);
+    assert!(max_error < 1e-4, "max error {}", max_error);
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_lowering_names_what_it_cannot_run() {
+    use common::*;
+    use style_transfer_wasm::pipeline::graph::lower_onnx;
+
+    let softmax = graph_model(
+        4,
+        vec![node("Softmax", &["input"], "output", vec![])],
+        vec![],
+    );
+    let error = lower_onnx(&softmax).unwrap_err();
+    assert!(error.contains("unsupported op Softmax"), "{}", error);
+
+    let bilinear = graph_model(
+        4,
+        vec![node(
+            "Resize",
+            &["input", "", "scales"],
+            "output",
+            vec![string("mode", "linear")],
+        )],
+        vec![floats("scales", &[4], vec![1.0, 1.0, 2.0, 2.0])],
+    );
+    let error = lower_onnx(&bilinear).unwrap_err();
+    assert!(error.contains("linear interpolation"), "{}", error);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_reference_padding_modes() {
+    // One channel, 1x3: [1, 2, 3]
+    let pad = |mode| Graph {
+        ops: vec![Op::Pad {
+            input: 0,
+            pads: [0, 2, 0, 2],
+            mode,
+        }],
+        output: 1,
+    };
+    let run = |graph: Graph| graph.run_reference(&[1.0, 2.0, 3.0], [1, 1, 3]).unwrap().0;
+    assert_eq!(
+        run(pad(PadMode::Reflect)),
+        vec![3.0, 2.0, 1.0, 2.0, 3.0, 2.0, 1.0]
+    );
+    assert_eq!(
+        run(pad(PadMode::Edge)),
+        vec![1.0, 1.0, 1.0, 2.0, 3.0, 3.0, 3.0]
+    );
+    assert_eq!(
+        run(pad(PadMode::Constant(9.0))),
+        vec![9.0, 9.0, 1.0, 2.0, 3.0, 9.0, 9.0]
+    );
+    // Reflecting needs more pixels than the padding
+    assert!(pad(PadMode::Reflect).shapes([1, 1, 2]).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_reference_shapes_check_operands() {
+    let graph = Graph {
+        ops: vec![
+            Op::Upsample {
+                input: 0,
+                scale: (2, 3),
+            },
+            Op::Unary {
+                input: 1,
+                kind: UnaryKind::Clip(0.0, 0.5),
+            },
+            Op::Binary {
+                lhs: Operand::Channel(vec![1.0, 2.0]),
+                rhs: Operand::Value(2),
+                kind: BinaryKind::Sub,
+            },
+        ],
+        output: 3,
+    };
+    let shapes = graph.shapes([2, 1, 1]).unwrap();
+    assert_eq!(shapes[3], [2, 2, 3]);
+    let (output, _) = graph.run_reference(&[0.25, 0.75], [2, 1, 1]).unwrap();
+    assert_eq!(&output[..6], &[0.75; 6]);
+    assert_eq!(&output[6..], &[1.5; 6]);
+    // Three channels don't fit two per-channel constants
+    assert!(graph.shapes([3, 1, 1]).is_err());
+}
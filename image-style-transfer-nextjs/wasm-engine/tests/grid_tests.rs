@@ -0,0 +1,44 @@
+use style_transfer_wasm::pipeline::grid::{
+    compose_grid, grid_dimensions, grid_strengths, MAX_GRID_CELLS,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_strengths_are_clamped_and_bounded() {
+    assert_eq!(grid_strengths(&[-0.5, 0.25, 2.0]), Ok(vec![0.0, 0.25, 1.0]));
+    assert!(grid_strengths(&[]).is_err());
+    assert!(grid_strengths(&[0.5; MAX_GRID_CELLS + 1]).is_err());
+    assert!(grid_strengths(&[0.5, f32::NAN]).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_grid_dimensions() {
+    assert_eq!(grid_dimensions(1, 0), (1, 1));
+    assert_eq!(grid_dimensions(4, 0), (2, 2));
+    assert_eq!(grid_dimensions(5, 0), (3, 2));
+    assert_eq!(grid_dimensions(9, 0), (3, 3));
+    assert_eq!(grid_dimensions(3, 10), (3, 1));
+    assert_eq!(grid_dimensions(5, 2), (2, 3));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_cells_are_placed_row_by_row() {
+    // Three 1x2 cells in two columns: the fourth slot stays transparent
+    let cells: Vec<Vec<u8>> = (1..=3u8).map(|v| vec![v; 8]).collect();
+    let grid = compose_grid(&cells, 1, 2, 2);
+    let pixel = |x: usize, y: usize| grid[(y * 2 + x) * 4];
+    assert_eq!(grid.len(), 2 * 4 * 4);
+    assert_eq!(
+        [pixel(0, 0), pixel(1, 0), pixel(0, 1), pixel(1, 1)],
+        [1, 2, 1, 2]
+    );
+    assert_eq!(
+        [pixel(0, 2), pixel(1, 2), pixel(0, 3), pixel(1, 3)],
+        [3, 0, 3, 0]
+    );
+}
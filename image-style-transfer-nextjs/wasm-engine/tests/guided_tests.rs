@@ -0,0 +1,49 @@
+use style_transfer_wasm::pipeline::guided::guided_upsample;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// An interleaved RGB gradient with a hard vertical edge at `edge`.
+fn edged(width: u32, height: u32, edge: u32) -> Vec<f32> {
+    (0..height)
+        .flat_map(|_| (0..width).map(move |x| if x < edge { 0.2 } else { 0.8 }))
+        .flat_map(|v| [v, v * 0.5, 1.0 - v])
+        .collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_linear_styles_carry_over_exactly() {
+    // A style that is a linear function of the input carries over exactly
+    // away from edges, and closely next to them
+    let input = edged(8, 8, 4);
+    let stylized: Vec<f32> = input.iter().map(|v| 0.5 * v + 0.1).collect();
+    let high_input = edged(32, 32, 16);
+    let upsampled = guided_upsample(&input, &stylized, (8, 8), high_input.clone(), (32, 32));
+    assert_eq!(upsampled.len(), 32 * 32 * 3);
+    for (i, (value, source)) in upsampled.iter().zip(&high_input).enumerate() {
+        let x = i / 3 % 32;
+        let tolerance = if x.abs_diff(16) > 4 { 1e-3 } else { 0.05 };
+        assert!(
+            (value - (0.5 * source + 0.1)).abs() < tolerance,
+            "{} for {} at {}",
+            value,
+            source,
+            x
+        );
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_edges_stay_sharp() {
+    let input = edged(8, 8, 4);
+    // Inverting the style across the edge
+    let stylized: Vec<f32> = input.iter().map(|v| 1.0 - v).collect();
+    let upsampled = guided_upsample(&input, &stylized, (8, 8), edged(32, 32, 16), (32, 32));
+    let red = |x: usize| upsampled[(16 * 32 + x) * 3];
+    // Right next to the edge each side keeps its own value, where plain
+    // bilinear upsampling would blend them
+    assert!(red(15) > 0.7, "{}", red(15));
+    assert!(red(16) < 0.3, "{}", red(16));
+}
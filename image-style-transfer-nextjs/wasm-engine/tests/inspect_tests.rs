@@ -0,0 +1,87 @@
+mod common;
+
+use style_transfer_wasm::pipeline::inspect::failing_node;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_failing_node_is_read_from_the_error() {
+    assert_eq!(
+        failing_node("Translating node #4 \"conv_2\" Conv: unsupported"),
+        Some("conv_2".to_string())
+    );
+    assert_eq!(failing_node("issue #12 without a node"), None);
+    assert_eq!(failing_node("no node at all"), None);
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_inspecting_a_runnable_model() {
+    use style_transfer_wasm::pipeline::inspect_model;
+
+    let bytes = common::onnx_model("Identity", &[Some(1), Some(3), None, None], &[None; 4]);
+    let inspection = inspect_model(&bytes).unwrap();
+    assert_eq!(inspection.opset, Some(13));
+    assert_eq!(inspection.ops.get("Identity"), Some(&1));
+    assert_eq!(inspection.parameter_count, 0);
+    assert_eq!(inspection.inputs.len(), 1);
+    assert_eq!(inspection.inputs[0].name, "input");
+    assert_eq!(inspection.inputs[0].shape, ["1", "3", "d2", "d3"]);
+    assert_eq!(inspection.inputs[0].dtype, "FLOAT");
+    assert_eq!(inspection.optimize_error, None);
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_unsupported_ops_are_reported_not_fatal() {
+    use style_transfer_wasm::pipeline::inspect_model;
+
+    let bytes = common::onnx_model(
+        "NoSuchOp",
+        &[Some(1), Some(3), Some(4), Some(4)],
+        &[None; 4],
+    );
+    let inspection = inspect_model(&bytes).unwrap();
+    assert_eq!(inspection.ops.get("NoSuchOp"), Some(&1));
+    let failure = inspection
+        .optimize_error
+        .expect("NoSuchOp can't be optimized");
+    assert!(failure.message.contains("NoSuchOp"), "{}", failure.message);
+    // Unnamed nodes are named after their first output
+    assert_eq!(failure.node.as_deref(), Some("output"));
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_plan_facts_describe_the_loaded_graph() {
+    use style_transfer_wasm::pipeline::{self, plan_facts};
+
+    let bytes = common::onnx_model("Relu", &[Some(1), Some(3), Some(8), Some(8)], &[None; 4]);
+    let facts = plan_facts(&pipeline::load_plan(&bytes).unwrap());
+    assert_eq!(facts.inputs.len(), 1);
+    assert_eq!(facts.inputs[0].name, "input");
+    assert_eq!(facts.inputs[0].shape, ["1", "3", "8", "8"]);
+    assert_eq!(facts.inputs[0].dtype, "FLOAT");
+    assert_eq!(facts.outputs.len(), 1);
+    assert_eq!(facts.outputs[0].name, "output");
+    assert_eq!(facts.outputs[0].shape, facts.inputs[0].shape);
+
+    // Unlike the file, the half precision plan takes f16
+    let half = plan_facts(&pipeline::inference::load_half_plan(&bytes).unwrap());
+    assert_eq!(half.inputs[0].dtype, "FLOAT16");
+    assert_eq!(
+        pipeline::inspect_model(&bytes).unwrap().inputs[0].dtype,
+        "FLOAT"
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_garbage_does_not_parse() {
+    assert!(style_transfer_wasm::pipeline::inspect_model(b"not a model").is_err());
+}
@@ -1,24 +1,1258 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use wasm_bindgen_test::*;
+use style_transfer_wasm::pipeline;
+use style_transfer_wasm::{ImageSourceKind, OutputFormat, ProcessOptions, StyleTransferEngine};
+use wasm_bindgen_test::*;
 
-    wasm_bindgen_test_configure!(run_in_browser);
+wasm_bindgen_test_configure!(run_in_browser);
 
-    #[wasm_bindgen_test]
-    fn test_engine_creation() {
-        let engine = StyleTransferEngine::new();
-        assert_eq!(engine.styles.len(), 3);
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_engine_creation() {
+    let engine = StyleTransferEngine::new();
+    assert_eq!(engine.model_count(), 6);
+    assert!(engine.style_names().contains(&"van_gogh_starry_night".to_string()));
+    assert!(engine.get_loaded_models().is_empty());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_image_blending() {
+    let engine = StyleTransferEngine::new();
+    let original = vec![0.0f32, 0.5f32, 1.0f32];
+    let stylized = vec![1.0f32, 0.5f32, 0.0f32];
+    let blended = engine.blend_tensors(&original, &stylized, 0.5);
+
+    assert_eq!(blended.len(), 3);
+    // Blending happens in gamma space, so the midpoint of 0 and 1 is 0.5^(1/2.2)
+    assert!((blended[0] - 0.5f32.powf(1.0 / 2.2)).abs() < 0.001);
+    assert!((blended[1] - 0.5).abs() < 0.001);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_blend_strength_endpoints() {
+    let original = vec![0.1f32, 0.4, 0.9];
+    let stylized = vec![0.8f32, 0.2, 0.3];
+
+    let none = pipeline::blend_tensors(&original, &stylized, 0.0);
+    let full = pipeline::blend_tensors(&original, &stylized, 1.0);
+    for i in 0..3 {
+        assert!((none[i] - original[i]).abs() < 1e-5);
+        assert!((full[i] - stylized[i]).abs() < 1e-5);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_blend_uses_shorter_input() {
+    let blended = pipeline::blend_tensors(&[0.5; 6], &[0.5; 3], 0.5);
+    assert_eq!(blended.len(), 3);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tensor_round_trip() {
+    let pixels: Vec<u8> = vec![0, 128, 255, 7, 10, 20, 30, 0];
+    let tensor = pipeline::rgba_to_tensor(&pixels);
+    assert_eq!(tensor.len(), 6);
+    assert!((tensor[2] - 1.0).abs() < f32::EPSILON);
+
+    let back = pipeline::tensor_to_rgba(&tensor, 2);
+    assert_eq!(back, vec![0, 128, 255, 255, 10, 20, 30, 255]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_clamped_float_pixels_match_8bit_path() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    let floats: Vec<f32> = bytes.iter().map(|&b| b as f32 / 255.0).collect();
+    assert_eq!(
+        pipeline::float_rgba_to_tensor(&floats, pipeline::ToneMap::Clamp),
+        pipeline::rgba_to_tensor(&bytes)
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tone_maps_compress_hdr_into_range() {
+    for tone_map in [pipeline::ToneMap::Reinhard, pipeline::ToneMap::Aces] {
+        let mapped: Vec<f32> = [-1.0, 0.0, 0.5, 1.0, 4.0, 100.0]
+            .iter()
+            .map(|&v| tone_map.apply(v))
+            .collect();
+        assert_eq!(mapped[0], 0.0);
+        assert!(mapped.windows(2).all(|w| w[0] <= w[1]), "{:?}", tone_map);
+        assert!(mapped.iter().all(|v| (0.0..=1.0).contains(v)));
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_simulated_styles_stay_in_range() {
+    let width = 8;
+    let input: Vec<f32> = (0..width * width * 3).map(|i| (i % 17) as f32 / 16.0).collect();
+    for style in [
+        "van_gogh_starry_night",
+        "picasso_cubist",
+        "cyberpunk_neon",
+        "monet_water_lilies",
+        "anime_studio_ghibli",
+    ] {
+        let config = pipeline::SimulatedStyleConfig::preset(style).unwrap();
+        let output = pipeline::simulate_style(&input, &config, width as u32, pipeline::DEFAULT_SIMULATION_SEED);
+        assert_eq!(output.len(), input.len());
+        assert!(output.iter().all(|v| (0.0..=1.0).contains(v)), "{} out of range", style);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_neutral_config_passes_through() {
+    assert!(pipeline::SimulatedStyleConfig::preset("no_such_style").is_none());
+
+    let input = vec![0.25f32; 12];
+    let neutral = pipeline::SimulatedStyleConfig::default();
+    assert_eq!(pipeline::simulate_style(&input, &neutral, 2, 1), input);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_simulated_config_defaults_missing_fields() {
+    let config: pipeline::SimulatedStyleConfig =
+        serde_json::from_value(serde_json::json!({ "quantization_levels": 4 })).unwrap();
+    assert_eq!(config.quantization_levels, 4);
+    assert_eq!(config.channel_gain, [1.0, 1.0, 1.0]);
+    assert_eq!(config.grain, 0.0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_invalid_onnx_bytes_fail_to_load() {
+    assert!(pipeline::load_plan(b"definitely not an onnx model").is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_process_rgba_without_plan_uses_simulation() {
+    let mut metadata = pipeline::default_registry().remove(0);
+    metadata.input_width = 4;
+    metadata.input_height = 4;
+    let pixels = vec![128u8; 4 * 4 * 4];
+
+    let input_tensor = pipeline::rgba_to_tensor(&pixels);
+    let stylized = pipeline::stylize(&input_tensor, &metadata, None, pipeline::DEFAULT_SIMULATION_SEED);
+    assert_eq!(stylized.path, pipeline::InferencePath::Simulated);
+    assert!(stylized.onnx_error.is_none());
+
+    let output = pipeline::process_rgba(&pixels, &metadata, None, 1.0, None, 7);
+    assert_eq!(output.len(), pixels.len());
+    assert!(output.chunks_exact(4).all(|px| px[3] == 255));
+    assert_ne!(output, pixels);
+
+    // Zero strength reproduces the input colors
+    let unstyled = pipeline::process_rgba(&pixels, &metadata, None, 0.0, None, 7);
+    assert!(unstyled.chunks_exact(4).all(|px| px == [128, 128, 128, 255]));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_strength_map_is_resampled_and_clamped() {
+    let map = pipeline::StrengthMap::new(vec![0.0, 2.0], 2, 1).unwrap();
+    assert_eq!(map.resampled(2, 1), vec![0.0, 1.0]);
+    assert_eq!(map.resampled(4, 2), vec![0.0, 0.25, 0.75, 1.0, 0.0, 0.25, 0.75, 1.0]);
+
+    // Width is taken from whichever candidate size matches
+    let inferred = pipeline::StrengthMap::infer(vec![0.5; 6], &[(4, 4), (3, 2)]).unwrap();
+    assert_eq!(inferred.resampled(3, 2), vec![0.5; 6]);
+    assert!(pipeline::StrengthMap::new(vec![0.5; 5], 2, 2).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_strength_map_scales_global_strength_per_pixel() {
+    let mut metadata = pipeline::default_registry().remove(0);
+    metadata.input_width = 2;
+    metadata.input_height = 1;
+    let pixels = vec![128u8; 2 * 4];
+
+    // Left pixel unstyled, right pixel fully styled
+    let map = pipeline::StrengthMap::new(vec![0.0, 1.0], 2, 1).unwrap();
+    let output = pipeline::process_rgba(&pixels, &metadata, None, 1.0, Some(&map), 7);
+    let full = pipeline::process_rgba(&pixels, &metadata, None, 1.0, None, 7);
+    assert_eq!(&output[..4], &[128, 128, 128, 255]);
+    assert_eq!(&output[4..], &full[4..]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_mask_luma_and_alpha_become_strength() {
+    // White, black, mid gray, and white but fully transparent
+    let pixels = [255, 255, 255, 255, 0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255, 0];
+    let map = pipeline::StrengthMap::from_mask(&pixels, 4, 1, false).unwrap().resampled(4, 1);
+    let expected = [1.0, 0.0, 128.0 / 255.0, 0.0];
+    assert!(map.iter().zip(expected).all(|(value, expected)| (value - expected).abs() < 1e-5), "{:?}", map);
+
+    let inverted = pipeline::StrengthMap::from_mask(&pixels, 4, 1, true).unwrap().resampled(4, 1);
+    assert!(inverted.iter().zip(expected).all(|(value, expected)| (value - (1.0 - expected)).abs() < 1e-5), "{:?}", inverted);
+    assert!(pipeline::StrengthMap::from_mask(&pixels, 2, 1, false).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_metadata_defaults_missing_fields() {
+    let metadata: style_transfer_wasm::ModelMetadata =
+        serde_json::from_value(serde_json::json!({ "name": "custom", "input_width": 64 })).unwrap();
+    assert_eq!(metadata.kind, style_transfer_wasm::ModelKind::Onnx);
+    assert_eq!(metadata.input_width, 64);
+    assert_eq!(metadata.input_height, 256);
+    assert_eq!(metadata.model_color_space, pipeline::ColorSpace::Srgb);
+
+    let filter: style_transfer_wasm::ModelMetadata =
+        serde_json::from_value(serde_json::json!({ "kind": "js_filter" })).unwrap();
+    assert_eq!(filter.kind, style_transfer_wasm::ModelKind::JsFilter);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_process_options_default_missing_fields() {
+    let options: ProcessOptions =
+        serde_json::from_value(serde_json::json!({ "format": "webp", "quality": 0.8 })).unwrap();
+    assert_eq!(options.format, OutputFormat::Webp);
+    assert_eq!(options.format.mime_type(), "image/webp");
+    assert_eq!(options.quality, Some(0.8));
+    assert!(!options.keep_size);
+    assert_eq!(options.tone_map, pipeline::ToneMap::Clamp);
+    assert_eq!(options.working_space, pipeline::ColorSpace::Srgb);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn test_js_filter_registration() {
+    let mut engine = StyleTransferEngine::new();
+    let invert = js_sys::Function::new_with_args("t", "return t.map(v => 1 - v);");
+    engine
+        .register_js_filter("invert", wasm_bindgen::JsValue::UNDEFINED, invert)
+        .unwrap();
+    assert_eq!(engine.model_count(), 7);
+
+    // Built-in ONNX entries can't be replaced by a filter
+    let noop = js_sys::Function::new_with_args("t", "return t;");
+    assert!(engine
+        .register_js_filter("van_gogh_starry_night", wasm_bindgen::JsValue::UNDEFINED, noop)
+        .is_err());
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_process_element_rejects_unloaded_video() {
+    let mut engine = StyleTransferEngine::new();
+    let video = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("video")
+        .unwrap();
+    let error = engine
+        .process_element(
+            &video.into(),
+            "picasso_cubist",
+            1.0,
+            wasm_bindgen::JsValue::UNDEFINED,
+        )
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("InvalidInput"));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_background_color_parsing_and_flattening() {
+    assert_eq!(pipeline::parse_hex_color("#fff"), Ok([255, 255, 255]));
+    assert_eq!(pipeline::parse_hex_color("#1a2B3c"), Ok([0x1a, 0x2b, 0x3c]));
+    assert!(pipeline::parse_hex_color("1a2b3c").is_err());
+    assert!(pipeline::parse_hex_color("#12345").is_err());
+    assert!(pipeline::parse_hex_color("#ggg").is_err());
+
+    let mut pixels = vec![200, 100, 0, 255, 200, 100, 0, 0, 0, 0, 0, 128];
+    pipeline::flatten_alpha(&mut pixels, [255, 255, 255]);
+    assert_eq!(pixels, vec![200, 100, 0, 255, 255, 255, 255, 255, 127, 127, 127, 255]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_source_alpha_survives_the_round_trip() {
+    let source = vec![200, 100, 0, 255, 10, 20, 30, 0, 40, 50, 60, 128];
+    let alpha = pipeline::alpha_channel(&source).unwrap();
+    assert_eq!(alpha, vec![255, 0, 128]);
+    // Opaque sources need nothing restored
+    assert_eq!(pipeline::alpha_channel(&[1, 2, 3, 255]), None);
+    assert_eq!(pipeline::tensor::float_alpha_channel(&[0.1, 0.2, 0.3, 0.5]), Some(vec![128]));
+
+    let tensor = pipeline::rgba_to_tensor(&source);
+    let mut pixels = pipeline::tensor_to_rgba(&tensor, 3);
+    pipeline::tensor::restore_alpha(&mut pixels, &alpha);
+    assert_eq!(pixels, source);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_saliency_favors_central_contrast() {
+    // Flat gray with a bright square in the middle
+    let (width, height) = (16u32, 12u32);
+    let mut tensor = vec![0.5f32; (width * height * 3) as usize];
+    for y in 4..8 {
+        for x in 6..10 {
+            let i = ((y * width + x) * 3) as usize;
+            tensor[i..i + 3].copy_from_slice(&[1.0, 1.0, 1.0]);
+        }
+    }
+    let saliency = pipeline::saliency_map(&tensor, width, height);
+    assert_eq!(saliency.len(), (width * height) as usize);
+    assert!(saliency.iter().all(|s| (0.0..=1.0).contains(s)));
+    assert_eq!(saliency.iter().copied().fold(0.0f32, f32::max), 1.0);
+    assert!(saliency[(6 * width + 7) as usize] > 0.9);
+    assert!(saliency[0] < 0.1);
+    // Deterministic
+    assert_eq!(saliency, pipeline::saliency_map(&tensor, width, height));
+
+    let mut strengths = vec![1.0, 0.5];
+    pipeline::saliency::attenuate_salient(&mut strengths, &[1.0, 0.0], 0.75);
+    assert_eq!(strengths, vec![0.25, 0.5]);
+
+    let options: ProcessOptions =
+        serde_json::from_value(serde_json::json!({ "protect_subject": 1.5 })).unwrap();
+    assert!(options.subject_protection().is_err());
+    assert_eq!(ProcessOptions::default().subject_protection(), Ok(0.0));
+}
+
+// 8x4 tensor whose values encode (x, y, channel), so any H/W swap shows up
+fn position_tensor() -> Vec<f32> {
+    let mut tensor = Vec::new();
+    for y in 0..4 {
+        for x in 0..8 {
+            for c in 0..3 {
+                tensor.push((y * 100 + x * 10 + c) as f32);
+            }
+        }
+    }
+    tensor
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_planar_layout_for_non_square_tensors() {
+    let tensor = position_tensor();
+    let planar = pipeline::interleaved_to_planar(&tensor);
+    // [1, 3, H=4, W=8]: channel, then row, then column
+    for c in 0..3 {
+        for y in 0..4 {
+            for x in 0..8 {
+                assert_eq!(planar[c * 32 + y * 8 + x], (y * 100 + x * 10 + c) as f32);
+            }
+        }
+    }
+    assert_eq!(pipeline::planar_to_interleaved(&planar), tensor);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_non_square_simulated_style_uses_pixel_coordinates() {
+    let metadata = pipeline::default_registry()
+        .into_iter()
+        .find(|m| m.name == "cinematic_widescreen")
+        .unwrap();
+    assert_eq!((metadata.input_width, metadata.input_height), (384, 216));
+    assert_eq!(metadata.kind, style_transfer_wasm::ModelKind::Simulated);
+
+    let config = pipeline::SimulatedStyleConfig {
+        swirl_frequency: 1.0,
+        swirl_amplitude: 0.1,
+        ..Default::default()
+    };
+    let output = pipeline::simulate_style(&vec![0.5; 8 * 4 * 3], &config, 8, 0);
+    for y in 0..4 {
+        for x in 0..8 {
+            let expected = 0.5 + (x as f32).sin() * 0.1 + (y as f32).cos() * 0.1;
+            assert!((output[(y * 8 + x) * 3] - expected).abs() < 1e-6);
+        }
+    }
+
+    let small = style_transfer_wasm::ModelMetadata {
+        input_width: 8,
+        input_height: 4,
+        ..metadata
+    };
+    let pixels: Vec<u8> = (0..8 * 4 * 4).map(|i| i as u8).collect();
+    let output = pipeline::process_rgba(&pixels, &small, None, 0.0, None, 7);
+    assert_eq!(output.len(), pixels.len());
+    // Zero strength keeps every pixel in place
+    for (out, px) in output.chunks_exact(4).zip(pixels.chunks_exact(4)) {
+        assert_eq!(out[..3], px[..3]);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_download_retry_policy() {
+    use pipeline::retry::{backoff_delay_ms, is_retryable_status};
+
+    for status in [408, 429, 500, 502, 503, 504] {
+        assert!(is_retryable_status(status), "{} should retry", status);
+    }
+    for status in [400, 401, 403, 404, 410] {
+        assert!(!is_retryable_status(status), "{} should fail fast", status);
+    }
+
+    // Exponential from the base, jitter scaling by [0.5, 1.5)
+    assert_eq!(backoff_delay_ms(1000, 1, 0.5), 1000.0);
+    assert_eq!(backoff_delay_ms(1000, 2, 0.5), 2000.0);
+    assert_eq!(backoff_delay_ms(1000, 3, 0.0), 2000.0);
+    assert_eq!(backoff_delay_ms(1000, 3, 1.0), 6000.0);
+    assert!(backoff_delay_ms(1000, u32::MAX, 0.5).is_finite());
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_error_reporter_receives_context() {
+    use wasm_bindgen::JsCast;
+
+    let contexts = js_sys::Array::new();
+    let sink = contexts.clone();
+    let reporter = wasm_bindgen::closure::Closure::<dyn FnMut(wasm_bindgen::JsValue, wasm_bindgen::JsValue)>::new(
+        move |_error, context| {
+            sink.push(&context);
+        },
+    );
+    let mut engine = StyleTransferEngine::new();
+    engine.set_error_reporter(Some(reporter.as_ref().unchecked_ref::<js_sys::Function>().clone()));
+
+    assert!(engine.load_model("no_such_model", None, None).await.is_err());
+    // Reports are delivered in a microtask
+    wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(&wasm_bindgen::JsValue::UNDEFINED))
+        .await
+        .unwrap();
+
+    assert_eq!(contexts.length(), 1);
+    let context = contexts.get(0);
+    let field = |name: &str| js_sys::Reflect::get(&context, &name.into()).unwrap();
+    assert_eq!(field("operation").as_string().as_deref(), Some("load_model"));
+    assert_eq!(field("model").as_string().as_deref(), Some("no_such_model"));
+    assert!(field("operation_id").as_f64().unwrap() >= 1.0);
+
+    // Another engine's failures go to its own reporter, not this one
+    let mut other = StyleTransferEngine::new();
+    assert!(other.load_model("no_such_model", None, None).await.is_err());
+    wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(&wasm_bindgen::JsValue::UNDEFINED))
+        .await
+        .unwrap();
+    assert_eq!(contexts.length(), 1);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_model_runtime_names() {
+    use style_transfer_wasm::{ModelRuntime, WebGpuState};
+
+    let names: Vec<serde_json::Value> = [
+        ModelRuntime::Tract,
+        ModelRuntime::External,
+        ModelRuntime::Simulated,
+    ]
+    .iter()
+    .map(|runtime| serde_json::to_value(runtime).unwrap())
+    .collect();
+    assert_eq!(names, vec!["tract", "external", "simulated"]);
+
+    let states: Vec<serde_json::Value> = [WebGpuState::Never, WebGpuState::Available, WebGpuState::Lost]
+        .iter()
+        .map(|state| serde_json::to_value(state).unwrap())
+        .collect();
+    assert_eq!(states, vec!["never", "available", "lost"]);
+
+    // Builds without tract still link, but can't load anything
+    if !cfg!(feature = "backend-tract") {
+        assert!(pipeline::load_plan(&[]).is_err());
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_model_state_shape() {
+    use style_transfer_wasm::ModelState;
+
+    assert_eq!(serde_json::to_value(ModelState::NotLoaded).unwrap(), serde_json::json!({ "state": "not_loaded" }));
+    assert_eq!(serde_json::to_value(ModelState::Parsing).unwrap(), serde_json::json!({ "state": "parsing" }));
+    assert_eq!(
+        serde_json::to_value(ModelState::Failed { error: "404".to_string() }).unwrap(),
+        serde_json::json!({ "state": "failed", "error": "404" })
+    );
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_model_state_follows_loading() {
+    let mut engine = StyleTransferEngine::new();
+    let state = |engine: &StyleTransferEngine, name: &str| {
+        let state = engine.get_model_state(name).unwrap();
+        js_sys::Reflect::get(&state, &"state".into()).unwrap().as_string().unwrap()
+    };
+    assert!(!engine.is_model_loaded("van_gogh_starry_night"));
+    assert_eq!(state(&engine, "van_gogh_starry_night"), "not_loaded");
+    assert_eq!(state(&engine, "cinematic_widescreen"), "ready");
+    assert!(engine.get_model_state("no_such_style").is_err());
+
+    // Bytes no runtime can parse leave the style on the simulated filter
+    let bytes = js_sys::Uint8Array::from(&b"not an onnx model"[..]);
+    engine
+        .load_model_from_bytes("van_gogh_starry_night", &bytes, wasm_bindgen::JsValue::UNDEFINED)
+        .await
+        .unwrap();
+    assert!(engine.is_model_loaded("van_gogh_starry_night"));
+    assert_eq!(state(&engine, "van_gogh_starry_night"), "ready");
+
+    engine.unload_model("van_gogh_starry_night").unwrap();
+    assert_eq!(state(&engine, "van_gogh_starry_night"), "not_loaded");
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_warm_up_runs_one_inference() {
+    let mut engine = StyleTransferEngine::new();
+    let elapsed = engine.warm_up("cinematic_widescreen").await.unwrap();
+    assert!(elapsed >= 0.0);
+
+    let error = engine.warm_up("no_such_style").await.unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("ModelNotFound"));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_warm_up_leaves_the_result_cache_alone() {
+    fn cache_entries(engine: &StyleTransferEngine) -> f64 {
+        let cache = js_sys::Reflect::get(&engine.get_stats(), &"result_cache".into()).unwrap();
+        js_sys::Reflect::get(&cache, &"entries".into()).unwrap().as_f64().unwrap()
+    }
+
+    let mut engine = StyleTransferEngine::new();
+    let before = cache_entries(&engine);
+    engine.warm_up("cinematic_widescreen").await.unwrap();
+    engine.warm_up("cinematic_widescreen").await.unwrap();
+    assert_eq!(cache_entries(&engine), before);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_model_usage_counters() {
+    use style_transfer_wasm::{Backend, ModelUsage};
+
+    let mut usage = ModelUsage::default();
+    usage.record_load(120.0, 1_000.0);
+    usage.record_inference(Backend::Onnx, false, 40.0, 2_000.0);
+    usage.record_inference(Backend::Simulated, true, 1.0, 3_000.0);
+    usage.record_error(4_000.0);
+
+    assert_eq!((usage.times_loaded, usage.last_load_ms), (1, 120.0));
+    assert_eq!((usage.inference_count, usage.cache_hits), (2, 1));
+    assert_eq!((usage.onnx_count, usage.simulated_count, usage.js_filter_count), (1, 1, 0));
+    assert_eq!((usage.total_inference_ms, usage.last_inference_ms), (41.0, 1.0));
+    assert_eq!(usage.error_count, 1);
+    assert_eq!(usage.last_used, Some(4_000.0));
+
+    let json = serde_json::to_value(&usage).unwrap();
+    assert_eq!(json["times_loaded"], 1);
+    assert_eq!(json["last_used"], 4_000.0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_process_result_shape() {
+    use style_transfer_wasm::{Backend, ProcessResult, StrengthVariant, Timings};
+
+    let backends: Vec<serde_json::Value> = [Backend::Onnx, Backend::Simulated, Backend::JsFilter]
+        .iter()
+        .map(|backend| serde_json::to_value(backend).unwrap())
+        .collect();
+    assert_eq!(backends, vec!["onnx", "simulated", "js_filter"]);
+
+    let result = ProcessResult {
+        data_url: "data:image/png;base64,".to_string(),
+        format: OutputFormat::Png,
+        mime_type: "image/png",
+        format_fallback: false,
+        width: 256,
+        height: 256,
+        backend: Backend::Simulated,
+        simulated: true,
+        from_cache: false,
+        downscale_factor: 0.5,
+        timings: Timings::default(),
+        saliency_data_url: None,
+        variants: vec![StrengthVariant {
+            strength: 0.35,
+            data_url: None,
+            error: Some("encoder failed".to_string()),
+        }],
+    };
+    let json = serde_json::to_value(&result).unwrap();
+    let mut fields: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+    fields.sort_unstable();
+    assert_eq!(
+        fields,
+        vec![
+            "backend", "data_url", "downscale_factor", "format", "format_fallback", "from_cache", "height",
+            "mime_type", "saliency_data_url", "simulated", "timings", "variants", "width",
+        ]
+    );
+    assert_eq!(json["variants"][0]["error"], "encoder failed");
+    assert_eq!(json["format"], "png");
+    assert_eq!(json["backend"], "simulated");
+    let mut timings: Vec<&str> = json["timings"].as_object().unwrap().keys().map(String::as_str).collect();
+    timings.sort_unstable();
+    assert_eq!(timings, vec!["blend_ms", "decode_ms", "encode_ms", "inference_ms", "pass_ms", "postprocess_ms", "preprocess_ms", "resize_ms", "tensor_ms", "total_ms", "variant_ms"]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tensor_layouts() {
+    use pipeline::TensorLayout;
+
+    assert_eq!(TensorLayout::parse("NCHW"), Ok(TensorLayout::Nchw));
+    assert_eq!(TensorLayout::parse("nhwc"), Ok(TensorLayout::Nhwc));
+    assert!(TensorLayout::parse("chw").is_err());
+
+    // Two pixels, planar: R R G G B B
+    let planar = vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+    let mut tensor = planar.clone();
+    let mut scratch = Vec::new();
+    TensorLayout::Nchw.to_interleaved(&mut tensor, &mut scratch);
+    assert_eq!(tensor, vec![0.1, 0.3, 0.5, 0.2, 0.4, 0.6]);
+    assert_eq!(TensorLayout::Nchw.from_interleaved(tensor.clone()), planar);
+
+    let mut interleaved = tensor.clone();
+    TensorLayout::Nhwc.to_interleaved(&mut interleaved, &mut scratch);
+    assert_eq!(interleaved, tensor);
+    assert_eq!(TensorLayout::Nhwc.from_interleaved(tensor.clone()), tensor);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_run_inference_raw_validates_and_copies() {
+    let mut engine = StyleTransferEngine::new();
+    let too_short = js_sys::Float32Array::new_with_length(10);
+    let error = engine
+        .run_inference_raw("cinematic_widescreen", &too_short, 384, 216, "nchw")
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("InvalidInput"));
+
+    let input = js_sys::Float32Array::new_with_length(384 * 216 * 3);
+    input.fill(0.5, 0, input.length());
+    let output = engine
+        .run_inference_raw("cinematic_widescreen", &input, 384, 216, "nchw")
+        .await
+        .unwrap();
+    assert_eq!(output.length(), input.length());
+    assert!(output.buffer() != input.buffer());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_dispose_is_idempotent() {
+    let mut engine = StyleTransferEngine::new();
+    assert!(!engine.is_disposed());
+    engine.dispose();
+    assert!(engine.is_disposed());
+    engine.dispose();
+    assert!(engine.is_disposed());
+    assert!(engine.get_loaded_models().is_empty());
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_disposed_engine_rejects_calls() {
+    let mut engine = StyleTransferEngine::new();
+    engine.dispose();
+    let error = engine.load_model("cinematic_widescreen", None, None).await.unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("EngineDisposed"));
+
+    let stats = engine.get_stats();
+    let memory = js_sys::Reflect::get(&stats, &"total_memory_mb".into()).unwrap();
+    assert_eq!(memory.as_f64(), Some(0.0));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_sequence_frames_ramp_in_the_blend() {
+    use wasm_bindgen::JsCast;
+
+    let mut engine = StyleTransferEngine::new();
+    let canvas = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("canvas")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlCanvasElement>()
+        .unwrap();
+    canvas.set_width(32);
+    canvas.set_height(32);
+    let frame: wasm_bindgen::JsValue = canvas.into();
+
+    let error = engine
+        .process_sequence_frame(&frame, 1.0, wasm_bindgen::JsValue::UNDEFINED)
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("InvalidInput"));
+
+    engine.begin_sequence("picasso_cubist", 0.5, None).unwrap();
+    let mut blends = Vec::new();
+    for _ in 0..2 {
+        let result = engine
+            .process_sequence_frame(&frame, 1.0, wasm_bindgen::JsValue::UNDEFINED)
+            .await
+            .unwrap();
+        let blend = js_sys::Reflect::get(&result, &"effective_blend".into()).unwrap();
+        blends.push(blend.as_f64().unwrap());
+    }
+    assert_eq!(blends, [0.0, 0.125]);
+    engine.end_sequence();
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_split_pipeline_matches_process_image() {
+    use wasm_bindgen::JsCast;
+
+    let canvas = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("canvas")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlCanvasElement>()
+        .unwrap();
+    canvas.set_width(40);
+    canvas.set_height(30);
+    let ctx = canvas
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<web_sys::CanvasRenderingContext2d>()
+        .unwrap();
+    ctx.set_fill_style_str("#c84");
+    ctx.fill_rect(0.0, 0.0, 20.0, 30.0);
+    let url = canvas.to_data_url().unwrap();
+
+    let mut engine = StyleTransferEngine::new();
+    let expected = engine.process_image(&url, "picasso_cubist", 0.6).await.unwrap();
+
+    let preprocessed = engine
+        .preprocess_image(&url, "picasso_cubist", None)
+        .await
+        .unwrap();
+    let get = |key: &str| js_sys::Reflect::get(&preprocessed, &key.into()).unwrap();
+    assert_eq!(get("layout").as_string().as_deref(), Some("nchw"));
+    let (width, height) = (get("width").as_f64().unwrap(), get("height").as_f64().unwrap());
+    let original: js_sys::Float32Array = get("tensor").dyn_into().unwrap();
+    let stylized = engine
+        .run_inference_raw("picasso_cubist", &original, width as u32, height as u32, "nchw")
+        .await
+        .unwrap();
+    let actual = engine
+        .postprocess_tensor(&stylized, "picasso_cubist", 0.6, Some(original), None)
+        .unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_image_source_kinds() {
+    assert_eq!(ImageSourceKind::of("data:image/png;base64,AAAA"), ImageSourceKind::DataUrl);
+    assert_eq!(ImageSourceKind::of("blob:https://example.com/1234"), ImageSourceKind::BlobUrl);
+    assert_eq!(ImageSourceKind::of("HTTPS://example.com/a.png"), ImageSourceKind::HttpUrl);
+    assert_eq!(ImageSourceKind::of("http://example.com/a.png"), ImageSourceKind::HttpUrl);
+    assert_eq!(ImageSourceKind::of("a.png"), ImageSourceKind::Other);
+    assert_eq!(ImageSourceKind::BlobUrl.name(), "blob");
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn process_error(engine: &mut StyleTransferEngine, url: &str) -> (String, wasm_bindgen::JsValue) {
+    let error = engine.process_image(url, "picasso_cubist", 1.0).await.unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    (code.as_string().unwrap(), error)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_malformed_data_url_fails_to_decode() {
+    let mut engine = StyleTransferEngine::new();
+    let (code, error) = process_error(&mut engine, "data:image/png;base64,bm90IGEgcG5n").await;
+    assert_eq!(code, "DecodeFailed");
+    let kind = js_sys::Reflect::get(&error, &"source_kind".into()).unwrap();
+    assert_eq!(kind.as_string().as_deref(), Some("data"));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_sizeless_svg_is_rejected() {
+    let mut engine = StyleTransferEngine::new();
+    let svg = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg'%3E%3C/svg%3E";
+    let (code, _) = process_error(&mut engine, svg).await;
+    assert_eq!(code, "InvalidInput");
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_one_pixel_png_is_upscaled() {
+    // A single opaque red pixel
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let mut engine = StyleTransferEngine::new();
+    let result = engine
+        .process_image_v2(png, "picasso_cubist", 1.0, wasm_bindgen::JsValue::UNDEFINED)
+        .await
+        .unwrap();
+    let width = js_sys::Reflect::get(&result, &"width".into()).unwrap();
+    assert!(width.as_f64().unwrap() > 1.0);
+    let factor = js_sys::Reflect::get(&result, &"downscale_factor".into()).unwrap();
+    assert!(factor.as_f64().unwrap().is_finite());
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_capabilities_report() {
+    let mut engine = StyleTransferEngine::new();
+    let before = engine.get_capabilities().unwrap();
+    engine.initialize().await.unwrap();
+    let report = engine.get_capabilities().unwrap();
+    let get = |value: &wasm_bindgen::JsValue, key: &str| {
+        js_sys::Reflect::get(value, &key.into()).unwrap()
+    };
+
+    let encoders: Vec<String> = js_sys::Array::from(&get(&report, "encoders"))
+        .iter()
+        .map(|format| format.as_string().unwrap())
+        .collect();
+    assert_eq!(encoders[..2], ["png", "jpeg"]);
+    assert_eq!(
+        get(&report, "simd").as_bool(),
+        Some(cfg!(target_feature = "simd128"))
+    );
+    assert_eq!(
+        get(&report, "webgpu").as_bool(),
+        Some(engine.is_webgpu_ready())
+    );
+    assert!(get(&report, "max_input_dimension").as_f64().unwrap() >= 2048.0);
+    assert_eq!(
+        get(&before, "cross_origin_isolated").as_bool(),
+        get(&report, "cross_origin_isolated").as_bool()
+    );
+
+    let refreshed = engine.refresh_capabilities().unwrap();
+    let refreshed_encoders = js_sys::Array::from(&get(&refreshed, "encoders"));
+    assert_eq!(refreshed_encoders.length(), encoders.len() as u32);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_strength_variants_match_single_blends() {
+    let options: ProcessOptions = serde_json::from_value(serde_json::json!({ "strength_variants": [0.35, 1.0] })).unwrap();
+    assert_eq!(options.variant_strengths().unwrap(), [0.35, 1.0]);
+    for bad in [vec![1.5], vec![0.1; 9]] {
+        let options = ProcessOptions { strength_variants: bad, ..Default::default() };
+        assert!(options.variant_strengths().is_err());
+    }
+
+    let original: Vec<f32> = (0..48).map(|i| i as f32 / 47.0).collect();
+    let stylized: Vec<f32> = original.iter().rev().copied().collect();
+    let map: Vec<f32> = (0..16).map(|i| i as f32 / 15.0).collect();
+    let mut blended = Vec::new();
+    for strength in [0.0, 0.35, 1.0] {
+        for map in [None, Some(&map[..])] {
+            let space = pipeline::ColorSpace::Srgb;
+            pipeline::apply_strength_into(&original, &stylized, strength, map, space, &mut blended);
+            assert_eq!(blended, pipeline::apply_strength(&original, stylized.clone(), strength, map, space));
+        }
+    }
+    let mut pixels = vec![7; 3];
+    pipeline::tensor_to_rgba_into(&blended, 16, &mut pixels);
+    assert_eq!(pixels, pipeline::tensor_to_rgba(&blended, 16));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_strength_variants_share_one_inference() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let options = js_sys::JSON::parse(r#"{ "strength_variants": [0.35, 1.0] }"#).unwrap();
+    let mut engine = StyleTransferEngine::new();
+    let result = engine
+        .process_image_v2(png, "picasso_cubist", 1.0, options)
+        .await
+        .unwrap();
+    let get = |value: &wasm_bindgen::JsValue, key: &str| js_sys::Reflect::get(value, &key.into()).unwrap();
+
+    let variants = js_sys::Array::from(&get(&result, "variants"));
+    assert_eq!(variants.length(), 2);
+    assert_eq!(get(&variants.get(0), "strength").as_f64(), Some(0.35));
+    assert!(get(&variants.get(0), "error").is_null());
+    assert_eq!(get(&variants.get(1), "data_url").as_string(), get(&result, "data_url").as_string());
+    let variant_ms = js_sys::Array::from(&get(&get(&result, "timings"), "variant_ms"));
+    assert_eq!(variant_ms.length(), 2);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_process_pixels_keeps_the_source_size() {
+    let mut engine = StyleTransferEngine::new();
+    let pixels: Vec<u8> = (0..6 * 4 * 4).map(|i| (i * 7 % 256) as u8).collect();
+    let output = engine
+        .process_pixels(&pixels, 6, 4, "picasso_cubist", 0.8)
+        .await
+        .unwrap();
+    assert_eq!(output.len(), pixels.len());
+
+    let error = engine
+        .process_pixels(&pixels, 5, 4, "picasso_cubist", 0.8)
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("InvalidInput"));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_process_pixels_keeps_the_source_alpha() {
+    let mut engine = StyleTransferEngine::new();
+    // Transparent left half, half-transparent right half
+    let pixels: Vec<u8> = (0..6 * 4)
+        .flat_map(|i| [200, 100, 50, if i % 6 < 3 { 0 } else { 128 }])
+        .collect();
+    let output = engine
+        .process_pixels(&pixels, 6, 4, "picasso_cubist", 0.8)
+        .await
+        .unwrap();
+    let alpha: Vec<u8> = output.chunks(4).map(|px| px[3]).collect();
+    let expected: Vec<u8> = pixels.chunks(4).map(|px| px[3]).collect();
+    assert_eq!(alpha, expected);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_persistent_model_cache_keeps_one_version() {
+    let mut engine = StyleTransferEngine::new();
+    engine.clear_model_cache().await.unwrap();
+    let register = |version: &str| {
+        js_sys::JSON::parse(&format!(
+            r#"{{ "name": "cached", "model_url": "data:application/octet-stream;base64,AAEC", "version": "{}", "size_mb": 0.1 }}"#,
+            version
+        ))
+        .unwrap()
+    };
+    engine.register_model(register("1")).unwrap();
+    // Not a real model, so it falls back to simulation after downloading
+    engine.load_model("cached", None, None).await.unwrap();
+
+    let cached = js_sys::Array::from(&engine.get_cached_models().await.unwrap());
+    assert_eq!(cached.length(), 1);
+    let get = |key: &str| js_sys::Reflect::get(&cached.get(0), &key.into()).unwrap();
+    assert_eq!(get("version").as_string().as_deref(), Some("1"));
+    assert_eq!(get("bytes").as_f64(), Some(3.0));
+
+    // A new version replaces the old one
+    engine.remove_model("cached").unwrap();
+    engine.register_model(register("2")).unwrap();
+    engine.load_model("cached", None, None).await.unwrap();
+    let cached = js_sys::Array::from(&engine.get_cached_models().await.unwrap());
+    assert_eq!(cached.length(), 1);
+
+    assert_eq!(engine.invalidate_cached_model("cached").await.unwrap(), 1);
+    assert_eq!(engine.invalidate_cached_model("cached").await.unwrap(), 0);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_load_model_reports_progress_and_cancels() {
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+
+    let mut engine = StyleTransferEngine::new();
+    let metadata = js_sys::JSON::parse(
+        r#"{ "name": "watched", "model_url": "data:application/octet-stream;base64,AAECAwQF", "size_mb": 0.1 }"#,
+    )
+    .unwrap();
+    engine.register_model(metadata).unwrap();
+
+    let controller = web_sys::AbortController::new().unwrap();
+    controller.abort();
+    let error = engine
+        .load_model("watched", None, Some(controller.signal()))
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("Cancelled"));
+    assert!(engine.get_loaded_models().is_empty());
+
+    let reports = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let seen = reports.clone();
+    let on_progress = Closure::<dyn FnMut(f64, wasm_bindgen::JsValue)>::new(move |loaded, total: wasm_bindgen::JsValue| {
+        seen.borrow_mut().push((loaded, total.as_f64()));
+    });
+    engine
+        .load_model("watched", Some(on_progress.as_ref().unchecked_ref::<js_sys::Function>().clone()), None)
+        .await
+        .unwrap();
+    let (loaded, _) = *reports.borrow().last().expect("at least one progress report");
+    assert_eq!(loaded, 6.0);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_tiled_processing_keeps_the_source_resolution() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let options = js_sys::JSON::parse(r#"{ "tiled": true }"#).unwrap();
+    let mut engine = StyleTransferEngine::new();
+    let result = engine
+        .process_image_v2(png, "picasso_cubist", 1.0, options)
+        .await
+        .unwrap();
+    let get = |key: &str| js_sys::Reflect::get(&result, &key.into()).unwrap();
+    assert_eq!(get("width").as_f64(), Some(1.0));
+    assert_eq!(get("height").as_f64(), Some(1.0));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_style_image_needs_an_adain_model() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let mut engine = StyleTransferEngine::new();
+    let error = engine
+        .process_with_style_image(png, png, "picasso_cubist", 1.0, wasm_bindgen::JsValue::UNDEFINED)
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("InvalidInput"));
+}
+
+// Lengths that aren't whole vectors, so SIMD builds run both their vector
+// loops and the scalar tails
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_pixel_loops_match_scalar_definitions() {
+    let pixels: Vec<u8> = (0..7 * 4).map(|i| (i * 37 % 256) as u8).collect();
+    let tensor = pipeline::rgba_to_tensor(&pixels);
+    let expected: Vec<f32> = pixels.chunks_exact(4).flat_map(|px| px[..3].iter().map(|&v| v as f32 / 255.0)).collect();
+    assert_eq!(tensor, expected);
+
+    let values = [-0.5, 0.0, 0.2, 0.5, 0.999, 1.0, 1.5, f32::NAN, f32::INFINITY, 0.75, 0.1, 0.3, 0.6, 0.9, 0.01, 0.4, 0.8, 0.2];
+    let rgba = pipeline::tensor_to_rgba(&values, 6);
+    for (px, rgb) in rgba.chunks_exact(4).zip(values.chunks_exact(3)) {
+        for (&byte, &v) in px.iter().zip(rgb) {
+            assert_eq!(byte, (v * 255.0).clamp(0.0, 255.0) as u8);
+        }
+        assert_eq!(px[3], 255);
     }
 
-    #[wasm_bindgen_test]
-    fn test_image_blending() {
-        let engine = StyleTransferEngine::new();
-        let original = vec![0.0f32, 0.5f32, 1.0f32];
-        let stylized = vec![1.0f32, 0.5f32, 0.0f32];
-        let blended = engine.blend_images(&original, &stylized, 0.5);
-        
-        assert_eq!(blended.len(), 3);
-        assert!((blended[0] - 0.5).abs() < 0.001);
+    let original: Vec<f32> = (0..11).map(|i| i as f32 / 10.0).collect();
+    let stylized: Vec<f32> = original.iter().rev().copied().collect();
+    let gamma = pipeline::blend_tensors(&original, &stylized, 0.3);
+    let mut linear = Vec::new();
+    pipeline::blend_tensors_into(&original, &stylized, 0.3, None, pipeline::ColorSpace::Linear, &mut linear);
+    for (i, (&o, &s)) in original.iter().zip(&stylized).enumerate() {
+        let expected = (o.powf(2.2) * 0.7 + s.powf(2.2) * 0.3).powf(1.0 / 2.2);
+        assert!((gamma[i] - expected).abs() < 1e-5, "{} vs {}", gamma[i], expected);
+        assert_eq!(linear[i], (o * 0.7 + s * 0.3).clamp(0.0, 1.0));
     }
 }
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_aborted_signal_cancels_processing() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let controller = web_sys::AbortController::new().unwrap();
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"signal".into(), &controller.signal()).unwrap();
+    controller.abort();
+    let mut engine = StyleTransferEngine::new();
+    let error = engine
+        .process_image_v2(png, "picasso_cubist", 1.0, options.into())
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("Cancelled"));
+
+    // Anything else in `signal` is rejected up front
+    let options = js_sys::JSON::parse(r#"{ "signal": true }"#).unwrap();
+    let error = engine
+        .process_image_v2(png, "picasso_cubist", 1.0, options)
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("InvalidInput"));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_error_codes_name_the_failure() {
+    use style_transfer_wasm::EngineError;
+
+    let parse = EngineError::OnnxParseError("Cannot parse 'uploaded': not protobuf".to_string());
+    assert_eq!(parse.code(), "OnnxParseError");
+    assert_eq!(parse.to_string(), "OnnxParseError: Cannot parse 'uploaded': not protobuf");
+    let fetch = EngineError::FetchFailed { message: "Fetching failed".to_string(), status: Some(404) };
+    assert_eq!(fetch.code(), "FetchFailed");
+    assert_eq!(fetch.message(), "Fetching failed");
+    let integrity = EngineError::IntegrityError("'picasso_cubist' failed its integrity check".to_string());
+    assert_eq!(integrity.code(), "IntegrityError");
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_unknown_style_is_model_not_found() {
+    let mut engine = StyleTransferEngine::new();
+    let error = engine.load_model("no_such_style", None, None).await.unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("ModelNotFound"));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_target_size_keeps_the_aspect_ratio() {
+    let options: ProcessOptions = serde_json::from_value(serde_json::json!({ "target_width": 300, "backend": "cpu" })).unwrap();
+    assert_eq!(options.backend, Some(style_transfer_wasm::config::PreferredBackend::Cpu));
+    assert_eq!(options.output_size((256, 128)), (300, 150));
+    let options = ProcessOptions { target_height: Some(64), ..Default::default() };
+    assert_eq!(options.output_size((256, 128)), (128, 64));
+    let options = ProcessOptions { target_width: Some(10), target_height: Some(20), ..Default::default() };
+    assert_eq!(options.output_size((256, 128)), (10, 20));
+    assert_eq!(ProcessOptions::default().output_size((256, 128)), (256, 128));
+
+    assert!(options.check_target_size().is_ok());
+    for bad in [0, 20_000] {
+        let options = ProcessOptions { target_width: Some(bad), ..Default::default() };
+        assert!(options.check_target_size().is_err());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_result_is_returned_at_the_target_size() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let options = js_sys::JSON::parse(r#"{ "target_width": 40, "target_height": 30, "strength_variants": [0.5] }"#).unwrap();
+    let mut engine = StyleTransferEngine::new();
+    let result = engine
+        .process_image_v2(png, "picasso_cubist", 1.0, options)
+        .await
+        .unwrap();
+    let get = |value: &wasm_bindgen::JsValue, key: &str| js_sys::Reflect::get(value, &key.into()).unwrap();
+    assert_eq!(get(&result, "width").as_f64(), Some(40.0));
+    assert_eq!(get(&result, "height").as_f64(), Some(30.0));
+    let variants = js_sys::Array::from(&get(&result, "variants"));
+    assert!(get(&variants.get(0), "error").is_null());
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_image_data_skips_encoding() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let options = js_sys::JSON::parse(r#"{ "target_width": 8, "target_height": 6 }"#).unwrap();
+    let mut engine = StyleTransferEngine::new();
+    let image_data = engine
+        .process_image_data(png, "picasso_cubist", 0.5, options)
+        .await
+        .unwrap();
+    assert_eq!((image_data.width(), image_data.height()), (8, 6));
+    let pixels = image_data.data();
+    assert_eq!(pixels.len(), 8 * 6 * 4);
+    assert!(pixels.chunks_exact(4).all(|pixel| pixel[3] == 255));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_video_frame_keeps_its_timestamp() {
+    use wasm_bindgen::JsCast;
+
+    let init = js_sys::JSON::parse(r#"{ "format": "RGBA", "codedWidth": 4, "codedHeight": 2, "timestamp": 33366, "duration": 33366 }"#).unwrap();
+    let pixels = js_sys::Uint8Array::from(&[200u8; 4 * 2 * 4][..]);
+    let constructor = js_sys::Reflect::get(&js_sys::global(), &"VideoFrame".into()).unwrap();
+    let frame = js_sys::Reflect::construct(constructor.unchecked_ref(), &js_sys::Array::of2(&pixels, &init)).unwrap();
+    let options = js_sys::JSON::parse(r#"{ "consume": true }"#).unwrap();
+    let mut engine = StyleTransferEngine::new();
+    let output = engine.process_video_frame(&frame, "picasso_cubist", 0.5, options).await.unwrap();
+    let get = |value: &wasm_bindgen::JsValue, key: &str| js_sys::Reflect::get(value, &key.into()).unwrap();
+    assert_eq!(get(&output, "timestamp").as_f64(), Some(33366.0));
+    assert_eq!(get(&output, "codedWidth").as_f64(), Some(4.0));
+    // The input was consumed, so reading it again fails
+    let error = engine.process_video_frame(&frame, "picasso_cubist", 0.5, wasm_bindgen::JsValue::UNDEFINED).await.unwrap_err();
+    assert_eq!(js_sys::Reflect::get(&error, &"code".into()).unwrap().as_string().as_deref(), Some("InvalidInput"));
+    js_sys::Reflect::apply(get(&output, "close").unchecked_ref(), &output, &js_sys::Array::new()).unwrap();
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_stream_needs_insertable_streams() {
+    let mut engine = StyleTransferEngine::new();
+    let not_a_stream = js_sys::Object::new();
+    let error = engine
+        .stream_video(&not_a_stream, &not_a_stream, "picasso_cubist", 0.5, wasm_bindgen::JsValue::UNDEFINED)
+        .await
+        .unwrap_err();
+    assert_eq!(js_sys::Reflect::get(&error, &"code".into()).unwrap().as_string().as_deref(), Some("InvalidInput"));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_progress_is_reported_per_stage() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let reports = js_sys::Array::new();
+    let sink = reports.clone();
+    let on_progress = wasm_bindgen::closure::Closure::<dyn FnMut(String, f64)>::new(move |stage: String, percent: f64| {
+        sink.push(&js_sys::Array::of2(&stage.into(), &percent.into()));
+    });
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"passes".into(), &2.into()).unwrap();
+    js_sys::Reflect::set(&options, &"on_progress".into(), on_progress.as_ref()).unwrap();
+    let mut engine = StyleTransferEngine::new();
+    engine.process_image_v2(png, "picasso_cubist", 0.5, options.into()).await.unwrap();
+
+    let reports: Vec<(String, f64)> = reports
+        .iter()
+        .map(|report| {
+            let report = js_sys::Array::from(&report);
+            (report.get(0).as_string().unwrap(), report.get(1).as_f64().unwrap())
+        })
+        .collect();
+    let stages: Vec<&str> = reports.iter().map(|(stage, _)| stage.as_str()).collect();
+    assert_eq!(stages, ["decode", "preprocess", "inference", "inference", "postprocess", "encode"]);
+    assert!(reports.windows(2).all(|pair| pair[0].1 <= pair[1].1), "{:?}", reports);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_segmentation_falls_back_without_a_model() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let mut engine = StyleTransferEngine::new();
+    engine.process_with_segmentation(png, "picasso_cubist", 0.5, wasm_bindgen::JsValue::UNDEFINED).await.unwrap();
+    // The built-in entry was registered and runs on the heuristic
+    assert!(engine.style_names().contains(&"portrait_segmentation".to_string()));
+    let error = engine.process_image_v2(png, "portrait_segmentation", 0.5, wasm_bindgen::JsValue::UNDEFINED).await.unwrap_err();
+    assert_eq!(js_sys::Reflect::get(&error, &"code".into()).unwrap().as_string().as_deref(), Some("InvalidInput"));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+async fn test_dual_styles_mix_between_the_two() {
+    let png = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+    let width = |result: &wasm_bindgen::JsValue| js_sys::Reflect::get(result, &"width".into()).unwrap().as_f64();
+    let mut engine = StyleTransferEngine::new();
+    let mixed = engine.process_image_dual(png, "picasso_cubist", "cyberpunk_neon", 0.3, 1.0, wasm_bindgen::JsValue::UNDEFINED).await.unwrap();
+    let single = engine.process_image_v2(png, "picasso_cubist", 1.0, wasm_bindgen::JsValue::UNDEFINED).await.unwrap();
+    assert_eq!(width(&mixed), width(&single));
+
+    let error = engine.process_image_dual(png, "picasso_cubist", "cyberpunk_neon", 1.5, 1.0, wasm_bindgen::JsValue::UNDEFINED).await.unwrap_err();
+    assert_eq!(js_sys::Reflect::get(&error, &"code".into()).unwrap().as_string().as_deref(), Some("InvalidInput"));
+    let error = engine.process_image_dual(png, "picasso_cubist", "no_such_style", 0.5, 1.0, wasm_bindgen::JsValue::UNDEFINED).await.unwrap_err();
+    assert_eq!(js_sys::Reflect::get(&error, &"code".into()).unwrap().as_string().as_deref(), Some("ModelNotFound"));
+}
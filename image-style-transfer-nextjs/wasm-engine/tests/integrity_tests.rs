@@ -0,0 +1,65 @@
+use serde_json::json;
+use style_transfer_wasm::pipeline::integrity::{to_hex, validate_sha256, verify_digest};
+use style_transfer_wasm::pipeline::registry::{self, MetadataPatch};
+use style_transfer_wasm::pipeline::{default_registry, ModelMetadata};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// SHA-256 of the empty string.
+const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+fn empty_digest() -> Vec<u8> {
+    (0..32)
+        .map(|i| u8::from_str_radix(&EMPTY_SHA256[i * 2..i * 2 + 2], 16).unwrap())
+        .collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_digests_match_hex_in_either_case() {
+    let digest = empty_digest();
+    assert_eq!(to_hex(&digest), EMPTY_SHA256);
+    assert_eq!(verify_digest(&digest, EMPTY_SHA256), Ok(()));
+    assert_eq!(
+        verify_digest(&digest, &EMPTY_SHA256.to_ascii_uppercase()),
+        Ok(())
+    );
+
+    let mut tampered = digest.clone();
+    tampered[31] ^= 1;
+    let error = verify_digest(&tampered, EMPTY_SHA256).unwrap_err();
+    assert!(error.contains(EMPTY_SHA256), "{}", error);
+    assert!(error.contains(&to_hex(&tampered)), "{}", error);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_malformed_checksums_are_rejected() {
+    assert!(validate_sha256(EMPTY_SHA256).is_ok());
+    assert!(validate_sha256(&EMPTY_SHA256[..63]).is_err());
+    assert!(validate_sha256(&format!("{}0", EMPTY_SHA256)).is_err());
+    assert!(validate_sha256(&EMPTY_SHA256.replace('e', "g")).is_err());
+
+    let metadata: ModelMetadata = serde_json::from_value(json!({
+        "name": "checked",
+        "model_url": "/models/checked.onnx",
+        "sha256": "not-a-digest",
+    }))
+    .unwrap();
+    assert!(registry::validate_metadata(&metadata).is_err());
+    assert_eq!(default_registry()[0].sha256, None);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_patch_sets_checksum() {
+    let original = default_registry().remove(0);
+    let patch: MetadataPatch = serde_json::from_value(json!({ "sha256": EMPTY_SHA256 })).unwrap();
+    assert_eq!(
+        patch.apply(&original).unwrap().sha256.as_deref(),
+        Some(EMPTY_SHA256)
+    );
+    let patch: MetadataPatch = serde_json::from_value(json!({ "sha256": "abc" })).unwrap();
+    assert!(patch.apply(&original).is_err());
+}
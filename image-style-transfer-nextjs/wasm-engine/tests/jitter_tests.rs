@@ -0,0 +1,58 @@
+use style_transfer_wasm::pipeline::jitter::{
+    accumulate, finish_average, jitter_offsets, shift_tensor, MAX_JITTER, MAX_PASSES,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// A 3x2 tensor whose red channel numbers the pixels.
+fn numbered() -> Vec<f32> {
+    (0..6).flat_map(|i| [i as f32, 0.5, 1.0]).collect()
+}
+
+fn reds(tensor: &[f32]) -> Vec<f32> {
+    tensor.chunks(3).map(|pixel| pixel[0]).collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_offsets_are_seeded_distinct_and_bounded() {
+    assert_eq!(jitter_offsets(0, 7), vec![(0, 0)]);
+    assert_eq!(jitter_offsets(1, 7), vec![(0, 0)]);
+    let offsets = jitter_offsets(10, 7);
+    assert_eq!(offsets.len(), MAX_PASSES as usize);
+    assert_eq!(offsets, jitter_offsets(4, 7));
+    for (i, &(dx, dy)) in offsets.iter().enumerate() {
+        assert!(dx.abs() <= MAX_JITTER && dy.abs() <= MAX_JITTER);
+        assert!(!offsets[..i].contains(&(dx, dy)));
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_shift_replicates_edges() {
+    let tensor = numbered();
+    assert_eq!(shift_tensor(&tensor, 3, 2, (0, 0)), tensor);
+    // Right by one: the left column repeats
+    assert_eq!(
+        reds(&shift_tensor(&tensor, 3, 2, (1, 0))),
+        [0.0, 0.0, 1.0, 3.0, 3.0, 4.0]
+    );
+    // Up by one: the bottom row repeats
+    assert_eq!(
+        reds(&shift_tensor(&tensor, 3, 2, (0, -1))),
+        [3.0, 4.0, 5.0, 3.0, 4.0, 5.0]
+    );
+    // Other channels are carried along unchanged
+    let shifted = shift_tensor(&tensor, 3, 2, (2, 1));
+    assert!(shifted.chunks(3).all(|pixel| pixel[1..] == [0.5, 1.0]));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_averaging() {
+    let mut sum = vec![1.0, 2.0];
+    accumulate(&mut sum, &[3.0, 4.0]);
+    finish_average(&mut sum, 2);
+    assert_eq!(sum, [2.0, 3.0]);
+}
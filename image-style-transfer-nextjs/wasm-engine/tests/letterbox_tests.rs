@@ -0,0 +1,36 @@
+use style_transfer_wasm::pipeline::Letterbox;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_sources_are_fitted_without_stretching() {
+    let wide = Letterbox::fit((1600, 900), (256, 256));
+    assert_eq!((wide.x, wide.y, wide.width, wide.height), (0, 56, 256, 144));
+    let tall = Letterbox::fit((300, 600), (256, 256));
+    assert_eq!((tall.x, tall.y, tall.width, tall.height), (64, 0, 128, 256));
+    assert!(Letterbox::fit((512, 512), (256, 256)).is_full());
+    assert!(!wide.is_full());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_padding_repeats_edges_and_crops_back() {
+    // A 2x2 source in a 2x4 input
+    let letterbox = Letterbox::fit((2, 2), (2, 4));
+    assert_eq!(
+        (letterbox.x, letterbox.y, letterbox.width, letterbox.height),
+        (0, 1, 2, 2)
+    );
+    let source = [
+        0.1, 0.1, 0.1, 0.2, 0.2, 0.2, //
+        0.3, 0.3, 0.3, 0.4, 0.4, 0.4,
+    ];
+    let padded = letterbox.pad(&source);
+    assert_eq!(padded.len(), 2 * 4 * 3);
+    assert_eq!(padded[..6], source[..6]);
+    assert_eq!(padded[6..18], source);
+    assert_eq!(padded[18..], source[6..]);
+    assert_eq!(letterbox.crop(&padded), source);
+}
@@ -0,0 +1,78 @@
+use style_transfer_wasm::pipeline::lut::{Lut3d, LUT_PRESETS};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// A `.cube` file of `size` mapping each color through `grade`.
+fn cube(size: usize, grade: impl Fn([f32; 3]) -> [f32; 3]) -> String {
+    let mut text = format!("TITLE \"test\"\n# comment\nLUT_3D_SIZE {}\n", size);
+    let step = 1.0 / (size - 1) as f32;
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                let [r, g, b] = grade([r as f32 * step, g as f32 * step, b as f32 * step]);
+                text.push_str(&format!("{} {} {}\n", r, g, b));
+            }
+        }
+    }
+    text
+}
+
+fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+    a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-5)
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_identity_cube_leaves_colors_alone() {
+    let lut = Lut3d::parse_cube(&cube(5, |rgb| rgb)).unwrap();
+    assert_eq!(lut.size, 5);
+    for rgb in [[0.0, 0.0, 0.0], [0.3, 0.6, 0.9], [1.0, 0.5, 0.125]] {
+        assert!(close(lut.lookup(rgb), rgb), "{:?}", lut.lookup(rgb));
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_entries_are_red_fastest_and_interpolated() {
+    // Swapping red and blue shows the axis order; a 2³ grid of a linear
+    // grade is exact everywhere through trilinear interpolation
+    let lut = Lut3d::parse_cube(&cube(2, |[r, g, b]| [b, g, r])).unwrap();
+    assert!(close(lut.lookup([1.0, 0.0, 0.0]), [0.0, 0.0, 1.0]));
+    assert!(close(lut.lookup([0.25, 0.5, 0.75]), [0.75, 0.5, 0.25]));
+    let mut tensor = vec![0.2, 0.4, 0.6, 1.0, 1.0, 0.0];
+    lut.apply(&mut tensor);
+    assert!(close([tensor[0], tensor[1], tensor[2]], [0.6, 0.4, 0.2]));
+    assert!(close([tensor[3], tensor[4], tensor[5]], [0.0, 1.0, 1.0]));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_domain_scales_the_lookup() {
+    let text = cube(2, |rgb| rgb).replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nDOMAIN_MAX 2 2 2");
+    let lut = Lut3d::parse_cube(&text).unwrap();
+    assert!(close(lut.lookup([1.0, 1.0, 1.0]), [0.5, 0.5, 0.5]));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_malformed_cubes_are_rejected() {
+    let valid = cube(2, |rgb| rgb);
+    assert!(Lut3d::parse_cube(&valid.replace("LUT_3D_SIZE 2\n", "")).is_err());
+    assert!(Lut3d::parse_cube(&valid.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 3")).is_err());
+    assert!(Lut3d::parse_cube(&valid.replace("LUT_3D_SIZE 2", "LUT_1D_SIZE 2")).is_err());
+    assert!(Lut3d::parse_cube(&format!("{}0 0\n", valid)).is_err());
+    assert!(Lut3d::parse_cube(&valid.replacen("0 0 0", "0 x 0", 1)).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_presets_grade_within_range() {
+    for name in LUT_PRESETS {
+        let lut = Lut3d::preset(name).unwrap();
+        let graded = lut.lookup([0.2, 0.5, 0.8]);
+        assert!(graded.iter().all(|v| (0.0..=1.0).contains(v)), "{}", name);
+        assert!(!close(graded, [0.2, 0.5, 0.8]), "{} changes nothing", name);
+    }
+    assert!(Lut3d::preset("sepia_dreams").is_none());
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+
+use style_transfer_wasm::memory::Counting;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_counting_allocator_tracks_current_and_peak() {
+    let counting = Counting::new(System);
+    let small = Layout::from_size_align(100, 8).unwrap();
+    unsafe {
+        let a = counting.alloc(small);
+        let b = counting.alloc_zeroed(small);
+        assert_eq!(counting.heap_bytes(), 200);
+        let b = counting.realloc(b, small, 300);
+        assert_eq!(counting.heap_bytes(), 400);
+        counting.dealloc(a, small);
+        counting.dealloc(b, Layout::from_size_align(300, 8).unwrap());
+    }
+    assert_eq!(counting.heap_bytes(), 0);
+    assert_eq!(counting.peak_heap_bytes(), 400);
+    counting.reset_peak();
+    assert_eq!(counting.peak_heap_bytes(), 0);
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_plan_estimate_covers_weights_and_activations() {
+    use common::*;
+    use style_transfer_wasm::pipeline;
+
+    let nodes = vec![node(
+        "Conv",
+        &["input", "weight", "bias"],
+        "output",
+        vec![ints("pads", &[1, 1, 1, 1])],
+    )];
+    let initializer = vec![
+        floats("weight", &[3, 3, 3, 3], ramp(81, 0.2)),
+        floats("bias", &[3], vec![0.0; 3]),
+    ];
+    let plan = pipeline::load_plan(&graph_model(16, nodes, initializer)).unwrap();
+    let image_bytes = 3 * 16 * 16 * 4;
+    // At least the weights and the output image
+    assert!(pipeline::plan_size_estimate(&plan) >= 84 * 4 + image_bytes);
+}
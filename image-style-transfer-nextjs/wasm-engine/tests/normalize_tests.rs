@@ -0,0 +1,99 @@
+use style_transfer_wasm::pipeline::normalize::{model_input, IMAGENET_MEAN, IMAGENET_STD};
+use style_transfer_wasm::pipeline::registry::validate_local_metadata;
+use style_transfer_wasm::pipeline::{ModelMetadata, ValueRange};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn model(input_range: ValueRange, mean: [f32; 3], std: [f32; 3]) -> ModelMetadata {
+    ModelMetadata {
+        name: "normalized".to_string(),
+        input_range,
+        mean,
+        std,
+        ..ModelMetadata::default()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_plain_models_borrow_the_tensor() {
+    let tensor = [0.0, 0.5, 1.0];
+    let input = model_input(&tensor, &ModelMetadata::default());
+    assert!(matches!(input, std::borrow::Cow::Borrowed(_)));
+    assert_eq!(*input, tensor);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_inputs_are_scaled_to_the_range() {
+    let tensor = [0.0, 0.5, 1.0];
+    let signed = model(ValueRange::Signed, [0.0; 3], [1.0; 3]);
+    assert_eq!(*model_input(&tensor, &signed), [-1.0, 0.0, 1.0]);
+    let bytes = model(ValueRange::Bytes, [0.0; 3], [1.0; 3]);
+    assert_eq!(*model_input(&tensor, &bytes), [0.0, 127.5, 255.0]);
+
+    // Caffe-style models subtract a mean given in bytes
+    let caffe = model(ValueRange::Bytes, [100.0, 120.0, 140.0], [1.0; 3]);
+    assert_eq!(*model_input(&tensor, &caffe), [-100.0, 7.5, 115.0]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_imagenet_statistics_standardize_each_channel() {
+    let imagenet = model(ValueRange::Unit, IMAGENET_MEAN, IMAGENET_STD);
+    // The mean itself becomes zero, one std above it one
+    let tensor = [
+        IMAGENET_MEAN[0],
+        IMAGENET_MEAN[1],
+        IMAGENET_MEAN[2],
+        IMAGENET_MEAN[0] + IMAGENET_STD[0],
+        IMAGENET_MEAN[1] + IMAGENET_STD[1],
+        IMAGENET_MEAN[2] + IMAGENET_STD[2],
+    ];
+    let input = model_input(&tensor, &imagenet);
+    for (value, expected) in input.iter().zip([0.0, 0.0, 0.0, 1.0, 1.0, 1.0]) {
+        assert!((value - expected).abs() < 1e-5, "{:?}", input);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_normalization_fields_parse_and_validate() {
+    let metadata: ModelMetadata = serde_json::from_value(serde_json::json!({
+        "name": "imported",
+        "input_range": "0_255",
+        "mean": [123.675, 116.28, 103.53],
+        "std": [58.395, 57.12, 57.375],
+    }))
+    .unwrap();
+    assert_eq!(metadata.input_range, ValueRange::Bytes);
+    assert!(validate_local_metadata(&metadata).is_ok());
+
+    assert!(validate_local_metadata(&model(ValueRange::Unit, [0.0; 3], [1.0, 0.0, 1.0])).is_err());
+    assert!(validate_local_metadata(&model(ValueRange::Unit, [f32::NAN; 3], [1.0; 3])).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_outputs_are_mapped_back_to_unit_range() {
+    use style_transfer_wasm::pipeline::normalize::model_output;
+
+    let with_output = |output_range| ModelMetadata {
+        output_range,
+        ..ModelMetadata::default()
+    };
+    let mut tanh = [-1.0, 0.0, 1.0];
+    model_output(&mut tanh, &with_output(ValueRange::Signed));
+    assert_eq!(tanh, [0.0, 0.5, 1.0]);
+    let mut bytes = [0.0, 51.0, 255.0];
+    model_output(&mut bytes, &with_output(ValueRange::Bytes));
+    assert_eq!(bytes, [0.0, 0.2, 1.0]);
+    let mut unit = [0.25, 1.5];
+    model_output(&mut unit, &with_output(ValueRange::Unit));
+    assert_eq!(unit, [0.25, 1.5]);
+
+    for range in [ValueRange::Unit, ValueRange::Signed, ValueRange::Bytes] {
+        assert!((range.compress(range.expand(0.3)) - 0.3).abs() < 1e-6);
+    }
+}
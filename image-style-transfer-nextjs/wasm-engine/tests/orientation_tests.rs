@@ -0,0 +1,117 @@
+use style_transfer_wasm::pipeline::codec::{decode_rgba, encode_png};
+use style_transfer_wasm::pipeline::orientation::{exif_orientation, Orientation};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// A 3x2 image whose pixels are numbered 0..6 in their red channel.
+fn numbered() -> Vec<u8> {
+    (0..6).flat_map(|i| [i, 0, 0, 255]).collect()
+}
+
+fn reds(pixels: &[u8]) -> Vec<u8> {
+    pixels.chunks_exact(4).map(|px| px[0]).collect()
+}
+
+/// A TIFF header with an IFD0 holding only the Orientation tag.
+fn tiff(orientation: u16, little_endian: bool) -> Vec<u8> {
+    let u16_bytes = |v: u16| {
+        if little_endian {
+            v.to_le_bytes()
+        } else {
+            v.to_be_bytes()
+        }
+    };
+    let u32_bytes = |v: u32| {
+        if little_endian {
+            v.to_le_bytes()
+        } else {
+            v.to_be_bytes()
+        }
+    };
+    let mut tiff = if little_endian {
+        b"II".to_vec()
+    } else {
+        b"MM".to_vec()
+    };
+    tiff.extend(u16_bytes(42));
+    tiff.extend(u32_bytes(8));
+    tiff.extend(u16_bytes(1));
+    tiff.extend(u16_bytes(0x0112));
+    tiff.extend(u16_bytes(3));
+    tiff.extend(u32_bytes(1));
+    tiff.extend(u16_bytes(orientation));
+    tiff.extend([0, 0]);
+    tiff.extend(u32_bytes(0));
+    tiff
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_orientations_turn_pixels_upright() {
+    // 0 1 2
+    // 3 4 5
+    let cases = [
+        (1, (3, 2), vec![0, 1, 2, 3, 4, 5]),
+        (2, (3, 2), vec![2, 1, 0, 5, 4, 3]),
+        (3, (3, 2), vec![5, 4, 3, 2, 1, 0]),
+        (4, (3, 2), vec![3, 4, 5, 0, 1, 2]),
+        (5, (2, 3), vec![0, 3, 1, 4, 2, 5]),
+        (6, (2, 3), vec![3, 0, 4, 1, 5, 2]),
+        (7, (2, 3), vec![5, 2, 4, 1, 3, 0]),
+        (8, (2, 3), vec![2, 5, 1, 4, 0, 3]),
+    ];
+    for (value, size, expected) in cases {
+        let orientation = Orientation::from_exif(value).unwrap();
+        let (pixels, width, height) = orientation.apply(&numbered(), 3, 2);
+        assert_eq!((width, height), size, "orientation {}", value);
+        assert_eq!(reds(&pixels), expected, "orientation {}", value);
+    }
+    assert_eq!(Orientation::from_exif(0), None);
+    assert_eq!(Orientation::from_exif(9), None);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_orientation_is_read_from_jpeg_exif() {
+    for little_endian in [true, false] {
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend(tiff(6, little_endian));
+        let mut jpeg = vec![0xFF, 0xD8];
+        // An APP0 segment first, as JFIF files have
+        jpeg.extend([0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00]);
+        jpeg.extend([0xFF, 0xE1]);
+        jpeg.extend(((payload.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(payload);
+        jpeg.extend([0xFF, 0xDA]);
+        assert_eq!(exif_orientation(&jpeg), Some(Orientation::Rotate90));
+    }
+
+    // Segments after the image data are never reached
+    let mut late = vec![0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02];
+    late.extend(tiff(6, true));
+    assert_eq!(exif_orientation(&late), None);
+    assert_eq!(exif_orientation(b"not an image"), None);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_decoding_applies_png_exif() {
+    let png = encode_png(&numbered(), 3, 2).unwrap();
+    // Insert an eXIf chunk right after IHDR; decoders don't check its CRC
+    let ihdr_end = 8 + 12 + 13;
+    let exif = tiff(8, false);
+    let mut chunk = (exif.len() as u32).to_be_bytes().to_vec();
+    chunk.extend(b"eXIf");
+    chunk.extend(&exif);
+    chunk.extend([0; 4]);
+    let mut tagged = png[..ihdr_end].to_vec();
+    tagged.extend(chunk);
+    tagged.extend(&png[ihdr_end..]);
+    assert_eq!(exif_orientation(&tagged), Some(Orientation::Rotate270));
+
+    let decoded = decode_rgba(&tagged).unwrap();
+    assert_eq!((decoded.width, decoded.height), (2, 3));
+    assert_eq!(reds(&decoded.pixels), [2, 5, 1, 4, 0, 3]);
+    assert_eq!(reds(&decode_rgba(&png).unwrap().pixels), [0, 1, 2, 3, 4, 5]);
+}
@@ -0,0 +1,65 @@
+use style_transfer_wasm::pipeline::pool::{BufferPool, Buffers, POOLED_RESOLUTIONS};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn buffers(len: usize) -> Buffers {
+    Buffers {
+        blend: Vec::with_capacity(len * 3),
+        pixels: Vec::with_capacity(len * 4),
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_buffers_are_reused_at_the_same_size() {
+    let mut pool = BufferPool::default();
+    pool.give((16, 16), buffers(256));
+    assert_eq!(pool.byte_len(), 256 * 3 * 4 + 256 * 4);
+    assert_eq!(pool.take((8, 8)).blend.capacity(), 0);
+    let reused = pool.take((16, 16));
+    assert!(reused.blend.capacity() >= 256 * 3);
+    assert!(reused.pixels.capacity() >= 256 * 4);
+    // Taken buffers leave the pool
+    assert_eq!(pool.byte_len(), 0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_buffers_given_back_separately_are_merged() {
+    let mut pool = BufferPool::default();
+    let Buffers { blend, pixels } = buffers(64);
+    pool.give(
+        (8, 8),
+        Buffers {
+            blend,
+            pixels: Vec::new(),
+        },
+    );
+    pool.give(
+        (8, 8),
+        Buffers {
+            blend: Vec::new(),
+            pixels,
+        },
+    );
+    let merged = pool.take((8, 8));
+    assert!(merged.blend.capacity() >= 64 * 3);
+    assert!(merged.pixels.capacity() >= 64 * 4);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_least_recently_given_size_is_dropped() {
+    let mut pool = BufferPool::default();
+    for side in 1..=POOLED_RESOLUTIONS as u32 {
+        pool.give((side, side), buffers(8));
+    }
+    // Giving size 1 again makes size 2 the oldest
+    pool.give((1, 1), buffers(8));
+    pool.give((99, 99), buffers(8));
+    assert_eq!(pool.take((2, 2)).pixels.capacity(), 0);
+    assert!(pool.take((1, 1)).pixels.capacity() >= 32);
+    pool.clear();
+    assert_eq!(pool.byte_len(), 0);
+}
@@ -0,0 +1,119 @@
+use style_transfer_wasm::pipeline::postprocess::{denoise, match_histogram, sharpen, Postprocess};
+use style_transfer_wasm::pipeline::XorShift64;
+use style_transfer_wasm::ProcessOptions;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// A gray interleaved RGB tensor, darker left of `edge` than right of it.
+fn edged(width: u32, height: u32, edge: u32) -> Vec<f32> {
+    (0..width * height)
+        .map(|i| if i % width < edge { 0.3 } else { 0.7 })
+        .flat_map(|v| [v; 3])
+        .collect()
+}
+
+fn red(tensor: &[f32], width: u32, x: u32, y: u32) -> f32 {
+    tensor[((y * width + x) * 3) as usize]
+}
+
+fn variance(values: &[f32]) -> f32 {
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / values.len() as f32
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_sharpening_steepens_edges_only() {
+    let mut tensor = edged(16, 8, 8);
+    sharpen(&mut tensor, (16, 8), 1.0);
+    // Flat areas are untouched; the sides of the edge are pushed apart
+    assert!((red(&tensor, 16, 1, 4) - 0.3).abs() < 1e-5);
+    assert!((red(&tensor, 16, 14, 4) - 0.7).abs() < 1e-5);
+    assert!(red(&tensor, 16, 7, 4) < 0.3);
+    assert!(red(&tensor, 16, 8, 4) > 0.7);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_denoising_smooths_grain_but_keeps_edges() {
+    let (width, height) = (16, 16);
+    let mut rng = XorShift64::new(7);
+    let mut tensor: Vec<f32> = edged(width, height, 8)
+        .into_iter()
+        .map(|v| v + (rng.next_f32() - 0.5) * 0.05)
+        .collect();
+    let flat = |tensor: &[f32]| -> Vec<f32> {
+        (0..height)
+            .flat_map(|y| (1..6).map(move |x| (x, y)))
+            .map(|(x, y)| red(tensor, width, x, y))
+            .collect()
+    };
+    let noisy = variance(&flat(&tensor));
+    denoise(&mut tensor, (width, height), 1.0);
+    assert!(variance(&flat(&tensor)) < noisy * 0.5);
+    let step = red(&tensor, width, 8, 8) - red(&tensor, width, 7, 8);
+    assert!(step > 0.35, "edge softened to {}", step);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_default_postprocess_changes_nothing() {
+    let original = edged(8, 8, 4);
+    let mut tensor = original.clone();
+    let postprocess = Postprocess::default();
+    assert!(postprocess.is_noop());
+    postprocess.apply(&mut tensor, (8, 8));
+    assert_eq!(tensor, original);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_postprocess_options_are_validated() {
+    let parse = |json| {
+        serde_json::from_value::<ProcessOptions>(json)
+            .unwrap()
+            .postprocess()
+    };
+    let valid = parse(serde_json::json!({ "sharpen": 0.5, "denoise": 0.25 })).unwrap();
+    assert_eq!(
+        valid,
+        Postprocess {
+            sharpen: 0.5,
+            denoise: 0.25
+        }
+    );
+    assert!(parse(serde_json::json!({ "sharpen": 3.0 })).is_err());
+    assert!(parse(serde_json::json!({ "denoise": -0.1 })).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_histogram_matching_takes_the_reference_values_in_order() {
+    // A dark, low-contrast "stylized" ramp takes the reference's spread of
+    // values, in its own order
+    let mut stylized: Vec<f32> = (0..16)
+        .map(|i| 0.1 + 0.01 * ((i * 7) % 16) as f32)
+        .flat_map(|v| [v, v, 0.5])
+        .collect();
+    let reference: Vec<f32> = (0..16)
+        .map(|i| i as f32 / 15.0)
+        .flat_map(|v| [v, 1.0 - v, v])
+        .collect();
+    let order: Vec<usize> = (0..16).map(|i| (i * 7) % 16).collect();
+    match_histogram(&mut stylized, &reference);
+    for (pixel, &rank) in stylized.chunks_exact(3).zip(&order) {
+        assert!(
+            (pixel[0] - rank as f32 / 15.0).abs() < 1e-5,
+            "{:?} at rank {}",
+            pixel,
+            rank
+        );
+        // Only the reference's distribution counts, not where its values are
+        assert!((pixel[1] - rank as f32 / 15.0).abs() < 1e-5);
+    }
+    // A flat channel stays flat, at the reference's median
+    let blue: Vec<f32> = stylized.iter().skip(2).step_by(3).copied().collect();
+    assert!(blue.iter().all(|&v| (v - blue[0]).abs() < 1e-6));
+    assert!((blue[0] - 0.5).abs() < 0.05);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_half_precision_plans_match_f32_closely() {
+    use common::*;
+    use style_transfer_wasm::pipeline::{self, inference::load_half_plan, ModelMetadata};
+
+    let nodes = vec![
+        node(
+            "Conv",
+            &["input", "weight", "bias"],
+            "conv",
+            vec![ints("pads", &[1, 1, 1, 1])],
+        ),
+        node("Sigmoid", &["conv"], "output", vec![]),
+    ];
+    let initializer = vec![
+        floats("weight", &[3, 3, 3, 3], ramp(81, 0.2)),
+        floats("bias", &[3], vec![0.1, -0.1, 0.0]),
+    ];
+    let bytes = graph_model(8, nodes, initializer);
+    let metadata = ModelMetadata {
+        name: "half".to_string(),
+        input_width: 8,
+        input_height: 8,
+        ..ModelMetadata::default()
+    };
+    let input: Vec<f32> = ramp(3 * 8 * 8, 0.5).iter().map(|v| v + 0.5).collect();
+
+    let full = pipeline::load_plan(&bytes).unwrap();
+    let half = load_half_plan(&bytes).unwrap();
+    let expected = pipeline::run_plan(&full, &input, &metadata).unwrap();
+    let output = pipeline::run_plan(&half, &input, &metadata).unwrap();
+    let max_error = output
+        .iter()
+        .zip(&expected)
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0, f32::max);
+    assert!(
+        max_error > 0.0 && max_error < 5e-3,
+        "max error {}",
+        max_error
+    );
+
+    // Half-precision plans still count as taking floats
+    assert!(pipeline::inference::check_quantization(&half, &metadata).is_ok());
+    let (features, shape) = pipeline::run_features(
+        &half,
+        &pipeline::interleaved_to_planar(&input),
+        [1, 3, 8, 8],
+    )
+    .unwrap();
+    assert_eq!(shape, vec![1, 3, 8, 8]);
+    assert_eq!(features.len(), input.len());
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_unoptimized_plans_match_once_optimized() {
+    use common::*;
+    use style_transfer_wasm::pipeline::{
+        self,
+        inference::{load_unoptimized_plan, Unoptimized},
+        ModelMetadata,
+    };
+
+    let nodes = vec![
+        node(
+            "Conv",
+            &["input", "weight", "bias"],
+            "conv",
+            vec![ints("pads", &[1, 1, 1, 1])],
+        ),
+        node("Relu", &["conv"], "output", vec![]),
+    ];
+    let initializer = vec![
+        floats("weight", &[3, 3, 3, 3], ramp(81, 0.2)),
+        floats("bias", &[3], vec![0.1, -0.1, 0.0]),
+    ];
+    let bytes = graph_model(8, nodes, initializer);
+    let metadata = ModelMetadata {
+        name: "deferred".to_string(),
+        input_width: 8,
+        input_height: 8,
+        ..ModelMetadata::default()
+    };
+    let input = ramp(3 * 8 * 8, 1.0);
+
+    let expected = pipeline::run_plan(&pipeline::load_plan(&bytes).unwrap(), &input, &metadata);
+    let unoptimized = load_unoptimized_plan(&bytes, false).unwrap();
+    let first = pipeline::run_plan(&unoptimized, &input, &metadata).unwrap();
+    let optimized = Unoptimized::of(&unoptimized).optimize().unwrap();
+    let later = pipeline::run_plan(&optimized, &input, &metadata).unwrap();
+    let expected = expected.unwrap();
+    for output in [first, later] {
+        assert!(output
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| (a - b).abs() < 1e-5));
+    }
+    // Both plans keep the graph's declared shapes
+    assert_eq!(
+        pipeline::plan_shapes(&unoptimized).unwrap(),
+        pipeline::plan_shapes(&optimized).unwrap()
+    );
+}
@@ -0,0 +1,50 @@
+use style_transfer_wasm::pipeline::prefetch::{PrefetchPriority, PrefetchQueue};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_urgent_prefetches_start_first_within_the_limit() {
+    let mut queue = PrefetchQueue::default();
+    assert!(queue.push("a", PrefetchPriority::Low));
+    assert!(queue.push("b", PrefetchPriority::Normal));
+    assert!(queue.push("c", PrefetchPriority::High));
+    assert!(queue.push("d", PrefetchPriority::Normal));
+    assert_eq!(queue.waiting(), vec!["c", "b", "d", "a"]);
+
+    assert_eq!(queue.start(2).as_deref(), Some("c"));
+    assert_eq!(queue.start(2).as_deref(), Some("b"));
+    assert_eq!(queue.start(2), None);
+    assert_eq!(queue.running(), ["c", "b"]);
+
+    // Running prefetches aren't queued again
+    assert!(!queue.push("c", PrefetchPriority::Low));
+    queue.finish("c");
+    assert_eq!(queue.start(2).as_deref(), Some("d"));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_queueing_again_only_raises_priority() {
+    let mut queue = PrefetchQueue::default();
+    queue.push("a", PrefetchPriority::Normal);
+    queue.push("b", PrefetchPriority::Normal);
+    queue.push("b", PrefetchPriority::High);
+    queue.push("b", PrefetchPriority::Low);
+    assert_eq!(queue.waiting(), vec!["b", "a"]);
+
+    assert!(queue.cancel("b"));
+    assert!(!queue.cancel("b"));
+    queue.clear();
+    assert_eq!(queue.start(1), None);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_priorities_parse_from_names() {
+    let priority: PrefetchPriority = serde_json::from_str(r#""high""#).unwrap();
+    assert_eq!(priority, PrefetchPriority::High);
+    assert_eq!(PrefetchPriority::default(), PrefetchPriority::Normal);
+    assert!(serde_json::from_str::<PrefetchPriority>(r#""urgent""#).is_err());
+}
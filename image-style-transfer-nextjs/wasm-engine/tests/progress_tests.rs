@@ -0,0 +1,38 @@
+use style_transfer_wasm::pipeline::progress::{Progress, Stage};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_percent_rises_through_the_stages() {
+    // 4 tiles of 2 passes
+    let mut progress = Progress::new(8);
+    let mut percents = vec![
+        progress.percent(Stage::Decode),
+        progress.percent(Stage::Preprocess),
+    ];
+    for _ in 0..8 {
+        percents.push(progress.percent(Stage::Inference));
+        progress.done += 1;
+    }
+    percents.push(progress.percent(Stage::Postprocess));
+    percents.push(progress.percent(Stage::Encode));
+    assert!(
+        percents.windows(2).all(|pair| pair[0] < pair[1]),
+        "{:?}",
+        percents
+    );
+    assert_eq!(percents[2], 10.0);
+    assert_eq!(percents[6], 50.0);
+    assert_eq!(percents.last(), Some(&95.0));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_extra_steps_stay_within_inference() {
+    let progress = Progress { steps: 2, done: 5 };
+    assert_eq!(progress.percent(Stage::Inference), 90.0);
+    assert_eq!(Progress::default().percent(Stage::Inference), 10.0);
+    assert_eq!(Stage::Postprocess.name(), "postprocess");
+}
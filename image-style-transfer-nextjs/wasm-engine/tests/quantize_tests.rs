@@ -0,0 +1,116 @@
+mod common;
+
+use style_transfer_wasm::pipeline::quantize::{Quantization, QuantizedType};
+use style_transfer_wasm::pipeline::registry::validate_metadata;
+use style_transfer_wasm::pipeline::ModelMetadata;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn metadata(quantization: Option<Quantization>) -> ModelMetadata {
+    ModelMetadata {
+        name: "q".to_string(),
+        input_width: 4,
+        input_height: 4,
+        model_url: "/models/q.onnx".to_string(),
+        quantization,
+        ..ModelMetadata::default()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_values_round_trip_through_the_encoding() {
+    let bytes = Quantization::default();
+    assert_eq!(bytes.quantize(0.6), 153);
+    assert_eq!(bytes.quantize(2.0), 255);
+    assert_eq!(bytes.quantize(-1.0), 0);
+    assert!((bytes.dequantize(128) - 128.0 / 255.0).abs() < 1e-6);
+
+    let signed = Quantization {
+        dtype: QuantizedType::Int8,
+        input_scale: 1.0 / 127.0,
+        input_zero_point: 0,
+        output_scale: 1.0 / 127.0,
+        output_zero_point: 0,
+    };
+    assert_eq!(signed.quantize(-1.0), -127);
+    assert_eq!(signed.quantize(-2.0), -128);
+    assert!((signed.dequantize(-127) + 1.0).abs() < 1e-6);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_invalid_encodings_are_rejected() {
+    assert!(validate_metadata(&metadata(Some(Quantization::default()))).is_ok());
+    let zero_scale = Quantization {
+        input_scale: 0.0,
+        ..Quantization::default()
+    };
+    assert!(validate_metadata(&metadata(Some(zero_scale))).is_err());
+    let signed_zero_point = Quantization {
+        output_zero_point: -1,
+        ..Quantization::default()
+    };
+    assert!(validate_metadata(&metadata(Some(signed_zero_point))).is_err());
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_uint8_models_are_quantized_around() {
+    use style_transfer_wasm::pipeline::{self, inference::check_quantization};
+    use tract_onnx::pb::tensor_proto::DataType;
+
+    let shape = [Some(1), Some(3), Some(4), Some(4)];
+    let bytes = common::typed_onnx_model("Identity", DataType::Uint8, &shape, &shape);
+    let plan = pipeline::load_plan(&bytes).unwrap();
+    let quantized = metadata(Some(Quantization::default()));
+    assert!(check_quantization(&plan, &quantized).is_ok());
+    let error = check_quantization(&plan, &metadata(None)).unwrap_err();
+    assert!(error.contains("quantization"), "{}", error);
+
+    let input = common::ramp(3 * 4 * 4, 0.5)
+        .into_iter()
+        .map(|v| v + 0.5)
+        .collect::<Vec<_>>();
+    let output = pipeline::run_plan(&plan, &input, &quantized).unwrap();
+    for (value, expected) in output.iter().zip(&input) {
+        assert!((value - expected).abs() <= 0.5 / 255.0 + 1e-6);
+    }
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_qdq_models_take_floats_unchanged() {
+    use common::*;
+    use style_transfer_wasm::pipeline::{self, inference::check_quantization};
+
+    // Quantized inside the graph only, so the entry needs no quantization
+    let bytes = graph_model(
+        4,
+        vec![
+            node("QuantizeLinear", &["input", "scale", "zero"], "q", vec![]),
+            node(
+                "DequantizeLinear",
+                &["q", "scale", "zero"],
+                "output",
+                vec![],
+            ),
+        ],
+        vec![floats("scale", &[], vec![1.0 / 255.0]), uint8("zero", 0)],
+    );
+    let plan = pipeline::load_plan(&bytes).unwrap();
+    assert!(check_quantization(&plan, &metadata(None)).is_ok());
+    assert!(check_quantization(&plan, &metadata(Some(Quantization::default()))).is_err());
+
+    let input = ramp(3 * 4 * 4, 0.5)
+        .into_iter()
+        .map(|v| v + 0.5)
+        .collect::<Vec<_>>();
+    let output = pipeline::run_plan(&plan, &input, &metadata(None)).unwrap();
+    for (value, expected) in output.iter().zip(&input) {
+        assert!((value - expected).abs() <= 0.5 / 255.0 + 1e-6);
+    }
+}
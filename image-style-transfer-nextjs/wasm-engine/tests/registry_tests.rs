@@ -0,0 +1,205 @@
+use serde_json::json;
+use style_transfer_wasm::pipeline::registry::{self, MetadataPatch};
+use style_transfer_wasm::pipeline::{default_registry, ModelKind, ModelMetadata};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn entry(name: &str) -> serde_json::Value {
+    json!({ "name": name, "model_url": format!("/models/{}.onnx", name), "size_mb": 1.0 })
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_builtin_registry_is_valid() {
+    for metadata in default_registry() {
+        assert_eq!(registry::validate_metadata(&metadata), Ok(()));
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_patch_updates_only_given_fields() {
+    let original = default_registry().remove(0);
+    let patch: MetadataPatch =
+        serde_json::from_value(json!({ "description": "new", "unknown_field": 1 })).unwrap();
+    assert!(!patch.changes_shape(&original));
+
+    let patched = patch.apply(&original).unwrap();
+    assert_eq!(patched.description, "new");
+    assert_eq!(patched.model_url, original.model_url);
+    assert_eq!(patched.name, original.name);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_patch_sets_cache_version() {
+    let original = default_registry().remove(0);
+    assert_eq!(original.version, "");
+    let patch: MetadataPatch = serde_json::from_value(json!({ "version": "2024-06" })).unwrap();
+    assert!(!patch.changes_shape(&original));
+    assert_eq!(patch.apply(&original).unwrap().version, "2024-06");
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_patch_rejects_invalid_result() {
+    let original = default_registry().remove(0);
+    let patch = MetadataPatch {
+        input_width: Some(0),
+        ..MetadataPatch::default()
+    };
+    assert!(patch.changes_shape(&original));
+    assert!(patch.apply(&original).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_adain_entries_need_a_decoder() {
+    let mut metadata: ModelMetadata = serde_json::from_value(json!({
+        "name": "any_style",
+        "kind": "adain",
+        "model_url": "/models/vgg_encoder.onnx",
+    }))
+    .unwrap();
+    assert_eq!(metadata.kind, ModelKind::Adain);
+    assert!(registry::validate_metadata(&metadata).is_err());
+    metadata.decoder_url = "/models/adain_decoder.onnx".to_string();
+    assert_eq!(registry::validate_metadata(&metadata), Ok(()));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_import_reports_each_bad_entry() {
+    let current = default_registry();
+    let entries = vec![
+        entry("fresh"),
+        entry("fresh"),
+        entry("van_gogh_starry_night"),
+        json!({ "name": "no_url" }),
+        json!("not an object"),
+        json!({ "name": "filter", "kind": "js_filter" }),
+    ];
+
+    let (registry, report) =
+        registry::import_entries(&current, entries, false, |_| false, |_| false);
+
+    assert_eq!(report.imported, vec!["fresh".to_string()]);
+    let rejected: Vec<usize> = report.rejected.iter().map(|r| r.index).collect();
+    assert_eq!(rejected, vec![1, 2, 3, 4, 5]);
+    assert_eq!(report.rejected[0].name.as_deref(), Some("fresh"));
+    assert_eq!(registry.len(), current.len() + 1);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_import_replace_keeps_loaded_shapes() {
+    let current = default_registry();
+    let mut resized = entry("van_gogh_starry_night");
+    resized["input_width"] = json!(512);
+    let entries = vec![resized, entry("picasso_cubist")];
+
+    let (registry, report) = registry::import_entries(
+        &current,
+        entries,
+        true,
+        |name| name == "van_gogh_starry_night",
+        |_| false,
+    );
+
+    assert_eq!(report.imported, vec!["picasso_cubist".to_string()]);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(registry.len(), 1);
+    assert_eq!(registry[0].kind, ModelKind::Onnx);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_manifest_merges_into_registry() {
+    let current = default_registry();
+    let unchanged = serde_json::to_value(&current[1]).unwrap();
+    let mut updated = serde_json::to_value(&current[0]).unwrap();
+    updated["description"] = json!("Retrained");
+    let mut resized = entry("monet_water_lilies");
+    resized["input_width"] = json!(512);
+    let manifest = json!({ "models": [
+        updated,
+        entry("fresh"),
+        unchanged,
+        entry("fresh"),
+        resized,
+        json!({ "name": "filter", "kind": "js_filter" }),
+    ] });
+
+    let entries = registry::manifest_entries(manifest).unwrap();
+    let (registry, report) = registry::merge_entries(
+        &current,
+        entries,
+        |name| name == "monet_water_lilies",
+        |_| false,
+    );
+
+    assert_eq!(report.added, vec!["fresh".to_string()]);
+    assert_eq!(report.updated, vec![current[0].name.clone()]);
+    assert_eq!(report.unchanged, vec![current[1].name.clone()]);
+    let rejected: Vec<usize> = report.rejected.iter().map(|r| r.index).collect();
+    assert_eq!(rejected, vec![3, 4, 5]);
+    // Updated entries stay in place and unmentioned ones are kept
+    assert_eq!(registry.len(), current.len() + 1);
+    assert_eq!(registry[0].description, "Retrained");
+    assert_eq!(registry[3], current[3]);
+    assert_eq!(registry.last().unwrap().name, "fresh");
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_version_changes_name_downloadable_entries() {
+    let before = default_registry();
+    let mut after = before.clone();
+    after[0].version = "2".to_string();
+    after[1].description = "Only the description".to_string();
+    // Simulation-only entries have no file to update
+    let simulated = after.iter().position(|m| m.model_url.is_empty()).unwrap();
+    after[simulated].version = "2".to_string();
+    let mut added = after[2].clone();
+    added.name = "added".to_string();
+    added.version = "1".to_string();
+    after.push(added);
+
+    let changes = registry::version_changes(&before, &after);
+    assert_eq!(
+        changes,
+        vec![registry::VersionChange {
+            name: before[0].name.clone(),
+            from: String::new(),
+            to: "2".to_string(),
+        }]
+    );
+    assert!(registry::version_changes(&after, &before)
+        .iter()
+        .all(|change| change.from == "2" && change.to.is_empty()));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_manifest_shapes() {
+    assert_eq!(
+        registry::manifest_entries(json!([entry("a")]))
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(registry::manifest_entries(json!({ "styles": [] })).is_err());
+    assert!(registry::manifest_entries(json!("models")).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_supplied_models_need_no_url() {
+    let mut metadata: ModelMetadata =
+        serde_json::from_value(json!({ "name": "uploaded", "input_width": 64 })).unwrap();
+    assert!(registry::validate_metadata(&metadata).is_err());
+    assert_eq!(registry::validate_local_metadata(&metadata), Ok(()));
+    metadata.input_channels = 1;
+    assert!(registry::validate_local_metadata(&metadata).is_err());
+}
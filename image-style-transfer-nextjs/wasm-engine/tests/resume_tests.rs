@@ -0,0 +1,141 @@
+use style_transfer_wasm::pipeline::resume::{
+    parse_content_range, ContentRange, PartialDownload, ResponseInfo,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn full_response(length: usize) -> ResponseInfo<'static> {
+    ResponseInfo {
+        status: 200,
+        content_length: Some(length),
+        etag: Some("\"v1\""),
+        accept_ranges: Some("bytes"),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_parse_content_range() {
+    assert_eq!(
+        parse_content_range("bytes 100-199/200"),
+        Some(ContentRange {
+            start: 100,
+            end: 199,
+            total: Some(200)
+        })
+    );
+    assert_eq!(parse_content_range("bytes 0-9/*").unwrap().total, None);
+    assert_eq!(parse_content_range("bytes 10-5/200"), None);
+    assert_eq!(parse_content_range("bytes 0-200/200"), None);
+    assert_eq!(parse_content_range("items 0-1/2"), None);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_interrupted_download_resumes_with_range() {
+    let mut partial = PartialDownload::default();
+    assert_eq!(partial.range_header(), None);
+
+    partial.begin(full_response(10)).unwrap();
+    partial.bytes.extend([0, 1, 2, 3]);
+    assert_eq!(partial.range_header().as_deref(), Some("bytes=4-"));
+    // Short of the advertised length
+    assert!(partial.clone().complete().is_err());
+
+    partial
+        .begin(ResponseInfo {
+            status: 206,
+            content_range: Some("bytes 4-9/10"),
+            etag: Some("\"v1\""),
+            ..Default::default()
+        })
+        .unwrap();
+    partial.bytes.extend([4, 5, 6, 7, 8, 9]);
+    assert_eq!(partial.complete(), Ok((0..10).collect()));
+    assert_eq!(partial, PartialDownload::default());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_mismatched_partial_responses_start_over() {
+    let interrupted = || {
+        let mut partial = PartialDownload::default();
+        partial.begin(full_response(10)).unwrap();
+        partial.bytes.extend([0, 1, 2, 3]);
+        partial
+    };
+
+    // Wrong offset, changed ETag, changed length, missing Content-Range
+    for (range, etag) in [
+        (Some("bytes 5-9/10"), "\"v1\""),
+        (Some("bytes 4-9/10"), "\"v2\""),
+        (Some("bytes 4-11/12"), "\"v1\""),
+        (None, "\"v1\""),
+    ] {
+        let mut partial = interrupted();
+        let response = ResponseInfo {
+            status: 206,
+            content_range: range,
+            etag: Some(etag),
+            ..Default::default()
+        };
+        assert!(partial.begin(response).is_err());
+        assert_eq!(partial.range_header(), None);
+    }
+
+    // A 200 to a range request replaces what was there
+    let mut partial = interrupted();
+    partial.begin(full_response(3)).unwrap();
+    partial.bytes.extend([7, 8, 9]);
+    assert_eq!(partial.complete(), Ok(vec![7, 8, 9]));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_servers_without_ranges_are_not_resumed() {
+    let mut partial = PartialDownload::default();
+    partial
+        .begin(ResponseInfo {
+            accept_ranges: Some("none"),
+            ..full_response(10)
+        })
+        .unwrap();
+    partial.bytes.extend([0, 1, 2]);
+    assert_eq!(partial.range_header(), None);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_encoded_responses_skip_length_check_and_resume() {
+    // Content-Length counts gzip bytes; the stream yields the decoded ones
+    let mut partial = PartialDownload::default();
+    partial
+        .begin(ResponseInfo {
+            content_encoding: Some("gzip"),
+            ..full_response(4)
+        })
+        .unwrap();
+    partial.bytes.extend(0..10);
+    assert_eq!(partial.range_header(), None);
+    assert_eq!(partial.complete(), Ok((0..10).collect()));
+
+    let mut partial = PartialDownload::default();
+    partial
+        .begin(ResponseInfo {
+            content_encoding: Some("Identity"),
+            ..full_response(10)
+        })
+        .unwrap();
+    partial.bytes.extend([0, 1, 2]);
+    assert_eq!(partial.range_header().as_deref(), Some("bytes=3-"));
+    assert!(partial
+        .begin(ResponseInfo {
+            status: 206,
+            content_range: Some("bytes 3-9/10"),
+            content_encoding: Some("br"),
+            ..Default::default()
+        })
+        .is_err());
+}
@@ -0,0 +1,49 @@
+use style_transfer_wasm::pipeline::registry::validate_metadata;
+use style_transfer_wasm::pipeline::segmentation::{person_probability, subject_strength};
+use style_transfer_wasm::pipeline::{portrait_segmentation, ModelKind};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_outputs_become_person_probabilities() {
+    // Probabilities pass through
+    let (person, size) = person_probability(&[0.0, 0.25, 1.0, 0.5], &[1, 1, 2, 2]).unwrap();
+    assert_eq!((person, size), (vec![0.0, 0.25, 1.0, 0.5], (2, 2)));
+
+    // Logits are squashed
+    let (person, _) = person_probability(&[0.0, 4.0, -4.0], &[1, 1, 1, 3]).unwrap();
+    assert!((person[0] - 0.5).abs() < 1e-6);
+    assert!(person[1] > 0.98 && person[2] < 0.02, "{:?}", person);
+
+    // Background and person scores are softmaxed
+    let (person, size) = person_probability(&[1.0, 0.0, 1.0, 3.0], &[1, 2, 1, 2]).unwrap();
+    assert_eq!(size, (2, 1));
+    assert!((person[0] - 0.5).abs() < 1e-6);
+    assert!(person[1] > 0.95, "{:?}", person);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_unexpected_outputs_are_rejected() {
+    assert!(person_probability(&[0.5; 4], &[1, 4]).is_err());
+    assert!(person_probability(&[0.5; 3], &[1, 1, 2, 2]).is_err());
+    assert!(person_probability(&[0.5; 12], &[1, 3, 2, 2]).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_people_are_spared_unless_inverted() {
+    assert_eq!(subject_strength(0.75, false), 0.25);
+    assert_eq!(subject_strength(0.75, true), 0.75);
+
+    let metadata = portrait_segmentation();
+    assert_eq!(metadata.kind, ModelKind::Segmentation);
+    assert!(validate_metadata(&metadata).is_ok());
+    let without_url = style_transfer_wasm::ModelMetadata {
+        model_url: String::new(),
+        ..metadata
+    };
+    assert!(validate_metadata(&without_url).is_err());
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use style_transfer_wasm::pipeline::{self, ModelMetadata, ShapeMismatch};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn metadata() -> ModelMetadata {
+    ModelMetadata {
+        name: "m".to_string(),
+        ..ModelMetadata::default()
+    }
+}
+
+fn concrete(dims: [usize; 4]) -> Vec<Option<usize>> {
+    dims.iter().map(|&dim| Some(dim)).collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_matching_and_symbolic_shapes_pass() {
+    let shape = concrete([1, 3, 256, 256]);
+    assert_eq!(
+        pipeline::check_model_shapes(&shape, &shape, &metadata()),
+        Ok(())
+    );
+
+    let symbolic = vec![Some(1), Some(3), None, None];
+    assert_eq!(
+        pipeline::check_model_shapes(&symbolic, &symbolic, &metadata()),
+        Ok(())
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_mismatches_name_the_dimension() {
+    let ok = concrete([1, 3, 256, 256]);
+    let small = concrete([1, 3, 224, 224]);
+    let error = pipeline::check_model_shapes(&small, &ok, &metadata()).unwrap_err();
+    assert_eq!(
+        error,
+        ShapeMismatch {
+            tensor: "input",
+            expected: [1, 3, 256, 256],
+            actual: small.clone(),
+            dimension: Some("height"),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "model input is [1, 3, 224, 224] but the metadata expects [1, 3, 256, 256] (height differs)"
+    );
+
+    let rgba = concrete([1, 4, 256, 256]);
+    let error = pipeline::check_model_shapes(&rgba, &ok, &metadata()).unwrap_err();
+    assert_eq!(error.dimension, Some("channels"));
+
+    let error = pipeline::check_model_shapes(&ok, &[Some(3), None, None], &metadata()).unwrap_err();
+    assert_eq!((error.tensor, error.dimension), ("output", None));
+    assert!(error.to_string().ends_with("(rank 3 instead of 4)"));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_concrete_inputs_correct_the_metadata() {
+    let small = concrete([1, 3, 224, 200]);
+    let corrected = pipeline::corrected_metadata(&small, &metadata()).unwrap();
+    assert_eq!((corrected.input_width, corrected.input_height), (200, 224));
+    assert_eq!(
+        pipeline::check_model_shapes(&small, &small, &corrected),
+        Ok(())
+    );
+
+    assert_eq!(
+        pipeline::corrected_metadata(&[Some(1), Some(3), None, None], &metadata()),
+        None
+    );
+}
+
+#[cfg(feature = "backend-tract")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_plan_shapes_come_from_the_graph() {
+    let shape = [Some(1), Some(3), Some(224), Some(224)];
+    let bytes = common::onnx_model("Identity", &shape, &shape);
+    let plan = pipeline::load_plan(&bytes).unwrap();
+    let (input, output) = pipeline::plan_shapes(&plan).unwrap();
+    assert_eq!(input, concrete([1, 3, 224, 224]));
+    assert_eq!(output, input);
+    assert!(pipeline::check_model_shapes(&input, &output, &metadata()).is_err());
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "backend-tract"))]
+#[wasm_bindgen_test]
+async fn test_model_loads_from_supplied_bytes() {
+    use style_transfer_wasm::StyleTransferEngine;
+
+    let bytes = common::onnx_model(
+        "Identity",
+        &[Some(1), Some(3), Some(4), Some(4)],
+        &[Some(1), Some(3), Some(4), Some(4)],
+    );
+    let bytes = js_sys::Uint8Array::from(&bytes[..]);
+    let metadata = js_sys::JSON::parse(r#"{ "input_width": 4, "input_height": 4 }"#).unwrap();
+    let mut engine = StyleTransferEngine::new();
+    engine
+        .load_model_from_bytes("uploaded", &bytes, metadata)
+        .await
+        .unwrap();
+    assert!(engine.get_loaded_models().contains(&"uploaded".to_string()));
+    let pixels = engine
+        .process_pixels(&[10; 4 * 4 * 4], 4, 4, "uploaded", 1.0)
+        .await
+        .unwrap();
+    assert_eq!(pixels.len(), 4 * 4 * 4);
+
+    // There's no URL to fetch it from again
+    engine.unload_model("uploaded").unwrap();
+    let error = engine
+        .process_pixels(&[10; 4 * 4 * 4], 4, 4, "uploaded", 1.0)
+        .await
+        .unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("ModelNotFound"));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_nhwc_models_are_checked_channels_last() {
+    let nhwc = ModelMetadata {
+        layout: pipeline::TensorLayout::Nhwc,
+        ..metadata()
+    };
+    let shape = concrete([1, 256, 256, 3]);
+    assert_eq!(pipeline::check_model_shapes(&shape, &shape, &nhwc), Ok(()));
+    let error =
+        pipeline::check_model_shapes(&concrete([1, 3, 256, 256]), &shape, &nhwc).unwrap_err();
+    assert_eq!(error.dimension, Some("height"));
+
+    let corrected = pipeline::corrected_metadata(&concrete([1, 224, 160, 3]), &nhwc).unwrap();
+    assert_eq!(
+        (
+            corrected.input_height,
+            corrected.input_width,
+            corrected.input_channels
+        ),
+        (224, 160, 3)
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_nhwc_outputs_reorder_to_nchw() {
+    let layout = pipeline::TensorLayout::Nhwc;
+    assert_eq!(layout.image_shape(3, 2, 4), [1, 2, 4, 3]);
+    // Two pixels of two channels each
+    let (planar, shape) = layout.to_nchw(vec![1.0, 2.0, 3.0, 4.0], &[1, 1, 2, 2]);
+    assert_eq!(
+        (planar, shape),
+        (vec![1.0, 3.0, 2.0, 4.0], vec![1, 2, 1, 2])
+    );
+    assert_eq!(layout.interleaved(&[0.1, 0.2, 0.3]), [0.1, 0.2, 0.3]);
+    assert_eq!(
+        pipeline::TensorLayout::Nchw.interleaved(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+        [1.0, 3.0, 5.0, 2.0, 4.0, 6.0]
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_symbolic_sides_make_a_model_dynamic() {
+    use pipeline::shapes::has_dynamic_size;
+    use pipeline::TensorLayout::{Nchw, Nhwc};
+
+    assert!(has_dynamic_size(&[Some(1), Some(3), None, None], Nchw));
+    assert!(has_dynamic_size(&[None, None, None, Some(3)], Nhwc));
+    assert!(!has_dynamic_size(&[Some(1), Some(3), None, None], Nhwc));
+    assert!(!has_dynamic_size(&concrete([1, 3, 256, 256]), Nchw));
+    assert!(!has_dynamic_size(&[Some(1), Some(3), None], Nchw));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_dynamic_inputs_keep_the_image_size() {
+    use pipeline::shapes::dynamic_input_size;
+
+    assert_eq!(dynamic_input_size(640, 480, 768), (640, 480));
+    // Rounded down to the stride multiple, never below it
+    assert_eq!(dynamic_input_size(643, 485, 768), (640, 480));
+    assert_eq!(dynamic_input_size(5, 3, 0), (8, 8));
+    // Large images are fitted within the cap first
+    assert_eq!(dynamic_input_size(4000, 3000, 768), (768, 576));
+}
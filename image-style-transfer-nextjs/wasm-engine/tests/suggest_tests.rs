@@ -0,0 +1,60 @@
+use style_transfer_wasm::pipeline::{self, ImageStats};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn solid(rgb: [u8; 3], size: u32) -> Vec<u8> {
+    (0..size * size)
+        .flat_map(|_| [rgb[0], rgb[1], rgb[2], 255])
+        .collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_stats_of_flat_and_striped_images() {
+    let gray = ImageStats::from_rgba(&solid([128, 128, 128], 16), 16, 16);
+    assert!((gray.mean_luminance - 128.0 / 255.0).abs() < 1e-4);
+    assert_eq!(
+        (gray.mean_saturation, gray.edge_density, gray.warm_share),
+        (0.0, 0.0, 0.5)
+    );
+
+    // 2px stripes; with 1px ones the Sobel taps cancel out
+    let stripes: Vec<u8> = (0..16 * 16u32)
+        .flat_map(|i| {
+            if ((i % 16) / 2).is_multiple_of(2) {
+                [255, 0, 0, 255]
+            } else {
+                [0, 0, 0, 255]
+            }
+        })
+        .collect();
+    let stats = ImageStats::from_rgba(&stripes, 16, 16);
+    assert!(stats.edge_density > 0.5);
+    assert_eq!(stats.warm_share, 1.0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_dark_muted_night_shot_suggests_cyberpunk() {
+    let stats = ImageStats::from_rgba(&solid([20, 24, 32], 16), 16, 16);
+    let suggestions = pipeline::rank_styles(&stats, &pipeline::default_registry(), 2);
+    assert_eq!(suggestions.len(), 2);
+    assert_eq!(suggestions[0].name, "cyberpunk_neon");
+    assert!(suggestions[0].reasons.contains(&"dark image".to_string()));
+    assert!(suggestions[0].score >= suggestions[1].score);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_bright_smooth_image_suggests_monet() {
+    let stats = ImageStats::from_rgba(&solid([190, 220, 230], 16), 16, 16);
+    let mut registry = pipeline::default_registry();
+    let suggestions = pipeline::rank_styles(&stats, &registry, 5);
+    assert_eq!(suggestions[0].name, "monet_water_lilies");
+
+    // Affinities are data, so a bias can change the ranking
+    registry[1].style_affinity.as_mut().unwrap().bias = 10.0;
+    let suggestions = pipeline::rank_styles(&stats, &registry, 1);
+    assert_eq!(suggestions[0].name, "picasso_cubist");
+}
@@ -0,0 +1,84 @@
+use style_transfer_wasm::pipeline::temporal::{TemporalBlend, RAMP_FRAMES};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_blend_ramps_in() {
+    let mut temporal = TemporalBlend::new(0.8).unwrap();
+    let mut weights = Vec::new();
+    for _ in 0..RAMP_FRAMES + 2 {
+        temporal.apply(vec![0.0; 3], (1, 1));
+        weights.push(temporal.last_effective_blend());
+    }
+    assert_eq!(weights, [0.0, 0.2, 0.4, 0.6, 0.8, 0.8]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_frames_are_averaged_with_the_previous_result() {
+    let mut temporal = TemporalBlend::new(0.8).unwrap();
+    assert_eq!(temporal.apply(vec![1.0; 3], (1, 1)), [1.0; 3]);
+    // Second frame: previous weighted 0.2
+    let second = temporal.apply(vec![0.0; 3], (1, 1));
+    assert!(
+        second.iter().all(|&v| (v - 0.2).abs() < 1e-6),
+        "{:?}",
+        second
+    );
+    // The blended result, not the raw frame, is what the next one sees
+    let third = temporal.apply(vec![0.0; 3], (1, 1));
+    assert!(
+        third.iter().all(|&v| (v - 0.08).abs() < 1e-6),
+        "{:?}",
+        third
+    );
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_resolution_change_starts_over() {
+    let mut temporal = TemporalBlend::new(0.5).unwrap();
+    temporal.apply(vec![1.0; 3], (1, 1));
+    temporal.apply(vec![1.0; 3], (1, 1));
+    assert_eq!(temporal.frames(), 2);
+    assert_eq!(temporal.apply(vec![0.0; 6], (2, 1)), [0.0; 6]);
+    assert_eq!(temporal.frames(), 1);
+    assert_eq!(temporal.last_effective_blend(), 0.0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_blend_weight_is_validated() {
+    assert!(TemporalBlend::new(f32::NAN).is_err());
+    let mut temporal = TemporalBlend::new(5.0).unwrap();
+    for _ in 0..RAMP_FRAMES + 1 {
+        temporal.apply(vec![0.0; 3], (1, 1));
+    }
+    assert_eq!(temporal.last_effective_blend(), 0.95);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_moving_pixels_drop_the_previous_frame() {
+    let mut temporal = TemporalBlend::new(0.8)
+        .unwrap()
+        .with_motion_threshold(0.2)
+        .unwrap();
+    for _ in 0..RAMP_FRAMES {
+        temporal.apply_with_input(vec![1.0; 9], &[0.5; 9], (3, 1));
+    }
+    // The first pixel is still, the second moved by half the threshold and
+    // the third by more than it
+    let input = [0.5, 0.5, 0.5, 0.6, 0.6, 0.6, 0.0, 0.0, 0.0];
+    let blended = temporal.apply_with_input(vec![0.0; 9], &input, (3, 1));
+    let expected = [0.8, 0.8, 0.8, 0.4, 0.4, 0.4, 0.0, 0.0, 0.0];
+    for (value, expected) in blended.iter().zip(expected) {
+        assert!((value - expected).abs() < 1e-5, "{:?}", blended);
+    }
+    assert!(TemporalBlend::new(0.5)
+        .unwrap()
+        .with_motion_threshold(0.0)
+        .is_err());
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "threads")]
+
+mod common;
+
+use common::*;
+use style_transfer_wasm::pipeline;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// A wide convolution, which tract runs as a matrix multiplication.
+fn conv_model() -> Vec<u8> {
+    let nodes = vec![
+        node(
+            "Conv",
+            &["input", "w1"],
+            "hidden",
+            vec![ints("pads", &[1, 1, 1, 1])],
+        ),
+        node("Relu", &["hidden"], "relu", vec![]),
+        node(
+            "Conv",
+            &["relu", "w2"],
+            "output",
+            vec![ints("pads", &[1, 1, 1, 1])],
+        ),
+    ];
+    let initializer = vec![
+        floats("w1", &[32, 3, 3, 3], ramp(32 * 3 * 9, 0.2)),
+        floats("w2", &[3, 32, 3, 3], ramp(3 * 32 * 9, 0.05)),
+    ];
+    graph_model(32, nodes, initializer)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn test_thread_pool_needs_a_worker() {
+    // wasm-bindgen-test runs in the page, where the main thread can't block
+    let error = style_transfer_wasm::use_thread_pool().unwrap_err();
+    let code = js_sys::Reflect::get(&error, &"code".into()).unwrap();
+    assert_eq!(code.as_string().as_deref(), Some("InvalidInput"));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn test_pooled_inference_matches_a_single_thread() {
+    let bytes = conv_model();
+    let input = ramp(3 * 32 * 32, 1.0);
+    let plan = pipeline::load_plan(&bytes).unwrap();
+    let (single, _) = pipeline::run_features(&plan, &input, [1, 3, 32, 32]).unwrap();
+
+    style_transfer_wasm::init_thread_pool(4).unwrap();
+    style_transfer_wasm::use_thread_pool().unwrap();
+    let plan = pipeline::load_plan(&bytes).unwrap();
+    let (pooled, shape) = pipeline::run_features(&plan, &input, [1, 3, 32, 32]).unwrap();
+    assert_eq!(shape, vec![1, 3, 32, 32]);
+    assert_eq!(single, pooled);
+}
@@ -0,0 +1,169 @@
+use style_transfer_wasm::pipeline::tiling::{
+    axis_weights, extract_tile, max_output_side, plan_tiles, tile_count, tile_origins, TileBlender,
+    TileBudget,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn budget(source: (u32, u32), dynamic: bool, time_budget_ms: f64) -> TileBudget {
+    TileBudget {
+        source_width: source.0,
+        source_height: source.1,
+        model_width: 256,
+        model_height: 256,
+        dynamic,
+        tile_ms: 100.0,
+        device_memory_gb: Some(8.0),
+        time_budget_ms,
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tile_count_accounts_for_overlap() {
+    assert_eq!(tile_count(200, 256, 32), 1);
+    assert_eq!(tile_count(256, 256, 32), 1);
+    assert_eq!(tile_count(480, 256, 32), 2);
+    assert_eq!(tile_count(481, 256, 32), 3);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_generous_budget_keeps_full_resolution() {
+    let plan = plan_tiles(&budget((1000, 600), false, 60_000.0));
+    assert_eq!((plan.output_width, plan.output_height), (1000, 600));
+    assert_eq!((plan.tile_width, plan.overlap), (256, 32));
+    assert_eq!((plan.columns, plan.rows), (5, 3));
+    assert_eq!(plan.tile_count, 15);
+    assert_eq!(plan.estimated_ms, 1500.0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tight_budget_shrinks_the_output() {
+    // A 12MP photo at 100 ms per tile doesn't fit in two seconds
+    let plan = plan_tiles(&budget((4000, 3000), false, 2000.0));
+    assert!(plan.estimated_ms <= 2000.0, "{:?}", plan);
+    assert!(plan.output_width < 4000);
+    // The aspect ratio survives the shrinking
+    let aspect = plan.output_width as f64 / plan.output_height as f64;
+    assert!((aspect - 4.0 / 3.0).abs() < 0.01, "{:?}", plan);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_impossible_budget_falls_back_to_one_tile() {
+    let plan = plan_tiles(&budget((4000, 3000), false, 1.0));
+    assert_eq!(plan.tile_count, 1);
+    assert!(plan.output_width <= 256 && plan.output_height <= 256);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_dynamic_models_prefer_fewer_larger_tiles() {
+    let plan = plan_tiles(&budget((2000, 2000), true, 600_000.0));
+    assert_eq!(plan.tile_width, 768);
+    assert_eq!(plan.tile_count, 9);
+
+    // Low-memory devices stay on small tiles and smaller outputs
+    let low_memory = plan_tiles(&TileBudget {
+        device_memory_gb: Some(1.0),
+        ..budget((4000, 3000), true, 600_000.0)
+    });
+    assert!(low_memory.tile_width <= 384);
+    assert_eq!(low_memory.output_width, max_output_side(Some(1.0)));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tile_origins_cover_the_axis() {
+    assert_eq!(tile_origins(200, 256, 32), vec![0]);
+    assert_eq!(tile_origins(480, 256, 32), vec![0, 224]);
+    // The last tile ends flush with the edge
+    assert_eq!(tile_origins(481, 256, 32), vec![0, 224, 225]);
+    for length in [257, 600, 1000, 4000] {
+        let origins = tile_origins(length, 256, 32);
+        assert_eq!(origins.len() as u32, tile_count(length, 256, 32));
+        assert_eq!(origins.last().unwrap() + 256, length);
+        assert!(origins
+            .windows(2)
+            .all(|pair| pair[1] > pair[0] && pair[1] <= pair[0] + 224));
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_feather_weights_ramp_across_overlaps() {
+    let origins = tile_origins(480, 256, 32);
+    let left = axis_weights(&origins, 0, 256);
+    let right = axis_weights(&origins, 1, 256);
+    // Full weight away from the seam, and at the image edges
+    assert_eq!(left[0], 1.0);
+    assert_eq!(right[255], 1.0);
+    assert_eq!(left[223], 1.0);
+    // Across the overlap the weights cross over and sum to one
+    for i in 0..32 {
+        let (a, b) = (left[224 + i], right[i]);
+        assert!(a > 0.0 && b > 0.0);
+        assert!((a + b - 1.0).abs() < 1e-6, "{} + {} at {}", a, b, i);
+    }
+    assert!(left[224] > left[255] && right[0] < right[31]);
+    assert_eq!(axis_weights(&[0], 0, 8), vec![1.0; 8]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_extract_tile_repeats_edges() {
+    // 3x2 image whose red channel is the pixel index
+    let image: Vec<f32> = (0..6).flat_map(|i| [i as f32, 0.0, 0.0]).collect();
+    let tile = extract_tile(&image, (3, 2), (1, 1), (3, 2));
+    let red: Vec<f32> = tile.chunks_exact(3).map(|px| px[0]).collect();
+    assert_eq!(red, vec![4.0, 5.0, 5.0, 4.0, 5.0, 5.0]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_blended_tiles_reassemble_the_image() {
+    let (width, height, tile, overlap) = (300, 200, 128, 16);
+    let image: Vec<f32> = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = ((i % width) as f32, (i / width) as f32);
+            [x / width as f32, y / height as f32, 0.5]
+        })
+        .collect();
+    let columns = tile_origins(width, tile, overlap);
+    let rows = tile_origins(height, tile, overlap);
+    let mut blender = TileBlender::new(width, height);
+    for (row, &y0) in rows.iter().enumerate() {
+        for (column, &x0) in columns.iter().enumerate() {
+            let tensor = extract_tile(&image, (width, height), (x0, y0), (tile, tile));
+            blender.add(
+                &tensor,
+                (x0, y0),
+                &axis_weights(&columns, column, tile),
+                &axis_weights(&rows, row, tile),
+            );
+        }
+    }
+    let blended = blender.finish();
+    assert_eq!(blended.len(), image.len());
+    let max_error = blended
+        .iter()
+        .zip(&image)
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0, f32::max);
+    assert!(max_error < 1e-5, "max error {}", max_error);
+
+    // A single tile larger than the image is cropped back to it
+    let mut single = TileBlender::new(3, 2);
+    let small: Vec<f32> = (0..6).flat_map(|i| [i as f32, 0.0, 0.0]).collect();
+    let tile = extract_tile(&small, (3, 2), (0, 0), (4, 4));
+    single.add(
+        &tile,
+        (0, 0),
+        &axis_weights(&[0], 0, 4),
+        &axis_weights(&[0], 0, 4),
+    );
+    assert_eq!(single.finish(), small);
+}
@@ -0,0 +1,90 @@
+use style_transfer_wasm::pipeline::registry::validate_metadata;
+use style_transfer_wasm::pipeline::super_resolution;
+use style_transfer_wasm::pipeline::upscale::{check_output, upscale_tiled};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// An interleaved RGB tensor whose pixels all differ.
+fn gradient(width: u32, height: u32) -> Vec<f32> {
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| [x as f32 / width as f32, y as f32 / height as f32, 0.5])
+        .collect()
+}
+
+/// Nearest-neighbour enlargement of a square RGB tile.
+fn nearest(tile: &[f32], side: u32, factor: u32) -> Vec<f32> {
+    let out = side * factor;
+    (0..out * out)
+        .flat_map(|i| {
+            let (x, y) = (i % out / factor, i / out / factor);
+            let at = ((y * side + x) * 3) as usize;
+            tile[at..at + 3].to_vec()
+        })
+        .collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tiles_join_without_seams() {
+    // Overlapping tiles that agree blend back into the untiled result
+    let (width, height) = (40, 24);
+    let tensor = gradient(width, height);
+    let mut tiles = 0;
+    let upscaled = upscale_tiled(&tensor, (width, height), (16, 16), 2, |tile| {
+        tiles += 1;
+        Ok::<_, String>(nearest(tile, 16, 2))
+    })
+    .unwrap();
+    assert!(tiles > 1);
+    let expected: Vec<f32> = (0..height * 2)
+        .flat_map(|y| (0..width * 2).map(move |x| (x / 2, y / 2)))
+        .flat_map(|(x, y)| {
+            let at = ((y * width + x) * 3) as usize;
+            tensor[at..at + 3].to_vec()
+        })
+        .collect();
+    assert_eq!(upscaled.len(), expected.len());
+    for (value, expected) in upscaled.iter().zip(&expected) {
+        assert!((value - expected).abs() < 1e-5, "{} vs {}", value, expected);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_small_results_use_one_padded_tile() {
+    let tensor = gradient(5, 3);
+    let upscaled = upscale_tiled(&tensor, (5, 3), (8, 8), 4, |tile| {
+        assert_eq!(tile.len(), 8 * 8 * 3);
+        Ok::<_, String>(nearest(tile, 8, 4))
+    })
+    .unwrap();
+    assert_eq!(upscaled.len(), 20 * 12 * 3);
+    assert_eq!(&upscaled[..3], &tensor[..3]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_tile_errors_stop_upscaling() {
+    let tensor = gradient(4, 4);
+    let result = upscale_tiled(&tensor, (4, 4), (4, 4), 2, |_| Err("broken".to_string()));
+    assert_eq!(result, Err("broken".to_string()));
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_output_shape_must_match_the_factor() {
+    assert!(check_output(&[1, 3, 256, 512], (256, 128), 2).is_ok());
+    assert!(check_output(&[1, 3, 512, 1024], (256, 128), 2).is_err());
+    assert!(check_output(&[1, 1, 256, 512], (256, 128), 2).is_err());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_super_resolution_entries_need_a_supported_factor() {
+    assert!(validate_metadata(&super_resolution(2)).is_ok());
+    assert!(validate_metadata(&super_resolution(4)).is_ok());
+    assert_eq!(super_resolution(4).name, "super_resolution_x4");
+    assert!(validate_metadata(&super_resolution(3)).is_err());
+}